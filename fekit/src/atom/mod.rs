@@ -7,6 +7,7 @@ use crate::point::Point;
 /** Atom is the primary struct used to define an AtomGroup or a Molecule */
 #[allow(dead_code)]
 #[derive(Default, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Atom {
     pub center: Point,
     pub charge: f32,
@@ -42,14 +43,39 @@ pub trait AtomOperations {
     fn index_of(&mut self, atom: &mut Atom) -> usize;
 }
 
-/** AtomGroup is a collection of atoms with specific name */
+/** GroupKind tells what an AtomGroup stands for in a molecule's hierarchy */
 #[allow(dead_code)]
-#[derive(Default, PartialEq, Debug)]
+#[derive(Default, Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum GroupKind {
+    #[default]
+    GENERIC,
+    MODEL,
+    CHAIN,
+    RESIDUE,
+    FRAGMENT,
+    LAYER,
+    STEREO,
+}
+
+/**
+ * AtomGroup is a collection of atoms with specific name.
+ *
+ * A standalone group owns its atoms (see AtomOperations). A group that is part of a
+ * Molecule (residue, chain, fragment, layer ...) instead refers to the molecule's atoms
+ * by index, and may contain nested sub-groups, e.g. model -> chain -> residue.
+ */
+#[allow(dead_code)]
+#[derive(Default, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct AtomGroup {
     pub name: String,
     pub remark: String,
+    pub kind: GroupKind,
 
     atom_list: Vec<Atom>,
+    atom_index_list: Vec<usize>,
+    group_list: Vec<AtomGroup>,
 }
 
 #[allow(dead_code)]
@@ -58,7 +84,147 @@ impl AtomGroup {
         AtomGroup {
             name: name,
             remark: remark,
+            kind: GroupKind::GENERIC,
             atom_list: Vec::new(),
+            atom_index_list: Vec::new(),
+            group_list: Vec::new(),
+        }
+    }
+
+    /** with_kind() returns this group tagged as the given kind */
+    pub fn with_kind(mut self, kind: GroupKind) -> AtomGroup {
+        self.kind = kind;
+        return self;
+    }
+
+    /** add_atom_index() adds a reference to the atom at the given index in the parent molecule */
+    pub fn add_atom_index(&mut self, index: usize) {
+        self.atom_index_list.push(index);
+    }
+
+    /** get_atom_indices() returns the atom indices referenced directly by this group */
+    pub fn get_atom_indices(&self) -> &[usize] {
+        return &self.atom_index_list;
+    }
+
+    /** get_all_atom_indices() returns the atom indices of this group followed by those of its sub-groups */
+    pub fn get_all_atom_indices(&self) -> Vec<usize> {
+        let mut indices = self.atom_index_list.clone();
+
+        for group in self.group_list.iter() {
+            indices.extend(group.get_all_atom_indices());
+        }
+
+        return indices;
+    }
+
+    /** contains_atom_index() checks if this group, or any of its sub-groups, refers to the atom index */
+    pub fn contains_atom_index(&self, index: usize) -> bool {
+        return self.atom_index_list.contains(&index)
+            || self.group_list.iter().any(|g| g.contains_atom_index(index));
+    }
+
+    /** add_group() adds a nested sub-group */
+    pub fn add_group(&mut self, group: AtomGroup) {
+        self.group_list.push(group);
+    }
+
+    /** get_groups() returns the sub-groups directly nested in this group */
+    pub fn get_groups(&self) -> &[AtomGroup] {
+        return &self.group_list;
+    }
+
+    /** get_group() returns the first sub-group with the given name */
+    pub fn get_group(&self, name: &str) -> Option<&AtomGroup> {
+        return self.group_list.iter().find(|g| g.name == name);
+    }
+
+    /** get_group_mut() returns the first sub-group with the given name, for editing */
+    pub fn get_group_mut(&mut self, name: &str) -> Option<&mut AtomGroup> {
+        return self.group_list.iter_mut().find(|g| g.name == name);
+    }
+
+    /** max_atom_index() returns the largest atom index referenced by this group or its sub-groups */
+    pub(crate) fn max_atom_index(&self) -> Option<usize> {
+        let own_max = self.atom_index_list.iter().copied().max();
+        let sub_max = self
+            .group_list
+            .iter()
+            .filter_map(|g| g.max_atom_index())
+            .max();
+
+        return own_max.max(sub_max);
+    }
+
+    /**
+     * remove_atom_index() drops references to a removed atom and shifts the indices after it,
+     * returns where the references were as (path of sub-group positions, position in the list)
+     */
+    pub(crate) fn remove_atom_index(&mut self, index: usize) -> Vec<(Vec<usize>, usize)> {
+        let mut positions: Vec<(Vec<usize>, usize)> = self
+            .atom_index_list
+            .iter()
+            .enumerate()
+            .filter(|(_, idx)| **idx == index)
+            .map(|(position, _)| (Vec::new(), position))
+            .collect();
+
+        self.atom_index_list.retain(|idx| *idx != index);
+
+        for idx in self.atom_index_list.iter_mut() {
+            if *idx > index {
+                *idx -= 1;
+            }
+        }
+
+        for (group_idx, group) in self.group_list.iter_mut().enumerate() {
+            for (mut path, position) in group.remove_atom_index(index) {
+                path.insert(0, group_idx);
+                positions.push((path, position));
+            }
+        }
+
+        return positions;
+    }
+
+    /**
+     * insert_atom_index() shifts the indices of a reinserted atom and those after it, and puts
+     * back the references returned by remove_atom_index()
+     */
+    pub(crate) fn insert_atom_index(&mut self, index: usize, positions: &[(Vec<usize>, usize)]) {
+        for idx in self.atom_index_list.iter_mut() {
+            if *idx >= index {
+                *idx += 1;
+            }
+        }
+
+        for (group_idx, group) in self.group_list.iter_mut().enumerate() {
+            let sub_positions: Vec<(Vec<usize>, usize)> = positions
+                .iter()
+                .filter(|(path, _)| path.first() == Some(&group_idx))
+                .map(|(path, position)| (path[1..].to_vec(), *position))
+                .collect();
+            group.insert_atom_index(index, &sub_positions);
+        }
+
+        for (_, position) in positions.iter().filter(|(path, _)| path.is_empty()) {
+            self.atom_index_list.insert(*position, index);
+        }
+    }
+
+    /** collect_groups_of_atom() collects this group and its sub-groups that contain the atom index */
+    pub(crate) fn collect_groups_of_atom<'a>(
+        &'a self,
+        index: usize,
+        groups: &mut Vec<&'a AtomGroup>,
+    ) {
+        if !self.contains_atom_index(index) {
+            return;
+        }
+
+        groups.push(self);
+        for group in self.group_list.iter() {
+            group.collect_groups_of_atom(index, groups);
         }
     }
 }
@@ -226,4 +392,29 @@ mod tests {
 
         assert_eq!(atom_group.get_number_of_atoms(), 1);
     }
+
+    #[test]
+    fn atom_group_nested() {
+        let mut chain = super::AtomGroup::new("A".to_string(), "Chain A".to_string());
+        let mut res_1 = super::AtomGroup::new("ALA1".to_string(), "Alanine".to_string());
+        let mut res_2 = super::AtomGroup::new("GLY2".to_string(), "Glycine".to_string());
+
+        res_1.add_atom_index(0);
+        res_1.add_atom_index(1);
+        res_2.add_atom_index(2);
+        res_2.add_atom_index(3);
+        chain.add_group(res_1);
+        chain.add_group(res_2);
+
+        assert_eq!(chain.get_atom_indices().len(), 0);
+        assert_eq!(chain.get_all_atom_indices(), vec![0, 1, 2, 3]);
+        assert!(chain.contains_atom_index(3));
+        assert!(!chain.contains_atom_index(4));
+        assert_eq!(chain.get_group("GLY2").unwrap().get_atom_indices(), &[2, 3]);
+        assert!(chain.get_group("SER3").is_none());
+
+        chain.remove_atom_index(1);
+        assert_eq!(chain.get_group("ALA1").unwrap().get_atom_indices(), &[0]);
+        assert_eq!(chain.get_group("GLY2").unwrap().get_atom_indices(), &[1, 2]);
+    }
 }
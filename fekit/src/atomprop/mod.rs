@@ -5,10 +5,103 @@
 
 /** defines commonly used atomic properties such as atomic number, weight and covalent radii */
 
-/** atomic number */
-const ATOMIC_NUMBER: [u8; 6] = [1, 2, 3, 4, 5, 6];
+/** element symbols, indexed by atomic number - 1 */
+const ELEMENT_SYMBOL: [&str; 118] = [
+    "H", "He", "Li", "Be", "B", "C", "N", "O", "F", "Ne", "Na", "Mg", "Al", "Si", "P", "S", "Cl",
+    "Ar", "K", "Ca", "Sc", "Ti", "V", "Cr", "Mn", "Fe", "Co", "Ni", "Cu", "Zn", "Ga", "Ge", "As",
+    "Se", "Br", "Kr", "Rb", "Sr", "Y", "Zr", "Nb", "Mo", "Tc", "Ru", "Rh", "Pd", "Ag", "Cd", "In",
+    "Sn", "Sb", "Te", "I", "Xe", "Cs", "Ba", "La", "Ce", "Pr", "Nd", "Pm", "Sm", "Eu", "Gd", "Tb",
+    "Dy", "Ho", "Er", "Tm", "Yb", "Lu", "Hf", "Ta", "W", "Re", "Os", "Ir", "Pt", "Au", "Hg", "Tl",
+    "Pb", "Bi", "Po", "At", "Rn", "Fr", "Ra", "Ac", "Th", "Pa", "U", "Np", "Pu", "Am", "Cm", "Bk",
+    "Cf", "Es", "Fm", "Md", "No", "Lr", "Rf", "Db", "Sg", "Bh", "Hs", "Mt", "Ds", "Rg", "Cn", "Nh",
+    "Fl", "Mc", "Lv", "Ts", "Og",
+];
 
-/** atomic weight */
-const ATOMIC_WEIGHT: [f64; 6] = [1.00784, 4.002602, 6.938, 9.0121831, 10.806, 12.0096];
-/** covalent radii */
-const COVALENT_RADII: [u8; 6] = [32, 46, 133, 102, 85, 75];
+/** atomic weight, in g/mol, indexed by atomic number - 1 */
+#[rustfmt::skip]
+const ATOMIC_WEIGHT: [f64; 118] = [
+    1.00784, 4.002602, 6.938, 9.0121831, 10.806, 12.0096, 14.00643, 15.99903, 18.998403163,
+    20.1797, 22.98976928, 24.304, 26.9815385, 28.084, 30.973761998, 32.059, 35.446, 39.948,
+    39.0983, 40.078, 44.955908, 47.867, 50.9415, 51.9961, 54.938044, 55.845, 58.933194, 58.6934,
+    63.546, 65.38, 69.723, 72.630, 74.921595, 78.971, 79.901, 83.798, 85.4678, 87.62, 88.90584,
+    91.224, 92.90637, 95.95, 98.0, 101.07, 102.90550, 106.42, 107.8682, 112.414, 114.818,
+    118.710, 121.760, 127.60, 126.90447, 131.293, 132.90545196, 137.327, 138.90547, 140.116,
+    140.90766, 144.242, 145.0, 150.36, 151.964, 157.25, 158.92535, 162.500, 164.93033, 167.259,
+    168.93422, 173.045, 174.9668, 178.49, 180.94788, 183.84, 186.207, 190.23, 192.217, 195.084,
+    196.966569, 200.592, 204.382, 207.2, 208.98040, 209.0, 210.0, 222.0, 223.0, 226.0, 227.0,
+    232.0377, 231.03588, 238.02891, 237.0, 244.0, 243.0, 247.0, 247.0, 251.0, 252.0, 257.0,
+    258.0, 259.0, 262.0, 267.0, 268.0, 269.0, 270.0, 269.0, 278.0, 281.0, 282.0, 285.0, 286.0,
+    289.0, 290.0, 293.0, 294.0, 294.0,
+];
+
+/** covalent (single bond) radii, in pm, indexed by atomic number - 1 */
+const COVALENT_RADII: [u8; 118] = [
+    32, 46, 133, 102, 85, 75, 71, 63, 64, 67, 155, 139, 126, 116, 111, 103, 99, 96, 196, 171, 148,
+    136, 134, 122, 119, 116, 111, 110, 112, 118, 124, 121, 121, 116, 114, 117, 210, 185, 163, 154,
+    147, 138, 128, 125, 125, 120, 128, 136, 142, 140, 140, 136, 133, 131, 232, 196, 180, 163, 176,
+    174, 173, 172, 168, 169, 168, 167, 166, 165, 164, 170, 162, 152, 146, 137, 131, 129, 122, 123,
+    124, 133, 144, 144, 151, 145, 147, 142, 223, 201, 186, 175, 169, 170, 171, 172, 166, 166, 168,
+    168, 165, 167, 173, 176, 161, 157, 149, 143, 141, 134, 129, 128, 121, 122, 136, 143, 162, 175,
+    165, 157,
+];
+
+/** get_atomic_number() returns the atomic number of an element symbol, ignoring case ("CL" is Cl) */
+#[allow(dead_code)]
+pub fn get_atomic_number(symbol: &str) -> Option<u8> {
+    let symbol = symbol.trim();
+
+    return ELEMENT_SYMBOL
+        .iter()
+        .position(|s| s.eq_ignore_ascii_case(symbol))
+        .map(|idx| (idx + 1) as u8);
+}
+
+/** get_element_symbol() returns the element symbol for an atomic number */
+#[allow(dead_code)]
+pub fn get_element_symbol(atomic_number: u8) -> Option<&'static str> {
+    if atomic_number == 0 {
+        return None;
+    }
+
+    return ELEMENT_SYMBOL.get(atomic_number as usize - 1).copied();
+}
+
+/** get_atomic_weight() returns the atomic weight, in g/mol, of an element symbol */
+#[allow(dead_code)]
+pub fn get_atomic_weight(symbol: &str) -> Option<f64> {
+    let atomic_number = get_atomic_number(symbol)?;
+    return Some(ATOMIC_WEIGHT[atomic_number as usize - 1]);
+}
+
+/** get_covalent_radius() returns the covalent radius, in angstrom, of an element symbol */
+#[allow(dead_code)]
+pub fn get_covalent_radius(symbol: &str) -> Option<f32> {
+    let atomic_number = get_atomic_number(symbol)?;
+    return Some(COVALENT_RADII[atomic_number as usize - 1] as f32 / 100.0);
+}
+
+/** Unit tests for the above module */
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn atomprop_symbols() {
+        assert_eq!(super::get_atomic_number("C"), Some(6));
+        assert_eq!(super::get_atomic_number("CL"), Some(17));
+        assert_eq!(super::get_atomic_number(" fe "), Some(26));
+        assert_eq!(super::get_atomic_number("Xx"), None);
+        assert_eq!(super::get_element_symbol(8), Some("O"));
+        assert_eq!(super::get_element_symbol(118), Some("Og"));
+        assert_eq!(super::get_element_symbol(0), None);
+        assert_eq!(super::get_element_symbol(119), None);
+    }
+
+    #[test]
+    fn atomprop_tables() {
+        assert_eq!(super::get_atomic_weight("C"), Some(12.0096));
+        assert_eq!(super::get_atomic_weight("Fe"), Some(55.845));
+        assert_eq!(super::get_covalent_radius("C"), Some(0.75));
+        assert_eq!(super::get_covalent_radius("Cl"), Some(0.99));
+        assert_eq!(super::get_covalent_radius("Og"), Some(1.57));
+        assert_eq!(super::get_covalent_radius("Xx"), None);
+    }
+}
@@ -8,6 +8,7 @@ use crate::atom::Atom;
 
 #[allow(dead_code)]
 #[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum BondType {
     SINGLE,
     DOUBLE,
@@ -17,6 +18,37 @@ pub enum BondType {
     WEAK,
 }
 
+#[allow(dead_code)]
+impl BondType {
+    /**
+     * get_mdl_code() returns the MDL molfile bond type code of this bond type,
+     * coordinate and weak bonds use the V3000 coordination (9) and hydrogen (10) codes
+     */
+    pub fn get_mdl_code(&self) -> u8 {
+        match self {
+            BondType::SINGLE => return 1,
+            BondType::DOUBLE => return 2,
+            BondType::TRIPLE => return 3,
+            BondType::AROMATIC => return 4,
+            BondType::COORDINATE => return 9,
+            BondType::WEAK => return 10,
+        }
+    }
+
+    /** from_mdl_code() returns the bond type for an MDL molfile bond type code */
+    pub fn from_mdl_code(code: u8) -> Option<BondType> {
+        match code {
+            1 => return Some(BondType::SINGLE),
+            2 => return Some(BondType::DOUBLE),
+            3 => return Some(BondType::TRIPLE),
+            4 => return Some(BondType::AROMATIC),
+            9 => return Some(BondType::COORDINATE),
+            10 => return Some(BondType::WEAK),
+            _ => return None,
+        }
+    }
+}
+
 #[allow(dead_code)]
 #[derive(Clone, PartialEq, Debug)]
 pub struct Bond {
@@ -27,6 +59,7 @@ pub struct Bond {
 
 #[allow(dead_code)]
 #[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BondIndex {
     pub atom_1_idx: usize,
     pub atom_2_idx: usize,
@@ -77,4 +110,24 @@ mod tests {
         assert_eq!(bond.atom_b.symbol, "O".to_string());
         assert_eq!(bond.bond_type, BondType::SINGLE);
     }
+
+    #[test]
+    fn bond_mdl_code() {
+        for bond_type in [
+            BondType::SINGLE,
+            BondType::DOUBLE,
+            BondType::TRIPLE,
+            BondType::AROMATIC,
+            BondType::COORDINATE,
+            BondType::WEAK,
+        ] {
+            assert_eq!(
+                BondType::from_mdl_code(bond_type.get_mdl_code()),
+                Some(bond_type)
+            );
+        }
+
+        assert_eq!(BondType::DOUBLE.get_mdl_code(), 2);
+        assert_eq!(BondType::from_mdl_code(8), None);
+    }
 }
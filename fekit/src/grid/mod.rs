@@ -0,0 +1,464 @@
+/**
+ * Author: V. Ganesh
+ * License: MIT
+ */
+
+/**
+ * grid module, scalar values on a regular 3D grid, such as an electron density or an
+ * electrostatic potential.
+ *
+ * Point (i, j, k) of the grid is at origin + i * axes[0] + j * axes[1] + k * axes[2], in
+ * angstrom, and its value is data[(i * n1 + j) * n2 + k] for dimensions [n0, n1, n2], the
+ * order of Gaussian cube files. The axes need not be orthogonal. Volumes and integrals are
+ * in cubic angstrom, values given per cubic bohr (as in cube files) integrate to atomic
+ * units after dividing by BOHR^3.
+ */
+use std::error::Error;
+use std::fmt;
+
+/** the largest difference in origin or axes for two grids to be taken as the same, in angstrom */
+const GRID_TOLERANCE: f64 = 1e-6;
+
+/** GridError - the reason a grid could not be built or combined with another */
+#[allow(dead_code)]
+#[derive(Clone, PartialEq, Debug)]
+pub enum GridError {
+    /** the number of values does not match the dimensions */
+    DataLength { expected: usize, found: usize },
+    /** the grids do not have the same origin, axes and dimensions */
+    Mismatch,
+}
+
+impl fmt::Display for GridError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GridError::DataLength { expected, found } => write!(
+                f,
+                "the grid needs {} values, {} were given",
+                expected, found
+            ),
+            GridError::Mismatch => write!(
+                f,
+                "the grids do not have the same origin, axes and dimensions"
+            ),
+        }
+    }
+}
+
+impl Error for GridError {}
+
+/** GridStatistics - the extreme values of a grid, with the points where they occur */
+#[allow(dead_code)]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct GridStatistics {
+    pub minimum: f64,
+    pub minimum_point: [usize; 3],
+    pub maximum: f64,
+    pub maximum_point: [usize; 3],
+    pub mean: f64,
+}
+
+/**
+ * IsovalueStatistics - the part of a grid inside an isosurface: the points at or above a
+ * positive isovalue, or at or below a negative one
+ */
+#[allow(dead_code)]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct IsovalueStatistics {
+    pub isovalue: f64,
+    pub points: usize,
+    /** the volume inside the isosurface, in cubic angstrom */
+    pub volume: f64,
+    /** the integral of the values inside the isosurface */
+    pub integral: f64,
+}
+
+/** Grid - values on a regular grid */
+#[allow(dead_code)]
+#[derive(Clone, PartialEq, Debug)]
+pub struct Grid {
+    pub origin: [f64; 3],
+    /** the step between neighbouring points along each axis, in angstrom */
+    pub axes: [[f64; 3]; 3],
+    pub dimensions: [usize; 3],
+    pub data: Vec<f64>,
+}
+
+fn dot(u: &[f64; 3], v: &[f64; 3]) -> f64 {
+    return u[0] * v[0] + u[1] * v[1] + u[2] * v[2];
+}
+
+fn cross(u: &[f64; 3], v: &[f64; 3]) -> [f64; 3] {
+    return [
+        u[1] * v[2] - u[2] * v[1],
+        u[2] * v[0] - u[0] * v[2],
+        u[0] * v[1] - u[1] * v[0],
+    ];
+}
+
+#[allow(dead_code)]
+impl Grid {
+    /** new() creates a grid with all values zero */
+    pub fn new(origin: [f64; 3], axes: [[f64; 3]; 3], dimensions: [usize; 3]) -> Grid {
+        let size = dimensions[0] * dimensions[1] * dimensions[2];
+        return Grid {
+            origin: origin,
+            axes: axes,
+            dimensions: dimensions,
+            data: vec![0.0; size],
+        };
+    }
+
+    /** from_data() creates a grid with the given values, in the order of get_index() */
+    pub fn from_data(
+        origin: [f64; 3],
+        axes: [[f64; 3]; 3],
+        dimensions: [usize; 3],
+        data: Vec<f64>,
+    ) -> Result<Grid, GridError> {
+        let expected = dimensions[0] * dimensions[1] * dimensions[2];
+        if data.len() != expected {
+            return Err(GridError::DataLength {
+                expected: expected,
+                found: data.len(),
+            });
+        }
+
+        return Ok(Grid {
+            origin: origin,
+            axes: axes,
+            dimensions: dimensions,
+            data: data,
+        });
+    }
+
+    /** get_index() returns the index in data of point (i, j, k) */
+    pub fn get_index(&self, i: usize, j: usize, k: usize) -> usize {
+        return (i * self.dimensions[1] + j) * self.dimensions[2] + k;
+    }
+
+    pub fn get(&self, i: usize, j: usize, k: usize) -> f64 {
+        return self.data[self.get_index(i, j, k)];
+    }
+
+    pub fn set(&mut self, i: usize, j: usize, k: usize, value: f64) {
+        let idx = self.get_index(i, j, k);
+        self.data[idx] = value;
+    }
+
+    /** get_position() returns the position of point (i, j, k), in angstrom */
+    pub fn get_position(&self, i: usize, j: usize, k: usize) -> [f64; 3] {
+        let mut position = self.origin;
+        for (n, axis) in [i, j, k].iter().zip(self.axes.iter()) {
+            for (coordinate, step) in position.iter_mut().zip(axis.iter()) {
+                *coordinate += *n as f64 * step;
+            }
+        }
+        return position;
+    }
+
+    /** get_voxel_volume() returns the volume of one grid cell, in cubic angstrom */
+    pub fn get_voxel_volume(&self) -> f64 {
+        let [a, b, c] = &self.axes;
+        return dot(a, &cross(b, c)).abs();
+    }
+
+    /**
+     * is_compatible() checks that another grid has the same points, so that the two can
+     * be combined point by point
+     */
+    pub fn is_compatible(&self, other: &Grid) -> bool {
+        let close = |u: &[f64; 3], v: &[f64; 3]| {
+            u.iter()
+                .zip(v.iter())
+                .all(|(x, y)| (x - y).abs() < GRID_TOLERANCE)
+        };
+
+        return self.dimensions == other.dimensions
+            && close(&self.origin, &other.origin)
+            && self
+                .axes
+                .iter()
+                .zip(other.axes.iter())
+                .all(|(u, v)| close(u, v));
+    }
+
+    /** combine() returns the grid of f applied to the values of two grids, point by point */
+    fn combine(&self, other: &Grid, f: fn(f64, f64) -> f64) -> Result<Grid, GridError> {
+        if !self.is_compatible(other) {
+            return Err(GridError::Mismatch);
+        }
+
+        let mut grid = self.clone();
+        for (value, other_value) in grid.data.iter_mut().zip(other.data.iter()) {
+            *value = f(*value, *other_value);
+        }
+        return Ok(grid);
+    }
+
+    /** add() returns the sum of two grids */
+    pub fn add(&self, other: &Grid) -> Result<Grid, GridError> {
+        return self.combine(other, |a, b| a + b);
+    }
+
+    /** subtract() returns this grid minus another, e.g. a difference density */
+    pub fn subtract(&self, other: &Grid) -> Result<Grid, GridError> {
+        return self.combine(other, |a, b| a - b);
+    }
+
+    /** multiply() returns the product of two grids, point by point */
+    pub fn multiply(&self, other: &Grid) -> Result<Grid, GridError> {
+        return self.combine(other, |a, b| a * b);
+    }
+
+    /** scale() returns the grid with every value multiplied by a factor */
+    pub fn scale(&self, factor: f64) -> Grid {
+        return self.map(|value| value * factor);
+    }
+
+    /** map() returns the grid with f applied to every value */
+    pub fn map<F: Fn(f64) -> f64>(&self, f: F) -> Grid {
+        let mut grid = self.clone();
+        for value in grid.data.iter_mut() {
+            *value = f(*value);
+        }
+        return grid;
+    }
+
+    /**
+     * get_grid_coordinates() returns the position as (fractional) grid indices, the
+     * inverse of get_position()
+     */
+    pub fn get_grid_coordinates(&self, position: [f64; 3]) -> [f64; 3] {
+        let [a, b, c] = &self.axes;
+        let volume = dot(a, &cross(b, c));
+        let d = [
+            position[0] - self.origin[0],
+            position[1] - self.origin[1],
+            position[2] - self.origin[2],
+        ];
+
+        // rows of the inverse axes matrix are the reciprocal vectors
+        return [
+            dot(&d, &cross(b, c)) / volume,
+            dot(&d, &cross(c, a)) / volume,
+            dot(&d, &cross(a, b)) / volume,
+        ];
+    }
+
+    /**
+     * interpolate() returns the value at a position by trilinear interpolation of the
+     * eight surrounding points, None if the position is outside the grid
+     */
+    pub fn interpolate(&self, position: [f64; 3]) -> Option<f64> {
+        let coordinates = self.get_grid_coordinates(position);
+
+        let mut lower = [0; 3];
+        let mut fraction = [0.0; 3];
+        for axis in 0..3 {
+            let last = self.dimensions[axis].checked_sub(1)? as f64;
+            let x = coordinates[axis];
+            if x < -GRID_TOLERANCE || x > last + GRID_TOLERANCE {
+                return None;
+            }
+            // the last cell holds points on the far face of the grid
+            let x = x.clamp(0.0, last);
+            let cell = x.floor().min((last - 1.0).max(0.0));
+            lower[axis] = cell as usize;
+            fraction[axis] = x - cell;
+        }
+
+        let mut value = 0.0;
+        for corner in 0..8 {
+            let mut weight = 1.0;
+            let mut point = lower;
+            for axis in 0..3 {
+                if corner & (1 << axis) != 0 {
+                    weight *= fraction[axis];
+                    point[axis] = (point[axis] + 1).min(self.dimensions[axis] - 1);
+                } else {
+                    weight *= 1.0 - fraction[axis];
+                }
+            }
+            if weight != 0.0 {
+                value += weight * self.get(point[0], point[1], point[2]);
+            }
+        }
+
+        return Some(value);
+    }
+
+    /** integrate() returns the sum of the values times the voxel volume */
+    pub fn integrate(&self) -> f64 {
+        return self.data.iter().sum::<f64>() * self.get_voxel_volume();
+    }
+
+    /** get_point() returns the grid indices of a position in data */
+    fn get_point(&self, idx: usize) -> [usize; 3] {
+        let k = idx % self.dimensions[2];
+        let j = (idx / self.dimensions[2]) % self.dimensions[1];
+        let i = idx / (self.dimensions[1] * self.dimensions[2]);
+        return [i, j, k];
+    }
+
+    /** get_statistics() returns the minimum, maximum and mean of the values, NaN if it has none */
+    pub fn get_statistics(&self) -> GridStatistics {
+        if self.data.is_empty() {
+            return GridStatistics {
+                minimum: f64::NAN,
+                minimum_point: [0; 3],
+                maximum: f64::NAN,
+                maximum_point: [0; 3],
+                mean: f64::NAN,
+            };
+        }
+
+        let (mut minimum, mut maximum) = (0, 0);
+        for (idx, value) in self.data.iter().enumerate() {
+            if *value < self.data[minimum] {
+                minimum = idx;
+            }
+            if *value > self.data[maximum] {
+                maximum = idx;
+            }
+        }
+
+        return GridStatistics {
+            minimum: self.data[minimum],
+            minimum_point: self.get_point(minimum),
+            maximum: self.data[maximum],
+            maximum_point: self.get_point(maximum),
+            mean: self.data.iter().sum::<f64>() / self.data.len() as f64,
+        };
+    }
+
+    /**
+     * get_isovalue_statistics() returns the number of points, volume and integral inside
+     * the isosurface of a value: the points at or above a positive (or zero) isovalue,
+     * or at or below a negative one, as for the negative lobes of a potential
+     */
+    pub fn get_isovalue_statistics(&self, isovalue: f64) -> IsovalueStatistics {
+        let inside = |value: f64| {
+            if isovalue < 0.0 {
+                value <= isovalue
+            } else {
+                value >= isovalue
+            }
+        };
+
+        let (mut points, mut sum) = (0, 0.0);
+        for value in self.data.iter().filter(|value| inside(**value)) {
+            points += 1;
+            sum += value;
+        }
+
+        let voxel_volume = self.get_voxel_volume();
+        return IsovalueStatistics {
+            isovalue: isovalue,
+            points: points,
+            volume: points as f64 * voxel_volume,
+            integral: sum * voxel_volume,
+        };
+    }
+}
+
+/** Unit tests for the above module */
+#[cfg(test)]
+mod tests {
+    use crate::grid::Grid;
+    use crate::grid::GridError;
+
+    fn assert_close(a: f64, b: f64) {
+        assert!((a - b).abs() < 1e-9, "{} != {}", a, b);
+    }
+
+    /** get_linear_grid() returns a skewed grid holding 1 + x + 2y - z */
+    fn get_linear_grid() -> Grid {
+        let axes = [[0.5, 0.0, 0.0], [0.1, 0.4, 0.0], [0.0, 0.2, 0.3]];
+        let mut grid = Grid::new([-1.0, 0.5, 2.0], axes, [4, 3, 5]);
+        for i in 0..4 {
+            for j in 0..3 {
+                for k in 0..5 {
+                    let p = grid.get_position(i, j, k);
+                    grid.set(i, j, k, 1.0 + p[0] + 2.0 * p[1] - p[2]);
+                }
+            }
+        }
+        return grid;
+    }
+
+    #[test]
+    fn grid_interpolate() {
+        let grid = get_linear_grid();
+        assert_eq!(grid.get_index(1, 2, 3), 28);
+        assert_eq!(grid.get_position(0, 0, 0), [-1.0, 0.5, 2.0]);
+
+        let coordinates = grid.get_grid_coordinates(grid.get_position(3, 1, 4));
+        for (x, expected) in coordinates.iter().zip([3.0, 1.0, 4.0]) {
+            assert_close(*x, expected);
+        }
+
+        // a linear function is reproduced exactly, also on the far faces
+        for f in [[0.5, 1.3, 2.7], [2.9, 0.1, 3.5], [3.0, 1.5, 4.0]] {
+            let mut p = grid.origin;
+            for (x, axis) in f.iter().zip(grid.axes.iter()) {
+                for (coordinate, step) in p.iter_mut().zip(axis.iter()) {
+                    *coordinate += x * step;
+                }
+            }
+            let value = grid.interpolate(p).unwrap();
+            assert_close(value, 1.0 + p[0] + 2.0 * p[1] - p[2]);
+        }
+        let far = grid.get_position(3, 2, 4);
+        assert_close(grid.interpolate(far).unwrap(), grid.get(3, 2, 4));
+        assert_eq!(grid.interpolate([-2.0, 0.5, 2.0]), None);
+    }
+
+    #[test]
+    fn grid_arithmetic() {
+        let grid = get_linear_grid();
+        let doubled = grid.add(&grid).unwrap();
+        assert_eq!(doubled, grid.scale(2.0));
+        let difference = doubled.subtract(&grid).unwrap();
+        assert_eq!(difference, grid);
+        let squared = grid.multiply(&grid).unwrap();
+        assert_close(squared.get(1, 1, 1), grid.get(1, 1, 1).powi(2));
+
+        let mut shifted = grid.clone();
+        shifted.origin[0] += 0.1;
+        assert_eq!(grid.subtract(&shifted), Err(GridError::Mismatch));
+
+        let err = Grid::from_data([0.0; 3], grid.axes, [2, 2, 2], vec![1.0; 7]).unwrap_err();
+        assert_eq!(err.to_string(), "the grid needs 8 values, 7 were given");
+    }
+
+    #[test]
+    fn grid_statistics() {
+        let axes = [[0.5, 0.0, 0.0], [0.0, 0.5, 0.0], [0.0, 0.0, 0.5]];
+        let data = vec![0.5, -0.2, 1.5, 0.0, -0.8, 0.3, 0.1, 0.7];
+        let grid = Grid::from_data([0.0; 3], axes, [2, 2, 2], data).unwrap();
+
+        assert_close(grid.get_voxel_volume(), 0.125);
+        assert_close(grid.integrate(), 2.1 * 0.125);
+
+        let statistics = grid.get_statistics();
+        assert_eq!(statistics.maximum, 1.5);
+        assert_eq!(statistics.maximum_point, [0, 1, 0]);
+        assert_eq!(statistics.minimum, -0.8);
+        assert_eq!(statistics.minimum_point, [1, 0, 0]);
+        assert_close(statistics.mean, 2.1 / 8.0);
+
+        let positive = grid.get_isovalue_statistics(0.5);
+        assert_eq!(positive.points, 3);
+        assert_close(positive.volume, 0.375);
+        assert_close(positive.integral, 2.7 * 0.125);
+
+        let negative = grid.get_isovalue_statistics(-0.1);
+        assert_eq!(negative.points, 2);
+        assert_close(negative.integral, -0.125);
+
+        let empty = Grid::new([0.0; 3], axes, [0, 0, 0]).get_statistics();
+        assert!(empty.minimum.is_nan() && empty.maximum.is_nan() && empty.mean.is_nan());
+        assert_eq!(empty.maximum_point, [0, 0, 0]);
+    }
+}
@@ -0,0 +1,330 @@
+/**
+ * Author: V. Ganesh
+ * License: MIT
+ */
+
+/**
+ * mmcif module, the _atom_site loop of macromolecular CIF files.
+ *
+ * Atoms are grouped as in a PDB file, into chain groups holding residue groups (named by
+ * the residue name, with the residue number and insertion code as remark), inside model
+ * groups if there is more than one model. The author (auth_) chain, residue and atom
+ * names are used where given, as they are the ones a PDB file would have. The atom name,
+ * alternate location, occupancy, B-factor and whether an atom is a HETATM are kept as
+ * atom properties.
+ */
+use crate::atom::Atom;
+use crate::atom::AtomGroup;
+use crate::atom::AtomOperations;
+use crate::atom::GroupKind;
+use crate::io::cif::get_cell;
+use crate::io::cif::get_space_group;
+use crate::io::cif::guess_element;
+use crate::io::cif::parse_number;
+use crate::io::cif::CifBlock;
+use crate::io::cif::CifLoop;
+use crate::io::cif::FORMAT;
+use crate::io::IoError;
+use crate::molecule::Molecule;
+use crate::point::Point;
+use crate::property::AtomProperty;
+use crate::property::Property;
+
+/** Hierarchy - collects the model, chain and residue groups of the atoms read so far */
+struct Hierarchy {
+    models: Vec<(String, Vec<AtomGroup>)>,
+    residue: Option<([String; 4], AtomGroup)>,
+}
+
+impl Hierarchy {
+    /** end_residue() moves the current residue into its chain, in its model */
+    fn end_residue(&mut self) {
+        if let Some(([model_name, chain_name, _, _], residue)) = self.residue.take() {
+            let chains = match self.models.iter().position(|(name, _)| *name == model_name) {
+                Some(position) => &mut self.models[position].1,
+                None => {
+                    self.models.push((model_name, Vec::new()));
+                    &mut self.models.last_mut().unwrap().1
+                }
+            };
+
+            match chains.iter_mut().find(|c| c.name == chain_name) {
+                Some(chain) => chain.add_group(residue),
+                None => {
+                    let mut chain =
+                        AtomGroup::new(chain_name, "".to_string()).with_kind(GroupKind::CHAIN);
+                    chain.add_group(residue);
+                    chains.push(chain);
+                }
+            }
+        }
+    }
+
+    /** add_atom() adds an atom to its residue, key is the model, chain, residue name and number */
+    fn add_atom(&mut self, index: usize, key: [String; 4]) {
+        if self
+            .residue
+            .as_ref()
+            .is_none_or(|(current, _)| *current != key)
+        {
+            self.end_residue();
+            let residue = AtomGroup::new(key[2].to_string(), key[3].to_string())
+                .with_kind(GroupKind::RESIDUE);
+            self.residue = Some((key, residue));
+        }
+
+        if let Some((_, residue)) = self.residue.as_mut() {
+            residue.add_atom_index(index);
+        }
+    }
+
+    /** into_groups() returns the chains, in model groups if there is more than one model */
+    fn into_groups(mut self) -> Vec<AtomGroup> {
+        self.end_residue();
+
+        if self.models.len() == 1 {
+            return self
+                .models
+                .pop()
+                .map(|(_, chains)| chains)
+                .unwrap_or_default();
+        }
+
+        let mut groups = Vec::new();
+        for (name, chains) in self.models {
+            let mut model = AtomGroup::new(name, "".to_string()).with_kind(GroupKind::MODEL);
+            for chain in chains {
+                model.add_group(chain);
+            }
+            groups.push(model);
+        }
+        return groups;
+    }
+}
+
+/** build_molecule() makes the molecule of an mmCIF block */
+pub(super) fn build_molecule(block: &CifBlock, atom_site: &CifLoop) -> Result<Molecule, IoError> {
+    let name = block.get_value("_entry_id").unwrap_or(&block.name);
+    let mut mol = Molecule::new(name.to_string(), "".to_string());
+    if let Some(title) = block.get_value("_struct_title") {
+        mol.remark = title.trim().to_string();
+    }
+
+    // a 1 x 1 x 1 cell is the placeholder used by NMR and modelled structures
+    if let Some(lattice) = get_cell(block)? {
+        if lattice.get_parameters()[..3]
+            .iter()
+            .any(|length| (length - 1.0).abs() > 1.0e-6)
+        {
+            mol.set_lattice(Some(lattice));
+            if let Some(space_group) = get_space_group(block) {
+                mol.set_property("space_group", Property::Text(space_group.to_string()));
+            }
+        }
+    }
+
+    // the first of the data names given, author names before label names
+    let column = |tags: &[&str]| tags.iter().find_map(|tag| atom_site.get_column(tag));
+    let coordinate_columns = [
+        column(&["_atom_site_cartn_x"]),
+        column(&["_atom_site_cartn_y"]),
+        column(&["_atom_site_cartn_z"]),
+    ];
+    let group_column = column(&["_atom_site_group_pdb"]);
+    let symbol_column = column(&["_atom_site_type_symbol"]);
+    let atom_name_column = column(&["_atom_site_auth_atom_id", "_atom_site_label_atom_id"]);
+    let alt_loc_column = column(&["_atom_site_label_alt_id", "_atom_site_auth_alt_id"]);
+    let residue_name_column = column(&["_atom_site_auth_comp_id", "_atom_site_label_comp_id"]);
+    let chain_column = column(&["_atom_site_auth_asym_id", "_atom_site_label_asym_id"]);
+    let residue_number_column = column(&["_atom_site_auth_seq_id", "_atom_site_label_seq_id"]);
+    let insertion_code_column = column(&["_atom_site_pdbx_pdb_ins_code"]);
+    let occupancy_column = column(&["_atom_site_occupancy"]);
+    let b_factor_column = column(&["_atom_site_b_iso_or_equiv"]);
+    let charge_column = column(&["_atom_site_pdbx_formal_charge"]);
+    let model_column = column(&["_atom_site_pdbx_pdb_model_num"]);
+
+    let mut atom_names = Vec::new();
+    let mut alt_locs = Vec::new();
+    let mut occupancies = Vec::new();
+    let mut b_factors = Vec::new();
+    let mut hetatms = Vec::new();
+    let mut hierarchy = Hierarchy {
+        models: Vec::new(),
+        residue: None,
+    };
+
+    for (row, values) in atom_site.get_rows().iter().enumerate() {
+        let error =
+            |message: String| IoError::parse(FORMAT, atom_site.get_line_number(row), message);
+        let text_of = |column: Option<usize>| {
+            column
+                .and_then(|c| values[c].as_deref())
+                .unwrap_or("")
+                .to_string()
+        };
+        let number_of = |column: Option<usize>| {
+            column
+                .and_then(|c| values[c].as_deref())
+                .and_then(parse_number)
+        };
+
+        let mut position = [0.0; 3];
+        for (k, column) in coordinate_columns.iter().enumerate() {
+            let text = text_of(*column);
+            position[k] = parse_number(&text)
+                .ok_or_else(|| error(format!("expected a coordinate, found \"{}\"", text)))?;
+        }
+
+        let atom_name = text_of(atom_name_column);
+        let symbol = match symbol_column.and_then(|c| values[c].as_deref()) {
+            Some(symbol) => guess_element(symbol, false),
+            None => guess_element(&atom_name, true),
+        };
+
+        let index = mol.get_atoms().len();
+        mol.add_atom(Atom {
+            center: Point {
+                x: position[0] as f32,
+                y: position[1] as f32,
+                z: position[2] as f32,
+            },
+            charge: number_of(charge_column).unwrap_or(0.0) as f32,
+            symbol: symbol,
+            remark: "".to_string(),
+        });
+
+        atom_names.push(atom_name);
+        alt_locs.push(text_of(alt_loc_column));
+        occupancies.push(number_of(occupancy_column).unwrap_or(1.0));
+        b_factors.push(number_of(b_factor_column).unwrap_or(0.0));
+        hetatms.push(text_of(group_column).eq_ignore_ascii_case("HETATM"));
+
+        let residue_number = text_of(residue_number_column) + &text_of(insertion_code_column);
+        hierarchy.add_atom(
+            index,
+            [
+                text_of(model_column),
+                text_of(chain_column),
+                text_of(residue_name_column),
+                residue_number,
+            ],
+        );
+    }
+
+    for group in hierarchy.into_groups() {
+        mol.add_group(group);
+    }
+
+    mol.set_atom_property("atom_name", AtomProperty::Texts(atom_names));
+    mol.set_atom_property("alt_loc", AtomProperty::Texts(alt_locs));
+    mol.set_atom_property("occupancy", AtomProperty::Reals(occupancies));
+    mol.set_atom_property("b_factor", AtomProperty::Reals(b_factors));
+    mol.set_atom_property("hetatm", AtomProperty::Logicals(hetatms));
+
+    return Ok(mol);
+}
+
+/** Unit tests for the above module */
+#[cfg(test)]
+mod tests {
+    use crate::atom::AtomOperations;
+    use crate::atom::GroupKind;
+    use crate::property::AtomProperty;
+
+    const ENSEMBLE: &str = "\
+data_1ABC
+_entry.id 1ABC
+_struct.title 'Two models of a dipeptide and water'
+loop_
+_atom_site.group_PDB
+_atom_site.id
+_atom_site.type_symbol
+_atom_site.label_atom_id
+_atom_site.label_alt_id
+_atom_site.label_comp_id
+_atom_site.label_asym_id
+_atom_site.label_seq_id
+_atom_site.pdbx_PDB_ins_code
+_atom_site.Cartn_x
+_atom_site.Cartn_y
+_atom_site.Cartn_z
+_atom_site.occupancy
+_atom_site.B_iso_or_equiv
+_atom_site.pdbx_formal_charge
+_atom_site.auth_seq_id
+_atom_site.auth_asym_id
+_atom_site.pdbx_PDB_model_num
+ATOM   1 N N   . GLY A 1 ? 1.000 2.000 3.000 1.00 10.0 1 1 A 1
+ATOM   2 C CA  . GLY A 1 ? 2.000 2.000 3.000 1.00 10.0 ? 1 A 1
+ATOM   3 C CA  . ALA A 2 A 3.000 2.000 3.000 0.50 12.0 ? 2 A 1
+HETATM 4 O O   . HOH B . ? 9.000 9.000 9.000 1.00 30.0 ? 101 W 1
+ATOM   5 N N   . GLY A 1 ? 1.100 2.000 3.000 1.00 10.0 1 1 A 2
+ATOM   6 C CA  . GLY A 1 ? 2.100 2.000 3.000 1.00 10.0 ? 1 A 2
+ATOM   7 C CA  . ALA A 2 A 3.100 2.000 3.000 0.50 12.0 ? 2 A 2
+HETATM 8 O O   . HOH B . ? 9.100 9.000 9.000 1.00 30.0 ? 101 W 2
+";
+
+    #[test]
+    fn mmcif_read() {
+        let mut mol = crate::io::cif::read(ENSEMBLE.as_bytes()).unwrap();
+
+        assert_eq!(mol.name, "1ABC".to_string());
+        assert_eq!(
+            mol.remark,
+            "Two models of a dipeptide and water".to_string()
+        );
+        assert_eq!(mol.get_number_of_atoms(), 8);
+        assert_eq!(mol.get_atom(0).charge, 1.0);
+        assert_eq!(mol.get_atom(3).symbol, "O".to_string());
+        assert!(mol.get_lattice().is_none());
+
+        let models = mol.get_groups();
+        assert_eq!(models.len(), 2);
+        assert_eq!(models[1].kind, GroupKind::MODEL);
+        assert_eq!(models[1].name, "2".to_string());
+
+        let chains = models[0].get_groups();
+        assert_eq!(chains.len(), 2);
+        assert_eq!(chains[1].name, "W".to_string());
+        assert_eq!(chains[1].kind, GroupKind::CHAIN);
+
+        let residues = chains[0].get_groups();
+        assert_eq!(residues.len(), 2);
+        assert_eq!(residues[1].name, "ALA".to_string());
+        assert_eq!(residues[1].remark, "2A".to_string());
+        assert_eq!(residues[0].get_atom_indices(), [0, 1]);
+
+        assert_eq!(
+            mol.get_atom_property("hetatm"),
+            Some(&AtomProperty::Logicals(vec![
+                false, false, false, true, false, false, false, true
+            ]))
+        );
+        match mol.get_atom_property("occupancy") {
+            Some(AtomProperty::Reals(values)) => assert_eq!(values[2], 0.5),
+            _ => panic!("expected occupancies"),
+        }
+    }
+
+    #[test]
+    fn mmcif_read_single_model() {
+        let text: String = ENSEMBLE
+            .lines()
+            .take(26)
+            .map(|line| line.to_string() + "\n")
+            .collect();
+        let mol = crate::io::cif::read(text.as_bytes()).unwrap();
+
+        assert_eq!(mol.get_atoms().len(), 4);
+        assert_eq!(mol.get_groups().len(), 2);
+        assert_eq!(mol.get_groups()[0].kind, GroupKind::CHAIN);
+
+        let text = ENSEMBLE.replace("3.100 2.000", "3.100 ?");
+        assert_eq!(
+            crate::io::cif::read(text.as_bytes())
+                .unwrap_err()
+                .to_string(),
+            "cif: line 29: expected a coordinate, found \"\""
+        );
+    }
+}
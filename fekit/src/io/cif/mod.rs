@@ -0,0 +1,1014 @@
+/**
+ * Author: V. Ganesh
+ * License: MIT
+ */
+
+/**
+ * cif module, reads Crystallographic Information Files (CIF) and macromolecular CIF (mmCIF).
+ *
+ * The STAR syntax is read into CifBlocks, one per data_ block, holding the single data
+ * items and the loops. Data names are compared in lower case with the first '.' read as
+ * '_', so the DDL2 name _atom_site.Cartn_x is the same as the DDL1 name _atom_site_Cartn_x.
+ * Bare '.' (inapplicable) and '?' (unknown) values are read as missing.
+ *
+ * A block with an _atom_site loop becomes a molecule, named by the block:
+ * - small molecule CIFs give the atoms of the asymmetric unit, at their fractional (or
+ *   cartesian) coordinates, with the cell as the lattice, the space group and symmetry
+ *   operations as the "space_group" and "symmetry_operations" properties, and the site
+ *   labels and occupancies as the "atom_name" and "occupancy" atom properties. The reader
+ *   can instead fill the unit cell by applying the symmetry operations.
+ * - mmCIF files (see mmcif) give the same groups and atom properties as a PDB file.
+ *
+ * Standard uncertainties, e.g. 5.4310(2), are dropped from numbers.
+ */
+use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::io::BufRead;
+
+use crate::atom::Atom;
+use crate::atom::AtomOperations;
+use crate::atomprop;
+use crate::io::IoError;
+use crate::io::LineReader;
+use crate::lattice::Lattice;
+use crate::molecule::Molecule;
+use crate::point::Point;
+use crate::property::AtomProperty;
+use crate::property::Property;
+
+mod mmcif;
+
+const FORMAT: &str = "cif";
+
+/** fractional distance below which two atoms of a filled unit cell are the same site */
+const SITE_TOLERANCE: f64 = 1.0e-3;
+
+/** Token - a token of the STAR syntax, tags are normalised by normalize_tag() */
+#[derive(Clone, PartialEq, Debug)]
+enum Token {
+    Data(String),
+    Loop,
+    Save(String),
+    Reserved(String),
+    Tag(String),
+    Value(Option<String>),
+}
+
+/** normalize_tag() returns the data name in lower case, with the DDL2 '.' as '_' */
+fn normalize_tag(tag: &str) -> String {
+    return tag.to_lowercase().replacen('.', "_", 1);
+}
+
+/** bare_token() classifies an unquoted word */
+fn bare_token(word: &str) -> Token {
+    let lower = word.to_lowercase();
+
+    if let Some(name) = lower.strip_prefix("data_") {
+        return Token::Data(word[word.len() - name.len()..].to_string());
+    }
+    if lower == "loop_" {
+        return Token::Loop;
+    }
+    if let Some(name) = lower.strip_prefix("save_") {
+        return Token::Save(name.to_string());
+    }
+    if lower == "global_" || lower == "stop_" {
+        return Token::Reserved(lower);
+    }
+    if word.starts_with('_') {
+        return Token::Tag(normalize_tag(word));
+    }
+    if word == "." || word == "?" {
+        return Token::Value(None);
+    }
+
+    return Token::Value(Some(word.to_string()));
+}
+
+/**
+ * tokenize_line() splits a line into tokens, a quote only closes a quoted value if it is
+ * followed by white space, so 'O5'' and 'a dog's life' are single values
+ */
+fn tokenize_line(line: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = line.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+        if c == '#' {
+            break;
+        }
+
+        if c == '\'' || c == '"' {
+            let mut end = i + 1;
+            while end < chars.len()
+                && !(chars[end] == c && chars.get(end + 1).is_none_or(|n| n.is_whitespace()))
+            {
+                end += 1;
+            }
+            if end >= chars.len() {
+                return Err(format!("missing closing {} in quoted value", c));
+            }
+
+            tokens.push(Token::Value(Some(chars[i + 1..end].iter().collect())));
+            i = end + 1;
+            continue;
+        }
+
+        let start = i;
+        while i < chars.len() && !chars[i].is_whitespace() {
+            i += 1;
+        }
+        tokens.push(bare_token(&chars[start..i].iter().collect::<String>()));
+    }
+
+    return Ok(tokens);
+}
+
+/** Tokenizer - reads the tokens of a STAR file, with the line each one starts on */
+struct Tokenizer<R: BufRead> {
+    reader: LineReader<R>,
+    tokens: VecDeque<(Token, usize)>,
+}
+
+impl<R: BufRead> Tokenizer<R> {
+    fn new(reader: R) -> Tokenizer<R> {
+        Tokenizer {
+            reader: LineReader::new(reader, FORMAT),
+            tokens: VecDeque::new(),
+        }
+    }
+
+    /** fill() reads lines until there are tokens, or the input ends */
+    fn fill(&mut self) -> Result<(), IoError> {
+        while self.tokens.is_empty() {
+            let line = match self.reader.next_line()? {
+                Some(line) => line,
+                None => return Ok(()),
+            };
+            let line_number = self.reader.get_line_number();
+
+            // a text field runs from a line starting with ';' to the next such line
+            let line = match line.strip_prefix(';') {
+                Some(first) => {
+                    let mut text = vec![first.to_string()];
+                    let rest = loop {
+                        match self.reader.next_line()? {
+                            Some(line) if line.starts_with(';') => break line[1..].to_string(),
+                            Some(line) => text.push(line),
+                            None => {
+                                return Err(IoError::parse(
+                                    FORMAT,
+                                    line_number,
+                                    "text field is not closed by a line starting with ;"
+                                        .to_string(),
+                                ))
+                            }
+                        }
+                    };
+
+                    if text[0].trim().is_empty() {
+                        text.remove(0);
+                    }
+                    self.tokens
+                        .push_back((Token::Value(Some(text.join("\n"))), line_number));
+                    rest
+                }
+                None => line,
+            };
+
+            let tokens = tokenize_line(&line).map_err(|message| self.reader.error(message))?;
+            let line_number = self.reader.get_line_number();
+            self.tokens
+                .extend(tokens.into_iter().map(|token| (token, line_number)));
+        }
+
+        return Ok(());
+    }
+
+    fn next_token(&mut self) -> Result<Option<(Token, usize)>, IoError> {
+        self.fill()?;
+        return Ok(self.tokens.pop_front());
+    }
+
+    fn peek_token(&mut self) -> Result<Option<&Token>, IoError> {
+        self.fill()?;
+        return Ok(self.tokens.front().map(|(token, _)| token));
+    }
+}
+
+/** CifLoop - the rows of values of a loop_, with the line each row starts on */
+#[allow(dead_code)]
+#[derive(Clone, PartialEq, Debug)]
+pub struct CifLoop {
+    tags: Vec<String>,
+    rows: Vec<Vec<Option<String>>>,
+    lines: Vec<usize>,
+    line_number: usize,
+}
+
+#[allow(dead_code)]
+impl CifLoop {
+    /** get_tags() returns the normalised data names of the columns */
+    pub fn get_tags(&self) -> &[String] {
+        return &self.tags;
+    }
+
+    /** get_column() returns the column of a data name */
+    pub fn get_column(&self, tag: &str) -> Option<usize> {
+        let tag = normalize_tag(tag);
+        return self.tags.iter().position(|t| *t == tag);
+    }
+
+    /** get_rows() returns the rows, missing values are None */
+    pub fn get_rows(&self) -> &[Vec<Option<String>>] {
+        return &self.rows;
+    }
+
+    /** get_line_number() returns the line the row starts on, the loop_ line if there is no such row */
+    pub fn get_line_number(&self, row: usize) -> usize {
+        return self.lines.get(row).copied().unwrap_or(self.line_number);
+    }
+}
+
+/** CifBlock - the data items and loops of a data_ block */
+#[allow(dead_code)]
+#[derive(Clone, PartialEq, Debug)]
+pub struct CifBlock {
+    pub name: String,
+    items: HashMap<String, (Option<String>, usize)>,
+    loops: Vec<CifLoop>,
+}
+
+#[allow(dead_code)]
+impl CifBlock {
+    pub fn new(name: String) -> CifBlock {
+        CifBlock {
+            name: name,
+            items: HashMap::new(),
+            loops: Vec::new(),
+        }
+    }
+
+    /** get_value() returns the value of a single data item, None if missing or unknown */
+    pub fn get_value(&self, tag: &str) -> Option<&str> {
+        return self
+            .items
+            .get(&normalize_tag(tag))
+            .and_then(|(value, _)| value.as_deref());
+    }
+
+    /** get_loop() returns the loop with the given data name in it */
+    pub fn get_loop(&self, tag: &str) -> Option<&CifLoop> {
+        return self.loops.iter().find(|l| l.get_column(tag).is_some());
+    }
+
+    /** get_loops() returns all loops of the block */
+    pub fn get_loops(&self) -> &[CifLoop] {
+        return &self.loops;
+    }
+
+    /** get_number() returns a single data item as a number */
+    fn get_number(&self, tag: &str) -> Result<Option<f64>, IoError> {
+        match self.items.get(&normalize_tag(tag)) {
+            Some((Some(value), line_number)) => match parse_number(value) {
+                Some(number) => return Ok(Some(number)),
+                None => {
+                    return Err(IoError::parse(
+                        FORMAT,
+                        *line_number,
+                        format!("expected a number for {}, found \"{}\"", tag, value),
+                    ))
+                }
+            },
+            _ => return Ok(None),
+        }
+    }
+}
+
+/** BlockParser - groups the tokens of a STAR file into data blocks */
+struct BlockParser<R: BufRead> {
+    tokenizer: Tokenizer<R>,
+}
+
+impl<R: BufRead> BlockParser<R> {
+    /** read_block() reads the next data block, or returns None at the end of the file */
+    fn read_block(&mut self) -> Result<Option<CifBlock>, IoError> {
+        // anything before the first data_ is not part of a block
+        let mut block = loop {
+            match self.tokenizer.next_token()? {
+                Some((Token::Data(name), _)) => break CifBlock::new(name),
+                Some(_) => continue,
+                None => return Ok(None),
+            }
+        };
+
+        loop {
+            match self.tokenizer.peek_token()? {
+                None | Some(Token::Data(_)) => return Ok(Some(block)),
+                _ => {}
+            }
+
+            let (token, line_number) = self.tokenizer.next_token()?.unwrap();
+            let error = |message: String| IoError::parse(FORMAT, line_number, message);
+
+            match token {
+                Token::Tag(tag) => match self.tokenizer.next_token()? {
+                    Some((Token::Value(value), line_number)) => {
+                        block.items.insert(tag, (value, line_number));
+                    }
+                    _ => return Err(error(format!("missing value for {}", tag))),
+                },
+                Token::Loop => {
+                    let cif_loop = self.read_loop(line_number)?;
+                    block.loops.push(cif_loop);
+                }
+                Token::Value(_) => return Err(error("value without a data name".to_string())),
+                // save frames (used by dictionaries) are read as part of the block
+                Token::Save(_) | Token::Reserved(_) | Token::Data(_) => {}
+            }
+        }
+    }
+
+    fn read_loop(&mut self, line_number: usize) -> Result<CifLoop, IoError> {
+        let mut cif_loop = CifLoop {
+            tags: Vec::new(),
+            rows: Vec::new(),
+            lines: Vec::new(),
+            line_number: line_number,
+        };
+
+        while let Some(Token::Tag(_)) = self.tokenizer.peek_token()? {
+            if let Some((Token::Tag(tag), _)) = self.tokenizer.next_token()? {
+                cif_loop.tags.push(tag);
+            }
+        }
+        if cif_loop.tags.is_empty() {
+            return Err(IoError::parse(
+                FORMAT,
+                line_number,
+                "loop_ without data names".to_string(),
+            ));
+        }
+
+        let mut row = Vec::new();
+        let mut last_line = line_number;
+        while let Some(Token::Value(_)) = self.tokenizer.peek_token()? {
+            if let Some((Token::Value(value), line_number)) = self.tokenizer.next_token()? {
+                if row.is_empty() {
+                    cif_loop.lines.push(line_number);
+                }
+                row.push(value);
+                last_line = line_number;
+            }
+
+            if row.len() == cif_loop.tags.len() {
+                cif_loop.rows.push(std::mem::take(&mut row));
+            }
+        }
+
+        if !row.is_empty() {
+            return Err(IoError::parse(
+                FORMAT,
+                last_line,
+                format!(
+                    "loop with {} data names ends with a row of {} values",
+                    cif_loop.tags.len(),
+                    row.len()
+                ),
+            ));
+        }
+
+        return Ok(cif_loop);
+    }
+}
+
+/** read_blocks() reads all data blocks of a CIF (or other STAR) file */
+#[allow(dead_code)]
+pub fn read_blocks<R: BufRead>(reader: R) -> Result<Vec<CifBlock>, IoError> {
+    let mut parser = BlockParser {
+        tokenizer: Tokenizer::new(reader),
+    };
+
+    let mut blocks = Vec::new();
+    while let Some(block) = parser.read_block()? {
+        blocks.push(block);
+    }
+
+    return Ok(blocks);
+}
+
+/** parse_number() reads a number, dropping its standard uncertainty, e.g. 1.542(3) */
+fn parse_number(text: &str) -> Option<f64> {
+    let number = match text.find('(') {
+        Some(position) if text.ends_with(')') => &text[..position],
+        _ => text,
+    };
+
+    return number.parse().ok();
+}
+
+/**
+ * guess_element() returns the element of a type symbol ("Fe3+", "CL") or site label ("Ca1",
+ * "C12a"), a label only names a two letter element if the second letter is lower case
+ */
+fn guess_element(text: &str, is_label: bool) -> String {
+    let letters: Vec<char> = text
+        .chars()
+        .take_while(|c| c.is_ascii_alphabetic())
+        .collect();
+
+    if letters.len() >= 2 && (!is_label || letters[1].is_ascii_lowercase()) {
+        let two = format!("{}{}", letters[0], letters[1]);
+        if let Some(symbol) =
+            atomprop::get_atomic_number(&two).and_then(atomprop::get_element_symbol)
+        {
+            return symbol.to_string();
+        }
+    }
+
+    match letters.first() {
+        Some(first) => {
+            let one = first.to_ascii_uppercase().to_string();
+            match atomprop::get_atomic_number(&one).and_then(atomprop::get_element_symbol) {
+                Some(symbol) => return symbol.to_string(),
+                None => return one,
+            }
+        }
+        None => return "X".to_string(),
+    }
+}
+
+/** get_cell() returns the lattice from the cell parameters, if the lengths are given */
+fn get_cell(block: &CifBlock) -> Result<Option<Lattice>, IoError> {
+    let mut lengths = Vec::new();
+    for tag in ["_cell_length_a", "_cell_length_b", "_cell_length_c"] {
+        match block.get_number(tag)? {
+            Some(length) => lengths.push(length),
+            None => return Ok(None),
+        }
+    }
+
+    let mut angles = Vec::new();
+    for tag in ["_cell_angle_alpha", "_cell_angle_beta", "_cell_angle_gamma"] {
+        angles.push(block.get_number(tag)?.unwrap_or(90.0));
+    }
+
+    return Ok(Some(Lattice::from_parameters(
+        lengths[0], lengths[1], lengths[2], angles[0], angles[1], angles[2],
+    )));
+}
+
+/** get_space_group() returns the Hermann-Mauguin symbol of the space group, DDL1 or DDL2 */
+fn get_space_group(block: &CifBlock) -> Option<&str> {
+    return block
+        .get_value("_space_group_name_H-M_alt")
+        .or_else(|| block.get_value("_symmetry_space_group_name_H-M"));
+}
+
+/**
+ * SymmetryOperation - a symmetry operation of a space group, acting on fractional
+ * coordinates as rotation * x + translation
+ */
+#[allow(dead_code)]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct SymmetryOperation {
+    pub rotation: [[f64; 3]; 3],
+    pub translation: [f64; 3],
+}
+
+#[allow(dead_code)]
+impl SymmetryOperation {
+    /** parse() reads an operation written as in CIF files, e.g. "-x+1/2, y, 1/2-z" */
+    pub fn parse(text: &str) -> Result<SymmetryOperation, String> {
+        let components: Vec<&str> = text.split(',').collect();
+        if components.len() != 3 {
+            return Err(format!(
+                "expected a symmetry operation such as x,y,z, found \"{}\"",
+                text
+            ));
+        }
+
+        let mut operation = SymmetryOperation {
+            rotation: [[0.0; 3]; 3],
+            translation: [0.0; 3],
+        };
+
+        for (row, component) in components.iter().enumerate() {
+            let invalid = || format!("invalid symmetry operation \"{}\"", text);
+            let chars: Vec<char> = component.chars().filter(|c| !c.is_whitespace()).collect();
+            if chars.is_empty() {
+                return Err(invalid());
+            }
+
+            let mut i = 0;
+            while i < chars.len() {
+                let mut sign = 1.0;
+                if chars[i] == '+' || chars[i] == '-' {
+                    sign = if chars[i] == '-' { -1.0 } else { 1.0 };
+                    i += 1;
+                }
+
+                let start = i;
+                while i < chars.len()
+                    && (chars[i].is_ascii_digit() || chars[i] == '.' || chars[i] == '/')
+                {
+                    i += 1;
+                }
+                let number: String = chars[start..i].iter().collect();
+                let value = match number.split_once('/') {
+                    Some((numerator, denominator)) => {
+                        let numerator: f64 = numerator.parse().map_err(|_| invalid())?;
+                        let denominator: f64 = denominator.parse().map_err(|_| invalid())?;
+                        numerator / denominator
+                    }
+                    None if number.is_empty() => 1.0,
+                    None => number.parse().map_err(|_| invalid())?,
+                };
+
+                // a number is a translation, unless a coordinate follows (2x, 0.5*y)
+                if i < chars.len() && chars[i] == '*' {
+                    i += 1;
+                }
+                match chars.get(i).map(|c| c.to_ascii_lowercase()) {
+                    Some(axis @ ('x' | 'y' | 'z')) => {
+                        operation.rotation[row][(axis as u8 - b'x') as usize] += sign * value;
+                        i += 1;
+                    }
+                    _ if !number.is_empty() => operation.translation[row] += sign * value,
+                    _ => return Err(invalid()),
+                }
+            }
+        }
+
+        return Ok(operation);
+    }
+
+    /** apply() returns the fractional coordinates moved by the operation */
+    pub fn apply(&self, fractional: [f64; 3]) -> [f64; 3] {
+        let mut moved = self.translation;
+        for (value, rotation) in moved.iter_mut().zip(self.rotation.iter()) {
+            *value += rotation[0] * fractional[0]
+                + rotation[1] * fractional[1]
+                + rotation[2] * fractional[2];
+        }
+        return moved;
+    }
+}
+
+/** get_symmetry_operations() reads the symmetry operations loop (DDL1 or DDL2), with their text */
+fn get_symmetry_operations(block: &CifBlock) -> Result<Vec<(String, SymmetryOperation)>, IoError> {
+    let mut operations = Vec::new();
+
+    for tag in [
+        "_space_group_symop_operation_xyz",
+        "_symmetry_equiv_pos_as_xyz",
+    ] {
+        if let Some(cif_loop) = block.get_loop(tag) {
+            let column = cif_loop.get_column(tag).unwrap();
+            for (row, values) in cif_loop.get_rows().iter().enumerate() {
+                if let Some(text) = &values[column] {
+                    let operation = SymmetryOperation::parse(text).map_err(|message| {
+                        IoError::parse(FORMAT, cif_loop.get_line_number(row), message)
+                    })?;
+                    operations.push((text.to_string(), operation));
+                }
+            }
+            break;
+        }
+    }
+
+    return Ok(operations);
+}
+
+/** CifReader - reads the molecules of the data blocks of a CIF file one at a time */
+#[allow(dead_code)]
+pub struct CifReader<R: BufRead> {
+    parser: BlockParser<R>,
+    fill_unit_cell: bool,
+    failed: bool,
+}
+
+#[allow(dead_code)]
+impl<R: BufRead> CifReader<R> {
+    pub fn new(reader: R) -> CifReader<R> {
+        CifReader {
+            parser: BlockParser {
+                tokenizer: Tokenizer::new(reader),
+            },
+            fill_unit_cell: false,
+            failed: false,
+        }
+    }
+
+    /**
+     * fill_unit_cell() sets whether the symmetry operations are applied to the atoms of a
+     * small molecule CIF, giving all atoms of the unit cell instead of the asymmetric unit
+     */
+    pub fn fill_unit_cell(mut self, fill_unit_cell: bool) -> CifReader<R> {
+        self.fill_unit_cell = fill_unit_cell;
+        return self;
+    }
+
+    /**
+     * read_molecule() reads the next data block with atoms, or returns None at the end of
+     * the file, blocks without an _atom_site loop are skipped
+     */
+    pub fn read_molecule(&mut self) -> Result<Option<Molecule>, IoError> {
+        while let Some(block) = self.parser.read_block()? {
+            if let Some(mol) = build_molecule(&block, self.fill_unit_cell)? {
+                return Ok(Some(mol));
+            }
+        }
+
+        return Ok(None);
+    }
+}
+
+impl<R: BufRead> Iterator for CifReader<R> {
+    type Item = Result<Molecule, IoError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+
+        let molecule = self.read_molecule();
+        self.failed = molecule.is_err();
+        return molecule.transpose();
+    }
+}
+
+/** read() reads the first data block with atoms of a CIF or mmCIF file */
+#[allow(dead_code)]
+pub fn read<R: BufRead>(reader: R) -> Result<Molecule, IoError> {
+    match CifReader::new(reader).read_molecule()? {
+        Some(mol) => return Ok(mol),
+        None => {
+            return Err(IoError::parse(
+                FORMAT,
+                0,
+                "no data block with an _atom_site loop".to_string(),
+            ))
+        }
+    }
+}
+
+/** build_molecule() makes a molecule of a block, None if it has no atoms */
+fn build_molecule(block: &CifBlock, fill_unit_cell: bool) -> Result<Option<Molecule>, IoError> {
+    let atom_site = match block
+        .get_loop("_atom_site_label")
+        .or_else(|| block.get_loop("_atom_site_id"))
+        .or_else(|| block.get_loop("_atom_site_fract_x"))
+        .or_else(|| block.get_loop("_atom_site_cartn_x"))
+    {
+        Some(atom_site) => atom_site,
+        None => return Ok(None),
+    };
+
+    if atom_site.get_column("_atom_site_label_atom_id").is_some()
+        || atom_site.get_column("_atom_site_auth_atom_id").is_some()
+    {
+        return mmcif::build_molecule(block, atom_site).map(Some);
+    }
+
+    return build_crystal(block, atom_site, fill_unit_cell).map(Some);
+}
+
+/** build_crystal() makes the molecule of a small molecule CIF */
+fn build_crystal(
+    block: &CifBlock,
+    atom_site: &CifLoop,
+    fill_unit_cell: bool,
+) -> Result<Molecule, IoError> {
+    let mut mol = Molecule::new(block.name.to_string(), "".to_string());
+    if let Some(name) = block.get_value("_chemical_name_systematic") {
+        mol.remark = name.trim().to_string();
+    }
+
+    let lattice = get_cell(block)?;
+    mol.set_lattice(lattice);
+    if let Some(space_group) = get_space_group(block) {
+        mol.set_property("space_group", Property::Text(space_group.to_string()));
+    }
+
+    let operations = get_symmetry_operations(block)?;
+    if !operations.is_empty() {
+        let texts: Vec<&str> = operations.iter().map(|(text, _)| text.as_str()).collect();
+        mol.set_property("symmetry_operations", Property::Text(texts.join("; ")));
+    }
+
+    let column = |tag: &str| atom_site.get_column(tag);
+    let (position_columns, fractional) = match (
+        column("_atom_site_fract_x"),
+        column("_atom_site_fract_y"),
+        column("_atom_site_fract_z"),
+    ) {
+        (Some(x), Some(y), Some(z)) => ([x, y, z], true),
+        _ => match (
+            column("_atom_site_cartn_x"),
+            column("_atom_site_cartn_y"),
+            column("_atom_site_cartn_z"),
+        ) {
+            (Some(x), Some(y), Some(z)) => ([x, y, z], false),
+            _ => {
+                return Err(IoError::parse(
+                    FORMAT,
+                    atom_site.get_line_number(0),
+                    "_atom_site loop without coordinates".to_string(),
+                ))
+            }
+        },
+    };
+    if fractional && lattice.is_none() {
+        return Err(IoError::parse(
+            FORMAT,
+            atom_site.get_line_number(0),
+            "fractional coordinates without cell lengths".to_string(),
+        ));
+    }
+
+    let label_column = column("_atom_site_label");
+    let symbol_column = column("_atom_site_type_symbol");
+    let occupancy_column = column("_atom_site_occupancy");
+
+    // the sites, as fractional coordinates if there is a cell
+    let mut sites: Vec<([f64; 3], String, String, f64)> = Vec::new();
+    for (row, values) in atom_site.get_rows().iter().enumerate() {
+        let error =
+            |message: String| IoError::parse(FORMAT, atom_site.get_line_number(row), message);
+
+        let mut position = [0.0; 3];
+        for (k, column) in position_columns.iter().enumerate() {
+            position[k] = match values[*column]
+                .as_deref()
+                .map(|text| (text, parse_number(text)))
+            {
+                Some((_, Some(number))) => number,
+                Some((text, None)) => {
+                    return Err(error(format!("expected a coordinate, found \"{}\"", text)))
+                }
+                None => return Err(error("missing coordinate".to_string())),
+            };
+        }
+        if let (false, Some(lattice)) = (fractional, lattice.as_ref()) {
+            position = lattice.to_fractional(position);
+        }
+
+        let label = label_column
+            .and_then(|c| values[c].clone())
+            .unwrap_or_default();
+        let symbol = match symbol_column.and_then(|c| values[c].as_deref()) {
+            Some(symbol) => guess_element(symbol, false),
+            None => guess_element(&label, true),
+        };
+        let occupancy = occupancy_column
+            .and_then(|c| values[c].as_deref())
+            .and_then(parse_number)
+            .unwrap_or(1.0);
+
+        sites.push((position, label, symbol, occupancy));
+    }
+
+    if fill_unit_cell && lattice.is_some() && !operations.is_empty() {
+        let operations: Vec<SymmetryOperation> = operations.iter().map(|(_, op)| *op).collect();
+        sites = fill_sites(&sites, &operations);
+    }
+
+    let mut atom_names = Vec::new();
+    let mut occupancies = Vec::new();
+    for (position, label, symbol, occupancy) in sites {
+        let center = match lattice.as_ref() {
+            Some(lattice) => lattice.to_cartesian(position),
+            None => position,
+        };
+
+        mol.add_atom(Atom {
+            center: Point {
+                x: center[0] as f32,
+                y: center[1] as f32,
+                z: center[2] as f32,
+            },
+            charge: 0.0,
+            symbol: symbol,
+            remark: "".to_string(),
+        });
+        atom_names.push(label);
+        occupancies.push(occupancy);
+    }
+
+    mol.set_atom_property("atom_name", AtomProperty::Texts(atom_names));
+    mol.set_atom_property("occupancy", AtomProperty::Reals(occupancies));
+
+    return Ok(mol);
+}
+
+/**
+ * fill_sites() applies the symmetry operations to each site, wrapped into the unit cell,
+ * dropping the images that fall on a site already there (atoms on special positions)
+ */
+fn fill_sites(
+    sites: &[([f64; 3], String, String, f64)],
+    operations: &[SymmetryOperation],
+) -> Vec<([f64; 3], String, String, f64)> {
+    let mut filled: Vec<([f64; 3], String, String, f64)> = Vec::new();
+
+    for (position, label, symbol, occupancy) in sites.iter() {
+        for operation in operations.iter() {
+            let mut image = operation.apply(*position);
+            for value in image.iter_mut() {
+                *value -= value.floor();
+                if *value >= 1.0 - SITE_TOLERANCE {
+                    *value = 0.0;
+                }
+            }
+
+            let is_duplicate = filled.iter().any(|(other, _, _, _)| {
+                (0..3).all(|k| {
+                    let difference = image[k] - other[k];
+                    (difference - difference.round()).abs() < SITE_TOLERANCE
+                })
+            });
+            if !is_duplicate {
+                filled.push((image, label.to_string(), symbol.to_string(), *occupancy));
+            }
+        }
+    }
+
+    return filled;
+}
+
+/** Unit tests for the above module */
+#[cfg(test)]
+mod tests {
+    use super::CifReader;
+    use super::SymmetryOperation;
+    use crate::atom::AtomOperations;
+    use crate::property::AtomProperty;
+    use crate::property::Property;
+
+    const ROCK_SALT: &str = "\
+#\\#CIF_1.1
+data_NaCl
+_chemical_name_systematic 'sodium chloride'
+_cell_length_a 5.6402(3)
+_cell_length_b 5.6402(3)
+_cell_length_c 5.6402(3)
+_cell_angle_alpha 90
+_cell_angle_beta 90
+_cell_angle_gamma 90
+_symmetry_space_group_name_H-M 'F m -3 m'
+_publ_section_title
+;
+ The structure of rock salt
+;
+loop_
+_symmetry_equiv_pos_as_xyz
+x,y,z
+x,1/2+y,1/2+z
+1/2+x,y,1/2+z
+1/2+x,1/2+y,z
+-x,-y,-z
+loop_
+_atom_site_label
+_atom_site_type_symbol
+_atom_site_fract_x
+_atom_site_fract_y
+_atom_site_fract_z
+_atom_site_occupancy
+Na1 Na+ 0 0 0 1
+Cl1 Cl- 0.5 0.5 0.5 1.0(0)
+";
+
+    #[test]
+    fn cif_blocks() {
+        let text = "data_first\n_a 'it''s' _b \"x y\" # comment\nloop_ _c _d 1 . 2 ?\n\
+                    data_second\n_text\n;\nline 1\nline 2\n;\n";
+        let blocks = super::read_blocks(text.as_bytes()).unwrap();
+
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[0].name, "first".to_string());
+        assert_eq!(blocks[0].get_value("_A"), Some("it''s"));
+        assert_eq!(blocks[0].get_value("_b"), Some("x y"));
+
+        let cif_loop = blocks[0].get_loop("_d").unwrap();
+        assert_eq!(cif_loop.get_tags(), ["_c".to_string(), "_d".to_string()]);
+        assert_eq!(
+            cif_loop.get_rows(),
+            [
+                vec![Some("1".to_string()), None],
+                vec![Some("2".to_string()), None]
+            ]
+        );
+        assert_eq!(blocks[1].get_value("_text"), Some("line 1\nline 2"));
+    }
+
+    #[test]
+    fn cif_read() {
+        let mut mol = super::read(ROCK_SALT.as_bytes()).unwrap();
+
+        assert_eq!(mol.name, "NaCl".to_string());
+        assert_eq!(mol.remark, "sodium chloride".to_string());
+        assert_eq!(mol.get_number_of_atoms(), 2);
+        assert_eq!(mol.get_atom(0).symbol, "Na".to_string());
+        assert_eq!(mol.get_atom(1).symbol, "Cl".to_string());
+        assert!((mol.get_atom(1).center.x - 2.8201).abs() < 1.0e-4);
+        assert_eq!(
+            mol.get_property("space_group"),
+            Some(&Property::Text("F m -3 m".to_string()))
+        );
+        assert_eq!(
+            mol.get_atom_property("atom_name"),
+            Some(&AtomProperty::Texts(vec![
+                "Na1".to_string(),
+                "Cl1".to_string()
+            ]))
+        );
+
+        let parameters = mol.get_lattice().unwrap().get_parameters();
+        assert!((parameters[0] - 5.6402).abs() < 1.0e-9);
+
+        // Na at the origin and Cl at the centre each have four images in the cell
+        let mol = CifReader::new(ROCK_SALT.as_bytes())
+            .fill_unit_cell(true)
+            .next()
+            .unwrap()
+            .unwrap();
+        assert_eq!(mol.get_atoms().len(), 8);
+        assert_eq!(
+            mol.get_atoms()
+                .iter()
+                .filter(|at| at.symbol == "Cl")
+                .count(),
+            4
+        );
+    }
+
+    #[test]
+    fn cif_symmetry_operation() {
+        let operation = SymmetryOperation::parse("-x+1/2, y-x, 0.25-Z").unwrap();
+        assert_eq!(
+            operation.rotation,
+            [[-1.0, 0.0, 0.0], [-1.0, 1.0, 0.0], [0.0, 0.0, -1.0]]
+        );
+        assert_eq!(operation.translation, [0.5, 0.0, 0.25]);
+        let moved = operation.apply([0.1, 0.2, 0.3]);
+        for (value, expected) in moved.iter().zip([0.4, 0.1, -0.05]) {
+            assert!((value - expected).abs() < 1.0e-12);
+        }
+
+        assert!(SymmetryOperation::parse("x,y").is_err());
+        assert!(SymmetryOperation::parse("x,y,w").is_err());
+    }
+
+    #[test]
+    fn cif_read_errors() {
+        let text = ROCK_SALT.replace("Cl1 Cl- 0.5", "Cl1 Cl- half");
+        assert_eq!(
+            super::read(text.as_bytes()).unwrap_err().to_string(),
+            "cif: line 30: expected a coordinate, found \"half\""
+        );
+
+        let text = ROCK_SALT.replace("1.0(0)\n", "");
+        assert_eq!(
+            super::read(text.as_bytes()).unwrap_err().to_string(),
+            "cif: line 30: loop with 6 data names ends with a row of 5 values"
+        );
+
+        let text = "data_x\n_title\n;\nnever closed\n";
+        assert_eq!(
+            super::read(text.as_bytes()).unwrap_err().to_string(),
+            "cif: line 3: text field is not closed by a line starting with ;"
+        );
+
+        assert_eq!(
+            super::read("data_x\n_cell_length_a 5\n".as_bytes())
+                .unwrap_err()
+                .to_string(),
+            "cif: no data block with an _atom_site loop"
+        );
+
+        // an _atom_site loop without rows is valid, the errors about it name the loop_ line
+        let text = "data_x\n_cell_length_a 5\n_cell_length_b 5\n_cell_length_c 5\n\
+                    loop_\n_atom_site_label\n_atom_site_fract_x\n_atom_site_fract_y\n\
+                    _atom_site_fract_z\n";
+        assert_eq!(super::read(text.as_bytes()).unwrap().get_atoms().len(), 0);
+        assert_eq!(
+            super::read("data_x\nloop_\n_atom_site_label\n".as_bytes())
+                .unwrap_err()
+                .to_string(),
+            "cif: line 2: _atom_site loop without coordinates"
+        );
+        let text = "data_x\nloop_\n_atom_site_label\n_atom_site_fract_x\n\
+                    _atom_site_fract_y\n_atom_site_fract_z\n";
+        assert_eq!(
+            super::read(text.as_bytes()).unwrap_err().to_string(),
+            "cif: line 2: fractional coordinates without cell lengths"
+        );
+    }
+}
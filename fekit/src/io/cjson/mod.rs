@@ -0,0 +1,421 @@
+/**
+ * Author: V. Ganesh
+ * License: MIT
+ */
+
+/**
+ * cjson module, reads and writes the Chemical JSON format used by Avogadro.
+ *
+ * Atom charges are written as the "fekit" set of partial charges. When reading, they come
+ * from the "fekit" set, else the "Mulliken" set, else the only set in the file, else the
+ * formal charges. Every other set of partial charges is kept as the "<name>_charges" atom
+ * property, and written back as the set of that name. Bond orders are 1, 2 or 3, as in
+ * Avogadro: aromatic, coordinate and weak bonds are written as single bonds. The Molecule
+ * remark is kept as a property.
+ */
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::io::Write;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::atom::Atom;
+use crate::atom::AtomOperations;
+use crate::atomprop;
+use crate::bond::BondType;
+use crate::io::IoError;
+use crate::molecule::Molecule;
+use crate::point::Point;
+use crate::property::AtomProperty;
+
+const FORMAT: &str = "cjson";
+
+/** name of the set of partial charges holding the atom charges */
+const CHARGE_SET: &str = "fekit";
+
+/** sets of partial charges read as the atom charges, in order of preference */
+const PREFERRED_CHARGE_SETS: [&str; 2] = [CHARGE_SET, "Mulliken"];
+
+/** suffix of the atom properties holding the other sets of partial charges */
+const CHARGES_SUFFIX: &str = "_charges";
+
+/** symbol used for atoms with atomic number 0 (dummy atoms) */
+const DUMMY_SYMBOL: &str = "X";
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct ChemicalJson {
+    #[serde(alias = "chemical json", default)]
+    chemical_json: u32,
+    #[serde(default)]
+    name: String,
+    atoms: Atoms,
+    #[serde(default, skip_serializing_if = "Bonds::is_empty")]
+    bonds: Bonds,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    partial_charges: BTreeMap<String, Vec<f64>>,
+    #[serde(default, skip_serializing_if = "serde_json::Map::is_empty")]
+    properties: serde_json::Map<String, serde_json::Value>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct Atoms {
+    elements: Elements,
+    #[serde(default)]
+    coords: Coords,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    formal_charges: Vec<i32>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct Elements {
+    number: Vec<u8>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct Coords {
+    #[serde(rename = "3d", default, skip_serializing_if = "Vec::is_empty")]
+    coords_3d: Vec<f64>,
+    #[serde(rename = "2d", default, skip_serializing_if = "Vec::is_empty")]
+    coords_2d: Vec<f64>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct Bonds {
+    connections: Connections,
+    #[serde(default)]
+    order: Vec<u8>,
+}
+
+impl Bonds {
+    fn is_empty(&self) -> bool {
+        return self.connections.index.is_empty();
+    }
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct Connections {
+    index: Vec<usize>,
+}
+
+fn parse_error(message: String) -> IoError {
+    return IoError::parse(FORMAT, 0, message);
+}
+
+/** read() reads a molecule in Chemical JSON format */
+#[allow(dead_code)]
+pub fn read<R: Read>(reader: R) -> Result<Molecule, IoError> {
+    let cjson: ChemicalJson = serde_json::from_reader(reader).map_err(|err| {
+        if err.is_io() {
+            return IoError::Io(err.into());
+        }
+
+        return IoError::parse(FORMAT, err.line(), err.to_string());
+    })?;
+
+    let remark = match cjson.properties.get("remark") {
+        Some(serde_json::Value::String(remark)) => remark.to_string(),
+        _ => "".to_string(),
+    };
+    let mut mol = Molecule::new(cjson.name, remark);
+
+    let numbers = &cjson.atoms.elements.number;
+    let (coords, dimension) = if !cjson.atoms.coords.coords_3d.is_empty() {
+        (&cjson.atoms.coords.coords_3d, 3)
+    } else {
+        (&cjson.atoms.coords.coords_2d, 2)
+    };
+
+    if coords.len() != dimension * numbers.len() {
+        return Err(parse_error(format!(
+            "{} atoms need {} {}d coordinates, found {}",
+            numbers.len(),
+            dimension * numbers.len(),
+            dimension,
+            coords.len()
+        )));
+    }
+
+    let charge_set = PREFERRED_CHARGE_SETS
+        .iter()
+        .find(|name| cjson.partial_charges.contains_key(**name))
+        .map(|name| name.to_string())
+        .or_else(|| match cjson.partial_charges.len() {
+            1 => cjson.partial_charges.keys().next().cloned(),
+            _ => None,
+        });
+    for (name, charges) in cjson.partial_charges.iter() {
+        if charges.len() != numbers.len() {
+            return Err(parse_error(format!(
+                "{} atoms but {} {} charges",
+                numbers.len(),
+                charges.len(),
+                name
+            )));
+        }
+    }
+
+    let charges = match charge_set.as_ref() {
+        Some(name) => cjson.partial_charges[name].to_vec(),
+        None => cjson
+            .atoms
+            .formal_charges
+            .iter()
+            .map(|c| *c as f64)
+            .collect(),
+    };
+
+    if !charges.is_empty() && charges.len() != numbers.len() {
+        return Err(parse_error(format!(
+            "{} atoms but {} charges",
+            numbers.len(),
+            charges.len()
+        )));
+    }
+
+    for (idx, number) in numbers.iter().enumerate() {
+        let symbol = atomprop::get_element_symbol(*number).unwrap_or(DUMMY_SYMBOL);
+
+        mol.add_atom(Atom {
+            center: Point {
+                x: coords[dimension * idx] as f32,
+                y: coords[dimension * idx + 1] as f32,
+                z: if dimension == 3 {
+                    coords[dimension * idx + 2] as f32
+                } else {
+                    0.0
+                },
+            },
+            charge: charges.get(idx).copied().unwrap_or(0.0) as f32,
+            symbol: symbol.to_string(),
+            remark: "".to_string(),
+        });
+    }
+
+    let index = &cjson.bonds.connections.index;
+    let order = &cjson.bonds.order;
+
+    if !index.len().is_multiple_of(2) {
+        return Err(parse_error(
+            "bond connections must come in pairs of atom indices".to_string(),
+        ));
+    }
+
+    for bond_idx in 0..index.len() / 2 {
+        let (atom_1_idx, atom_2_idx) = (index[2 * bond_idx], index[2 * bond_idx + 1]);
+
+        if atom_1_idx >= numbers.len() || atom_2_idx >= numbers.len() {
+            return Err(parse_error(format!(
+                "bond {} refers to a missing atom",
+                bond_idx
+            )));
+        }
+
+        let bond_type = match order.get(bond_idx).copied().unwrap_or(1) {
+            1 => BondType::SINGLE,
+            2 => BondType::DOUBLE,
+            3 => BondType::TRIPLE,
+            code => {
+                return Err(parse_error(format!(
+                    "unknown order {} of bond {}",
+                    code, bond_idx
+                )))
+            }
+        };
+
+        mol.add_bond(atom_1_idx, atom_2_idx, bond_type);
+    }
+
+    for (name, charges) in cjson.partial_charges.iter() {
+        if Some(name) != charge_set.as_ref() {
+            let property = format!("{}{}", name, CHARGES_SUFFIX);
+            mol.set_atom_property(&property, AtomProperty::Reals(charges.to_vec()));
+        }
+    }
+
+    return Ok(mol);
+}
+
+/** write() writes a molecule in Chemical JSON format */
+#[allow(dead_code)]
+pub fn write<W: Write>(writer: W, mol: &Molecule) -> Result<(), IoError> {
+    let mut cjson = ChemicalJson {
+        chemical_json: 1,
+        name: mol.name.to_string(),
+        ..Default::default()
+    };
+
+    for atom in mol.get_atoms().iter() {
+        let number = atomprop::get_atomic_number(&atom.symbol).unwrap_or(0);
+
+        cjson.atoms.elements.number.push(number);
+        cjson.atoms.coords.coords_3d.extend([
+            atom.center.x as f64,
+            atom.center.y as f64,
+            atom.center.z as f64,
+        ]);
+    }
+
+    if mol.get_atoms().iter().any(|at| at.charge != 0.0) {
+        cjson.partial_charges.insert(
+            CHARGE_SET.to_string(),
+            mol.get_atoms().iter().map(|at| at.charge as f64).collect(),
+        );
+    }
+    for (name, values) in mol.get_atom_properties().iter() {
+        match (name.strip_suffix(CHARGES_SUFFIX), values) {
+            (Some(set), AtomProperty::Reals(charges)) if !set.is_empty() && set != CHARGE_SET => {
+                cjson
+                    .partial_charges
+                    .insert(set.to_string(), charges.to_vec());
+            }
+            _ => {}
+        }
+    }
+
+    for bond in mol.get_bonds().iter() {
+        cjson.bonds.connections.index.push(bond.atom_1_idx);
+        cjson.bonds.connections.index.push(bond.atom_2_idx);
+        cjson.bonds.order.push(match bond.bond_type {
+            BondType::DOUBLE => 2,
+            BondType::TRIPLE => 3,
+            _ => 1,
+        });
+    }
+
+    if !mol.remark.is_empty() {
+        cjson.properties.insert(
+            "remark".to_string(),
+            serde_json::Value::String(mol.remark.to_string()),
+        );
+    }
+
+    serde_json::to_writer_pretty(writer, &cjson).map_err(|err| IoError::Io(err.into()))?;
+    return Ok(());
+}
+
+/** Unit tests for the above module */
+#[cfg(test)]
+mod tests {
+    use crate::atom::Atom;
+    use crate::atom::AtomOperations;
+    use crate::bond::BondType;
+    use crate::io::IoError;
+    use crate::molecule::Molecule;
+    use crate::point::Point;
+    use crate::property::AtomProperty;
+
+    #[test]
+    fn cjson_round_trip() {
+        let mut mol = Molecule::new("H2O".to_string(), "Water Molecule".to_string());
+        for (symbol, x, z, charge) in [
+            ("O", 0.0, 0.0, -0.8),
+            ("H", 0.758602, 0.504284, 0.4),
+            ("H", 0.758602, -0.504284, 0.4),
+        ] {
+            mol.add_atom(Atom {
+                center: Point { x: x, y: 0.0, z: z },
+                charge: charge,
+                symbol: symbol.to_string(),
+                remark: "".to_string(),
+            });
+        }
+        mol.add_bond(0, 1, BondType::SINGLE);
+        mol.add_bond(0, 2, BondType::DOUBLE);
+        let mulliken = AtomProperty::Reals(vec![-0.7, 0.35, 0.35]);
+        mol.set_atom_property("Mulliken_charges", mulliken.clone());
+
+        let mut buffer = Vec::new();
+        super::write(&mut buffer, &mol).unwrap();
+
+        let read_mol = super::read(buffer.as_slice()).unwrap();
+        assert_eq!(read_mol.name, "H2O".to_string());
+        assert_eq!(read_mol.remark, "Water Molecule".to_string());
+        assert_eq!(read_mol.get_atoms(), mol.get_atoms());
+        assert_eq!(read_mol.get_bonds(), mol.get_bonds());
+        assert_eq!(
+            read_mol.get_atom_property("Mulliken_charges"),
+            Some(&mulliken)
+        );
+
+        // only the orders 1, 2 and 3 are written
+        mol.add_bond(1, 2, BondType::AROMATIC);
+        mol.set_bond_type(0, 1, BondType::WEAK);
+        let mut buffer = Vec::new();
+        super::write(&mut buffer, &mol).unwrap();
+        let mut read_mol = super::read(buffer.as_slice()).unwrap();
+        assert_eq!(read_mol.get_bond_type(0, 1), BondType::SINGLE);
+        assert_eq!(read_mol.get_bond_type(0, 2), BondType::DOUBLE);
+        assert_eq!(read_mol.get_bond_type(1, 2), BondType::SINGLE);
+    }
+
+    #[test]
+    fn cjson_read_avogadro() {
+        let text = r#"{
+  "chemical json": 0,
+  "name": "ethene",
+  "atoms": {
+    "elements": { "number": [ 6, 6 ] },
+    "coords": { "3d": [ 0.0, 0.0, 0.0, 1.33, 0.0, 0.0 ] },
+    "formalCharges": [ 0, -1 ]
+  },
+  "bonds": {
+    "connections": { "index": [ 0, 1 ] },
+    "order": [ 2 ]
+  }
+}"#;
+
+        let mut mol = super::read(text.as_bytes()).unwrap();
+        assert_eq!(mol.get_number_of_atoms(), 2);
+        assert_eq!(mol.get_atom(1).symbol, "C".to_string());
+        assert_eq!(mol.get_atom(1).center.x, 1.33);
+        assert_eq!(mol.get_atom(1).charge, -1.0);
+        assert_eq!(mol.get_bond_type(0, 1), BondType::DOUBLE);
+
+        // the charges come from the Mulliken set, the others are kept
+        let text = r#"{
+  "atoms": {
+    "elements": { "number": [ 8, 1, 1 ] },
+    "coords": { "3d": [ 0, 0, 0, 0.76, 0, 0.5, -0.76, 0, 0.5 ] }
+  },
+  "partialCharges": {
+    "GFN2": [ -0.56, 0.28, 0.28 ],
+    "Mulliken": [ -0.8, 0.4, 0.4 ]
+  }
+}"#;
+        let mut mol = super::read(text.as_bytes()).unwrap();
+        assert_eq!(mol.get_atom(0).charge, -0.8);
+        assert_eq!(
+            mol.get_atom_property("GFN2_charges"),
+            Some(&AtomProperty::Reals(vec![-0.56, 0.28, 0.28]))
+        );
+        assert!(mol.get_atom_property("Mulliken_charges").is_none());
+    }
+
+    #[test]
+    fn cjson_read_errors() {
+        let text = "{\n  \"atoms\": {\n    \"elements\": { \"number\": [ 6, ] }\n  }\n}";
+        match super::read(text.as_bytes()) {
+            Err(IoError::Parse { line_number, .. }) => assert_eq!(line_number, 3),
+            _ => panic!("expected a parse error"),
+        }
+
+        let text =
+            r#"{ "atoms": { "elements": { "number": [ 6 ] }, "coords": { "3d": [ 0.0 ] } } }"#;
+        assert!(super::read(text.as_bytes()).is_err());
+
+        let text = r#"{ "atoms": { "elements": { "number": [ 6 ] }, "coords": { "3d": [ 0, 0, 0 ] } },
+            "bonds": { "connections": { "index": [ 0, 1 ] } } }"#;
+        assert!(super::read(text.as_bytes()).is_err());
+
+        let text = r#"{ "atoms": { "elements": { "number": [ 6, 6 ] },
+            "coords": { "3d": [ 0, 0, 0, 1.5, 0, 0 ] } },
+            "bonds": { "connections": { "index": [ 0, 1 ] }, "order": [ 4 ] } }"#;
+        assert_eq!(
+            super::read(text.as_bytes()).unwrap_err().to_string(),
+            "cjson: unknown order 4 of bond 0"
+        );
+    }
+}
@@ -0,0 +1,778 @@
+/**
+ * Author: V. Ganesh
+ * License: MIT
+ */
+
+/**
+ * cml module, reads and writes molecules in the Chemical Markup Language.
+ *
+ * The reader takes each <molecule> of a document, with the atoms of its <atomArray>
+ * and the bonds of its <bondArray>, given either one element per atom or bond or as
+ * arrays of attributes (atomID="a1 a2" elementType="O H"). The CML 1 form, with the
+ * values in <string builtin="..."> and <floatArray builtin="..."> children, is read
+ * as well. Atoms have 3D coordinates (x3, y3, z3) or 2D ones (x2, y2, with z as 0),
+ * formal charges become the atom charges and isotope numbers the "isotope" atom
+ * property. The <scalar> and <array> values of a <propertyList> become molecule
+ * properties, by title or else by dictRef, and a "remark" property is the molecule
+ * remark.
+ *
+ * Bond orders are written as 1, 2, 3 and A, CML has no order for coordinate and weak
+ * bonds, which are written as single bonds. Only whole charges are written, as formal
+ * charges.
+ */
+use std::collections::HashMap;
+use std::io::BufRead;
+use std::io::Write;
+
+use crate::atom::Atom;
+use crate::atom::AtomOperations;
+use crate::bond::BondType;
+use crate::io::xml;
+use crate::io::xml::XmlEvent;
+use crate::io::xml::XmlReader;
+use crate::io::IoError;
+use crate::molecule::Molecule;
+use crate::point::Point;
+use crate::property::AtomProperty;
+use crate::property::Property;
+
+const FORMAT: &str = "cml";
+
+const NAMESPACE: &str = "http://www.xml-cml.org/schema";
+
+type Attributes = Vec<(String, String)>;
+
+/** CmlMolecule - the atoms and bonds of a <molecule> as they are read */
+struct CmlMolecule {
+    mol: Molecule,
+    ids: HashMap<String, usize>,
+    isotopes: Vec<i64>,
+}
+
+/** get_tokens() returns the whitespace separated values of an attribute */
+fn get_tokens<'a>(attributes: &'a [(String, String)], name: &str) -> Vec<&'a str> {
+    return xml::get_attribute(attributes, name)
+        .map(|value| value.split_whitespace().collect())
+        .unwrap_or_default();
+}
+
+/** get_bond_type() returns the bond type of a CML bond order */
+fn get_bond_type(order: &str) -> Option<BondType> {
+    match order.to_uppercase().as_str() {
+        "1" | "S" => return Some(BondType::SINGLE),
+        "2" | "D" => return Some(BondType::DOUBLE),
+        "3" | "T" => return Some(BondType::TRIPLE),
+        "A" => return Some(BondType::AROMATIC),
+        _ => return None,
+    }
+}
+
+/** get_order() returns the CML bond order of a bond type */
+fn get_order(bond_type: BondType) -> &'static str {
+    match bond_type {
+        BondType::DOUBLE => return "2",
+        BondType::TRIPLE => return "3",
+        BondType::AROMATIC => return "A",
+        BondType::SINGLE | BondType::COORDINATE | BondType::WEAK => return "1",
+    }
+}
+
+/**
+ * read_children() reads the rest of an atom, bond or array element. The values of CML 1
+ * builtin children are added to its attributes, and its atom and bond children are
+ * returned with theirs
+ */
+fn read_children<R: BufRead>(
+    xml: &mut XmlReader<R>,
+    attributes: &mut Attributes,
+) -> Result<Vec<Attributes>, IoError> {
+    let mut children = Vec::new();
+
+    while let Some(event) = xml.next_event()? {
+        match event {
+            XmlEvent::StartElement {
+                name,
+                attributes: mut child_attributes,
+            } => match xml::local_name(&name) {
+                "atom" | "bond" => {
+                    read_children(xml, &mut child_attributes)?;
+                    children.push(child_attributes);
+                }
+                _ => {
+                    let builtin = xml::get_attribute(&child_attributes, "builtin");
+                    let Some(builtin) = builtin.map(|b| b.to_string()) else {
+                        xml.skip_element()?;
+                        continue;
+                    };
+                    let value = xml.read_text()?.trim().to_string();
+                    let key = match builtin.as_str() {
+                        "atomId" => "atomID".to_string(),
+                        "atomRef" if xml::get_attribute(attributes, "atomRef1").is_some() => {
+                            "atomRef2".to_string()
+                        }
+                        "atomRef" => "atomRef1".to_string(),
+                        _ => builtin,
+                    };
+                    attributes.push((key, value));
+                }
+            },
+            XmlEvent::EndElement { .. } => break,
+            XmlEvent::Text(_) => {}
+        }
+    }
+
+    return Ok(children);
+}
+
+impl CmlMolecule {
+    fn new(name: String) -> CmlMolecule {
+        CmlMolecule {
+            mol: Molecule::new(name, "".to_string()),
+            ids: HashMap::new(),
+            isotopes: Vec::new(),
+        }
+    }
+
+    /** add_atoms() adds the atoms of an <atom>, or of an <atomArray> in array form */
+    fn add_atoms<R: BufRead>(
+        &mut self,
+        xml: &XmlReader<R>,
+        attributes: &[(String, String)],
+        id_name: &str,
+    ) -> Result<(), IoError> {
+        let symbols = get_tokens(attributes, "elementType");
+        if symbols.is_empty() {
+            return Err(xml.error("an atom has no elementType".to_string()));
+        }
+
+        let column = |name: &str| -> Result<Option<Vec<&str>>, IoError> {
+            let tokens = get_tokens(attributes, name);
+            if tokens.is_empty() {
+                return Ok(None);
+            }
+            if tokens.len() != symbols.len() {
+                return Err(xml.error(format!(
+                    "{} has {} values for {} atoms",
+                    name,
+                    tokens.len(),
+                    symbols.len()
+                )));
+            }
+            return Ok(Some(tokens));
+        };
+        let number = |name: &str, value: &str| -> Result<f64, IoError> {
+            return value
+                .parse()
+                .map_err(|_| xml.error(format!("invalid {} \"{}\"", name, value)));
+        };
+
+        let ids = column(id_name)?;
+        let x3 = column("x3")?;
+        let (x, y, z) = match x3 {
+            Some(_) => ("x3", "y3", Some("z3")),
+            None => ("x2", "y2", None),
+        };
+        let coordinates = [column(x)?, column(y)?, z.map(column).transpose()?.flatten()];
+        let charges = column("formalCharge")?;
+        let isotopes = column("isotopeNumber")?;
+
+        for (idx, symbol) in symbols.iter().enumerate() {
+            let mut center = [0.0; 3];
+            for (axis, values) in coordinates.iter().enumerate() {
+                if let Some(values) = values {
+                    center[axis] = number(["x", "y", "z"][axis], values[idx])?;
+                }
+            }
+
+            if let Some(ids) = &ids {
+                self.ids
+                    .insert(ids[idx].to_string(), self.mol.get_atoms().len());
+            }
+            self.isotopes.push(match &isotopes {
+                Some(isotopes) => number("isotopeNumber", isotopes[idx])? as i64,
+                None => 0,
+            });
+
+            self.mol.add_atom(Atom {
+                center: Point {
+                    x: center[0] as f32,
+                    y: center[1] as f32,
+                    z: center[2] as f32,
+                },
+                charge: match &charges {
+                    Some(charges) => number("formalCharge", charges[idx])? as f32,
+                    None => 0.0,
+                },
+                symbol: symbol.to_string(),
+                remark: "".to_string(),
+            });
+        }
+
+        return Ok(());
+    }
+
+    /** add_bonds() adds the bonds of a <bond>, or of a <bondArray> in array form */
+    fn add_bonds<R: BufRead>(
+        &mut self,
+        xml: &XmlReader<R>,
+        attributes: &[(String, String)],
+    ) -> Result<(), IoError> {
+        let mut refs_1 = get_tokens(attributes, "atomRef1");
+        let mut refs_2 = get_tokens(attributes, "atomRef2");
+        let refs = get_tokens(attributes, "atomRefs2");
+        if refs.len() == 2 {
+            (refs_1, refs_2) = (vec![refs[0]], vec![refs[1]]);
+        }
+        let orders = get_tokens(attributes, "order");
+
+        if refs_1.is_empty() || refs_1.len() != refs_2.len() {
+            return Err(xml.error("a bond does not have two atom references".to_string()));
+        }
+        if !orders.is_empty() && orders.len() != refs_1.len() {
+            return Err(xml.error(format!(
+                "{} bond orders for {} bonds",
+                orders.len(),
+                refs_1.len()
+            )));
+        }
+
+        let index = |id: &str| -> Result<usize, IoError> {
+            return self
+                .ids
+                .get(id)
+                .copied()
+                .ok_or_else(|| xml.error(format!("a bond refers to unknown atom \"{}\"", id)));
+        };
+
+        let mut bonds = Vec::new();
+        for (idx, (ref_1, ref_2)) in refs_1.iter().zip(refs_2.iter()).enumerate() {
+            let order = orders.get(idx).copied().unwrap_or("1");
+            let bond_type = get_bond_type(order)
+                .ok_or_else(|| xml.error(format!("unknown bond order \"{}\"", order)))?;
+            bonds.push((index(ref_1)?, index(ref_2)?, bond_type));
+        }
+        for (atom_1_idx, atom_2_idx, bond_type) in bonds {
+            self.mol.add_bond(atom_1_idx, atom_2_idx, bond_type);
+        }
+
+        return Ok(());
+    }
+
+    /** add_property() adds the value of a <scalar> or <array> as a molecule property */
+    fn add_property<R: BufRead>(
+        &mut self,
+        xml: &XmlReader<R>,
+        name: &str,
+        element: &str,
+        data_type: &str,
+        text: &str,
+    ) -> Result<(), IoError> {
+        let data_type = xml::local_name(data_type);
+        let invalid = |value: &str| xml.error(format!("invalid {} \"{}\"", data_type, value));
+        let logical = |value: &str| match value {
+            "true" | "1" => Ok(true),
+            "false" | "0" => Ok(false),
+            _ => Err(invalid(value)),
+        };
+        let tokens: Vec<&str> = text.split_whitespace().collect();
+
+        let value = match (element, data_type) {
+            ("scalar", "double" | "float" | "decimal") => {
+                Property::Real(text.parse().map_err(|_| invalid(text))?)
+            }
+            ("scalar", "integer" | "int" | "long") => {
+                Property::Integer(text.parse().map_err(|_| invalid(text))?)
+            }
+            ("scalar", "boolean") => Property::Logical(logical(text)?),
+            ("array", "double" | "float" | "decimal") => Property::Reals(
+                tokens
+                    .iter()
+                    .map(|value| value.parse().map_err(|_| invalid(value)))
+                    .collect::<Result<_, _>>()?,
+            ),
+            ("array", "integer" | "int" | "long") => Property::Integers(
+                tokens
+                    .iter()
+                    .map(|value| value.parse().map_err(|_| invalid(value)))
+                    .collect::<Result<_, _>>()?,
+            ),
+            ("array", "boolean") => Property::Logicals(
+                tokens
+                    .iter()
+                    .map(|value| logical(value))
+                    .collect::<Result<_, _>>()?,
+            ),
+            _ => Property::Text(text.to_string()),
+        };
+
+        if name == "remark" {
+            self.mol.remark = text.to_string();
+        } else {
+            self.mol.set_property(name, value);
+        }
+        return Ok(());
+    }
+
+    fn into_molecule(mut self) -> Molecule {
+        if self.isotopes.iter().any(|isotope| *isotope != 0) {
+            self.mol
+                .set_atom_property("isotope", AtomProperty::Integers(self.isotopes));
+        }
+        return self.mol;
+    }
+}
+
+/** read_molecule() reads the rest of a <molecule> element */
+fn read_molecule<R: BufRead>(
+    xml: &mut XmlReader<R>,
+    attributes: &[(String, String)],
+) -> Result<Molecule, IoError> {
+    let title = xml::get_attribute(attributes, "title").unwrap_or("");
+    let mut cml = CmlMolecule::new(title.to_string());
+    let mut property = None;
+    let mut depth = 1;
+
+    while let Some(event) = xml.next_event()? {
+        match event {
+            XmlEvent::StartElement {
+                name,
+                mut attributes,
+            } => match xml::local_name(&name) {
+                element @ ("atom" | "atomArray") => {
+                    let id_name = if element == "atom" { "id" } else { "atomID" };
+                    let children = read_children(xml, &mut attributes)?;
+                    if xml::get_attribute(&attributes, "elementType").is_some() {
+                        cml.add_atoms(xml, &attributes, id_name)?;
+                    }
+                    for child in children.iter() {
+                        cml.add_atoms(xml, child, "id")?;
+                    }
+                }
+                "bond" | "bondArray" => {
+                    let children = read_children(xml, &mut attributes)?;
+                    if xml::get_attribute(&attributes, "atomRef1").is_some()
+                        || xml::get_attribute(&attributes, "atomRefs2").is_some()
+                    {
+                        cml.add_bonds(xml, &attributes)?;
+                    }
+                    for child in children.iter() {
+                        cml.add_bonds(xml, child)?;
+                    }
+                }
+                "name" => {
+                    let text = xml.read_text()?;
+                    if cml.mol.name.is_empty() {
+                        cml.mol.name = text.trim().to_string();
+                    }
+                }
+                "property" => {
+                    property = xml::get_attribute(&attributes, "title")
+                        .or_else(|| xml::get_attribute(&attributes, "dictRef"))
+                        .map(|name| xml::local_name(name).to_string());
+                    depth += 1;
+                }
+                element @ ("scalar" | "array") => {
+                    let element = element.to_string();
+                    let text = xml.read_text()?;
+                    let name = property.clone().or_else(|| {
+                        xml::get_attribute(&attributes, "title")
+                            .or_else(|| xml::get_attribute(&attributes, "dictRef"))
+                            .map(|name| xml::local_name(name).to_string())
+                    });
+                    if let Some(name) = name {
+                        let data_type = xml::get_attribute(&attributes, "dataType").unwrap_or("");
+                        cml.add_property(xml, &name, &element, data_type, text.trim())?;
+                    }
+                }
+                _ => depth += 1,
+            },
+            XmlEvent::EndElement { name } => {
+                if xml::local_name(&name) == "property" {
+                    property = None;
+                }
+                depth -= 1;
+                if depth == 0 {
+                    break;
+                }
+            }
+            XmlEvent::Text(_) => {}
+        }
+    }
+
+    if cml.mol.name.is_empty() {
+        cml.mol.name = xml::get_attribute(attributes, "id")
+            .unwrap_or("")
+            .to_string();
+    }
+
+    return Ok(cml.into_molecule());
+}
+
+/** read_all() reads all the molecules of a CML document */
+#[allow(dead_code)]
+pub fn read_all<R: BufRead>(reader: R) -> Result<Vec<Molecule>, IoError> {
+    let mut xml = XmlReader::new(reader, FORMAT);
+    let mut molecules = Vec::new();
+
+    while let Some(event) = xml.next_event()? {
+        if let XmlEvent::StartElement { name, attributes } = event {
+            if xml::local_name(&name) == "molecule" {
+                molecules.push(read_molecule(&mut xml, &attributes)?);
+            }
+        }
+    }
+
+    return Ok(molecules);
+}
+
+/** read() reads the first molecule of a CML document */
+#[allow(dead_code)]
+pub fn read<R: BufRead>(reader: R) -> Result<Molecule, IoError> {
+    let mut xml = XmlReader::new(reader, FORMAT);
+
+    while let Some(event) = xml.next_event()? {
+        if let XmlEvent::StartElement { name, attributes } = event {
+            if xml::local_name(&name) == "molecule" {
+                return read_molecule(&mut xml, &attributes);
+            }
+        }
+    }
+
+    return Err(xml.error("the document has no molecule".to_string()));
+}
+
+/** CmlWriter - writes molecules in CML, with 3D coordinates by default */
+#[allow(dead_code)]
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct CmlWriter {
+    two_dimensional: bool,
+}
+
+#[allow(dead_code)]
+impl CmlWriter {
+    pub fn new() -> CmlWriter {
+        return CmlWriter::default();
+    }
+
+    /** two_dimensional() sets whether 2D coordinates (x2, y2) are written, else 3D ones */
+    pub fn two_dimensional(mut self, two_dimensional: bool) -> CmlWriter {
+        self.two_dimensional = two_dimensional;
+        return self;
+    }
+
+    /** write_molecule() writes a <molecule> element, indented by the given prefix */
+    fn write_molecule<W: Write>(
+        &self,
+        writer: &mut W,
+        mol: &Molecule,
+        id: &str,
+        namespace: bool,
+        indent: &str,
+    ) -> Result<(), IoError> {
+        write!(writer, "{}<molecule", indent)?;
+        if namespace {
+            write!(writer, " xmlns=\"{}\"", NAMESPACE)?;
+        }
+        write!(writer, " id=\"{}\"", id)?;
+        if !mol.name.is_empty() {
+            write!(writer, " title=\"{}\"", xml::escape(&mol.name))?;
+        }
+        writeln!(writer, ">")?;
+
+        let atoms = mol.get_atoms();
+        let isotopes = match mol.get_atom_property("isotope") {
+            Some(AtomProperty::Integers(values)) => values.to_vec(),
+            _ => vec![0; atoms.len()],
+        };
+
+        if !atoms.is_empty() {
+            writeln!(writer, "{}  <atomArray>", indent)?;
+            for (idx, atom) in atoms.iter().enumerate() {
+                write!(
+                    writer,
+                    "{}    <atom id=\"a{}\" elementType=\"{}\"",
+                    indent,
+                    idx + 1,
+                    xml::escape(&atom.symbol)
+                )?;
+                if atom.charge != 0.0 && atom.charge.fract() == 0.0 {
+                    write!(writer, " formalCharge=\"{}\"", atom.charge as i64)?;
+                }
+                if isotopes.get(idx).copied().unwrap_or(0) != 0 {
+                    write!(writer, " isotopeNumber=\"{}\"", isotopes[idx])?;
+                }
+                if self.two_dimensional {
+                    write!(
+                        writer,
+                        " x2=\"{:.6}\" y2=\"{:.6}\"",
+                        atom.center.x, atom.center.y
+                    )?;
+                } else {
+                    write!(
+                        writer,
+                        " x3=\"{:.6}\" y3=\"{:.6}\" z3=\"{:.6}\"",
+                        atom.center.x, atom.center.y, atom.center.z
+                    )?;
+                }
+                writeln!(writer, "/>")?;
+            }
+            writeln!(writer, "{}  </atomArray>", indent)?;
+        }
+
+        if !mol.get_bonds().is_empty() {
+            writeln!(writer, "{}  <bondArray>", indent)?;
+            for (idx, bond) in mol.get_bonds().iter().enumerate() {
+                writeln!(
+                    writer,
+                    "{}    <bond id=\"b{}\" atomRefs2=\"a{} a{}\" order=\"{}\"/>",
+                    indent,
+                    idx + 1,
+                    bond.atom_1_idx + 1,
+                    bond.atom_2_idx + 1,
+                    get_order(bond.bond_type)
+                )?;
+            }
+            writeln!(writer, "{}  </bondArray>", indent)?;
+        }
+
+        let mut properties: Vec<(&str, Property)> = Vec::new();
+        if !mol.remark.is_empty() {
+            properties.push(("remark", Property::Text(mol.remark.to_string())));
+        }
+        for (name, value) in mol.get_properties().iter() {
+            properties.push((name, value.clone()));
+        }
+
+        if !properties.is_empty() {
+            writeln!(writer, "{}  <propertyList>", indent)?;
+            for (name, value) in properties.iter() {
+                let logicals = |values: &[bool]| {
+                    let words: Vec<String> = values.iter().map(|v| v.to_string()).collect();
+                    return words.join(" ");
+                };
+                let (element, data_type, size, text) = match value {
+                    Property::Text(_) => ("scalar", "string", None, value.to_string()),
+                    Property::Integer(_) => ("scalar", "integer", None, value.to_string()),
+                    Property::Real(_) => ("scalar", "double", None, value.to_string()),
+                    Property::Logical(v) => ("scalar", "boolean", None, v.to_string()),
+                    Property::Integers(values) => {
+                        ("array", "integer", Some(values.len()), value.to_string())
+                    }
+                    Property::Reals(values) => {
+                        ("array", "double", Some(values.len()), value.to_string())
+                    }
+                    Property::Logicals(values) => {
+                        ("array", "boolean", Some(values.len()), logicals(values))
+                    }
+                };
+
+                writeln!(
+                    writer,
+                    "{}    <property title=\"{}\">",
+                    indent,
+                    xml::escape(name)
+                )?;
+                write!(
+                    writer,
+                    "{}      <{} dataType=\"xsd:{}\"",
+                    indent, element, data_type
+                )?;
+                if let Some(size) = size {
+                    write!(writer, " size=\"{}\"", size)?;
+                }
+                writeln!(writer, ">{}</{}>", xml::escape(&text), element)?;
+                writeln!(writer, "{}    </property>", indent)?;
+            }
+            writeln!(writer, "{}  </propertyList>", indent)?;
+        }
+
+        writeln!(writer, "{}</molecule>", indent)?;
+        return Ok(());
+    }
+
+    /** write() writes a molecule as a CML document */
+    pub fn write<W: Write>(&self, mut writer: W, mol: &Molecule) -> Result<(), IoError> {
+        writeln!(writer, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>")?;
+        return self.write_molecule(&mut writer, mol, "m1", true, "");
+    }
+
+    /** write_all() writes molecules as a CML document, in a <cml> element */
+    pub fn write_all<W: Write>(
+        &self,
+        mut writer: W,
+        molecules: &[Molecule],
+    ) -> Result<(), IoError> {
+        writeln!(writer, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>")?;
+        writeln!(writer, "<cml xmlns=\"{}\">", NAMESPACE)?;
+        for (idx, mol) in molecules.iter().enumerate() {
+            self.write_molecule(&mut writer, mol, &format!("m{}", idx + 1), false, "  ")?;
+        }
+        writeln!(writer, "</cml>")?;
+        return Ok(());
+    }
+}
+
+/** write() writes a molecule as a CML document, with 3D coordinates */
+#[allow(dead_code)]
+pub fn write<W: Write>(writer: W, mol: &Molecule) -> Result<(), IoError> {
+    return CmlWriter::new().write(writer, mol);
+}
+
+/** Unit tests for the above module */
+#[cfg(test)]
+mod tests {
+    use crate::atom::Atom;
+    use crate::atom::AtomOperations;
+    use crate::bond::BondType;
+    use crate::io::cml::CmlWriter;
+    use crate::molecule::Molecule;
+    use crate::point::Point;
+    use crate::property::AtomProperty;
+    use crate::property::Property;
+
+    fn get_hydroxide() -> Molecule {
+        let mut mol = Molecule::new("hydroxide <OH->".to_string(), "from a \"test\"".to_string());
+        for (symbol, charge, z) in [("O", -1.0, 0.0), ("H", 0.0, 0.97)] {
+            mol.add_atom(Atom {
+                center: Point {
+                    x: 0.5,
+                    y: 0.0,
+                    z: z,
+                },
+                charge: charge,
+                symbol: symbol.to_string(),
+                remark: "".to_string(),
+            });
+        }
+        mol.add_bond(0, 1, BondType::SINGLE);
+        mol.set_atom_property("isotope", AtomProperty::Integers(vec![18, 0]));
+        mol.set_property("energy", Property::Real(-75.5));
+        mol.set_property("multiplicity", Property::Integer(1));
+        mol.set_property("optimized", Property::Logical(true));
+        mol.set_property("dipole", Property::Reals(vec![0.0, 0.0, 1.25]));
+        mol.set_property("frozen", Property::Logicals(vec![true, false]));
+        return mol;
+    }
+
+    #[test]
+    fn cml_round_trip() {
+        let mol = get_hydroxide();
+        let mut buffer = Vec::new();
+        super::write(&mut buffer, &mol).unwrap();
+        let text = String::from_utf8(buffer).unwrap();
+        assert!(text.contains("title=\"hydroxide &lt;OH-&gt;\""));
+        assert!(text.contains("formalCharge=\"-1\" isotopeNumber=\"18\""));
+
+        let read_mol = super::read(text.as_bytes()).unwrap();
+        assert_eq!(read_mol.name, mol.name);
+        assert_eq!(read_mol.remark, mol.remark);
+        assert_eq!(read_mol.get_atoms(), mol.get_atoms());
+        assert_eq!(read_mol.get_bonds().len(), 1);
+        assert_eq!(read_mol.get_bonds()[0].bond_type, BondType::SINGLE);
+        assert_eq!(read_mol.get_properties(), mol.get_properties());
+        assert_eq!(
+            read_mol.get_atom_property("isotope"),
+            Some(&AtomProperty::Integers(vec![18, 0]))
+        );
+
+        let mut buffer = Vec::new();
+        CmlWriter::new()
+            .two_dimensional(true)
+            .write_all(&mut buffer, &[mol.clone(), mol])
+            .unwrap();
+        let molecules = super::read_all(buffer.as_slice()).unwrap();
+        assert_eq!(molecules.len(), 2);
+        assert_eq!(
+            molecules[1].get_atoms()[1].center,
+            Point {
+                x: 0.5,
+                y: 0.0,
+                z: 0.0
+            }
+        );
+    }
+
+    #[test]
+    fn cml_read_array_forms() {
+        let text = "<?xml version=\"1.0\"?>
+<cml:cml xmlns:cml=\"http://www.xml-cml.org/schema\">
+  <cml:molecule id=\"co2\">
+    <cml:atomArray atomID=\"a1 a2 a3\" elementType=\"O C O\"
+                   x3=\"-1.16 0.0 1.16\" y3=\"0 0 0\" z3=\"0 0 0\"/>
+    <cml:bondArray atomRef1=\"a1 a2\" atomRef2=\"a2 a3\" order=\"D 2\"/>
+    <cml:propertyList>
+      <cml:property dictRef=\"cml:molwt\"><cml:scalar dataType=\"xsd:double\">44.01</cml:scalar></cml:property>
+    </cml:propertyList>
+  </cml:molecule>
+  <molecule title=\"formaldehyde\">
+    <atomArray>
+      <stringArray builtin=\"atomId\">a1 a2 a3 a4</stringArray>
+      <stringArray builtin=\"elementType\">C O H H</stringArray>
+      <floatArray builtin=\"x2\">0.0 1.2 -0.5 -0.5</floatArray>
+      <floatArray builtin=\"y2\">0.0 0.0 0.9 -0.9</floatArray>
+    </atomArray>
+    <bondArray>
+      <bond><string builtin=\"atomRef\">a1</string><string builtin=\"atomRef\">a2</string><string builtin=\"order\">2</string></bond>
+      <bond atomRefs2=\"a1 a3\"/>
+      <bond atomRefs2=\"a1 a4\" order=\"S\"/>
+    </bondArray>
+  </molecule>
+</cml:cml>";
+
+        let molecules = super::read_all(text.as_bytes()).unwrap();
+        assert_eq!(molecules.len(), 2);
+
+        let co2 = &molecules[0];
+        assert_eq!(co2.name, "co2");
+        assert_eq!(co2.get_atoms()[0].symbol, "O");
+        assert_eq!(co2.get_atoms()[2].center.x, 1.16);
+        let bonds: Vec<(usize, usize, BondType)> = co2
+            .get_bonds()
+            .iter()
+            .map(|bnd| (bnd.atom_1_idx, bnd.atom_2_idx, bnd.bond_type))
+            .collect();
+        assert_eq!(
+            bonds,
+            vec![(0, 1, BondType::DOUBLE), (1, 2, BondType::DOUBLE)]
+        );
+        assert_eq!(co2.get_property("molwt"), Some(&Property::Real(44.01)));
+
+        let formaldehyde = &molecules[1];
+        assert_eq!(formaldehyde.name, "formaldehyde");
+        assert_eq!(formaldehyde.get_atoms().len(), 4);
+        assert_eq!(
+            formaldehyde.get_atoms()[3].center,
+            Point {
+                x: -0.5,
+                y: -0.9,
+                z: 0.0
+            }
+        );
+        assert_eq!(formaldehyde.get_bonds().len(), 3);
+        assert_eq!(formaldehyde.get_bonds()[0].bond_type, BondType::DOUBLE);
+        assert_eq!(formaldehyde.get_bonds()[2].bond_type, BondType::SINGLE);
+    }
+
+    #[test]
+    fn cml_read_errors() {
+        let error = |text: &str| super::read(text.as_bytes()).unwrap_err().to_string();
+
+        assert_eq!(error("<cml/>"), "cml: line 1: the document has no molecule");
+        assert_eq!(
+            error("<molecule>\n<atomArray atomID=\"a1 a2\" elementType=\"C O\" x3=\"0\"/>\n</molecule>"),
+            "cml: line 2: x3 has 1 values for 2 atoms"
+        );
+        assert_eq!(
+            error("<molecule>\n<atom id=\"a1\" elementType=\"C\"/>\n<bond atomRefs2=\"a1 a2\"/>\n</molecule>"),
+            "cml: line 3: a bond refers to unknown atom \"a2\""
+        );
+        assert_eq!(
+            error("<molecule>\n<atom id=\"a1\" elementType=\"C\"/><atom id=\"a2\" elementType=\"C\"/>\n<bond atomRefs2=\"a1 a2\" order=\"5\"/>\n</molecule>"),
+            "cml: line 3: unknown bond order \"5\""
+        );
+        assert_eq!(
+            error("<molecule>\n<atom id=\"a1\" elementType=\"C\">\n</molecule>"),
+            "cml: line 3: expected </atom>, found </molecule>"
+        );
+    }
+}
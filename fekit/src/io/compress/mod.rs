@@ -0,0 +1,359 @@
+/**
+ * Author: V. Ganesh
+ * License: MIT
+ */
+
+/**
+ * compress module, reads and writes gzip, bzip2 and xz compressed files.
+ *
+ * open() returns a reader of the uncompressed contents of a file, whether it is
+ * compressed or not, so every reader of the io module takes compressed files:
+ *
+ *   for frame in XyzReader::new(compress::open("trajectory.xyz.gz")?) { ... }
+ *
+ * The compression is found from the first bytes of the file, so a compressed file without
+ * the extension is read too. Files made of several compressed streams one after the other,
+ * e.g. by bgzip or by appending to a .gz file, are read to the end. create() compresses
+ * by the extension of the file name. Both decompress and compress as the contents are
+ * read or written, so the memory used does not grow with the size of the file.
+ *
+ * gzip is always read and written. bzip2 and xz build C libraries, so they need the
+ * compression feature, without it their files give an error.
+ */
+use std::fs::File;
+use std::io;
+use std::io::BufRead;
+use std::io::BufReader;
+use std::io::BufWriter;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+
+use crate::io::IoError;
+
+/** magic numbers at the start of compressed files */
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+const BZIP2_MAGIC: &[u8] = b"BZh";
+const XZ_MAGIC: &[u8] = &[0xfd, b'7', b'z', b'X', b'Z', 0x00];
+
+/** Compression - how the contents of a file are compressed */
+#[allow(dead_code)]
+#[derive(Default, Clone, Copy, PartialEq, Debug)]
+pub enum Compression {
+    /** not compressed */
+    #[default]
+    NONE,
+    GZIP,
+    BZIP2,
+    XZ,
+}
+
+#[allow(dead_code)]
+impl Compression {
+    /** from_magic() returns the compression of contents starting with the given bytes */
+    pub fn from_magic(bytes: &[u8]) -> Compression {
+        if bytes.starts_with(GZIP_MAGIC) {
+            return Compression::GZIP;
+        } else if bytes.starts_with(BZIP2_MAGIC) {
+            return Compression::BZIP2;
+        } else if bytes.starts_with(XZ_MAGIC) {
+            return Compression::XZ;
+        }
+        return Compression::NONE;
+    }
+
+    /** from_path() returns the compression given by the extension of a file name */
+    pub fn from_path(path: &Path) -> Compression {
+        let extension = match path.extension() {
+            Some(extension) => extension.to_string_lossy().to_lowercase(),
+            None => return Compression::NONE,
+        };
+        match extension.as_str() {
+            "gz" | "gzip" => return Compression::GZIP,
+            "bz2" | "bzip2" => return Compression::BZIP2,
+            "xz" => return Compression::XZ,
+            _ => return Compression::NONE,
+        }
+    }
+
+    /** get_extension() returns the extension of files with this compression */
+    pub fn get_extension(&self) -> Option<&'static str> {
+        match self {
+            Compression::NONE => return None,
+            Compression::GZIP => return Some("gz"),
+            Compression::BZIP2 => return Some("bz2"),
+            Compression::XZ => return Some("xz"),
+        }
+    }
+}
+
+/**
+ * strip_extension() returns a path without its compression extension, e.g. run/water.sdf
+ * for run/water.sdf.gz, to find the format of the contents
+ */
+#[allow(dead_code)]
+pub fn strip_extension(path: &Path) -> PathBuf {
+    match Compression::from_path(path) {
+        Compression::NONE => return path.to_path_buf(),
+        _ => return path.with_extension(""),
+    }
+}
+
+/** get_unsupported_error() returns the error of a compression fekit is built without */
+#[cfg(not(feature = "compression"))]
+fn get_unsupported_error(compression: Compression) -> IoError {
+    let name = compression.get_extension().unwrap_or("");
+    return IoError::parse(
+        name,
+        0,
+        "compressed files need fekit built with the compression feature".to_string(),
+    );
+}
+
+/**
+ * decompress() returns a reader of the uncompressed contents of a stream, found to be
+ * compressed or not from its first bytes
+ */
+#[allow(dead_code)]
+pub fn decompress<'a, R: BufRead + 'a>(mut reader: R) -> Result<Box<dyn BufRead + 'a>, IoError> {
+    let compression = Compression::from_magic(reader.fill_buf()?);
+    match compression {
+        Compression::NONE => return Ok(Box::new(reader)),
+        Compression::GZIP => {
+            let decoder = flate2::bufread::MultiGzDecoder::new(reader);
+            return Ok(Box::new(BufReader::new(decoder)));
+        }
+        #[cfg(feature = "compression")]
+        Compression::BZIP2 => {
+            let decoder = bzip2::bufread::MultiBzDecoder::new(reader);
+            return Ok(Box::new(BufReader::new(decoder)));
+        }
+        #[cfg(feature = "compression")]
+        Compression::XZ => {
+            let decoder = xz2::bufread::XzDecoder::new_multi_decoder(reader);
+            return Ok(Box::new(BufReader::new(decoder)));
+        }
+        #[cfg(not(feature = "compression"))]
+        _ => return Err(get_unsupported_error(compression)),
+    }
+}
+
+/** open() opens a file for reading its uncompressed contents */
+#[allow(dead_code)]
+pub fn open<P: AsRef<Path>>(path: P) -> Result<Box<dyn BufRead>, IoError> {
+    return decompress(BufReader::new(File::open(path)?));
+}
+
+/** Encoder - the stream written to by a CompressedWriter */
+enum Encoder {
+    Plain(BufWriter<File>),
+    Gzip(flate2::write::GzEncoder<BufWriter<File>>),
+    #[cfg(feature = "compression")]
+    Bzip2(bzip2::write::BzEncoder<BufWriter<File>>),
+    #[cfg(feature = "compression")]
+    Xz(xz2::write::XzEncoder<BufWriter<File>>),
+}
+
+/**
+ * CompressedWriter - writes a file, compressing what is written. finish() must be called
+ * at the end, to write the end of the compressed stream and see any error in doing so.
+ */
+#[allow(dead_code)]
+pub struct CompressedWriter {
+    encoder: Encoder,
+}
+
+#[allow(dead_code)]
+impl CompressedWriter {
+    /** new() creates a file written with the given compression */
+    pub fn new<P: AsRef<Path>>(
+        path: P,
+        compression: Compression,
+    ) -> Result<CompressedWriter, IoError> {
+        #[cfg(not(feature = "compression"))]
+        if matches!(compression, Compression::BZIP2 | Compression::XZ) {
+            return Err(get_unsupported_error(compression));
+        }
+
+        let file = BufWriter::new(File::create(path)?);
+        let encoder = match compression {
+            Compression::NONE => Encoder::Plain(file),
+            Compression::GZIP => Encoder::Gzip(flate2::write::GzEncoder::new(
+                file,
+                flate2::Compression::default(),
+            )),
+            #[cfg(feature = "compression")]
+            Compression::BZIP2 => Encoder::Bzip2(bzip2::write::BzEncoder::new(
+                file,
+                bzip2::Compression::default(),
+            )),
+            #[cfg(feature = "compression")]
+            Compression::XZ => Encoder::Xz(xz2::write::XzEncoder::new(file, 6)),
+            #[cfg(not(feature = "compression"))]
+            _ => unreachable!(),
+        };
+        return Ok(CompressedWriter { encoder: encoder });
+    }
+
+    /** finish() ends the compressed stream and flushes the file */
+    pub fn finish(self) -> Result<(), IoError> {
+        let mut file = match self.encoder {
+            Encoder::Plain(file) => file,
+            Encoder::Gzip(encoder) => encoder.finish()?,
+            #[cfg(feature = "compression")]
+            Encoder::Bzip2(encoder) => encoder.finish()?,
+            #[cfg(feature = "compression")]
+            Encoder::Xz(encoder) => encoder.finish()?,
+        };
+        file.flush()?;
+        return Ok(());
+    }
+}
+
+impl Write for CompressedWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match &mut self.encoder {
+            Encoder::Plain(file) => return file.write(buf),
+            Encoder::Gzip(encoder) => return encoder.write(buf),
+            #[cfg(feature = "compression")]
+            Encoder::Bzip2(encoder) => return encoder.write(buf),
+            #[cfg(feature = "compression")]
+            Encoder::Xz(encoder) => return encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match &mut self.encoder {
+            Encoder::Plain(file) => return file.flush(),
+            Encoder::Gzip(encoder) => return encoder.flush(),
+            #[cfg(feature = "compression")]
+            Encoder::Bzip2(encoder) => return encoder.flush(),
+            #[cfg(feature = "compression")]
+            Encoder::Xz(encoder) => return encoder.flush(),
+        }
+    }
+}
+
+/** create() creates a file for writing, compressed by the extension of its name */
+#[allow(dead_code)]
+pub fn create<P: AsRef<Path>>(path: P) -> Result<CompressedWriter, IoError> {
+    let compression = Compression::from_path(path.as_ref());
+    return CompressedWriter::new(path, compression);
+}
+
+/** Unit tests for the above module */
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+    use std::io::Write;
+    use std::path::Path;
+
+    use crate::io::compress;
+    use crate::io::compress::Compression;
+    use crate::io::xyz::XyzReader;
+    use crate::testutil;
+
+    const FRAME: &str = "3\nwater\nO 0.0 0.0 0.1173\nH 0.0 0.7572 -0.4692\nH 0.0 -0.7572 -0.4692\n";
+
+    #[test]
+    fn compress_detect() {
+        assert_eq!(
+            Compression::from_path(Path::new("ligands.SDF.GZ")),
+            Compression::GZIP
+        );
+        assert_eq!(
+            Compression::from_path(Path::new("traj.xyz.bz2")),
+            Compression::BZIP2
+        );
+        assert_eq!(
+            Compression::from_path(Path::new("1crn.pdb.xz")),
+            Compression::XZ
+        );
+        assert_eq!(
+            Compression::from_path(Path::new("1crn.pdb")),
+            Compression::NONE
+        );
+        assert_eq!(Compression::from_magic(b"BZh91AY"), Compression::BZIP2);
+        assert_eq!(Compression::from_magic(b"3\nwater"), Compression::NONE);
+        assert_eq!(Compression::XZ.get_extension(), Some("xz"));
+
+        assert_eq!(
+            compress::strip_extension(Path::new("run/ligands.sdf.gz")),
+            Path::new("run/ligands.sdf")
+        );
+        assert_eq!(
+            compress::strip_extension(Path::new("run/ligands.sdf")),
+            Path::new("run/ligands.sdf")
+        );
+    }
+
+    #[test]
+    fn compress_round_trip() {
+        let mut names = vec!["traj.xyz", "traj.xyz.gz"];
+        if cfg!(feature = "compression") {
+            names.extend(["traj.xyz.bz2", "traj.xyz.xz"]);
+        }
+        for name in names {
+            let path = testutil::get_path("compress", name);
+            let mut writer = compress::create(&path).unwrap();
+            for _ in 0..100 {
+                writer.write_all(FRAME.as_bytes()).unwrap();
+            }
+            writer.finish().unwrap();
+
+            let frames: Vec<_> = XyzReader::new(compress::open(&path).unwrap())
+                .collect::<Result<_, _>>()
+                .unwrap();
+            assert_eq!(frames.len(), 100, "{}", name);
+            assert_eq!(frames[99].get_atoms().len(), 3);
+
+            let size = std::fs::metadata(&path).unwrap().len() as usize;
+            if name.ends_with(".xyz") {
+                assert_eq!(size, 100 * FRAME.len());
+            } else {
+                assert!(size < 10 * FRAME.len(), "{} is {} bytes", name, size);
+            }
+            std::fs::remove_file(&path).unwrap();
+        }
+    }
+
+    #[test]
+    fn compress_concatenated() {
+        // two gzip streams one after the other, as written by appending to a .gz file
+        let path = testutil::get_path("compress", "appended.xyz");
+        let mut file = std::fs::File::create(&path).unwrap();
+        for _ in 0..2 {
+            let mut encoder =
+                flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder.write_all(FRAME.as_bytes()).unwrap();
+            file.write_all(&encoder.finish().unwrap()).unwrap();
+        }
+        drop(file);
+
+        let mut contents = String::new();
+        compress::open(&path)
+            .unwrap()
+            .read_to_string(&mut contents)
+            .unwrap();
+        assert_eq!(contents, FRAME.repeat(2));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[cfg(not(feature = "compression"))]
+    #[test]
+    fn compress_unsupported() {
+        let path = testutil::get_path("compress", "traj.xyz.bz2");
+        let err = compress::create(&path).err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            "bz2: compressed files need fekit built with the compression feature"
+        );
+        assert!(!path.exists());
+
+        let err = compress::decompress(&b"\xfd7zXZ\x00"[..]).err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            "xz: compressed files need fekit built with the compression feature"
+        );
+    }
+}
@@ -0,0 +1,528 @@
+/**
+ * Author: V. Ganesh
+ * License: MIT
+ */
+
+/**
+ * cube module, reads and writes Gaussian cube (.cube) files.
+ *
+ * A cube file holds a molecule and the values of one or more functions on a grid: two
+ * comment lines, the number of atoms and the origin, the number of points and the step
+ * along each axis, a line per atom with its atomic number, nuclear charge and position,
+ * then the values, six per line, with the last axis running fastest. Lengths are in bohr,
+ * unless the number of points of the first axis is negative, when they are in angstrom.
+ * A negative number of atoms marks an orbital cube, with a line listing the orbitals
+ * after the atoms and the values of every orbital at each point.
+ *
+ * The comment lines become the name and remark of the molecule, and nuclear charges that
+ * differ from the atomic numbers (effective core potentials) the "nuclear_charge" atom
+ * property. Grids are in angstrom, the values are kept as they are, usually atomic units.
+ */
+use std::io::BufRead;
+use std::io::Write;
+
+use crate::atom::Atom;
+use crate::atom::AtomOperations;
+use crate::atomprop;
+use crate::grid::Grid;
+use crate::io::IoError;
+use crate::io::LineReader;
+use crate::io::BOHR;
+use crate::molecule::Molecule;
+use crate::point::Point;
+use crate::property::AtomProperty;
+
+const FORMAT: &str = "cube";
+
+/** the number of values on a line of the data section */
+const VALUES_PER_LINE: usize = 6;
+
+/** Cube - a molecule and the grids of a cube file, with the orbital of each grid if any */
+#[allow(dead_code)]
+#[derive(Clone, Debug)]
+pub struct Cube {
+    pub molecule: Molecule,
+    pub grids: Vec<Grid>,
+    /** the orbital numbers of an orbital cube, one per grid, empty for other cubes */
+    pub orbitals: Vec<i64>,
+}
+
+#[allow(dead_code)]
+impl Cube {
+    /** new() creates a cube with a single grid, e.g. a density or a potential */
+    pub fn new(molecule: Molecule, grid: Grid) -> Cube {
+        Cube {
+            molecule: molecule,
+            grids: vec![grid],
+            orbitals: Vec::new(),
+        }
+    }
+
+    /** get_grid() returns the first grid of the cube */
+    pub fn get_grid(&self) -> &Grid {
+        return &self.grids[0];
+    }
+}
+
+/** parse_fields() parses the numbers of a line, with the given number of leading integers */
+fn parse_fields(line: &str, integers: usize, reals: usize) -> Result<(Vec<i64>, Vec<f64>), String> {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    if fields.len() < integers + reals {
+        return Err(format!(
+            "expected {} numbers, found \"{}\"",
+            integers + reals,
+            line.trim()
+        ));
+    }
+
+    let mut parsed_integers = Vec::new();
+    for field in fields[..integers].iter() {
+        let value = field
+            .parse()
+            .map_err(|_| format!("invalid integer \"{}\"", field))?;
+        parsed_integers.push(value);
+    }
+    let mut parsed_reals = Vec::new();
+    for field in fields[integers..integers + reals].iter() {
+        let value = field
+            .parse()
+            .map_err(|_| format!("invalid number \"{}\"", field))?;
+        parsed_reals.push(value);
+    }
+
+    return Ok((parsed_integers, parsed_reals));
+}
+
+/**
+ * read_numbers() reads whitespace separated numbers, over as many lines as needed. The
+ * count comes from the file, so no room is reserved for the numbers before they are read.
+ */
+fn read_numbers<R: BufRead, T: std::str::FromStr>(
+    reader: &mut LineReader<R>,
+    count: usize,
+    what: &str,
+) -> Result<Vec<T>, IoError> {
+    let mut numbers = Vec::new();
+    while numbers.len() < count {
+        let line = reader.next_line()?.ok_or_else(|| {
+            reader.error(format!(
+                "unexpected end of file, expected {} {}, found {}",
+                count,
+                what,
+                numbers.len()
+            ))
+        })?;
+        for field in line.split_whitespace() {
+            if numbers.len() == count {
+                return Err(reader.error(format!("more {} than expected", what)));
+            }
+            let number = field
+                .parse()
+                .map_err(|_| reader.error(format!("invalid number \"{}\"", field)))?;
+            numbers.push(number);
+        }
+    }
+    return Ok(numbers);
+}
+
+/** read() reads a cube file */
+pub fn read<R: BufRead>(reader: R) -> Result<Cube, IoError> {
+    let mut reader = LineReader::new(reader, FORMAT);
+    let name = reader.expect_line("the first comment line")?;
+    let remark = reader.expect_line("the second comment line")?;
+
+    let line = reader.expect_line("the number of atoms and the origin")?;
+    let (integers, origin) = parse_fields(&line, 1, 3).map_err(|message| reader.error(message))?;
+    let number_of_atoms = integers[0].unsigned_abs() as usize;
+    let is_orbital_cube = integers[0] < 0;
+    // a fifth field gives the number of values at each point
+    let values_per_point = match line.split_whitespace().nth(4) {
+        Some(field) if !is_orbital_cube => field
+            .parse::<usize>()
+            .ok()
+            .filter(|n| *n > 0)
+            .ok_or_else(|| reader.error(format!("invalid number of values \"{}\"", field)))?,
+        _ => 1,
+    };
+
+    let mut dimensions = [0; 3];
+    let mut axes = [[0.0; 3]; 3];
+    let mut in_angstrom = false;
+    for axis in 0..3 {
+        let line = reader.expect_line("the points and step of an axis")?;
+        let (integers, step) =
+            parse_fields(&line, 1, 3).map_err(|message| reader.error(message))?;
+        if axis == 0 {
+            in_angstrom = integers[0] < 0;
+        }
+        if integers[0] == 0 {
+            return Err(reader.error("an axis has no points".to_string()));
+        }
+        dimensions[axis] = integers[0].unsigned_abs() as usize;
+        axes[axis] = [step[0], step[1], step[2]];
+    }
+
+    let scale = if in_angstrom { 1.0 } else { BOHR };
+    let to_angstrom = |v: &[f64]| [v[0] * scale, v[1] * scale, v[2] * scale];
+
+    let mut mol = Molecule::new(name.trim().to_string(), remark.trim().to_string());
+    let mut nuclear_charges = Vec::new();
+    let mut has_core_charges = false;
+    for _ in 0..number_of_atoms {
+        let line = reader.expect_line("an atom")?;
+        let (integers, reals) =
+            parse_fields(&line, 1, 4).map_err(|message| reader.error(message))?;
+        let symbol = u8::try_from(integers[0])
+            .ok()
+            .and_then(atomprop::get_element_symbol)
+            .ok_or_else(|| reader.error(format!("unknown atomic number {}", integers[0])))?;
+        let p = to_angstrom(&reals[1..4]);
+
+        has_core_charges |= (reals[0] - integers[0] as f64).abs() > 1e-6;
+        nuclear_charges.push(reals[0]);
+        mol.add_atom(Atom {
+            center: Point {
+                x: p[0] as f32,
+                y: p[1] as f32,
+                z: p[2] as f32,
+            },
+            charge: 0.0,
+            symbol: symbol.to_string(),
+            remark: "".to_string(),
+        });
+    }
+    if has_core_charges {
+        mol.set_atom_property("nuclear_charge", AtomProperty::Reals(nuclear_charges));
+    }
+
+    let mut orbitals = Vec::new();
+    if is_orbital_cube {
+        let line = reader.expect_line("the number of orbitals")?;
+        let (integers, _) = parse_fields(&line, 1, 0).map_err(|message| reader.error(message))?;
+        if integers[0] <= 0 {
+            return Err(reader.error(format!("invalid number of orbitals {}", integers[0])));
+        }
+        let count = integers[0] as usize;
+        orbitals = line
+            .split_whitespace()
+            .skip(1)
+            .map(|field| field.parse::<i64>())
+            .collect::<Result<Vec<i64>, _>>()
+            .map_err(|_| reader.error(format!("invalid orbitals \"{}\"", line.trim())))?;
+        if orbitals.len() < count {
+            let rest: Vec<i64> = read_numbers(&mut reader, count - orbitals.len(), "orbitals")?;
+            orbitals.extend(rest);
+        }
+        if orbitals.len() != count {
+            return Err(reader.error(format!("expected {} orbitals", count)));
+        }
+    }
+
+    let number_of_grids = if is_orbital_cube {
+        orbitals.len()
+    } else {
+        values_per_point
+    };
+    let count = dimensions
+        .iter()
+        .try_fold(number_of_grids, |count, points| count.checked_mul(*points))
+        .ok_or_else(|| reader.error("the grid has too many points".to_string()))?;
+    let values: Vec<f64> = read_numbers(&mut reader, count, "values")?;
+
+    let origin = to_angstrom(&origin);
+    let axes = [
+        to_angstrom(&axes[0]),
+        to_angstrom(&axes[1]),
+        to_angstrom(&axes[2]),
+    ];
+    let mut grids = Vec::with_capacity(number_of_grids);
+    for n in 0..number_of_grids {
+        let mut grid = Grid::new(origin, axes, dimensions);
+        for (value, point_values) in grid.data.iter_mut().zip(values.chunks(number_of_grids)) {
+            *value = point_values[n];
+        }
+        grids.push(grid);
+    }
+
+    return Ok(Cube {
+        molecule: mol,
+        grids: grids,
+        orbitals: orbitals,
+    });
+}
+
+/** format_value() formats a value as Gaussian does, e.g. " 1.23456E-03" */
+fn format_value(value: f64) -> String {
+    let text = format!("{:.5E}", value);
+    let (mantissa, exponent) = text.split_once('E').unwrap_or((&text, "0"));
+    let exponent: i32 = exponent.parse().unwrap_or(0);
+    let sign = if exponent < 0 { '-' } else { '+' };
+    return format!(
+        "{:>13}",
+        format!("{}E{}{:02}", mantissa, sign, exponent.abs())
+    );
+}
+
+/**
+ * write() writes a cube file, lengths in bohr. The grids must have the same points, and
+ * a cube with several grids needs an orbital number for each
+ */
+pub fn write<W: Write>(mut writer: W, cube: &Cube) -> Result<(), IoError> {
+    let error = |message: &str| IoError::parse(FORMAT, 0, message.to_string());
+    let grid = cube
+        .grids
+        .first()
+        .ok_or_else(|| error("the cube has no grid"))?;
+    if cube.grids.iter().any(|other| !grid.is_compatible(other)) {
+        return Err(error("the grids of the cube do not have the same points"));
+    }
+    let is_orbital_cube = !cube.orbitals.is_empty();
+    if (is_orbital_cube || cube.grids.len() > 1) && cube.orbitals.len() != cube.grids.len() {
+        return Err(error("a cube with several grids needs an orbital for each"));
+    }
+
+    let mol = &cube.molecule;
+    let one_line = |text: &str| text.trim().replace(['\n', '\r'], " ");
+    writeln!(writer, "{}", one_line(&mol.name))?;
+    writeln!(writer, "{}", one_line(&mol.remark))?;
+
+    let atoms = mol.get_atoms();
+    let number_of_atoms = atoms.len() as i64;
+    let to_bohr = |v: &[f64; 3]| [v[0] / BOHR, v[1] / BOHR, v[2] / BOHR];
+    let origin = to_bohr(&grid.origin);
+    writeln!(
+        writer,
+        "{:5}{:12.6}{:12.6}{:12.6}",
+        if is_orbital_cube {
+            -number_of_atoms
+        } else {
+            number_of_atoms
+        },
+        origin[0],
+        origin[1],
+        origin[2]
+    )?;
+    for (n, axis) in grid.dimensions.iter().zip(grid.axes.iter()) {
+        let step = to_bohr(axis);
+        writeln!(
+            writer,
+            "{:5}{:12.6}{:12.6}{:12.6}",
+            n, step[0], step[1], step[2]
+        )?;
+    }
+
+    let nuclear_charges = match mol.get_atom_property("nuclear_charge") {
+        Some(AtomProperty::Reals(values)) => Some(values),
+        _ => None,
+    };
+    for (idx, atom) in atoms.iter().enumerate() {
+        let atomic_number = atomprop::get_atomic_number(&atom.symbol).unwrap_or(0);
+        let nuclear_charge = nuclear_charges
+            .map(|values| values[idx])
+            .unwrap_or(atomic_number as f64);
+        let p = to_bohr(&[
+            atom.center.x as f64,
+            atom.center.y as f64,
+            atom.center.z as f64,
+        ]);
+        writeln!(
+            writer,
+            "{:5}{:12.6}{:12.6}{:12.6}{:12.6}",
+            atomic_number, nuclear_charge, p[0], p[1], p[2]
+        )?;
+    }
+
+    if is_orbital_cube {
+        write!(writer, "{:5}", cube.orbitals.len())?;
+        for (count, orbital) in cube.orbitals.iter().enumerate() {
+            if (count + 1).is_multiple_of(10) {
+                writeln!(writer)?;
+            }
+            write!(writer, "{:5}", orbital)?;
+        }
+        writeln!(writer)?;
+    }
+
+    // each row along the last axis starts a new line
+    let [n0, n1, n2] = grid.dimensions;
+    for i in 0..n0 {
+        for j in 0..n1 {
+            let mut count = 0;
+            for k in 0..n2 {
+                let idx = grid.get_index(i, j, k);
+                for other in cube.grids.iter() {
+                    write!(writer, "{}", format_value(other.data[idx]))?;
+                    count += 1;
+                    if count % VALUES_PER_LINE == 0 {
+                        writeln!(writer)?;
+                    }
+                }
+            }
+            if count % VALUES_PER_LINE != 0 {
+                writeln!(writer)?;
+            }
+        }
+    }
+
+    return Ok(());
+}
+
+/** Unit tests for the above module */
+#[cfg(test)]
+mod tests {
+    use crate::grid::Grid;
+    use crate::io::cube::Cube;
+    use crate::io::BOHR;
+    use crate::property::AtomProperty;
+    use crate::testutil;
+
+    const WATER_CUBE: &str = " water density
+ SCF Total Density
+    3   -1.000000   -1.000000   -1.000000
+    2    1.000000    0.000000    0.000000
+    3    0.000000    1.000000    0.000000
+    4    0.000000    0.000000    1.000000
+    8    8.000000    0.000000    0.000000    0.221665
+    1    1.000000    0.000000    1.430496   -0.886659
+    1    1.000000    0.000000   -1.430496   -0.886659
+ 1.00000E-01 2.00000E-01 3.00000E-01 4.00000E-01
+ 1.10000E-01 2.10000E-01 3.10000E-01 4.10000E-01
+ 1.20000E-01 2.20000E-01 3.20000E-01 4.20000E-01
+ 1.30000E-01 2.30000E-01 3.30000E-01 4.30000E-01
+ 1.40000E-01 2.40000E-01 3.40000E-01 4.40000E-01
+ 1.50000E-01 2.50000E-01 3.50000E-01 4.50000E-01
+";
+
+    #[test]
+    fn cube_read() {
+        let cube = super::read(WATER_CUBE.as_bytes()).unwrap();
+        let mol = &cube.molecule;
+        assert_eq!(mol.name, "water density");
+        assert_eq!(mol.remark, "SCF Total Density");
+        assert_eq!(mol.get_atoms().len(), 3);
+        assert_eq!(mol.get_atoms()[0].symbol, "O");
+        assert!((mol.get_atoms()[1].center.y as f64 - 1.430496 * BOHR).abs() < 1e-6);
+        assert_eq!(mol.get_atom_property("nuclear_charge"), None);
+        assert!(cube.orbitals.is_empty());
+
+        let grid = cube.get_grid();
+        assert_eq!(grid.dimensions, [2, 3, 4]);
+        assert!((grid.origin[0] + BOHR).abs() < 1e-12);
+        assert!((grid.axes[2][2] - BOHR).abs() < 1e-12);
+        assert_eq!(grid.get(0, 0, 3), 0.4);
+        assert_eq!(grid.get(1, 2, 1), 0.25);
+        let statistics = grid.get_statistics();
+        assert_eq!(statistics.maximum, 0.45);
+        assert_eq!(statistics.maximum_point, [1, 2, 3]);
+
+        let truncated: String = WATER_CUBE.lines().take(12).collect::<Vec<_>>().join("\n");
+        let err = super::read(truncated.as_bytes()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "cube: line 12: unexpected end of file, expected 24 values, found 12"
+        );
+
+        let bad_atom = WATER_CUBE.replace("    8    8.000000", "  200    8.000000");
+        let err = super::read(bad_atom.as_bytes()).unwrap_err();
+        assert_eq!(err.to_string(), "cube: line 7: unknown atomic number 200");
+    }
+
+    #[test]
+    fn cube_round_trip() {
+        let mut cube = super::read(WATER_CUBE.as_bytes()).unwrap();
+        cube.molecule
+            .set_atom_property("nuclear_charge", AtomProperty::Reals(vec![6.0, 1.0, 1.0]));
+
+        let mut output = Vec::new();
+        super::write(&mut output, &cube).unwrap();
+        let text = String::from_utf8(output).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[2], "    3   -1.000000   -1.000000   -1.000000");
+        assert_eq!(
+            lines[6],
+            "    8    6.000000    0.000000    0.000000    0.221665"
+        );
+        // a row of four values fits on one line
+        assert_eq!(
+            lines[9],
+            "  1.00000E-01  2.00000E-01  3.00000E-01  4.00000E-01"
+        );
+        assert_eq!(lines.len(), 15);
+
+        let again = super::read(text.as_bytes()).unwrap();
+        assert_eq!(
+            again.molecule.get_atom_property("nuclear_charge"),
+            Some(&AtomProperty::Reals(vec![6.0, 1.0, 1.0]))
+        );
+        assert_eq!(again.get_grid().data, cube.get_grid().data);
+        assert!(again.get_grid().is_compatible(cube.get_grid()));
+
+        assert_eq!(super::format_value(-1.5e-12), " -1.50000E-12");
+        assert_eq!(super::format_value(0.0), "  0.00000E+00");
+    }
+
+    #[test]
+    fn cube_orbitals() {
+        let water = testutil::get_water();
+        let axes = [[0.2, 0.0, 0.0], [0.0, 0.2, 0.0], [0.0, 0.0, 0.2]];
+        let homo = Grid::from_data([-1.0; 3], axes, [2, 2, 2], (0..8).map(f64::from).collect());
+        let lumo = homo.as_ref().unwrap().scale(-1.0);
+
+        let mut cube = Cube::new(water, homo.unwrap());
+        cube.grids.push(lumo);
+        let mut output = Vec::new();
+        let err = super::write(&mut output, &cube).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "cube: a cube with several grids needs an orbital for each"
+        );
+
+        cube.orbitals = vec![5, 6];
+        let mut output = Vec::new();
+        super::write(&mut output, &cube).unwrap();
+        let text = String::from_utf8(output).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert!(lines[2].starts_with("   -3"));
+        assert_eq!(lines[9], "    2    5    6");
+        // the values of both orbitals at a point are next to each other
+        assert!(lines[10].starts_with("  0.00000E+00 -0.00000E+00  1.00000E+00 -1.00000E+00"));
+
+        let again = super::read(text.as_bytes()).unwrap();
+        assert_eq!(again.orbitals, vec![5, 6]);
+        assert_eq!(again.grids.len(), 2);
+        assert_eq!(again.grids[1].get(1, 1, 1), -7.0);
+        assert!((again.grids[0].origin[2] + 1.0).abs() < 1e-6);
+        assert_eq!(again.molecule.get_atoms()[2].symbol, "H");
+    }
+
+    #[test]
+    fn cube_read_errors() {
+        let header = |atoms: &str, points: &str| {
+            format!(
+                "big\ncube\n{:>5}    0.000000    0.000000    0.000000\n\
+                 {:>5}    0.200000    0.000000    0.000000\n\
+                 {:>5}    0.000000    0.200000    0.000000\n\
+                 {:>5}    0.000000    0.000000    0.200000\n\
+                 8    8.000000    0.000000    0.000000    0.000000\n",
+                atoms, points, points, points
+            )
+        };
+
+        // the counts of the header do not decide how much is allocated
+        let err = super::read(header("1", "100000").as_bytes()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "cube: line 7: unexpected end of file, expected 1000000000000000 values, found 0"
+        );
+        let err = super::read(header("1", "2000000000").as_bytes()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "cube: line 7: the grid has too many points"
+        );
+        let err = super::read(header("2000000000", "2").as_bytes()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "cube: line 8: unexpected end of file, expected an atom"
+        );
+    }
+}
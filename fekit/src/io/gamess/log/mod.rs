@@ -0,0 +1,480 @@
+/**
+ * Author: V. Ganesh
+ * License: MIT
+ */
+
+/**
+ * log module, reads the results of a GAMESS (US) run from its output (.log) file.
+ *
+ * The geometry of each optimization step becomes a frame (the input geometry if none was
+ * printed in angstrom), along with the final SCF, MP2 and CCSD(T) energies of each step
+ * (in hartree), the energy gradients (hartree/bohr), each set of molecular orbitals
+ * printed (alpha and beta sets of a UHF run are separate sets), and the last Mulliken and
+ * Löwdin atomic populations and charges.
+ *
+ * A run that crashed or was cut short still gives what was read up to that point, with
+ * the reason in GamessLog.error.
+ */
+use std::io::BufRead;
+
+use crate::atom::Atom;
+use crate::atom::AtomOperations;
+use crate::atomprop;
+use crate::io::IoError;
+use crate::io::LineReader;
+use crate::io::BOHR;
+use crate::molecule::Molecule;
+use crate::point::Point;
+
+const FORMAT: &str = "gamess log";
+
+/** Orbital - a molecular orbital, with a coefficient for each basis function */
+#[allow(dead_code)]
+#[derive(Clone, Default, PartialEq, Debug)]
+pub struct Orbital {
+    pub energy: f64,
+    pub symmetry: String,
+    pub coefficients: Vec<f64>,
+}
+
+/** GamessLog - the results read from a GAMESS output file */
+#[allow(dead_code)]
+#[derive(Default, Debug)]
+pub struct GamessLog {
+    pub frames: Vec<Molecule>,
+    pub scf_energies: Vec<f64>,
+    pub mp2_energies: Vec<f64>,
+    pub ccsd_t_energies: Vec<f64>,
+    pub gradients: Vec<Vec<[f64; 3]>>,
+    pub orbitals: Vec<Vec<Orbital>>,
+    pub mulliken_populations: Vec<f64>,
+    pub mulliken_charges: Vec<f64>,
+    pub lowdin_populations: Vec<f64>,
+    pub lowdin_charges: Vec<f64>,
+    /** why the results are incomplete: an abnormal termination, or the file ending early */
+    pub error: Option<IoError>,
+}
+
+#[allow(dead_code)]
+impl GamessLog {
+    /** get_final_frame() returns the last geometry of the run */
+    pub fn get_final_frame(&self) -> Option<&Molecule> {
+        return self.frames.last();
+    }
+
+    /** get_final_energy() returns the last SCF energy of the run */
+    pub fn get_final_energy(&self) -> Option<f64> {
+        return self.scf_energies.last().copied();
+    }
+
+    /** is_complete() checks whether the run terminated normally */
+    pub fn is_complete(&self) -> bool {
+        return self.error.is_none();
+    }
+}
+
+/** get_value_after() returns the first number after the given text on a line */
+fn get_value_after(line: &str, text: &str) -> Option<f64> {
+    let idx = line.find(text)?;
+    return line[idx + text.len()..]
+        .split_whitespace()
+        .next()
+        .and_then(|value| value.parse().ok());
+}
+
+/** get_numbers() returns the numbers of a line, None if any field is not a number */
+fn get_numbers(fields: &[&str]) -> Option<Vec<f64>> {
+    return fields.iter().map(|field| field.parse().ok()).collect();
+}
+
+/** LogParser - reads the sections of an output file into a GamessLog */
+struct LogParser<R: BufRead> {
+    reader: LineReader<R>,
+    log: GamessLog,
+    angstrom_frames: Vec<Molecule>,
+    bohr_frames: Vec<Molecule>,
+}
+
+impl<R: BufRead> LogParser<R> {
+    /** read_coordinates() reads atom lines (label, nuclear charge, x, y, z) up to a blank line */
+    fn read_coordinates(&mut self, scale: f64) -> Result<Molecule, IoError> {
+        let mut mol = Molecule::new("".to_string(), "".to_string());
+
+        loop {
+            let line = self.reader.expect_line("the coordinates")?;
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.is_empty() {
+                return Ok(mol);
+            }
+
+            let numbers = match get_numbers(&fields[1..]) {
+                Some(numbers) if numbers.len() == 4 => numbers,
+                _ => {
+                    return Err(self.reader.error(format!(
+                        "expected an atom with its nuclear charge and coordinates, found \"{}\"",
+                        line.trim()
+                    )))
+                }
+            };
+
+            let symbol = atomprop::get_element_symbol(numbers[0].round() as u8).unwrap_or("X");
+            mol.add_atom(Atom {
+                center: Point {
+                    x: (numbers[1] * scale) as f32,
+                    y: (numbers[2] * scale) as f32,
+                    z: (numbers[3] * scale) as f32,
+                },
+                charge: 0.0,
+                symbol: symbol.to_string(),
+                remark: "".to_string(),
+            });
+        }
+    }
+
+    /** read_gradient() reads the gradient table, after its "GRADIENT OF THE ENERGY" title */
+    fn read_gradient(&mut self) -> Result<(), IoError> {
+        loop {
+            let line = self.reader.expect_line("the gradient")?;
+            if line.trim_start().starts_with("UNITS ARE HARTREE/BOHR") {
+                break;
+            }
+        }
+
+        let mut gradient = Vec::new();
+        loop {
+            let line = self.reader.expect_line("the gradient")?;
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.is_empty() {
+                break;
+            }
+
+            match get_numbers(&fields[fields.len().saturating_sub(3)..]) {
+                Some(numbers) if fields.len() >= 5 => {
+                    gradient.push([numbers[0], numbers[1], numbers[2]])
+                }
+                _ => {
+                    return Err(self.reader.error(format!(
+                        "expected the gradient of an atom, found \"{}\"",
+                        line.trim()
+                    )))
+                }
+            }
+        }
+
+        self.log.gradients.push(gradient);
+        return Ok(());
+    }
+
+    /**
+     * read_orbitals() reads a set of orbitals, printed in blocks of up to five: a line of
+     * orbital numbers, the energies, the symmetries and a line for each basis function
+     */
+    fn read_orbitals(&mut self) -> Result<(), IoError> {
+        let mut orbitals: Vec<Orbital> = Vec::new();
+
+        while let Some(line) = self.reader.peek_line()? {
+            let line = line.to_string();
+            let fields: Vec<&str> = line.split_whitespace().collect();
+
+            if fields.is_empty() || fields.iter().all(|field| field.starts_with("---")) {
+                self.reader.next_line()?;
+                continue;
+            }
+            if fields.iter().any(|field| field.parse::<usize>().is_err()) {
+                break;
+            }
+            self.reader.next_line()?;
+
+            let number_of_orbitals = fields.len();
+            let line = self.reader.expect_line("the orbital energies")?;
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let energies = get_numbers(&fields)
+                .filter(|energies| energies.len() == number_of_orbitals)
+                .ok_or_else(|| {
+                    self.reader.error(format!(
+                        "expected {} orbital energies, found \"{}\"",
+                        number_of_orbitals,
+                        line.trim()
+                    ))
+                })?;
+
+            let mut block: Vec<Orbital> = energies
+                .iter()
+                .map(|energy| Orbital {
+                    energy: *energy,
+                    ..Default::default()
+                })
+                .collect();
+
+            // the set ends at a blank line, or a line that is not part of it
+            loop {
+                let line = match self.reader.peek_line()? {
+                    Some(line) => line.to_string(),
+                    None => self.reader.expect_line("the orbital coefficients")?,
+                };
+                let fields: Vec<&str> = line.split_whitespace().collect();
+                let split = fields.len().saturating_sub(number_of_orbitals);
+                let has_coefficients = !block[0].coefficients.is_empty();
+
+                match get_numbers(&fields[split..]) {
+                    _ if fields.is_empty() => {
+                        self.reader.next_line()?;
+                        break;
+                    }
+                    Some(coefficients) if split > 0 => {
+                        for (orbital, coefficient) in block.iter_mut().zip(coefficients) {
+                            orbital.coefficients.push(coefficient);
+                        }
+                    }
+                    None if split == 0 && !has_coefficients => {
+                        for (orbital, symmetry) in block.iter_mut().zip(fields.iter()) {
+                            orbital.symmetry = symmetry.to_string();
+                        }
+                    }
+                    _ => break,
+                }
+                self.reader.next_line()?;
+            }
+
+            orbitals.extend(block);
+        }
+
+        self.log.orbitals.push(orbitals);
+        return Ok(());
+    }
+
+    /** read_populations() reads the table of Mulliken and Löwdin atomic populations */
+    fn read_populations(&mut self) -> Result<(), IoError> {
+        self.reader.expect_line("the populations")?;
+
+        let mut rows = Vec::new();
+        loop {
+            let line = self.reader.expect_line("the populations")?;
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.is_empty() {
+                break;
+            }
+
+            match get_numbers(&fields[fields.len().saturating_sub(4)..]) {
+                Some(numbers) if fields.len() >= 6 => rows.push(numbers),
+                _ => {
+                    return Err(self.reader.error(format!(
+                        "expected the populations of an atom, found \"{}\"",
+                        line.trim()
+                    )))
+                }
+            }
+        }
+
+        self.log.mulliken_populations = rows.iter().map(|row| row[0]).collect();
+        self.log.mulliken_charges = rows.iter().map(|row| row[1]).collect();
+        self.log.lowdin_populations = rows.iter().map(|row| row[2]).collect();
+        self.log.lowdin_charges = rows.iter().map(|row| row[3]).collect();
+        return Ok(());
+    }
+
+    /** read_line() reads one line of the output, and the block it starts */
+    fn read_line(&mut self, line: &str) -> Result<(), IoError> {
+        let trimmed = line.trim();
+
+        if trimmed.starts_with("COORDINATES OF ALL ATOMS ARE (ANGS)") {
+            self.reader.expect_line("the coordinates")?;
+            self.reader.expect_line("the coordinates")?;
+            let frame = self.read_coordinates(1.0)?;
+            self.angstrom_frames.push(frame);
+        } else if trimmed.starts_with("ATOM") && trimmed.ends_with("COORDINATES (BOHR)") {
+            self.reader.expect_line("the coordinates")?;
+            let frame = self.read_coordinates(BOHR)?;
+            self.bohr_frames.push(frame);
+        } else if trimmed.starts_with("FINAL") && trimmed.contains("ENERGY IS") {
+            if let Some(energy) = get_value_after(line, "ENERGY IS") {
+                self.log.scf_energies.push(energy);
+            }
+        } else if trimmed.starts_with("E(MP2)=") {
+            if let Some(energy) = get_value_after(line, "E(MP2)=") {
+                self.log.mp2_energies.push(energy);
+            }
+        } else if trimmed.starts_with("CCSD(T) ENERGY:") {
+            if let Some(energy) = get_value_after(line, "CCSD(T) ENERGY:") {
+                self.log.ccsd_t_energies.push(energy);
+            }
+        } else if trimmed == "GRADIENT OF THE ENERGY" {
+            self.read_gradient()?;
+        } else if trimmed == "EIGENVECTORS" || trimmed == "MOLECULAR ORBITALS" {
+            self.read_orbitals()?;
+        } else if trimmed == "TOTAL MULLIKEN AND LOWDIN ATOMIC POPULATIONS" {
+            self.read_populations()?;
+        } else if (trimmed.contains("TERMINATED -ABNORMALLY-")
+            || trimmed.contains("Execution terminated due to error"))
+            && self.log.error.is_none()
+        {
+            self.log.error = Some(self.reader.error(format!("the run failed: {}", trimmed)));
+        }
+
+        return Ok(());
+    }
+}
+
+/** read() reads the results of a GAMESS output file, it fails only if the file cannot be read */
+#[allow(dead_code)]
+pub fn read<R: BufRead>(reader: R) -> Result<GamessLog, IoError> {
+    let mut parser = LogParser {
+        reader: LineReader::new(reader, FORMAT),
+        log: GamessLog::default(),
+        angstrom_frames: Vec::new(),
+        bohr_frames: Vec::new(),
+    };
+    let mut terminated = false;
+
+    while let Some(line) = parser.reader.next_line()? {
+        if line.contains("EXECUTION OF GAMESS TERMINATED") {
+            terminated = true;
+        }
+
+        match parser.read_line(&line) {
+            Ok(()) => {}
+            Err(IoError::Io(err)) => return Err(IoError::Io(err)),
+            Err(err) => {
+                parser.log.error = Some(err);
+                terminated = true;
+                break;
+            }
+        }
+    }
+
+    if !terminated && parser.log.error.is_none() {
+        parser.log.error = Some(IoError::parse(
+            FORMAT,
+            parser.reader.get_line_number(),
+            "the run did not finish, GAMESS did not terminate normally".to_string(),
+        ));
+    }
+
+    let mut log = parser.log;
+    if parser.angstrom_frames.is_empty() {
+        log.frames = parser.bohr_frames;
+    } else {
+        log.frames = parser.angstrom_frames;
+    }
+    return Ok(log);
+}
+
+/** Unit tests for the above module */
+#[cfg(test)]
+mod tests {
+    const LOG: &str = " ATOM      ATOMIC                      COORDINATES (BOHR)
+           CHARGE         X                   Y                   Z
+ O           8.0     0.0000000000        0.0000000000        0.2253686530
+ H           1.0     0.0000000000        1.4423196600       -0.9014746120
+ H           1.0     0.0000000000       -1.4423196600       -0.9014746120
+
+          ------------
+          EIGENVECTORS
+          ------------
+
+                      1          2          3
+                  -20.2516    -1.2575    -0.5938
+                     A1         A1         B2
+    1  O  1  S    0.994213  -0.233812   0.000000
+    2  O  1  S    0.025826   0.844427   0.000000
+    3  H  2  S   -0.005600   0.155900   0.445000
+    4  H  3  S   -0.005600   0.155900  -0.445000
+
+                      4
+                   -0.4597
+                     A1
+    1  O  1  S    0.103612
+    2  O  1  S   -0.530476
+    3  H  2  S    0.276000
+    4  H  3  S    0.276000
+ ...... END OF RHF CALCULATION ......
+
+                       FINAL RHF ENERGY IS      -74.9629282934 AFTER   9 ITERATIONS
+
+          ----------------------
+          GRADIENT OF THE ENERGY
+          ----------------------
+
+ UNITS ARE HARTREE/BOHR    E'X               E'Y               E'Z
+    1 O                0.000000000        0.000000000       -0.025000000
+    2 H                0.000000000        0.015000000        0.012500000
+    3 H                0.000000000       -0.015000000        0.012500000
+
+ COORDINATES OF ALL ATOMS ARE (ANGS)
+   ATOM   CHARGE       X              Y              Z
+ ------------------------------------------------------------
+ O           8.0   0.0000000000   0.0000000000   0.1270000000
+ H           1.0   0.0000000000   0.7500000000  -0.4810000000
+ H           1.0   0.0000000000  -0.7500000000  -0.4810000000
+
+          TOTAL MULLIKEN AND LOWDIN ATOMIC POPULATIONS
+       ATOM         MULL.POP.    CHARGE          LOW.POP.     CHARGE
+    1 O             8.330414   -0.330414         8.249773   -0.249773
+    2 H             0.834793    0.165207         0.875114    0.124886
+    3 H             0.834793    0.165207         0.875114    0.124886
+
+";
+
+    #[test]
+    fn gamess_log_read() {
+        let text = format!("{} EXECUTION OF GAMESS TERMINATED NORMALLY\n", LOG);
+        let log = super::read(text.as_bytes()).unwrap();
+
+        assert!(log.is_complete());
+        assert_eq!(log.frames.len(), 1);
+        let water = log.get_final_frame().unwrap();
+        assert_eq!(water.get_atoms()[1].symbol, "H");
+        assert_eq!(water.get_atoms()[1].center.y, 0.75);
+        assert_eq!(log.get_final_energy(), Some(-74.9629282934));
+
+        assert_eq!(log.gradients.len(), 1);
+        assert_eq!(log.gradients[0][1], [0.0, 0.015, 0.0125]);
+
+        assert_eq!(log.orbitals.len(), 1);
+        let orbitals = &log.orbitals[0];
+        assert_eq!(orbitals.len(), 4);
+        assert_eq!(orbitals[2].symmetry, "B2");
+        assert_eq!(orbitals[3].energy, -0.4597);
+        assert_eq!(
+            orbitals[1].coefficients,
+            vec![-0.233812, 0.844427, 0.1559, 0.1559]
+        );
+
+        assert_eq!(log.mulliken_charges, vec![-0.330414, 0.165207, 0.165207]);
+        assert_eq!(log.lowdin_populations[0], 8.249773);
+    }
+
+    #[test]
+    fn gamess_log_incomplete() {
+        // only the input geometry, in bohr
+        let text = &LOG[..LOG.find("          ------------").unwrap()];
+        let log = super::read(text.as_bytes()).unwrap();
+        assert_eq!(log.frames.len(), 1);
+        assert!((log.frames[0].get_atoms()[1].center.y - 0.763239).abs() < 1e-5);
+        assert_eq!(
+            log.error.unwrap().to_string(),
+            "gamess log: line 6: the run did not finish, GAMESS did not terminate normally"
+        );
+
+        let text = format!(
+            "{} EXECUTION OF GAMESS TERMINATED -ABNORMALLY- AT Mon Jan  1 00:00:00 2024\n",
+            LOG
+        );
+        let log = super::read(text.as_bytes()).unwrap();
+        assert_eq!(log.scf_energies.len(), 1);
+        assert_eq!(
+            log.error.unwrap().to_string(),
+            "gamess log: line 52: the run failed: EXECUTION OF GAMESS TERMINATED \
+             -ABNORMALLY- AT Mon Jan  1 00:00:00 2024"
+        );
+
+        let text = LOG.replace("0.015000000        0.012500000", "0.015000000");
+        let log = super::read(text.as_bytes()).unwrap();
+        assert!(log.gradients.is_empty());
+        assert_eq!(
+            log.error.unwrap().to_string(),
+            "gamess log: line 36: expected the gradient of an atom, found \
+             \"2 H                0.000000000        0.015000000\""
+        );
+    }
+}
@@ -0,0 +1,465 @@
+/**
+ * Author: V. Ganesh
+ * License: MIT
+ */
+
+/**
+ * gamess module, writes GAMESS (US) input files.
+ *
+ * An input is a list of $GROUP ... $END groups of KEYWORD=value options, e.g. $CONTRL and
+ * $BASIS, followed by the $DATA group: a title, the point group and the atoms, each as
+ * its name, nuclear charge and Cartesian coordinates in angstrom. When the point group is
+ * not C1, only the symmetry unique atoms are written and GAMESS generates the others, so
+ * the molecule must be oriented in the GAMESS master frame: the principal axis along z, a
+ * CNV mirror plane as xz, a DN two-fold axis along x and the CS mirror plane as xy.
+ *
+ * The log module reads the results of a run.
+ */
+use std::io::Write;
+
+use crate::atomprop;
+use crate::io;
+use crate::io::IoError;
+use crate::molecule::Molecule;
+
+pub mod log;
+
+const FORMAT: &str = "gamess";
+
+/** longest line GAMESS reads, input is read from columns 2 to 80 */
+const MAXIMUM_LINE_LENGTH: usize = 79;
+
+/** tolerance (in angstrom) when matching an atom to the image of another */
+const SYMMETRY_TOLERANCE: f64 = 1e-3;
+
+type Matrix = [[f64; 3]; 3];
+
+const IDENTITY: Matrix = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+
+fn multiply(a: &Matrix, b: &Matrix) -> Matrix {
+    let mut product = [[0.0; 3]; 3];
+    for (i, row) in product.iter_mut().enumerate() {
+        for (j, value) in row.iter_mut().enumerate() {
+            *value = (0..3).map(|k| a[i][k] * b[k][j]).sum();
+        }
+    }
+    return product;
+}
+
+fn apply(m: &Matrix, p: &[f64; 3]) -> [f64; 3] {
+    let mut image = [0.0; 3];
+    for (value, row) in image.iter_mut().zip(m.iter()) {
+        *value = row[0] * p[0] + row[1] * p[1] + row[2] * p[2];
+    }
+    return image;
+}
+
+fn is_same(a: &Matrix, b: &Matrix) -> bool {
+    return a
+        .iter()
+        .flatten()
+        .zip(b.iter().flatten())
+        .all(|(x, y)| (x - y).abs() < 1e-6);
+}
+
+/** get_rotation() returns the rotation by 360/n degrees about z, followed by z -> -z if improper */
+fn get_rotation(n: usize, improper: bool) -> Matrix {
+    let angle = 2.0 * std::f64::consts::PI / n as f64;
+    let (sin, cos) = angle.sin_cos();
+    let z = if improper { -1.0 } else { 1.0 };
+    return [[cos, -sin, 0.0], [sin, cos, 0.0], [0.0, 0.0, z]];
+}
+
+/**
+ * get_operations() returns the operations of a point group in the GAMESS master frame,
+ * given its GAMESS name (C1, CS, CI, CN, S2N, CNH, CNV, DN, DNH, DND, T, TH, TD, O, OH)
+ * and, for the axial groups, the order n of the principal axis
+ */
+fn get_operations(group: &str, order: usize) -> Result<Vec<Matrix>, String> {
+    let reflection_xy: Matrix = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, -1.0]];
+    let reflection_xz: Matrix = [[1.0, 0.0, 0.0], [0.0, -1.0, 0.0], [0.0, 0.0, 1.0]];
+    let rotation_x: Matrix = [[1.0, 0.0, 0.0], [0.0, -1.0, 0.0], [0.0, 0.0, -1.0]];
+    let inversion: Matrix = [[-1.0, 0.0, 0.0], [0.0, -1.0, 0.0], [0.0, 0.0, -1.0]];
+    let rotation_111: Matrix = [[0.0, 0.0, 1.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]];
+    let reflection_diagonal: Matrix = [[0.0, 1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0]];
+
+    let group = group.to_uppercase();
+    let is_axial = matches!(
+        group.as_str(),
+        "CN" | "S2N" | "CNH" | "CNV" | "DN" | "DNH" | "DND"
+    );
+    if is_axial && order < 1 {
+        return Err(format!(
+            "the point group {} needs the order of its axis",
+            group
+        ));
+    }
+
+    let cn = get_rotation(order.max(1), false);
+    let s2n = get_rotation(2 * order.max(1), true);
+    let generators = match group.as_str() {
+        "C1" => vec![],
+        "CS" => vec![reflection_xy],
+        "CI" => vec![inversion],
+        "CN" => vec![cn],
+        "S2N" => vec![s2n],
+        "CNH" => vec![cn, reflection_xy],
+        "CNV" => vec![cn, reflection_xz],
+        "DN" => vec![cn, rotation_x],
+        "DNH" => vec![cn, rotation_x, reflection_xy],
+        "DND" => vec![s2n, rotation_x],
+        "T" => vec![rotation_x, rotation_111],
+        "TH" => vec![rotation_x, rotation_111, inversion],
+        "TD" => vec![rotation_x, rotation_111, reflection_diagonal],
+        "O" => vec![get_rotation(4, false), rotation_111],
+        "OH" => vec![get_rotation(4, false), rotation_111, inversion],
+        _ => return Err(format!("unknown point group \"{}\"", group)),
+    };
+
+    // the closure of the generators under multiplication
+    let mut operations = vec![IDENTITY];
+    let mut idx = 0;
+    while idx < operations.len() {
+        for generator in generators.iter() {
+            let product = multiply(generator, &operations[idx]);
+            if !operations.iter().any(|op| is_same(op, &product)) {
+                operations.push(product);
+            }
+        }
+        idx += 1;
+    }
+
+    return Ok(operations);
+}
+
+/**
+ * get_unique_atoms() returns the indices of the symmetry unique atoms, the first atom of
+ * each set of atoms the operations map onto each other
+ */
+fn get_unique_atoms(mol: &Molecule, operations: &[Matrix]) -> Result<Vec<usize>, String> {
+    let atoms = mol.get_atoms();
+    let positions: Vec<[f64; 3]> = atoms
+        .iter()
+        .map(|atom| {
+            [
+                atom.center.x as f64,
+                atom.center.y as f64,
+                atom.center.z as f64,
+            ]
+        })
+        .collect();
+
+    let mut is_image = vec![false; atoms.len()];
+    let mut unique = Vec::new();
+
+    for (idx, position) in positions.iter().enumerate() {
+        if !is_image[idx] {
+            unique.push(idx);
+        }
+
+        for op in operations.iter() {
+            let image = apply(op, position);
+            let found = positions.iter().enumerate().position(|(other, p)| {
+                atoms[other].symbol == atoms[idx].symbol
+                    && (0..3).all(|k| (p[k] - image[k]).abs() < SYMMETRY_TOLERANCE)
+            });
+
+            match found {
+                Some(other) => is_image[other] = is_image[other] || other != idx,
+                None => {
+                    return Err(format!(
+                        "atom {} has no symmetry equivalent, the molecule must have the \
+                         symmetry of the point group in the GAMESS master frame",
+                        idx + 1
+                    ))
+                }
+            }
+        }
+    }
+
+    return Ok(unique);
+}
+
+/** GamessWriter - writes a molecule as a GAMESS input file */
+#[allow(dead_code)]
+#[derive(Clone, PartialEq, Debug)]
+pub struct GamessWriter {
+    groups: Vec<(String, Vec<(String, String)>)>,
+    charge: Option<i64>,
+    multiplicity: Option<i64>,
+    point_group: String,
+    order: usize,
+}
+
+impl Default for GamessWriter {
+    fn default() -> GamessWriter {
+        return GamessWriter {
+            groups: Vec::new(),
+            charge: None,
+            multiplicity: None,
+            point_group: "C1".to_string(),
+            order: 0,
+        }
+        .option("CONTRL", "RUNTYP", "ENERGY")
+        .basis("GBASIS=STO NGAUSS=3");
+    }
+}
+
+#[allow(dead_code)]
+impl GamessWriter {
+    pub fn new() -> GamessWriter {
+        return GamessWriter::default();
+    }
+
+    /** option() sets an option of a group, e.g. option("SYSTEM", "MWORDS", "100") */
+    pub fn option(mut self, group: &str, keyword: &str, value: &str) -> GamessWriter {
+        let group = group.trim_start_matches('$').to_uppercase();
+        let keyword = keyword.to_uppercase();
+
+        let idx = match self.groups.iter().position(|(name, _)| *name == group) {
+            Some(idx) => idx,
+            None => {
+                self.groups.push((group, Vec::new()));
+                self.groups.len() - 1
+            }
+        };
+
+        let options = &mut self.groups[idx].1;
+        match options.iter_mut().find(|(other, _)| *other == keyword) {
+            Some(option) => option.1 = value.to_string(),
+            None => options.push((keyword, value.to_string())),
+        }
+        return self;
+    }
+
+    /** scftyp() sets the kind of SCF, by default RHF for singlets and UHF otherwise */
+    pub fn scftyp(self, scftyp: &str) -> GamessWriter {
+        return self.option("CONTRL", "SCFTYP", scftyp);
+    }
+
+    /** runtyp() sets the kind of run, e.g. ENERGY, GRADIENT, OPTIMIZE or HESSIAN */
+    pub fn runtyp(self, runtyp: &str) -> GamessWriter {
+        return self.option("CONTRL", "RUNTYP", runtyp);
+    }
+
+    /** basis() replaces the $BASIS group with the given options, e.g. "GBASIS=N31 NGAUSS=6" */
+    pub fn basis(mut self, options: &str) -> GamessWriter {
+        self.groups.retain(|(name, _)| name != "BASIS");
+        for option in options.split_whitespace() {
+            let (keyword, value) = option.split_once('=').unwrap_or((option, ""));
+            self = self.option("BASIS", keyword, value);
+        }
+        return self;
+    }
+
+    /** charge() sets the total charge, by default the sum of the atom charges */
+    pub fn charge(mut self, charge: i64) -> GamessWriter {
+        self.charge = Some(charge);
+        return self;
+    }
+
+    /**
+     * multiplicity() sets the spin multiplicity, by default a singlet for an even number
+     * of electrons and a doublet for an odd number
+     */
+    pub fn multiplicity(mut self, multiplicity: i64) -> GamessWriter {
+        self.multiplicity = Some(multiplicity);
+        return self;
+    }
+
+    /**
+     * point_group() sets the point group of the $DATA group, with the order of the
+     * principal axis for the axial groups, e.g. point_group("CNV", 2) for water
+     */
+    pub fn point_group(mut self, point_group: &str, order: usize) -> GamessWriter {
+        self.point_group = point_group.to_uppercase();
+        self.order = order;
+        return self;
+    }
+
+    /** write_group() writes a group, starting new lines before they get too long */
+    fn write_group<W: Write>(
+        writer: &mut W,
+        group: &str,
+        options: &[(String, String)],
+    ) -> Result<(), IoError> {
+        let mut line = format!(" ${}", group);
+        for (keyword, value) in options.iter() {
+            let option = format!(" {}={}", keyword, value);
+            if line.len() + option.len() > MAXIMUM_LINE_LENGTH {
+                writeln!(writer, "{}", line)?;
+                line = " ".repeat(group.len() + 2);
+            }
+            line.push_str(&option);
+        }
+
+        if line.len() + 5 > MAXIMUM_LINE_LENGTH {
+            writeln!(writer, "{}", line)?;
+            line = " ".repeat(group.len() + 2);
+        }
+        writeln!(writer, "{} $END", line)?;
+        return Ok(());
+    }
+
+    /** write() writes a molecule as a GAMESS input file */
+    pub fn write<W: Write>(&self, mut writer: W, mol: &Molecule) -> Result<(), IoError> {
+        let operations = get_operations(&self.point_group, self.order)
+            .map_err(|message| IoError::parse(FORMAT, 0, message))?;
+        let unique = get_unique_atoms(mol, &operations)
+            .map_err(|message| IoError::parse(FORMAT, 0, message))?;
+
+        let charge = self.charge.unwrap_or_else(|| io::get_total_charge(mol));
+        let multiplicity = self
+            .multiplicity
+            .unwrap_or_else(|| io::get_multiplicity(mol, charge));
+
+        let mut writer_with_defaults = self
+            .clone()
+            .option("CONTRL", "ICHARG", &charge.to_string())
+            .option("CONTRL", "MULT", &multiplicity.to_string());
+        if !self.has_option("CONTRL", "SCFTYP") {
+            let scftyp = if multiplicity == 1 { "RHF" } else { "UHF" };
+            writer_with_defaults = writer_with_defaults.scftyp(scftyp);
+        }
+        for (group, options) in writer_with_defaults.groups.iter() {
+            Self::write_group(&mut writer, group, options)?;
+        }
+
+        writeln!(writer, " $DATA")?;
+        let title = if !mol.remark.trim().is_empty() {
+            mol.remark.as_str()
+        } else if !mol.name.trim().is_empty() {
+            mol.name.as_str()
+        } else {
+            "untitled"
+        };
+        writeln!(writer, "{}", title.trim().replace(['\n', '\r'], " "))?;
+
+        if self.point_group == "C1" {
+            writeln!(writer, "C1")?;
+        } else if self.order > 0 {
+            writeln!(writer, "{} {}", self.point_group, self.order)?;
+            writeln!(writer)?;
+        } else {
+            writeln!(writer, "{}", self.point_group)?;
+            writeln!(writer)?;
+        }
+
+        let atoms = mol.get_atoms();
+        for idx in unique {
+            let atom = &atoms[idx];
+            let nuclear_charge = atomprop::get_atomic_number(&atom.symbol).unwrap_or(0);
+            writeln!(
+                writer,
+                "{:<4} {:5.1} {:16.10} {:16.10} {:16.10}",
+                atom.symbol, nuclear_charge as f64, atom.center.x, atom.center.y, atom.center.z
+            )?;
+        }
+        writeln!(writer, " $END")?;
+
+        return Ok(());
+    }
+
+    /** has_option() checks whether an option of a group is set */
+    fn has_option(&self, group: &str, keyword: &str) -> bool {
+        return self.groups.iter().any(|(name, options)| {
+            name == group && options.iter().any(|(other, _)| other == keyword)
+        });
+    }
+}
+
+/** write() writes a molecule as a GAMESS input file for an RHF/STO-3G energy, in C1 symmetry */
+#[allow(dead_code)]
+pub fn write<W: Write>(writer: W, mol: &Molecule) -> Result<(), IoError> {
+    return GamessWriter::new().write(writer, mol);
+}
+
+/** Unit tests for the above module */
+#[cfg(test)]
+mod tests {
+    use crate::io::gamess::GamessWriter;
+    use crate::testutil;
+
+    #[test]
+    fn gamess_point_groups() {
+        let order = |group: &str, n: usize| super::get_operations(group, n).unwrap().len();
+        assert_eq!(order("C1", 0), 1);
+        assert_eq!(order("CS", 0), 2);
+        assert_eq!(order("CNV", 2), 4);
+        assert_eq!(order("CNV", 3), 6);
+        assert_eq!(order("DNH", 6), 24);
+        assert_eq!(order("DND", 3), 12);
+        assert_eq!(order("S2N", 2), 4);
+        assert_eq!(order("T", 0), 12);
+        assert_eq!(order("TD", 0), 24);
+        assert_eq!(order("OH", 0), 48);
+        assert!(super::get_operations("CNV", 0).is_err());
+        assert!(super::get_operations("K", 0).is_err());
+    }
+
+    #[test]
+    fn gamess_write() {
+        let mut water = testutil::get_water();
+
+        let mut output = Vec::new();
+        GamessWriter::new()
+            .runtyp("OPTIMIZE")
+            .basis("GBASIS=N31 NGAUSS=6 NDFUNC=1")
+            .option("system", "mwords", "50")
+            .point_group("CNV", 2)
+            .write(&mut output, &water)
+            .unwrap();
+
+        let text = String::from_utf8(output).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(
+            lines[0],
+            " $CONTRL RUNTYP=OPTIMIZE ICHARG=0 MULT=1 SCFTYP=RHF $END"
+        );
+        assert_eq!(lines[1], " $BASIS GBASIS=N31 NGAUSS=6 NDFUNC=1 $END");
+        assert_eq!(lines[2], " $SYSTEM MWORDS=50 $END");
+        assert_eq!(lines[3], " $DATA");
+        assert_eq!(lines[4], "water");
+        assert_eq!(lines[5], "CNV 2");
+        assert_eq!(lines[6], "");
+        assert!(lines[7].starts_with("O      8.0"));
+        assert!(lines[8].starts_with("H      1.0"));
+        assert_eq!(lines[9], " $END");
+        assert_eq!(lines.len(), 10);
+
+        // every atom is written in C1, a doublet gets UHF
+        water.remark = "water cation".to_string();
+        let mut output = Vec::new();
+        GamessWriter::new()
+            .charge(1)
+            .write(&mut output, &water)
+            .unwrap();
+        let text = String::from_utf8(output).unwrap();
+        assert!(text.starts_with(
+            " $CONTRL RUNTYP=ENERGY ICHARG=1 MULT=2 SCFTYP=UHF $END\n \
+             $BASIS GBASIS=STO NGAUSS=3 $END\n $DATA\nwater cation\nC1\nO "
+        ));
+        assert_eq!(text.lines().count(), 9);
+
+        // the molecule is not in the master frame of the group
+        let err = GamessWriter::new()
+            .point_group("CNV", 3)
+            .write(Vec::new(), &water)
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("gamess: atom 2 has no symmetry equivalent"));
+
+        // long groups are continued on the next line
+        let mut output = Vec::new();
+        let mut writer = GamessWriter::new();
+        for k in 0..12 {
+            writer = writer.option("STATPT", &format!("OPTION{}", k), "1");
+        }
+        writer.write(&mut output, &water).unwrap();
+        let text = String::from_utf8(output).unwrap();
+        assert!(text.lines().all(|line| line.len() <= 79));
+        assert!(text.contains(" $STATPT OPTION0=1"));
+        assert!(text.contains("OPTION11=1 $END"));
+    }
+}
@@ -0,0 +1,648 @@
+/**
+ * Author: V. Ganesh
+ * License: MIT
+ */
+
+/**
+ * log module, reads the results of a Gaussian run from its output (.log/.out) file.
+ *
+ * Every geometry printed in the standard orientation becomes a frame (the input
+ * orientation if the run used nosymm), along with the SCF, MP2 and CCSD(T) energies (in
+ * hartree), the convergence table of each optimization step, the last Mulliken and ESP
+ * charges and dipole moment (in debye), the frequencies with their normal modes and IR
+ * intensities, and the thermochemistry. Frequencies are read from the default output,
+ * not the high precision (freq=hpmodes) one.
+ *
+ * A run that crashed or was cut short still gives what was read up to that point, with
+ * the reason in GaussianLog.error.
+ */
+use std::io::BufRead;
+
+use crate::atom::Atom;
+use crate::atom::AtomOperations;
+use crate::atomprop;
+use crate::io::gaussian::parse_number;
+use crate::io::IoError;
+use crate::io::LineReader;
+use crate::molecule::Molecule;
+use crate::point::Point;
+
+const FORMAT: &str = "gaussian log";
+
+/** ConvergenceItem - one row of the convergence table of an optimization step */
+#[allow(dead_code)]
+#[derive(Clone, PartialEq, Debug)]
+pub struct ConvergenceItem {
+    pub name: String,
+    pub value: f64,
+    pub threshold: f64,
+    pub converged: bool,
+}
+
+/** VibrationalMode - a normal mode, the displacements are those of each atom */
+#[allow(dead_code)]
+#[derive(Clone, Default, PartialEq, Debug)]
+pub struct VibrationalMode {
+    pub frequency: f64,
+    pub symmetry: String,
+    pub reduced_mass: f64,
+    pub force_constant: f64,
+    pub ir_intensity: f64,
+    pub displacements: Vec<[f64; 3]>,
+}
+
+/**
+ * Thermochemistry - the thermal corrections and the energies they give, all in hartree,
+ * at the given temperature (kelvin) and pressure (atm)
+ */
+#[allow(dead_code)]
+#[derive(Clone, Default, PartialEq, Debug)]
+pub struct Thermochemistry {
+    pub temperature: f64,
+    pub pressure: f64,
+    pub zero_point_correction: f64,
+    pub energy_correction: f64,
+    pub enthalpy_correction: f64,
+    pub free_energy_correction: f64,
+    pub zero_point_energy: f64,
+    pub energy: f64,
+    pub enthalpy: f64,
+    pub free_energy: f64,
+}
+
+/** ThermochemistryField - gives the field of Thermochemistry a line of the output sets */
+type ThermochemistryField = fn(&mut Thermochemistry) -> &mut f64;
+
+/** GaussianLog - the results read from a Gaussian output file */
+#[allow(dead_code)]
+#[derive(Default, Debug)]
+pub struct GaussianLog {
+    pub frames: Vec<Molecule>,
+    pub scf_energies: Vec<f64>,
+    pub mp2_energies: Vec<f64>,
+    pub ccsd_t_energies: Vec<f64>,
+    pub convergence: Vec<Vec<ConvergenceItem>>,
+    pub optimization_completed: bool,
+    pub mulliken_charges: Vec<f64>,
+    pub esp_charges: Vec<f64>,
+    pub dipole_moment: Option<[f64; 3]>,
+    pub modes: Vec<VibrationalMode>,
+    pub thermochemistry: Option<Thermochemistry>,
+    /** why the results are incomplete: an error termination, or the file ending early */
+    pub error: Option<IoError>,
+}
+
+#[allow(dead_code)]
+impl GaussianLog {
+    /** get_final_frame() returns the last geometry of the run */
+    pub fn get_final_frame(&self) -> Option<&Molecule> {
+        return self.frames.last();
+    }
+
+    /** get_final_energy() returns the last SCF energy of the run */
+    pub fn get_final_energy(&self) -> Option<f64> {
+        return self.scf_energies.last().copied();
+    }
+
+    /** is_complete() checks whether the run ended with a normal termination */
+    pub fn is_complete(&self) -> bool {
+        return self.error.is_none();
+    }
+}
+
+/** get_numbers() returns the numbers after "--" on a line of the frequency table */
+fn get_numbers(line: &str) -> Vec<f64> {
+    let values = match line.split_once("--") {
+        Some((_, values)) => values,
+        None => return Vec::new(),
+    };
+    return values.split_whitespace().filter_map(parse_number).collect();
+}
+
+/** get_value_after() returns the first number after the given text on a line */
+fn get_value_after(line: &str, text: &str) -> Option<f64> {
+    let idx = line.find(text)?;
+    let rest = line[idx + text.len()..].trim_start_matches(['=', ' ']);
+    return rest.split_whitespace().next().and_then(parse_number);
+}
+
+/** LogParser - reads the sections of an output file into a GaussianLog */
+struct LogParser<R: BufRead> {
+    reader: LineReader<R>,
+    log: GaussianLog,
+    standard_frames: Vec<Molecule>,
+    input_frames: Vec<Molecule>,
+    number_of_atoms: usize,
+}
+
+impl<R: BufRead> LogParser<R> {
+    /** expect_line() returns the next line, the file ending here means it was cut short */
+    fn expect_line(&mut self, what: &str) -> Result<String, IoError> {
+        return self.reader.expect_line(what);
+    }
+
+    /** read_orientation() reads the table of a "Standard orientation:" block */
+    fn read_orientation(&mut self) -> Result<Molecule, IoError> {
+        for _ in 0..4 {
+            self.expect_line("the orientation table")?;
+        }
+
+        let mut mol = Molecule::new("".to_string(), "".to_string());
+        loop {
+            let line = self.expect_line("the orientation table")?;
+            if line.trim().starts_with("---") {
+                break;
+            }
+
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let numbers: Vec<f64> = fields.iter().filter_map(|f| parse_number(f)).collect();
+            if fields.len() < 5 || numbers.len() != fields.len() {
+                return Err(self.reader.error(format!(
+                    "expected an atom of the orientation table, found \"{}\"",
+                    line.trim()
+                )));
+            }
+
+            let atomic_number = numbers[1] as u8;
+            let n = numbers.len();
+            mol.add_atom(Atom {
+                center: Point {
+                    x: numbers[n - 3] as f32,
+                    y: numbers[n - 2] as f32,
+                    z: numbers[n - 1] as f32,
+                },
+                charge: 0.0,
+                symbol: atomprop::get_element_symbol(atomic_number)
+                    .unwrap_or("X")
+                    .to_string(),
+                remark: "".to_string(),
+            });
+        }
+
+        self.number_of_atoms = mol.get_atoms().len();
+        return Ok(mol);
+    }
+
+    /** read_convergence() reads the convergence table, after its "Item" header line */
+    fn read_convergence(&mut self) -> Result<(), IoError> {
+        let mut items = Vec::new();
+
+        for _ in 0..4 {
+            let line = self.expect_line("the convergence table")?;
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let n = fields.len();
+            let values = if n >= 4 {
+                parse_number(fields[n - 3]).zip(parse_number(fields[n - 2]))
+            } else {
+                None
+            };
+
+            let (value, threshold) = values.ok_or_else(|| {
+                self.reader.error(format!(
+                    "expected a row of the convergence table, found \"{}\"",
+                    line.trim()
+                ))
+            })?;
+            items.push(ConvergenceItem {
+                name: fields[..n - 3].join(" "),
+                value: value,
+                threshold: threshold,
+                converged: fields[n - 1] == "YES",
+            });
+        }
+
+        self.log.convergence.push(items);
+        return Ok(());
+    }
+
+    /** read_charges() reads a table of atom charges, e.g. after "Mulliken charges:" */
+    fn read_charges(&mut self) -> Result<Vec<f64>, IoError> {
+        self.expect_line("the charges")?;
+
+        let mut charges = Vec::new();
+        loop {
+            let line = self.expect_line("the charges")?;
+            if line.trim_start().starts_with("Sum of") {
+                return Ok(charges);
+            }
+
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let charge = fields.get(2).and_then(|f| parse_number(f)).ok_or_else(|| {
+                self.reader.error(format!(
+                    "expected an atom charge, found \"{}\"",
+                    line.trim()
+                ))
+            })?;
+            charges.push(charge);
+        }
+    }
+
+    /** read_dipole() reads the line after "Dipole moment (field-independent basis, Debye):" */
+    fn read_dipole(&mut self) -> Result<(), IoError> {
+        let line = self.expect_line("the dipole moment")?;
+        let components = [
+            get_value_after(&line, "X="),
+            get_value_after(&line, "Y="),
+            get_value_after(&line, "Z="),
+        ];
+
+        match components {
+            [Some(x), Some(y), Some(z)] => self.log.dipole_moment = Some([x, y, z]),
+            _ => {
+                return Err(self.reader.error(format!(
+                    "expected the dipole moment components, found \"{}\"",
+                    line.trim()
+                )))
+            }
+        }
+        return Ok(());
+    }
+
+    /**
+     * read_modes() reads a block of up to three normal modes, from the "Frequencies --"
+     * line, with the symmetries from the line before it
+     */
+    fn read_modes(&mut self, line: &str, symmetries: &str) -> Result<(), IoError> {
+        let frequencies = get_numbers(line);
+        let symmetries: Vec<&str> = symmetries.split_whitespace().collect();
+
+        let mut modes: Vec<VibrationalMode> = frequencies
+            .iter()
+            .enumerate()
+            .map(|(idx, frequency)| VibrationalMode {
+                frequency: *frequency,
+                symmetry: if symmetries.len() == frequencies.len() {
+                    symmetries[idx].to_string()
+                } else {
+                    "".to_string()
+                },
+                ..Default::default()
+            })
+            .collect();
+
+        loop {
+            let line = self.expect_line("the normal modes")?;
+            let trimmed = line.trim_start();
+
+            if trimmed.starts_with("Atom") {
+                break;
+            }
+
+            let values = get_numbers(&line);
+            for (mode, value) in modes.iter_mut().zip(values.iter()) {
+                if trimmed.starts_with("Red. masses") {
+                    mode.reduced_mass = *value;
+                } else if trimmed.starts_with("Frc consts") {
+                    mode.force_constant = *value;
+                } else if trimmed.starts_with("IR Inten") {
+                    mode.ir_intensity = *value;
+                }
+            }
+        }
+
+        for _ in 0..self.number_of_atoms {
+            let line = self.expect_line("the normal modes")?;
+            let numbers: Vec<f64> = line.split_whitespace().filter_map(parse_number).collect();
+            if numbers.len() != 2 + 3 * modes.len() {
+                return Err(self.reader.error(format!(
+                    "expected the displacements of an atom, found \"{}\"",
+                    line.trim()
+                )));
+            }
+
+            for (mode, xyz) in modes.iter_mut().zip(numbers[2..].chunks(3)) {
+                mode.displacements.push([xyz[0], xyz[1], xyz[2]]);
+            }
+        }
+
+        self.log.modes.extend(modes);
+        return Ok(());
+    }
+
+    /** read_thermochemistry() reads a thermochemistry line, if it is one */
+    fn read_thermochemistry(&mut self, line: &str) -> bool {
+        let items: [(&str, ThermochemistryField); 8] = [
+            ("Zero-point correction=", |t| &mut t.zero_point_correction),
+            ("Thermal correction to Energy=", |t| {
+                &mut t.energy_correction
+            }),
+            ("Thermal correction to Enthalpy=", |t| {
+                &mut t.enthalpy_correction
+            }),
+            ("Thermal correction to Gibbs Free Energy=", |t| {
+                &mut t.free_energy_correction
+            }),
+            ("Sum of electronic and zero-point Energies=", |t| {
+                &mut t.zero_point_energy
+            }),
+            ("Sum of electronic and thermal Energies=", |t| &mut t.energy),
+            ("Sum of electronic and thermal Enthalpies=", |t| {
+                &mut t.enthalpy
+            }),
+            ("Sum of electronic and thermal Free Energies=", |t| {
+                &mut t.free_energy
+            }),
+        ];
+
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("Temperature") && trimmed.contains("Pressure") {
+            let thermochemistry = self
+                .log
+                .thermochemistry
+                .get_or_insert_with(Default::default);
+            thermochemistry.temperature = get_value_after(line, "Temperature").unwrap_or(0.0);
+            thermochemistry.pressure = get_value_after(line, "Pressure").unwrap_or(0.0);
+            return true;
+        }
+
+        for (text, field) in items.iter() {
+            if trimmed.starts_with(text) {
+                if let Some(value) = get_value_after(line, text) {
+                    let thermochemistry = self
+                        .log
+                        .thermochemistry
+                        .get_or_insert_with(Default::default);
+                    *field(thermochemistry) = value;
+                }
+                return true;
+            }
+        }
+
+        return false;
+    }
+
+    /** read_line() reads one line of the output, and the block it starts */
+    fn read_line(&mut self, line: &str, previous: &str) -> Result<(), IoError> {
+        let trimmed = line.trim();
+
+        if trimmed == "Standard orientation:" {
+            let frame = self.read_orientation()?;
+            self.standard_frames.push(frame);
+        } else if trimmed == "Input orientation:" {
+            let frame = self.read_orientation()?;
+            self.input_frames.push(frame);
+        } else if trimmed.starts_with("SCF Done:") {
+            if let Some(energy) = get_value_after(line, "=") {
+                self.log.scf_energies.push(energy);
+            }
+        } else if trimmed.contains("EUMP2 =") {
+            if let Some(energy) = get_value_after(line, "EUMP2 =") {
+                self.log.mp2_energies.push(energy);
+            }
+        } else if trimmed.starts_with("CCSD(T)=") {
+            if let Some(energy) = get_value_after(line, "CCSD(T)=") {
+                self.log.ccsd_t_energies.push(energy);
+            }
+        } else if trimmed.starts_with("Item") && trimmed.ends_with("Converged?") {
+            self.read_convergence()?;
+        } else if trimmed.starts_with("Optimization completed") {
+            self.log.optimization_completed = true;
+        } else if trimmed == "Mulliken charges:"
+            || trimmed == "Mulliken charges and spin densities:"
+            || trimmed == "Mulliken atomic charges:"
+        {
+            self.log.mulliken_charges = self.read_charges()?;
+        } else if trimmed == "ESP charges:" {
+            self.log.esp_charges = self.read_charges()?;
+        } else if trimmed.starts_with("Dipole moment (field-independent basis, Debye)") {
+            self.read_dipole()?;
+        } else if trimmed.starts_with("Frequencies --") {
+            self.read_modes(line, previous)?;
+        } else if trimmed.starts_with("Error termination") {
+            self.log.error = Some(self.reader.error(format!("the run failed: {}", trimmed)));
+        } else if trimmed.starts_with("Normal termination") {
+            self.log.error = None;
+        } else {
+            self.read_thermochemistry(line);
+        }
+
+        return Ok(());
+    }
+}
+
+/** read() reads the results of a Gaussian output file, it fails only if the file cannot be read */
+#[allow(dead_code)]
+pub fn read<R: BufRead>(reader: R) -> Result<GaussianLog, IoError> {
+    let mut parser = LogParser {
+        reader: LineReader::new(reader, FORMAT),
+        log: GaussianLog::default(),
+        standard_frames: Vec::new(),
+        input_frames: Vec::new(),
+        number_of_atoms: 0,
+    };
+
+    let mut previous = String::new();
+    let mut terminated = false;
+
+    while let Some(line) = parser.reader.next_line()? {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("Entering Link 1") {
+            terminated = false;
+        } else if trimmed.starts_with("Normal termination")
+            || trimmed.starts_with("Error termination")
+        {
+            terminated = true;
+        }
+
+        match parser.read_line(&line, &previous) {
+            Ok(()) => {}
+            Err(IoError::Io(err)) => return Err(IoError::Io(err)),
+            Err(err) => {
+                parser.log.error = Some(err);
+                terminated = true;
+                break;
+            }
+        }
+        previous = line;
+    }
+
+    if !terminated {
+        parser.log.error = Some(IoError::parse(
+            FORMAT,
+            parser.reader.get_line_number(),
+            "the run did not finish, there is no normal termination".to_string(),
+        ));
+    }
+
+    let mut log = parser.log;
+    if parser.standard_frames.is_empty() {
+        log.frames = parser.input_frames;
+    } else {
+        log.frames = parser.standard_frames;
+    }
+    return Ok(log);
+}
+
+/** Unit tests for the above module */
+#[cfg(test)]
+mod tests {
+    const ORIENTATION: &str =
+        " ---------------------------------------------------------------------
+ Center     Atomic      Atomic             Coordinates (Angstroms)
+ Number     Number       Type             X           Y           Z
+ ---------------------------------------------------------------------
+      1          8           0        0.000000    0.000000    0.119262
+      2          1           0        0.000000    0.763239   -0.477047
+      3          1           0        0.000000   -0.763239   -0.477047
+ ---------------------------------------------------------------------
+";
+
+    fn get_log() -> String {
+        return format!(
+            " Entering Link 1 = C:\\G16W\\l1.exe PID=      1234.
+ #P B3LYP/6-31G(d) Opt Freq
+                          Input orientation:
+{orientation}                         Standard orientation:
+{orientation} SCF Done:  E(RB3LYP) =  -76.4089533431     A.U. after   10 cycles
+         Item               Value     Threshold  Converged?
+ Maximum Force            0.012345     0.000450     NO
+ RMS     Force            0.008000     0.000300     NO
+ Maximum Displacement     0.020000     0.001800     NO
+ RMS     Displacement     0.015000     0.001200     NO
+                         Standard orientation:
+{orientation} SCF Done:  E(RB3LYP) =  -76.4089600000     A.U. after    8 cycles
+         Item               Value     Threshold  Converged?
+ Maximum Force            0.000123     0.000450     YES
+ RMS     Force            0.000080     0.000300     YES
+ Maximum Displacement     0.000234     0.001800     YES
+ RMS     Displacement     0.000180     0.001200     YES
+ Predicted change in Energy=-1.234567D-08
+ Optimization completed.
+    -- Stationary point found.
+ Mulliken charges:
+               1
+     1  O   -0.834000
+     2  H    0.417000
+     3  H    0.417000
+ Sum of Mulliken charges =   0.00000
+ Mulliken charges with hydrogens summed into heavy atoms:
+               1
+     1  O    0.000000
+ Sum of Mulliken charges =   0.00000
+ Dipole moment (field-independent basis, Debye):
+    X=              0.0000    Y=              0.0000    Z=             -2.0940  Tot=              2.0940
+ Harmonic frequencies (cm**-1), IR intensities (KM/Mole), Raman scattering
+                      1                      2                      3
+                     A1                     A1                     B2
+ Frequencies --   1713.0736              3727.3597              3849.1043
+ Red. masses --      1.0825                 1.0453                 1.0810
+ Frc consts  --      1.8717                 8.5563                 9.4358
+ IR Inten    --     75.1234                 2.3456                18.9012
+  Atom  AN      X      Y      Z        X      Y      Z        X      Y      Z
+     1   8     0.00   0.00   0.07     0.00   0.00   0.05     0.00   0.07   0.00
+     2   1     0.00   0.42  -0.56     0.00   0.59   0.39     0.00  -0.56   0.43
+     3   1     0.00  -0.42  -0.56     0.00  -0.59   0.39     0.00  -0.56  -0.43
+ - Thermochemistry -
+ Temperature   298.150 Kelvin.  Pressure   1.00000 Atm.
+ Zero-point correction=                           0.021241 (Hartree/Particle)
+ Thermal correction to Energy=                    0.024077
+ Thermal correction to Enthalpy=                  0.025021
+ Thermal correction to Gibbs Free Energy=         0.003598
+ Sum of electronic and zero-point Energies=            -76.387719
+ Sum of electronic and thermal Energies=               -76.384883
+ Sum of electronic and thermal Enthalpies=             -76.383939
+ Sum of electronic and thermal Free Energies=          -76.405362
+",
+            orientation = ORIENTATION
+        );
+    }
+
+    #[test]
+    fn gaussian_log_read() {
+        let text = get_log() + " Normal termination of Gaussian 16 at Mon Jan  1 00:00:00 2024.\n";
+        let log = super::read(text.as_bytes()).unwrap();
+
+        assert!(log.is_complete());
+        assert_eq!(log.frames.len(), 2);
+        let water = log.get_final_frame().unwrap();
+        assert_eq!(water.get_atoms()[0].symbol, "O");
+        assert_eq!(water.get_atoms()[2].center.y, -0.763239);
+
+        assert_eq!(log.scf_energies, vec![-76.4089533431, -76.40896]);
+        assert_eq!(log.get_final_energy(), Some(-76.40896));
+        assert_eq!(log.convergence.len(), 2);
+        assert!(log.convergence[0].iter().all(|item| !item.converged));
+        assert!(log.convergence[1].iter().all(|item| item.converged));
+        assert_eq!(log.convergence[1][2].name, "Maximum Displacement");
+        assert_eq!(log.convergence[1][0].threshold, 0.00045);
+        assert!(log.optimization_completed);
+
+        assert_eq!(log.mulliken_charges, vec![-0.834, 0.417, 0.417]);
+        assert_eq!(log.dipole_moment, Some([0.0, 0.0, -2.094]));
+
+        assert_eq!(log.modes.len(), 3);
+        assert_eq!(log.modes[0].symmetry, "A1");
+        assert_eq!(log.modes[2].frequency, 3849.1043);
+        assert_eq!(log.modes[0].ir_intensity, 75.1234);
+        assert_eq!(log.modes[1].reduced_mass, 1.0453);
+        assert_eq!(log.modes[2].displacements[1], [0.0, -0.56, 0.43]);
+
+        let thermochemistry = log.thermochemistry.unwrap();
+        assert_eq!(thermochemistry.temperature, 298.15);
+        assert_eq!(thermochemistry.zero_point_correction, 0.021241);
+        assert_eq!(thermochemistry.free_energy, -76.405362);
+    }
+
+    #[test]
+    fn gaussian_log_correlated_energies() {
+        let text = " SCF Done:  E(RHF) =  -76.0107465155     A.U. after   11 cycles
+ E2 =    -0.2010357413D+00 EUMP2 =    -0.76211782256800D+02
+ CCSD(T)= -0.76241234567D+02
+ ESP charges:
+               1
+     1  O   -0.780000
+     2  H    0.390000
+     3  H    0.390000
+ Sum of ESP charges =   0.00000
+ Normal termination of Gaussian 16 at Mon Jan  1 00:00:00 2024.
+";
+        let log = super::read(text.as_bytes()).unwrap();
+        assert!(log.is_complete());
+        assert!(log.frames.is_empty());
+        assert_eq!(log.mp2_energies, vec![-76.2117822568]);
+        assert_eq!(log.ccsd_t_energies, vec![-76.241234567]);
+        assert_eq!(log.esp_charges, vec![-0.78, 0.39, 0.39]);
+    }
+
+    #[test]
+    fn gaussian_log_incomplete() {
+        // cut short, the results up to there are kept
+        let text = get_log();
+        let log = super::read(text.as_bytes()).unwrap();
+        assert!(!log.is_complete());
+        assert_eq!(log.frames.len(), 2);
+        assert_eq!(
+            log.error.unwrap().to_string(),
+            "gaussian log: line 77: the run did not finish, there is no normal termination"
+        );
+
+        // crashed
+        let text = format!(
+            "{} Convergence failure -- run terminated.\n Error termination via Lnk1e in l502.exe.\n",
+            get_log()
+        );
+        let log = super::read(text.as_bytes()).unwrap();
+        assert_eq!(log.modes.len(), 3);
+        assert_eq!(
+            log.error.unwrap().to_string(),
+            "gaussian log: line 79: the run failed: Error termination via Lnk1e in l502.exe."
+        );
+
+        // cut inside a table
+        let lines: Vec<&str> = ORIENTATION.lines().take(6).collect();
+        let text = format!("  Standard orientation:\n{}\n", lines.join("\n"));
+        let log = super::read(text.as_bytes()).unwrap();
+        assert!(log.frames.is_empty());
+        assert_eq!(
+            log.error.unwrap().to_string(),
+            "gaussian log: line 8: unexpected end of file, expected the orientation table"
+        );
+
+        // a later job of the file did not finish
+        let text = format!(
+            "{} Normal termination of Gaussian 16.\n Entering Link 1 = l1.exe\n",
+            get_log()
+        );
+        assert!(!super::read(text.as_bytes()).unwrap().is_complete());
+    }
+}
@@ -0,0 +1,1273 @@
+/**
+ * Author: V. Ganesh
+ * License: MIT
+ */
+
+/**
+ * gaussian module, writes and reads Gaussian input (.gjf/.com) files.
+ *
+ * An input file is a Link0 section (%mem=, %nproc=, %chk=, ...), the route section
+ * (# lines), a title, the charge and multiplicity, and the geometry as Cartesian
+ * coordinates or a Z-matrix, each section ending with a blank line. The route decides
+ * which sections follow the geometry: the connectivity with geom=connectivity, and the
+ * ModRedundant lines with opt=modredundant.
+ *
+ * When reading, the Link0 commands become the "link0_<command>" properties of the
+ * molecule, the route the "route" property, the title the remark, and the charge and
+ * multiplicity the "charge" and "multiplicity" properties. Frozen atoms (a -1 flag after
+ * the label), fragment numbers and isotopes are kept as the "frozen", "fragment" and
+ * "isotope" atom properties, a Z-matrix is converted to Cartesians and its dummy atoms
+ * (X) are left out, as Gaussian does when numbering atoms. Lengths in bohr and angles in
+ * radians, given by Units=Bohr (or AU) and Units=Rad in the route, are converted to
+ * angstrom and degrees. Only the first job of a --Link1-- file is read.
+ *
+ * GaussianWriter writes these properties back, unless it is given its own route, Link0
+ * commands, charge or multiplicity. The log module reads the results of a run.
+ */
+use std::collections::HashMap;
+use std::io::BufRead;
+use std::io::Write;
+
+use crate::atom::Atom;
+use crate::atom::AtomOperations;
+use crate::atomprop;
+use crate::bond::BondType;
+use crate::io;
+use crate::io::IoError;
+use crate::io::LineReader;
+use crate::molecule::zmatrix::ZMatrix;
+use crate::molecule::zmatrix::ZMatrixEntry;
+use crate::molecule::zmatrix::ZMatrixError;
+use crate::molecule::zmatrix::DUMMY_SYMBOL;
+use crate::molecule::Molecule;
+use crate::point::Point;
+use crate::property::AtomProperty;
+use crate::property::Property;
+
+pub mod log;
+
+const FORMAT: &str = "gaussian";
+
+/** route written when neither the writer nor the molecule has one */
+const DEFAULT_ROUTE: &str = "#P HF/STO-3G SP";
+
+/** prefix of the molecule properties holding Link0 commands */
+const LINK0_PREFIX: &str = "link0_";
+
+/** GaussianWriter - writes a molecule as a Gaussian input file */
+#[allow(dead_code)]
+#[derive(Clone, Default, PartialEq, Debug)]
+pub struct GaussianWriter {
+    route: Option<String>,
+    link0: Vec<(String, String)>,
+    charge: Option<i64>,
+    multiplicity: Option<i64>,
+    zmatrix: bool,
+    variables: bool,
+    connectivity: bool,
+    modredundant: Vec<String>,
+}
+
+#[allow(dead_code)]
+impl GaussianWriter {
+    pub fn new() -> GaussianWriter {
+        return GaussianWriter::default();
+    }
+
+    /** route() sets the route section, "#" is added if it is missing */
+    pub fn route(mut self, route: &str) -> GaussianWriter {
+        let route = route.trim();
+        if route.starts_with('#') {
+            self.route = Some(route.to_string());
+        } else {
+            self.route = Some(format!("# {}", route));
+        }
+        return self;
+    }
+
+    /** link0() sets a Link0 command, e.g. link0("rwf", "job.rwf") writes %rwf=job.rwf */
+    pub fn link0(mut self, command: &str, value: &str) -> GaussianWriter {
+        let command = command.trim_start_matches('%').to_lowercase();
+        self.link0.retain(|(other, _)| *other != command);
+        self.link0.push((command, value.to_string()));
+        return self;
+    }
+
+    /** mem() sets the memory of the job, e.g. "4GB" */
+    pub fn mem(self, mem: &str) -> GaussianWriter {
+        return self.link0("mem", mem);
+    }
+
+    /** nproc() sets the number of processors of the job */
+    pub fn nproc(self, nproc: usize) -> GaussianWriter {
+        return self.link0("nproc", &nproc.to_string());
+    }
+
+    /** chk() sets the checkpoint file of the job */
+    pub fn chk(self, chk: &str) -> GaussianWriter {
+        return self.link0("chk", chk);
+    }
+
+    /** charge() sets the total charge, by default the sum of the atom charges */
+    pub fn charge(mut self, charge: i64) -> GaussianWriter {
+        self.charge = Some(charge);
+        return self;
+    }
+
+    /**
+     * multiplicity() sets the spin multiplicity, by default a singlet for an even number
+     * of electrons and a doublet for an odd number
+     */
+    pub fn multiplicity(mut self, multiplicity: i64) -> GaussianWriter {
+        self.multiplicity = Some(multiplicity);
+        return self;
+    }
+
+    /**
+     * zmatrix() sets whether the geometry is written as a Z-matrix, else as Cartesians,
+     * with references along the bonds and dummy atoms for linear fragments
+     */
+    pub fn zmatrix(mut self, zmatrix: bool) -> GaussianWriter {
+        self.zmatrix = zmatrix;
+        return self;
+    }
+
+    /**
+     * variables() sets whether the values of a Z-matrix are written as named variables
+     * (R1, A1, D1, ...) in a section after the geometry, else in the atom lines
+     */
+    pub fn variables(mut self, variables: bool) -> GaussianWriter {
+        self.variables = variables;
+        return self;
+    }
+
+    /**
+     * connectivity() sets whether the bonds are written as a connectivity section,
+     * geom=connectivity is added to the route if it is missing
+     */
+    pub fn connectivity(mut self, connectivity: bool) -> GaussianWriter {
+        self.connectivity = connectivity;
+        return self;
+    }
+
+    /**
+     * modredundant() adds a line to the ModRedundant section, e.g. "B 1 2 F" freezes the
+     * bond between atoms 1 and 2, the route must ask for opt=modredundant
+     */
+    pub fn modredundant(mut self, line: &str) -> GaussianWriter {
+        self.modredundant.push(line.trim().to_string());
+        return self;
+    }
+
+    /**
+     * get_route() returns the route written for a molecule, without a Units keyword as the
+     * geometry is always written in angstrom and degrees
+     */
+    fn get_route(&self, mol: &Molecule) -> String {
+        let mut route = match (&self.route, mol.get_property("route")) {
+            (Some(route), _) => route.clone(),
+            (None, Some(Property::Text(route))) => route.clone(),
+            _ => DEFAULT_ROUTE.to_string(),
+        };
+
+        let fields = split_fields(&route);
+        if fields
+            .iter()
+            .any(|field| field.to_lowercase().starts_with("units"))
+        {
+            route = fields
+                .into_iter()
+                .filter(|field| !field.to_lowercase().starts_with("units"))
+                .collect::<Vec<String>>()
+                .join(" ");
+        }
+
+        if self.connectivity && !route.to_lowercase().contains("connect") {
+            route.push_str(" geom=connectivity");
+        }
+        return route;
+    }
+
+    /** get_link0() returns the Link0 commands of the molecule, replaced by those of the writer */
+    fn get_link0(&self, mol: &Molecule) -> Vec<(String, String)> {
+        let mut link0: Vec<(String, String)> = Vec::new();
+
+        for (name, value) in mol.get_properties().iter() {
+            if let Some(command) = name.strip_prefix(LINK0_PREFIX) {
+                if !self.link0.iter().any(|(other, _)| other == command) {
+                    link0.push((command.to_string(), value.to_string()));
+                }
+            }
+        }
+
+        link0.extend(self.link0.iter().cloned());
+        return link0;
+    }
+
+    /** get_charge() returns the total charge written for a molecule */
+    fn get_charge(&self, mol: &Molecule) -> i64 {
+        return self.charge.unwrap_or_else(|| io::get_total_charge(mol));
+    }
+
+    /** get_multiplicity() returns the multiplicity written for a molecule */
+    fn get_multiplicity(&self, mol: &Molecule, charge: i64) -> i64 {
+        return self
+            .multiplicity
+            .unwrap_or_else(|| io::get_multiplicity(mol, charge));
+    }
+
+    /** write() writes a molecule as a Gaussian input file */
+    pub fn write<W: Write>(&self, mut writer: W, mol: &Molecule) -> Result<(), IoError> {
+        for (command, value) in self.get_link0(mol).iter() {
+            writeln!(writer, "%{}={}", command, value)?;
+        }
+        writeln!(writer, "{}", self.get_route(mol))?;
+        writeln!(writer)?;
+
+        let title = if !mol.remark.trim().is_empty() {
+            mol.remark.as_str()
+        } else if !mol.name.trim().is_empty() {
+            mol.name.as_str()
+        } else {
+            "untitled"
+        };
+        writeln!(writer, "{}", title.trim().replace(['\n', '\r'], " "))?;
+        writeln!(writer)?;
+
+        let charge = self.get_charge(mol);
+        writeln!(writer, "{} {}", charge, self.get_multiplicity(mol, charge))?;
+
+        let labels = get_labels(mol);
+        if self.zmatrix {
+            let zmatrix = ZMatrix::from_molecule(mol);
+            write_zmatrix(&mut writer, &labels, &zmatrix, self.variables)?;
+        } else {
+            write_cartesians(&mut writer, &labels, mol)?;
+        }
+        writeln!(writer)?;
+
+        if self.connectivity {
+            write_connectivity(&mut writer, mol)?;
+            writeln!(writer)?;
+        }
+
+        if !self.modredundant.is_empty() {
+            for line in self.modredundant.iter() {
+                writeln!(writer, "{}", line)?;
+            }
+            writeln!(writer)?;
+        }
+
+        return Ok(());
+    }
+}
+
+/** write() writes a molecule as a Gaussian input file, with its own route and Link0 commands */
+#[allow(dead_code)]
+pub fn write<W: Write>(writer: W, mol: &Molecule) -> Result<(), IoError> {
+    return GaussianWriter::new().write(writer, mol);
+}
+
+/** get_labels() returns the label of each atom, its symbol with any fragment and isotope */
+fn get_labels(mol: &Molecule) -> Vec<String> {
+    let integers = |name: &str| match mol.get_atom_property(name) {
+        Some(AtomProperty::Integers(values)) => Some(values.clone()),
+        _ => None,
+    };
+    let (fragments, isotopes) = (integers("fragment"), integers("isotope"));
+
+    let mut labels = Vec::new();
+    for (idx, atom) in mol.get_atoms().iter().enumerate() {
+        let mut parameters = Vec::new();
+        if let Some(fragment) = fragments.as_ref().map(|values| values[idx]) {
+            if fragment != 0 {
+                parameters.push(format!("Fragment={}", fragment));
+            }
+        }
+        if let Some(isotope) = isotopes.as_ref().map(|values| values[idx]) {
+            if isotope != 0 {
+                parameters.push(format!("Iso={}", isotope));
+            }
+        }
+
+        if parameters.is_empty() {
+            labels.push(atom.symbol.clone());
+        } else {
+            labels.push(format!("{}({})", atom.symbol, parameters.join(",")));
+        }
+    }
+
+    return labels;
+}
+
+/** write_cartesians() writes the atom lines, with freeze flags if the molecule has them */
+fn write_cartesians<W: Write>(
+    writer: &mut W,
+    labels: &[String],
+    mol: &Molecule,
+) -> Result<(), IoError> {
+    let frozen = match mol.get_atom_property("frozen") {
+        Some(AtomProperty::Logicals(values)) => Some(values),
+        _ => None,
+    };
+
+    for (idx, (label, atom)) in labels.iter().zip(mol.get_atoms().iter()).enumerate() {
+        write!(writer, "{:<4}", label)?;
+        if let Some(frozen) = frozen {
+            write!(writer, " {:>2}", if frozen[idx] { -1 } else { 0 })?;
+        }
+        writeln!(
+            writer,
+            " {:16.8} {:16.8} {:16.8}",
+            atom.center.x, atom.center.y, atom.center.z
+        )?;
+    }
+
+    return Ok(());
+}
+
+/**
+ * write_zmatrix() writes the Z-matrix lines, references are 1 based and count dummy
+ * atoms, and the variables after a blank line if the values are named
+ */
+fn write_zmatrix<W: Write>(
+    writer: &mut W,
+    labels: &[String],
+    zmatrix: &ZMatrix,
+    variables: bool,
+) -> Result<(), IoError> {
+    let names = zmatrix.get_variable_names();
+    let mut labels = labels.iter();
+    // the variables are written by kind, distances, then angles, then dihedrals
+    let mut values: [Vec<(&String, f64)>; 3] = [Vec::new(), Vec::new(), Vec::new()];
+
+    for (entry, entry_names) in zmatrix.entries.iter().zip(names.iter()) {
+        let label = if entry.is_dummy() {
+            DUMMY_SYMBOL
+        } else {
+            labels
+                .next()
+                .map(|label| label.as_str())
+                .unwrap_or(&entry.symbol)
+        };
+        write!(writer, "{:<4}", label)?;
+
+        let references = [entry.distance, entry.angle, entry.dihedral];
+        for (kind, (reference, name)) in references.iter().zip(entry_names.iter()).enumerate() {
+            if let (Some((reference, value)), Some(name)) = (reference, name) {
+                if variables {
+                    write!(writer, " {:>4} {:>6}", reference + 1, name)?;
+                    values[kind].push((name, *value));
+                } else {
+                    write!(writer, " {:>4} {:14.8}", reference + 1, value)?;
+                }
+            }
+        }
+        writeln!(writer)?;
+    }
+
+    if variables && zmatrix.entries.len() > 1 {
+        writeln!(writer)?;
+        for (name, value) in values.iter().flatten() {
+            writeln!(writer, "{:<6} {:14.8}", name, value)?;
+        }
+    }
+
+    return Ok(());
+}
+
+/** get_bond_order() returns the bond order written in the connectivity section */
+fn get_bond_order(bond_type: BondType) -> &'static str {
+    match bond_type {
+        BondType::DOUBLE => return "2.0",
+        BondType::TRIPLE => return "3.0",
+        BondType::AROMATIC => return "1.5",
+        BondType::WEAK => return "0.5",
+        _ => return "1.0",
+    }
+}
+
+/** write_connectivity() writes, for each atom, the later atoms bonded to it and the orders */
+fn write_connectivity<W: Write>(writer: &mut W, mol: &Molecule) -> Result<(), IoError> {
+    let mut bonded: Vec<Vec<(usize, BondType)>> = vec![Vec::new(); mol.get_atoms().len()];
+    for bond in mol.get_bonds().iter() {
+        let (a, b) = (bond.atom_1_idx, bond.atom_2_idx);
+        if a != b {
+            bonded[a.min(b)].push((a.max(b), bond.bond_type));
+        }
+    }
+
+    for (idx, list) in bonded.iter_mut().enumerate() {
+        list.sort_by_key(|(other, _)| *other);
+        write!(writer, " {}", idx + 1)?;
+        for (other, bond_type) in list.iter() {
+            write!(writer, " {} {}", other + 1, get_bond_order(*bond_type))?;
+        }
+        writeln!(writer)?;
+    }
+
+    return Ok(());
+}
+
+/** Value - a coordinate of the geometry, a number or a (possibly negated) variable */
+#[derive(Clone, Debug)]
+enum Value {
+    Number(f64),
+    Variable(String, bool),
+}
+
+/** Position - the coordinates of an atom line */
+#[derive(Clone, Debug)]
+enum Position {
+    Cartesian([Value; 3]),
+    Internal(Vec<(usize, Value)>),
+}
+
+/** AtomLine - an atom line of the geometry section */
+#[derive(Clone, Debug)]
+struct AtomLine {
+    label: String,
+    symbol: String,
+    charge: f32,
+    fragment: i64,
+    isotope: i64,
+    frozen: bool,
+    position: Position,
+    line_number: usize,
+}
+
+/** split_fields() splits a line at whitespace and commas, except inside parentheses */
+fn split_fields(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut depth = 0;
+
+    for c in line.chars() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            _ => {}
+        }
+
+        if depth == 0 && (c.is_whitespace() || c == ',') {
+            if !field.is_empty() {
+                fields.push(field.clone());
+                field.clear();
+            }
+        } else {
+            field.push(c);
+        }
+    }
+
+    if !field.is_empty() {
+        fields.push(field);
+    }
+    return fields;
+}
+
+/** strip_comment() removes a ! comment from a line */
+fn strip_comment(line: &str) -> &str {
+    match line.find('!') {
+        Some(idx) => return &line[..idx],
+        None => return line,
+    }
+}
+
+/** parse_number() reads a number, allowing Fortran D exponents */
+fn parse_number(text: &str) -> Option<f64> {
+    return text.replace(['D', 'd'], "E").parse().ok();
+}
+
+/** parse_value() reads a coordinate, a number or a variable name with an optional sign */
+fn parse_value(text: &str) -> Value {
+    if let Some(number) = parse_number(text) {
+        return Value::Number(number);
+    }
+
+    match text.strip_prefix('-') {
+        Some(name) => return Value::Variable(name.to_string(), true),
+        None => return Value::Variable(text.trim_start_matches('+').to_string(), false),
+    }
+}
+
+/**
+ * parse_label() reads an atom label into its symbol, fragment, isotope and charge, e.g.
+ * C, C1, 6, C(Fragment=2,Iso=13) or the Amber style C-CT-0.12
+ */
+fn parse_label(label: &str) -> Result<(String, i64, i64, f32), String> {
+    let (name, parameters) = match label.find('(') {
+        Some(idx) => (&label[..idx], label[idx + 1..].trim_end_matches(')')),
+        None => (label, ""),
+    };
+
+    let (mut fragment, mut isotope) = (0, 0);
+    for parameter in parameters.split(',').filter(|p| !p.trim().is_empty()) {
+        let (key, value) = parameter.split_once('=').unwrap_or((parameter, ""));
+        let value = value.trim();
+        match key.trim().to_lowercase().as_str() {
+            "fragment" => {
+                fragment = value
+                    .parse()
+                    .map_err(|_| format!("invalid fragment number \"{}\"", value))?
+            }
+            "iso" => {
+                // masses may be given as reals, e.g. Iso=2.0141
+                isotope = parse_number(value)
+                    .map(|mass| mass.round() as i64)
+                    .ok_or_else(|| format!("invalid isotope \"{}\"", value))?
+            }
+            _ => {}
+        }
+    }
+
+    let mut parts = name.splitn(3, '-');
+    let element = parts.next().unwrap_or("");
+    let charge = match parts.nth(1) {
+        Some(charge) => charge
+            .parse()
+            .map_err(|_| format!("invalid charge \"{}\" in \"{}\"", charge, label))?,
+        None => 0.0,
+    };
+
+    let symbol = if let Ok(atomic_number) = element.parse::<u8>() {
+        atomprop::get_element_symbol(atomic_number)
+            .unwrap_or(DUMMY_SYMBOL)
+            .to_string()
+    } else {
+        let letters: String = element
+            .chars()
+            .take_while(|c| c.is_ascii_alphabetic())
+            .collect();
+        if letters.eq_ignore_ascii_case(DUMMY_SYMBOL) {
+            DUMMY_SYMBOL.to_string()
+        } else {
+            atomprop::get_atomic_number(&letters)
+                .and_then(atomprop::get_element_symbol)
+                .ok_or_else(|| format!("unknown element in atom label \"{}\"", label))?
+                .to_string()
+        }
+    };
+
+    return Ok((symbol, fragment, isotope, charge));
+}
+
+/**
+ * parse_atom_line() reads an atom line, as Cartesians (with an optional freeze flag) or,
+ * if the geometry is a Z-matrix, as references to earlier atoms by number or label
+ */
+fn parse_atom_line(
+    fields: &[String],
+    previous: &[AtomLine],
+    is_zmatrix: bool,
+    line_number: usize,
+) -> Result<AtomLine, String> {
+    let (symbol, fragment, isotope, charge) = parse_label(&fields[0])?;
+    let mut frozen = false;
+
+    let position = if is_zmatrix {
+        let number_of_references = previous.len().min(3);
+        if fields.len() < 1 + 2 * number_of_references {
+            return Err(format!(
+                "expected {} references with values for Z-matrix atom {}",
+                number_of_references,
+                previous.len() + 1
+            ));
+        }
+
+        let mut references = Vec::new();
+        for k in 0..number_of_references {
+            let text = &fields[1 + 2 * k];
+            let reference = match text.parse::<usize>() {
+                Ok(number) if number >= 1 && number <= previous.len() => number - 1,
+                Ok(_) => return Err(format!("reference {} is not an earlier atom", text)),
+                Err(_) => previous
+                    .iter()
+                    .position(|other| other.label == *text)
+                    .ok_or_else(|| format!("unknown reference atom \"{}\"", text))?,
+            };
+            references.push((reference, parse_value(&fields[2 + 2 * k])));
+        }
+        Position::Internal(references)
+    } else {
+        let start = match fields.len() {
+            4 => 1,
+            n if n >= 5 && fields[1].parse::<i64>().is_ok() => {
+                frozen = fields[1] == "-1";
+                2
+            }
+            n if n >= 5 => 1,
+            _ => return Err(format!("expected x y z after \"{}\"", fields[0])),
+        };
+
+        Position::Cartesian([
+            parse_value(&fields[start]),
+            parse_value(&fields[start + 1]),
+            parse_value(&fields[start + 2]),
+        ])
+    };
+
+    return Ok(AtomLine {
+        label: fields[0].clone(),
+        symbol: symbol,
+        charge: charge,
+        fragment: fragment,
+        isotope: isotope,
+        frozen: frozen,
+        position: position,
+        line_number: line_number,
+    });
+}
+
+/** resolve() returns the number of a coordinate, looking up variables */
+fn resolve(value: &Value, variables: &HashMap<String, f64>) -> Result<f64, String> {
+    match value {
+        Value::Number(number) => return Ok(*number),
+        Value::Variable(name, negated) => {
+            let number = variables
+                .get(name)
+                .ok_or_else(|| format!("the variable \"{}\" is not defined", name))?;
+            return Ok(if *negated { -number } else { *number });
+        }
+    }
+}
+
+/** parse_variable() reads a variable definition, "R1 0.96", "R1=0.96" or "R1 = 0.96 F" */
+fn parse_variable(line: &str) -> Result<(String, f64), String> {
+    let line = line.replace('=', " ");
+    let fields = split_fields(&line);
+
+    if fields.len() < 2 {
+        return Err(format!(
+            "expected a variable and its value, found \"{}\"",
+            line.trim()
+        ));
+    }
+    let value = parse_number(&fields[1])
+        .ok_or_else(|| format!("invalid value \"{}\" for \"{}\"", fields[1], fields[0]))?;
+    return Ok((fields[0].clone(), value));
+}
+
+/** is_section_header() checks for the optional Variables: and Constants: lines */
+fn is_section_header(line: &str) -> bool {
+    let line = line.trim().to_lowercase();
+    return line.starts_with("variables") || line.starts_with("constants");
+}
+
+/** next_section_line() returns the next line of a section, None at its end */
+fn next_section_line<R: BufRead>(reader: &mut LineReader<R>) -> Result<Option<String>, IoError> {
+    match reader.next_line()? {
+        Some(line) if strip_comment(&line).trim().is_empty() => return Ok(None),
+        Some(line) => return Ok(Some(strip_comment(&line).trim().to_string())),
+        None => return Ok(None),
+    }
+}
+
+/**
+ * get_units() returns the factors converting the lengths and angles of the geometry to
+ * angstrom and degrees, from the Units keyword of a lowercase route, "units=bohr",
+ * "units(au,rad)" or "units=(bohr,deg)"
+ */
+fn get_units(route: &str) -> Result<(f64, f64), String> {
+    let mut length = 1.0;
+    let mut angle = 1.0;
+
+    for field in split_fields(route) {
+        let options = match field.strip_prefix("units") {
+            Some(options) => options.trim_start_matches('='),
+            None => continue,
+        };
+        let options = options.trim_start_matches('(').trim_end_matches(')');
+        for option in options.split(',').map(|option| option.trim()) {
+            match option {
+                "ang" => length = 1.0,
+                "bohr" | "au" => length = io::BOHR,
+                "deg" => angle = 1.0,
+                "rad" => angle = 180.0 / std::f64::consts::PI,
+                _ => return Err(format!("unknown units \"{}\" in the route", option)),
+            }
+        }
+    }
+
+    return Ok((length, angle));
+}
+
+/**
+ * build_atoms() converts the atom lines to atoms, with the Z-matrix if there is one, the
+ * lengths and angles are multiplied by the factors of the units
+ */
+fn build_atoms(
+    atom_lines: &[AtomLine],
+    variables: &HashMap<String, f64>,
+    units: (f64, f64),
+    reader_error: &dyn Fn(usize, String) -> IoError,
+) -> Result<Vec<Atom>, IoError> {
+    let (length, angle) = units;
+    let mut positions = Vec::new();
+    let mut zmatrix = ZMatrix::new();
+
+    for atom_line in atom_lines.iter() {
+        let error = |message| reader_error(atom_line.line_number, message);
+        match &atom_line.position {
+            Position::Cartesian(values) => {
+                let mut position = [0.0; 3];
+                for (coordinate, value) in position.iter_mut().zip(values.iter()) {
+                    *coordinate = resolve(value, variables).map_err(error)? * length;
+                }
+                positions.push(position);
+            }
+            Position::Internal(references) => {
+                let mut entry = ZMatrixEntry::new(&atom_line.symbol);
+                let mut resolved = Vec::new();
+                for (k, (reference, value)) in references.iter().enumerate() {
+                    let factor = if k == 0 { length } else { angle };
+                    let value = resolve(value, variables).map_err(error)? * factor;
+                    resolved.push((*reference, value));
+                }
+                entry.distance = resolved.first().copied();
+                entry.angle = resolved.get(1).copied();
+                entry.dihedral = resolved.get(2).copied();
+                zmatrix.entries.push(entry);
+            }
+        }
+    }
+
+    if !zmatrix.entries.is_empty() {
+        positions = zmatrix.to_positions().map_err(|err| {
+            let entry = match &err {
+                ZMatrixError::MissingReference { entry, .. } => *entry,
+                ZMatrixError::InvalidReference { entry, .. } => *entry,
+            };
+            reader_error(atom_lines[entry].line_number, err.to_string())
+        })?;
+    }
+
+    let atoms = atom_lines
+        .iter()
+        .zip(positions.iter())
+        .map(|(atom_line, p)| Atom {
+            center: Point {
+                x: p[0] as f32,
+                y: p[1] as f32,
+                z: p[2] as f32,
+            },
+            charge: atom_line.charge,
+            symbol: atom_line.symbol.clone(),
+            remark: "".to_string(),
+        })
+        .collect();
+    return Ok(atoms);
+}
+
+/** read_connectivity() reads the connectivity section into bonds */
+fn read_connectivity<R: BufRead>(
+    reader: &mut LineReader<R>,
+    mol: &mut Molecule,
+) -> Result<(), IoError> {
+    let number_of_atoms = mol.get_atoms().len();
+
+    while let Some(line) = next_section_line(reader)? {
+        let fields = split_fields(&line);
+        if fields.is_empty() {
+            continue;
+        }
+        let atom = fields[0]
+            .parse::<usize>()
+            .ok()
+            .filter(|atom| *atom >= 1 && *atom <= number_of_atoms)
+            .ok_or_else(|| reader.error(format!("invalid atom number \"{}\"", fields[0])))?;
+
+        for pair in fields[1..].chunks(2) {
+            let other = pair[0]
+                .parse::<usize>()
+                .ok()
+                .filter(|other| *other >= 1 && *other <= number_of_atoms && *other != atom)
+                .ok_or_else(|| reader.error(format!("invalid atom number \"{}\"", pair[0])))?;
+            let order = pair
+                .get(1)
+                .and_then(|order| parse_number(order))
+                .ok_or_else(|| {
+                    reader.error(format!("missing bond order for {}-{}", atom, other))
+                })?;
+
+            let bond_type = if order >= 2.75 {
+                BondType::TRIPLE
+            } else if order >= 1.75 {
+                BondType::DOUBLE
+            } else if order >= 1.25 {
+                BondType::AROMATIC
+            } else if order >= 0.75 {
+                BondType::SINGLE
+            } else {
+                BondType::WEAK
+            };
+            mol.add_bond(atom - 1, other - 1, bond_type);
+        }
+    }
+
+    return Ok(());
+}
+
+/** read() reads the first job of a Gaussian input file */
+#[allow(dead_code)]
+pub fn read<R: BufRead>(reader: R) -> Result<Molecule, IoError> {
+    let mut reader = LineReader::new(reader, FORMAT);
+    let mut mol = Molecule::new("".to_string(), "".to_string());
+
+    // Link0 commands, then the route, which may take several lines
+    let mut route = String::new();
+    loop {
+        let line = match reader.next_line()? {
+            Some(line) => line,
+            None if route.is_empty() => {
+                return Err(reader.error("the route section is missing".to_string()))
+            }
+            None => break,
+        };
+        let text = strip_comment(&line).trim();
+
+        if text.is_empty() {
+            if route.is_empty() {
+                continue;
+            }
+            break;
+        } else if route.is_empty() && text.starts_with('%') {
+            let (command, value) = text[1..].split_once('=').unwrap_or((&text[1..], ""));
+            let name = format!("{}{}", LINK0_PREFIX, command.trim().to_lowercase());
+            mol.set_property(&name, Property::Text(value.trim().to_string()));
+        } else if route.is_empty() && !text.starts_with('#') {
+            return Err(reader.error(format!(
+                "expected the route section starting with #, found \"{}\"",
+                text
+            )));
+        } else {
+            if !route.is_empty() {
+                route.push(' ');
+            }
+            route.push_str(text);
+        }
+    }
+    mol.set_property("route", Property::Text(route.clone()));
+    let route = route.to_lowercase();
+    let units = get_units(&route).map_err(|message| IoError::parse(FORMAT, 0, message))?;
+
+    let mut title = Vec::new();
+    while let Some(line) = next_section_line(&mut reader)? {
+        title.push(line);
+    }
+    mol.remark = title.join(" ");
+
+    let line = reader.expect_line("the charge and multiplicity")?;
+    let fields = split_fields(&line);
+    let numbers: Vec<i64> = fields.iter().filter_map(|f| f.parse().ok()).collect();
+    if numbers.len() < 2 || numbers.len() != fields.len() {
+        return Err(reader.error(format!(
+            "expected the charge and multiplicity, found \"{}\"",
+            line.trim()
+        )));
+    }
+    mol.set_property("charge", Property::Integer(numbers[0]));
+    mol.set_property("multiplicity", Property::Integer(numbers[1]));
+
+    // the geometry, possibly followed by the variables of a Z-matrix
+    let mut atom_lines: Vec<AtomLine> = Vec::new();
+    let mut variables: HashMap<String, f64> = HashMap::new();
+    let mut in_variables = false;
+    let mut is_zmatrix = false;
+
+    while let Some(line) = next_section_line(&mut reader)? {
+        if is_section_header(&line) {
+            in_variables = true;
+        } else if in_variables {
+            let (name, value) = parse_variable(&line).map_err(|message| reader.error(message))?;
+            variables.insert(name, value);
+        } else {
+            let fields = split_fields(&line);
+            if atom_lines.is_empty() {
+                is_zmatrix = fields.len() == 1;
+            }
+            let atom_line =
+                parse_atom_line(&fields, &atom_lines, is_zmatrix, reader.get_line_number())
+                    .map_err(|message| reader.error(message))?;
+            atom_lines.push(atom_line);
+        }
+    }
+
+    if atom_lines.is_empty() {
+        return Err(reader.error("the geometry has no atoms".to_string()));
+    }
+
+    let uses_variables = atom_lines
+        .iter()
+        .any(|atom_line| match &atom_line.position {
+            Position::Cartesian(values) => values.iter().any(|v| matches!(v, Value::Variable(..))),
+            Position::Internal(references) => references
+                .iter()
+                .any(|(_, v)| matches!(v, Value::Variable(..))),
+        });
+    if uses_variables {
+        // the Variables: line may be left out, and a Constants: section follows a blank line
+        let mut is_next_block = !in_variables;
+        loop {
+            if !is_next_block {
+                match reader.peek_line()? {
+                    Some(line) if line.trim().to_lowercase().starts_with("constants") => {}
+                    _ => break,
+                }
+            }
+            while let Some(line) = next_section_line(&mut reader)? {
+                if !is_section_header(&line) {
+                    let (name, value) =
+                        parse_variable(&line).map_err(|message| reader.error(message))?;
+                    variables.insert(name, value);
+                }
+            }
+            is_next_block = false;
+        }
+    }
+
+    let error = |line_number, message| IoError::parse(FORMAT, line_number, message);
+    let atoms = build_atoms(&atom_lines, &variables, units, &error)?;
+    let (atom_lines, atoms): (Vec<AtomLine>, Vec<Atom>) = atom_lines
+        .into_iter()
+        .zip(atoms)
+        .filter(|(atom_line, _)| atom_line.symbol != DUMMY_SYMBOL)
+        .unzip();
+    for atom in atoms {
+        mol.add_atom(atom);
+    }
+
+    if atom_lines.iter().any(|atom_line| atom_line.frozen) {
+        let frozen = atom_lines
+            .iter()
+            .map(|atom_line| atom_line.frozen)
+            .collect();
+        mol.set_atom_property("frozen", AtomProperty::Logicals(frozen));
+    }
+    if atom_lines.iter().any(|atom_line| atom_line.fragment != 0) {
+        let fragments = atom_lines
+            .iter()
+            .map(|atom_line| atom_line.fragment)
+            .collect();
+        mol.set_atom_property("fragment", AtomProperty::Integers(fragments));
+    }
+    if atom_lines.iter().any(|atom_line| atom_line.isotope != 0) {
+        let isotopes = atom_lines
+            .iter()
+            .map(|atom_line| atom_line.isotope)
+            .collect();
+        mol.set_atom_property("isotope", AtomProperty::Integers(isotopes));
+    }
+
+    if route.contains("connect") {
+        read_connectivity(&mut reader, &mut mol)?;
+    }
+
+    if route.contains("modredundant") {
+        let mut lines = Vec::new();
+        while let Some(line) = next_section_line(&mut reader)? {
+            lines.push(line);
+        }
+        if !lines.is_empty() {
+            mol.set_property("modredundant", Property::Text(lines.join("; ")));
+        }
+    }
+
+    return Ok(mol);
+}
+
+/** Unit tests for the above module */
+#[cfg(test)]
+mod tests {
+    use crate::bond::BondType;
+    use crate::io::gaussian::GaussianWriter;
+    use crate::molecule::builder::MoleculeBuilder;
+    use crate::molecule::Molecule;
+    use crate::property::AtomProperty;
+    use crate::property::Property;
+    use crate::testutil;
+
+    /** get_water() returns the shared water molecule, with a title of two lines */
+    fn get_water() -> Molecule {
+        let mut water = testutil::get_water();
+        water.remark = "water\nsinglet".to_string();
+        return water;
+    }
+
+    fn get_distance(mol: &Molecule, i: usize, j: usize) -> f32 {
+        let atoms = mol.get_atoms();
+        return atoms[i].center.clone().distance_from(&atoms[j].center);
+    }
+
+    #[test]
+    fn gaussian_write() {
+        let mut output = Vec::new();
+        GaussianWriter::new()
+            .mem("4GB")
+            .nproc(8)
+            .chk("water.chk")
+            .route("B3LYP/6-31G(d) Opt=ModRedundant")
+            .connectivity(true)
+            .modredundant("B 1 2 F")
+            .write(&mut output, &get_water())
+            .unwrap();
+
+        let text = String::from_utf8(output).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[0], "%mem=4GB");
+        assert_eq!(lines[1], "%nproc=8");
+        assert_eq!(lines[2], "%chk=water.chk");
+        assert_eq!(
+            lines[3],
+            "# B3LYP/6-31G(d) Opt=ModRedundant geom=connectivity"
+        );
+        assert_eq!(lines[5], "water singlet");
+        assert_eq!(lines[7], "0 1");
+        assert!(lines[8].starts_with("O "));
+        assert_eq!(lines[11], "");
+        assert_eq!(
+            lines[12].split_whitespace().collect::<Vec<_>>(),
+            ["1", "2", "1.0", "3", "1.0"]
+        );
+        assert_eq!(lines[13].trim(), "2");
+        assert_eq!(lines[14].trim(), "3");
+        assert_eq!(lines[15], "");
+        assert_eq!(lines[16], "B 1 2 F");
+        assert!(text.ends_with("B 1 2 F\n\n"));
+
+        // a radical gets a doublet, a cation a singlet
+        let methyl = MoleculeBuilder::new("CH3")
+            .xyz("C 0 0 0\nH 1.08 0 0\nH -0.54 0.935 0\nH -0.54 -0.935 0")
+            .build()
+            .unwrap();
+        let mut output = Vec::new();
+        super::write(&mut output, &methyl).unwrap();
+        let text = String::from_utf8(output).unwrap();
+        assert!(text.starts_with("#P HF/STO-3G SP\n\nCH3\n\n0 2\n"));
+
+        let mut output = Vec::new();
+        GaussianWriter::new()
+            .charge(1)
+            .write(&mut output, &methyl)
+            .unwrap();
+        assert!(String::from_utf8(output).unwrap().contains("\n1 1\n"));
+    }
+
+    #[test]
+    fn gaussian_round_trip() {
+        let mut water = get_water();
+        water.set_atom_property("frozen", AtomProperty::Logicals(vec![true, false, false]));
+        water.set_atom_property("isotope", AtomProperty::Integers(vec![0, 2, 0]));
+
+        for zmatrix in [false, true] {
+            let mut output = Vec::new();
+            GaussianWriter::new()
+                .chk("water.chk")
+                .zmatrix(zmatrix)
+                .connectivity(true)
+                .write(&mut output, &water)
+                .unwrap();
+
+            let mut mol = super::read(output.as_slice()).unwrap();
+            assert_eq!(mol.remark, "water singlet");
+            assert_eq!(
+                mol.get_property("link0_chk"),
+                Some(&Property::Text("water.chk".to_string()))
+            );
+            assert_eq!(
+                mol.get_property("route"),
+                Some(&Property::Text(
+                    "#P HF/STO-3G SP geom=connectivity".to_string()
+                ))
+            );
+            assert_eq!(
+                mol.get_property("multiplicity"),
+                Some(&Property::Integer(1))
+            );
+            assert_eq!(mol.get_atoms().len(), 3);
+            assert_eq!(mol.get_number_of_bonds(), 2);
+            assert_eq!(mol.get_bond_type(0, 2), BondType::SINGLE);
+            assert_eq!(
+                mol.get_atom_property("isotope"),
+                Some(&AtomProperty::Integers(vec![0, 2, 0]))
+            );
+            assert!((get_distance(&mol, 0, 1) - get_distance(&water, 0, 1)).abs() < 1e-4);
+            assert!((get_distance(&mol, 1, 2) - get_distance(&water, 1, 2)).abs() < 1e-4);
+
+            if zmatrix {
+                assert_eq!(mol.get_atom_property("frozen"), None);
+            } else {
+                assert_eq!(mol.get_atoms()[1].center, water.get_atoms()[1].center);
+                assert_eq!(
+                    mol.get_atom_property("frozen"),
+                    Some(&AtomProperty::Logicals(vec![true, false, false]))
+                );
+            }
+
+            // the properties read are written back as they were
+            let mut again = Vec::new();
+            super::write(&mut again, &mol).unwrap();
+            let mut first = Vec::new();
+            GaussianWriter::new().write(&mut first, &mol).unwrap();
+            assert_eq!(again, first);
+            assert!(String::from_utf8(again)
+                .unwrap()
+                .starts_with("%chk=water.chk\n#P HF/STO-3G SP geom=connectivity\n"));
+        }
+    }
+
+    #[test]
+    fn gaussian_write_zmatrix() {
+        let acetylene = MoleculeBuilder::new("acetylene")
+            .xyz("H 0.0 0.0 -1.663\nC 0.0 0.0 -0.601\nC 0.0 0.0 0.601\nH 0.0 0.0 1.663")
+            .bonds(&[(0, 1), (1, 2), (2, 3)])
+            .build()
+            .unwrap();
+
+        let mut output = Vec::new();
+        GaussianWriter::new()
+            .zmatrix(true)
+            .variables(true)
+            .connectivity(true)
+            .write(&mut output, &acetylene)
+            .unwrap();
+
+        let text = String::from_utf8(output).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        let fields = |line: &str| {
+            line.split_whitespace()
+                .map(String::from)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(fields(lines[5]), ["H"]);
+        assert_eq!(fields(lines[6]), ["C", "1", "R1"]);
+        assert_eq!(fields(lines[7]), ["X", "2", "R2", "1", "A1"]);
+        assert_eq!(fields(lines[8]), ["C", "2", "R3", "3", "A2", "1", "D1"]);
+        assert_eq!(lines[10], "");
+        let variable = |line: &str| {
+            let fields = fields(line);
+            (fields[0].clone(), fields[1].parse::<f64>().unwrap())
+        };
+        let expected = [
+            ("R1", 1.062),
+            ("R2", 1.0),
+            ("R3", 1.202),
+            ("R4", 1.062),
+            ("A1", 90.0),
+        ];
+        for (line, (name, value)) in lines[11..16].iter().zip(expected.iter()) {
+            let (found_name, found_value) = variable(line);
+            assert_eq!(found_name, *name);
+            assert!((found_value - value).abs() < 1e-5);
+        }
+
+        // the dummy atom is not read back as an atom
+        let mut mol = super::read(text.as_bytes()).unwrap();
+        let symbols: Vec<&str> = mol.get_atoms().iter().map(|a| a.symbol.as_str()).collect();
+        assert_eq!(symbols, ["H", "C", "C", "H"]);
+        assert_eq!(mol.get_number_of_bonds(), 3);
+        assert_eq!(mol.get_bond_type(2, 3), BondType::SINGLE);
+        assert!((get_distance(&mol, 0, 3) - 3.326).abs() < 1e-4);
+    }
+
+    #[test]
+    fn gaussian_read_zmatrix() {
+        let input = "%Mem=1GB
+%NProcShared=4
+#P MP2/6-31G(d)
+ Opt ! tight convergence is not needed
+
+hydrogen peroxide, C2
+
+0,1
+O1
+O2 O1 ROO
+H3 1 ROH 2 AOOH
+H4 O2 ROH O1 AOOH 3 -D
+
+Variables:
+ROO 1.45
+ROH = 0.97
+AOOH 100.0
+D 120.0
+
+";
+        let mol = super::read(input.as_bytes()).unwrap();
+        assert_eq!(mol.remark, "hydrogen peroxide, C2");
+        assert_eq!(
+            mol.get_property("route"),
+            Some(&Property::Text("#P MP2/6-31G(d) Opt".to_string()))
+        );
+        assert_eq!(
+            mol.get_property("link0_nprocshared"),
+            Some(&Property::Text("4".to_string()))
+        );
+        let symbols: Vec<&str> = mol.get_atoms().iter().map(|a| a.symbol.as_str()).collect();
+        assert_eq!(symbols, ["O", "O", "H", "H"]);
+        assert!((get_distance(&mol, 0, 1) - 1.45).abs() < 1e-5);
+        assert!((get_distance(&mol, 1, 3) - 0.97).abs() < 1e-5);
+        assert!((get_distance(&mol, 0, 2) - 0.97).abs() < 1e-5);
+
+        // the constants follow the variables after a blank line, atoms may be numbers
+        let input = "# HF\n\ncyanide\n\n-1 1\n6\n7 1 r\n\nVariables:\n\nConstants:\nr 1.16\n\n";
+        let mol = super::read(input.as_bytes()).unwrap();
+        assert_eq!(mol.get_property("charge"), Some(&Property::Integer(-1)));
+        assert_eq!(mol.get_atoms()[1].symbol, "N");
+        assert_eq!(mol.get_atoms()[1].center.z, 1.16);
+    }
+
+    #[test]
+    fn gaussian_read_units() {
+        let input = "# hf units=bohr\n\nhydrogen\n\n0 2\nH 0.0 0.0 1.0\n\n";
+        let mol = super::read(input.as_bytes()).unwrap();
+        assert!((mol.get_atoms()[0].center.z - 0.529177).abs() < 1e-5);
+
+        // a Z-matrix in bohr and radians
+        let input = "# HF Units(AU,Rad)\n\nwater\n\n0 1\nO\nH 1 1.8\nH 1 1.8 2 a\n\na 1.8\n\n";
+        let mol = super::read(input.as_bytes()).unwrap();
+        let distance = 1.8 * crate::io::BOHR as f32;
+        assert!((get_distance(&mol, 0, 1) - distance).abs() < 1e-5);
+        assert!((get_distance(&mol, 0, 2) - distance).abs() < 1e-5);
+        let expected = 2.0 * distance * (0.9f32).sin();
+        assert!((get_distance(&mol, 1, 2) - expected).abs() < 1e-4);
+
+        // the geometry is written back in angstrom, so the units are left out of the route
+        let mut output = Vec::new();
+        super::write(&mut output, &mol).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.starts_with("# HF\n"));
+        let copy = super::read(output.as_bytes()).unwrap();
+        assert!((get_distance(&copy, 0, 1) - distance).abs() < 1e-4);
+
+        let input = "# HF units=furlong\n\ntitle\n\n0 1\nH 0 0 0\n\n";
+        assert_eq!(
+            super::read(input.as_bytes()).unwrap_err().to_string(),
+            "gaussian: unknown units \"furlong\" in the route"
+        );
+    }
+
+    #[test]
+    fn gaussian_read_errors() {
+        let error = |input: &str| super::read(input.as_bytes()).unwrap_err().to_string();
+
+        assert_eq!(
+            error("%chk=a.chk\nHF/STO-3G\n"),
+            "gaussian: line 2: expected the route section starting with #, found \"HF/STO-3G\""
+        );
+        assert_eq!(
+            error("# HF\n\ntitle\n\n0\nH 0 0 0\n"),
+            "gaussian: line 5: expected the charge and multiplicity, found \"0\""
+        );
+        assert_eq!(
+            error("# HF\n\ntitle\n\n0 1\nH 0 0 0\nQ 0 0 1\n"),
+            "gaussian: line 7: unknown element in atom label \"Q\""
+        );
+        assert_eq!(
+            error("# HF\n\ntitle\n\n0 1\nH\nH 1 r\n\nd 0.74\n"),
+            "gaussian: line 7: the variable \"r\" is not defined"
+        );
+        assert_eq!(
+            error("# HF geom=connectivity\n\ntitle\n\n0 1\nH\nH 1 0.74\n\n1 3 1.0\n"),
+            "gaussian: line 9: invalid atom number \"3\""
+        );
+    }
+}
@@ -0,0 +1,469 @@
+/**
+ * Author: V. Ganesh
+ * License: MIT
+ */
+
+/**
+ * gromacs module, reads and writes GROMACS .gro coordinate files and writes .top topologies.
+ *
+ * A .gro file is in nanometre, positions are converted to angstrom and velocities to
+ * angstrom per picosecond (the "velocity" atom property). Residues become residue groups,
+ * named by the residue name with the residue number as remark, as for PDB files, and the
+ * atom names are kept as the "atom_name" atom property; the element is guessed from the
+ * atom name. The box becomes the lattice. The precision of the coordinates is taken from
+ * the spacing of their decimal points, as GROMACS does. A .gro file has no bonds.
+ *
+ * The writer puts the cell in the form GROMACS needs (a along x, b in the xy plane),
+ * turning the positions with it. A molecule without a lattice is written with a zero box,
+ * to be set with gmx editconf.
+ *
+ * write_topology() writes the atoms, bonds, angles and dihedrals of the molecule as one
+ * [ moleculetype ], with the angles and dihedrals derived from the bonds. The parameters
+ * are left to the force field included before it; atom types are the "atom_type" atom
+ * property, or else the element symbols.
+ */
+use std::io::BufRead;
+use std::io::Write;
+
+use crate::atom::Atom;
+use crate::atom::AtomGroup;
+use crate::atom::AtomOperations;
+use crate::atom::GroupKind;
+use crate::atomprop;
+use crate::io::pdb;
+use crate::io::pdb::column;
+use crate::io::pdb::AtomRecord;
+use crate::io::IoError;
+use crate::io::LineReader;
+use crate::lattice::Lattice;
+use crate::molecule::Molecule;
+use crate::point::Point;
+use crate::property::AtomProperty;
+
+const FORMAT: &str = "gro";
+
+/** angstrom in a nanometre */
+const NM: f64 = 10.0;
+
+/** get_field_width() returns the width of the coordinate fields of an atom line, from its decimal points */
+fn get_field_width(line: &str) -> usize {
+    let fields = column(line, 20, line.len());
+    if let Some(first) = fields.find('.') {
+        if let Some(second) = fields[first + 1..].find('.') {
+            return second + 1;
+        }
+    }
+    return 8;
+}
+
+/** get_records() returns the residue name and number of each atom */
+fn get_records(mol: &Molecule) -> Vec<AtomRecord> {
+    let default_record = AtomRecord {
+        chain: "".to_string(),
+        residue_name: pdb::UNKNOWN_RESIDUE.to_string(),
+        residue_number: "1".to_string(),
+    };
+    let mut records = vec![default_record.clone(); mol.get_atoms().len()];
+    pdb::collect_records(mol.get_groups(), &default_record, &mut records);
+    return records;
+}
+
+/** get_atom_names() returns the "atom_name" atom property, or else the element symbols */
+fn get_atom_names(mol: &Molecule) -> Vec<String> {
+    match mol.get_atom_property("atom_name") {
+        Some(AtomProperty::Texts(names)) => return names.to_vec(),
+        _ => {
+            return mol
+                .get_atoms()
+                .iter()
+                .map(|at| at.symbol.to_string())
+                .collect()
+        }
+    }
+}
+
+/** read() reads a GROMACS .gro file, the first frame if it has several */
+#[allow(dead_code)]
+pub fn read<R: BufRead>(reader: R) -> Result<Molecule, IoError> {
+    let mut reader = LineReader::new(reader, FORMAT);
+
+    let title = reader.expect_line("the title")?;
+    let mut mol = Molecule::new(title.trim().to_string(), "".to_string());
+
+    let line = reader.expect_line("the number of atoms")?;
+    let number_of_atoms: usize = line
+        .trim()
+        .parse()
+        .map_err(|_| reader.error("expected the number of atoms".to_string()))?;
+
+    let mut atom_names = Vec::new();
+    let mut velocities = Vec::new();
+    let mut residues: Vec<AtomGroup> = Vec::new();
+    let mut residue_key = None;
+
+    for idx in 0..number_of_atoms {
+        let line = reader.expect_line("an atom")?;
+        let residue_number = column(&line, 0, 5).trim().to_string();
+        let residue_name = column(&line, 5, 10).trim().to_string();
+        let atom_name = column(&line, 10, 15).trim().to_string();
+
+        let width = get_field_width(&line);
+        let field = |n: usize| column(&line, 20 + n * width, 20 + (n + 1) * width).trim();
+        let position: Vec<f64> = (0..3)
+            .map(|n| field(n).parse::<f64>())
+            .collect::<Result<_, _>>()
+            .map_err(|_| reader.error("expected the x, y and z of an atom".to_string()))?;
+        if let Ok(velocity) = (3..6)
+            .map(|n| field(n).parse::<f64>())
+            .collect::<Result<Vec<f64>, _>>()
+        {
+            velocities.push([velocity[0] * NM, velocity[1] * NM, velocity[2] * NM]);
+        }
+
+        let key = (residue_number.to_string(), residue_name.to_string());
+        if residue_key.as_ref() != Some(&key) {
+            residues.push(
+                AtomGroup::new(residue_name.to_string(), residue_number.to_string())
+                    .with_kind(GroupKind::RESIDUE),
+            );
+            residue_key = Some(key);
+        }
+        if let Some(residue) = residues.last_mut() {
+            residue.add_atom_index(idx);
+        }
+
+        mol.add_atom(Atom {
+            center: Point {
+                x: (position[0] * NM) as f32,
+                y: (position[1] * NM) as f32,
+                z: (position[2] * NM) as f32,
+            },
+            charge: 0.0,
+            // ions are named as their residue, NA in NA, while CA in a residue is a carbon
+            symbol: pdb::guess_element("", &atom_name, atom_name == residue_name),
+            remark: "".to_string(),
+        });
+        atom_names.push(atom_name);
+    }
+
+    let line = reader.expect_line("the box")?;
+    let values: Vec<f64> = line
+        .split_whitespace()
+        .map(|field| field.parse::<f64>().map(|value| value * NM))
+        .collect::<Result<_, _>>()
+        .map_err(|_| reader.error("expected the box vectors".to_string()))?;
+    let vectors = match values[..] {
+        [x, y, z] => [[x, 0.0, 0.0], [0.0, y, 0.0], [0.0, 0.0, z]],
+        [v1x, v2y, v3z, v1y, v1z, v2x, v2z, v3x, v3y] => {
+            [[v1x, v1y, v1z], [v2x, v2y, v2z], [v3x, v3y, v3z]]
+        }
+        _ => return Err(reader.error("expected 3 or 9 box values".to_string())),
+    };
+    if vectors.iter().flatten().any(|value| *value != 0.0) {
+        mol.set_lattice(Some(Lattice::new(vectors)));
+    }
+
+    for residue in residues {
+        mol.add_group(residue);
+    }
+    mol.set_atom_property("atom_name", AtomProperty::Texts(atom_names));
+    if velocities.len() == number_of_atoms && number_of_atoms > 0 {
+        mol.set_atom_property("velocity", AtomProperty::Vectors(velocities));
+    }
+
+    return Ok(mol);
+}
+
+/** write() writes a molecule as a GROMACS .gro file */
+#[allow(dead_code)]
+pub fn write<W: Write>(mut writer: W, mol: &Molecule) -> Result<(), IoError> {
+    let atoms = mol.get_atoms();
+    let records = get_records(mol);
+    let atom_names = get_atom_names(mol);
+    let velocities = match mol.get_atom_property("velocity") {
+        Some(AtomProperty::Vectors(values)) => Some(values),
+        _ => None,
+    };
+
+    // GROMACS needs a along x and b in the xy plane
+    let lattice = mol.get_lattice();
+    let standard = lattice.map(|lattice| lattice.get_standard_form());
+    let turn = |position: [f64; 3]| match (lattice, standard.as_ref()) {
+        (Some(lattice), Some(standard)) if !lattice.is_standard_form() => {
+            standard.to_cartesian(lattice.to_fractional(position))
+        }
+        _ => position,
+    };
+
+    let title = if mol.name.is_empty() {
+        "fekit"
+    } else {
+        &mol.name
+    };
+    writeln!(writer, "{}", title.replace('\n', " "))?;
+    writeln!(writer, "{:5}", atoms.len())?;
+
+    for (idx, atom) in atoms.iter().enumerate() {
+        let residue_number = pdb::split_residue_number(&records[idx].residue_number).0;
+        let residue_name: String = records[idx].residue_name.chars().take(5).collect();
+        let atom_name: String = atom_names[idx].chars().take(5).collect();
+        let position = turn([
+            atom.center.x as f64,
+            atom.center.y as f64,
+            atom.center.z as f64,
+        ]);
+
+        write!(
+            writer,
+            "{:>5}{:<5}{:>5}{:>5}{:8.3}{:8.3}{:8.3}",
+            residue_number.rem_euclid(100000),
+            residue_name,
+            atom_name,
+            (idx + 1) % 100000,
+            position[0] / NM,
+            position[1] / NM,
+            position[2] / NM
+        )?;
+        if let Some(velocities) = velocities {
+            let velocity = turn(velocities[idx]);
+            write!(
+                writer,
+                "{:8.4}{:8.4}{:8.4}",
+                velocity[0] / NM,
+                velocity[1] / NM,
+                velocity[2] / NM
+            )?;
+        }
+        writeln!(writer)?;
+    }
+
+    let vectors = match (lattice, standard) {
+        (Some(lattice), _) if lattice.is_standard_form() => lattice.vectors,
+        (_, Some(standard)) => standard.vectors,
+        _ => [[0.0; 3]; 3],
+    };
+    let [v1, v2, v3] = vectors.map(|vector| vector.map(|value| value / NM));
+    if [v1[1], v1[2], v2[0], v2[2], v3[0], v3[1]]
+        .iter()
+        .all(|v| v.abs() < 1e-10)
+    {
+        writeln!(writer, "{:10.5}{:10.5}{:10.5}", v1[0], v2[1], v3[2])?;
+    } else {
+        writeln!(
+            writer,
+            "{:10.5}{:10.5}{:10.5}{:10.5}{:10.5}{:10.5}{:10.5}{:10.5}{:10.5}",
+            v1[0], v2[1], v3[2], v1[1], v1[2], v2[0], v2[2], v3[0], v3[1]
+        )?;
+    }
+
+    return Ok(());
+}
+
+/** write_topology() writes the molecule as a GROMACS .top topology, without parameters */
+#[allow(dead_code)]
+pub fn write_topology<W: Write>(mut writer: W, mol: &Molecule) -> Result<(), IoError> {
+    let atoms = mol.get_atoms();
+    let records = get_records(mol);
+    let atom_names = get_atom_names(mol);
+    let atom_types = match mol.get_atom_property("atom_type") {
+        Some(AtomProperty::Texts(types)) => types.to_vec(),
+        _ => atoms.iter().map(|at| at.symbol.to_string()).collect(),
+    };
+
+    let name: String = mol.name.split_whitespace().collect::<Vec<_>>().join("_");
+    let name = if name.is_empty() {
+        "MOL".to_string()
+    } else {
+        name
+    };
+
+    writeln!(
+        writer,
+        "; topology written by fekit, include the force field first"
+    )?;
+    writeln!(writer)?;
+    writeln!(writer, "[ moleculetype ]")?;
+    writeln!(writer, "; name  nrexcl")?;
+    writeln!(writer, "{}  3", name)?;
+    writeln!(writer)?;
+
+    writeln!(writer, "[ atoms ]")?;
+    writeln!(
+        writer,
+        ";   nr       type  resnr residue  atom   cgnr     charge       mass"
+    )?;
+    for (idx, atom) in atoms.iter().enumerate() {
+        writeln!(
+            writer,
+            "{:>6} {:>10} {:>6} {:>7} {:>5} {:>6} {:>10.4} {:>10.4}",
+            idx + 1,
+            atom_types[idx],
+            pdb::split_residue_number(&records[idx].residue_number).0,
+            records[idx].residue_name,
+            atom_names[idx],
+            idx + 1,
+            atom.charge,
+            atomprop::get_atomic_weight(&atom.symbol).unwrap_or(0.0)
+        )?;
+    }
+
+    if !mol.get_bonds().is_empty() {
+        writeln!(writer)?;
+        writeln!(writer, "[ bonds ]")?;
+        writeln!(writer, ";  ai    aj funct")?;
+        for bond in mol.get_bonds().iter() {
+            writeln!(
+                writer,
+                "{:>5} {:>5} {:>5}",
+                bond.atom_1_idx + 1,
+                bond.atom_2_idx + 1,
+                1
+            )?;
+        }
+    }
+
+    let angles = mol.get_angles();
+    if !angles.is_empty() {
+        writeln!(writer)?;
+        writeln!(writer, "[ angles ]")?;
+        writeln!(writer, ";  ai    aj    ak funct")?;
+        for [i, j, k] in angles {
+            writeln!(writer, "{:>5} {:>5} {:>5} {:>5}", i + 1, j + 1, k + 1, 1)?;
+        }
+    }
+
+    let dihedrals = mol.get_dihedrals();
+    if !dihedrals.is_empty() {
+        writeln!(writer)?;
+        writeln!(writer, "[ dihedrals ]")?;
+        writeln!(writer, ";  ai    aj    ak    al funct")?;
+        for [i, j, k, l] in dihedrals {
+            writeln!(
+                writer,
+                "{:>5} {:>5} {:>5} {:>5} {:>5}",
+                i + 1,
+                j + 1,
+                k + 1,
+                l + 1,
+                9
+            )?;
+        }
+    }
+
+    writeln!(writer)?;
+    writeln!(writer, "[ system ]")?;
+    writeln!(writer, "{}", name)?;
+    writeln!(writer)?;
+    writeln!(writer, "[ molecules ]")?;
+    writeln!(writer, "{}  1", name)?;
+
+    return Ok(());
+}
+
+/** Unit tests for the above module */
+#[cfg(test)]
+mod tests {
+    use crate::atom::GroupKind;
+    use crate::bond::BondType;
+    use crate::property::AtomProperty;
+
+    const GRO: &str = "Water and sodium t= 0.00000
+    4
+    1SOL     OW    1   0.126   0.639   0.677  0.1000 -0.2000  0.0000
+    1SOL    HW1    2   0.190   0.614   0.604  0.0000  0.0000  0.0000
+    1SOL    HW2    3   0.177   0.694   0.737  0.0000  0.0000  0.0000
+    2NA      NA    4   1.000   1.000   1.000  0.0000  0.0000  0.0000
+   1.86206   1.86206   1.86206
+";
+
+    #[test]
+    fn gro_read() {
+        let mol = super::read(GRO.as_bytes()).unwrap();
+        assert_eq!(mol.name, "Water and sodium t= 0.00000");
+
+        let symbols: Vec<&str> = mol
+            .get_atoms()
+            .iter()
+            .map(|at| at.symbol.as_str())
+            .collect();
+        assert_eq!(symbols, vec!["O", "H", "H", "Na"]);
+        assert_eq!(mol.get_atoms()[3].center.x, 10.0);
+        assert_eq!(mol.get_atoms()[0].center.x, 1.26);
+
+        let groups = mol.get_groups();
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].kind, GroupKind::RESIDUE);
+        assert_eq!(groups[0].name, "SOL");
+        assert_eq!(groups[0].get_atom_indices(), &[0, 1, 2]);
+        assert_eq!(groups[1].remark, "2");
+
+        match mol.get_atom_property("velocity") {
+            Some(AtomProperty::Vectors(values)) => assert_eq!(values[0], [1.0, -2.0, 0.0]),
+            property => panic!("unexpected {:?}", property),
+        }
+        assert_eq!(mol.get_lattice().unwrap().vectors[1][1], 18.6206);
+
+        // five decimals, the field width comes from the decimal points
+        let gro = "precise\n 1\n    1MOL      C    1  0.12345  1.00000 -0.50000\n 1.0 1.0 1.0\n";
+        let mol = super::read(gro.as_bytes()).unwrap();
+        assert_eq!(mol.get_atoms()[0].center.z, -5.0);
+        assert_eq!(mol.get_atom_property("velocity"), None);
+    }
+
+    #[test]
+    fn gro_round_trip() {
+        let mut mol = super::read(GRO.as_bytes()).unwrap();
+        mol.add_bond(0, 1, BondType::SINGLE);
+        mol.add_bond(0, 2, BondType::SINGLE);
+
+        let mut buffer = Vec::new();
+        super::write(&mut buffer, &mol).unwrap();
+        let text = String::from_utf8(buffer).unwrap();
+        assert_eq!(
+            text.lines().nth(2).unwrap(),
+            "    1SOL     OW    1   0.126   0.639   0.677  0.1000 -0.2000  0.0000"
+        );
+        assert_eq!(
+            text.lines().nth(6).unwrap(),
+            "   1.86206   1.86206   1.86206"
+        );
+
+        let read_mol = super::read(text.as_bytes()).unwrap();
+        assert_eq!(read_mol.get_atoms(), mol.get_atoms());
+        assert_eq!(read_mol.get_groups(), mol.get_groups());
+        assert_eq!(read_mol.get_lattice(), mol.get_lattice());
+
+        let mut buffer = Vec::new();
+        super::write_topology(&mut buffer, &mol).unwrap();
+        let text = String::from_utf8(buffer).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert!(lines.contains(&"Water_and_sodium_t=_0.00000  3"));
+        assert!(
+            lines.contains(&"     1          O      1     SOL    OW      1     0.0000    15.9990")
+        );
+        assert!(lines.contains(&"    2     1     3     1"));
+        assert!(!lines.contains(&"[ dihedrals ]"));
+    }
+
+    #[test]
+    fn gro_read_errors() {
+        let error = |text: &str| super::read(text.as_bytes()).unwrap_err().to_string();
+
+        assert_eq!(
+            error("title\nmany\n"),
+            "gro: line 2: expected the number of atoms"
+        );
+        assert_eq!(
+            error("title\n1\n    1SOL     OW    1   0.126   x.639\n"),
+            "gro: line 3: expected the x, y and z of an atom"
+        );
+        assert_eq!(
+            error("title\n1\n    1SOL     OW    1   0.126   0.639   0.677\n"),
+            "gro: line 4: unexpected end of file, expected the box"
+        );
+        assert_eq!(
+            error("title\n0\n1.0 1.0\n"),
+            "gro: line 3: expected 3 or 9 box values"
+        );
+    }
+}
@@ -0,0 +1,760 @@
+/**
+ * Author: V. Ganesh
+ * License: MIT
+ */
+
+/**
+ * lammps module, reads and writes LAMMPS data files in the full and atomic atom styles.
+ *
+ * The writer numbers the atom types in order of appearance, by the "atom_type" atom
+ * property or else by element, with the type name as a comment in the Masses section.
+ * Bond types are the pairs of atom types with the bond order, angle and dihedral types
+ * the triples and quadruples of atom types, read in either direction. The angles and
+ * dihedrals are derived from the bond graph. In the full style the molecule ID of an atom
+ * is its "molecule_id" atom property, or else its fragment (connected part of the bond
+ * graph). The atomic style has no charges, bonds, angles or dihedrals. The cell is written
+ * in the restricted triclinic form LAMMPS needs (a along x, b in the xy plane) and a
+ * molecule without a lattice gets the box around its atoms, with a margin of BOX_MARGIN.
+ * Force field coefficients and velocities are not written.
+ *
+ * The reader takes the style from the comment after the Atoms keyword, or else from the
+ * number of columns. The box becomes the lattice, image flags are applied to the
+ * positions, and the element of each type comes from the comment of its mass, or else is
+ * the element with the nearest atomic weight. Angles, dihedrals and impropers are not
+ * read back since they follow from the bonds, and all bonds are read as single bonds.
+ */
+use std::collections::HashMap;
+use std::io::BufRead;
+use std::io::Write;
+
+use crate::atom::Atom;
+use crate::atom::AtomOperations;
+use crate::atomprop;
+use crate::bond::BondType;
+use crate::io::IoError;
+use crate::io::LineReader;
+use crate::lattice::Lattice;
+use crate::molecule::Molecule;
+use crate::point::Point;
+use crate::property::AtomProperty;
+
+const FORMAT: &str = "lammps";
+
+/** space left around the atoms of a molecule without a lattice, in angstrom */
+pub const BOX_MARGIN: f64 = 5.0;
+
+/** AtomStyle - the columns of the Atoms section */
+#[allow(dead_code)]
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum AtomStyle {
+    /** id type x y z */
+    ATOMIC,
+    /** id molecule-ID type q x y z */
+    #[default]
+    FULL,
+}
+
+#[allow(dead_code)]
+impl AtomStyle {
+    /** get_name() returns the LAMMPS name of the atom style */
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            AtomStyle::ATOMIC => return "atomic",
+            AtomStyle::FULL => return "full",
+        }
+    }
+
+    /** get_number_of_columns() returns the number of columns of an atom line, without image flags */
+    fn get_number_of_columns(&self) -> usize {
+        match self {
+            AtomStyle::ATOMIC => return 5,
+            AtomStyle::FULL => return 7,
+        }
+    }
+}
+
+/** get_element() returns the element whose atomic weight is nearest to a mass */
+fn get_element(mass: f64) -> Option<&'static str> {
+    return (1..=118)
+        .filter_map(atomprop::get_element_symbol)
+        .filter_map(|symbol| Some((symbol, atomprop::get_atomic_weight(symbol)?)))
+        .filter(|(_, weight)| (weight - mass).abs() < 0.5)
+        .min_by(|(_, a), (_, b)| (a - mass).abs().total_cmp(&(b - mass).abs()))
+        .map(|(symbol, _)| symbol);
+}
+
+/** Types - numbers the distinct keys in order of appearance, from 1 */
+struct Types<K> {
+    keys: Vec<K>,
+}
+
+impl<K: PartialEq + Clone> Types<K> {
+    fn new() -> Types<K> {
+        Types { keys: Vec::new() }
+    }
+
+    fn get(&mut self, key: K) -> usize {
+        if let Some(idx) = self.keys.iter().position(|k| *k == key) {
+            return idx + 1;
+        }
+        self.keys.push(key);
+        return self.keys.len();
+    }
+}
+
+/** LammpsWriter - writes LAMMPS data files, in the full atom style by default */
+#[allow(dead_code)]
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct LammpsWriter {
+    atom_style: AtomStyle,
+}
+
+#[allow(dead_code)]
+impl LammpsWriter {
+    pub fn new() -> LammpsWriter {
+        return LammpsWriter::default();
+    }
+
+    /** atom_style() sets the atom style of the Atoms section */
+    pub fn atom_style(mut self, atom_style: AtomStyle) -> LammpsWriter {
+        self.atom_style = atom_style;
+        return self;
+    }
+
+    /** write() writes a molecule as a LAMMPS data file */
+    pub fn write<W: Write>(&self, mut writer: W, mol: &Molecule) -> Result<(), IoError> {
+        let atoms = mol.get_atoms();
+        let full = self.atom_style == AtomStyle::FULL;
+
+        let type_names: Vec<String> = match mol.get_atom_property("atom_type") {
+            Some(AtomProperty::Texts(types)) => types.to_vec(),
+            _ => atoms.iter().map(|at| at.symbol.to_string()).collect(),
+        };
+        let mut atom_types = Types::new();
+        let atom_type_ids: Vec<usize> = type_names
+            .iter()
+            .map(|name| atom_types.get(name.to_string()))
+            .collect();
+        let t = |idx: usize| atom_type_ids[idx];
+
+        // types read the same in either direction
+        let mut bond_types = Types::new();
+        let mut bonds = Vec::new();
+        let mut angle_types = Types::new();
+        let mut angles = Vec::new();
+        let mut dihedral_types = Types::new();
+        let mut dihedrals = Vec::new();
+
+        if full {
+            for bond in mol.get_bonds().iter() {
+                let (a, b) = (bond.atom_1_idx, bond.atom_2_idx);
+                let key = (
+                    t(a).min(t(b)),
+                    t(a).max(t(b)),
+                    bond.bond_type.get_mdl_code(),
+                );
+                bonds.push((bond_types.get(key), [a, b]));
+            }
+            for [i, j, k] in mol.get_angles() {
+                let key = [t(i), t(j), t(k)].min([t(k), t(j), t(i)]);
+                angles.push((angle_types.get(key), [i, j, k]));
+            }
+            for [i, j, k, l] in mol.get_dihedrals() {
+                let key = [t(i), t(j), t(k), t(l)].min([t(l), t(k), t(j), t(i)]);
+                dihedrals.push((dihedral_types.get(key), [i, j, k, l]));
+            }
+        }
+
+        let title = if mol.name.is_empty() {
+            "LAMMPS data file written by fekit"
+        } else {
+            &mol.name
+        };
+        writeln!(writer, "{}", title.replace('\n', " "))?;
+        writeln!(writer)?;
+        writeln!(writer, "{:>12} atoms", atoms.len())?;
+        if full {
+            writeln!(writer, "{:>12} bonds", bonds.len())?;
+            writeln!(writer, "{:>12} angles", angles.len())?;
+            writeln!(writer, "{:>12} dihedrals", dihedrals.len())?;
+        }
+        writeln!(writer)?;
+        writeln!(writer, "{:>12} atom types", atom_types.keys.len())?;
+        if full {
+            writeln!(writer, "{:>12} bond types", bond_types.keys.len())?;
+            writeln!(writer, "{:>12} angle types", angle_types.keys.len())?;
+            writeln!(writer, "{:>12} dihedral types", dihedral_types.keys.len())?;
+        }
+        writeln!(writer)?;
+
+        let positions: Vec<[f64; 3]> = atoms
+            .iter()
+            .map(|at| [at.center.x as f64, at.center.y as f64, at.center.z as f64])
+            .collect();
+        let (origin, vectors, positions) = match mol.get_lattice() {
+            Some(lattice) if lattice.is_standard_form() => ([0.0; 3], lattice.vectors, positions),
+            Some(lattice) => {
+                let standard = lattice.get_standard_form();
+                let positions = positions
+                    .iter()
+                    .map(|p| standard.to_cartesian(lattice.to_fractional(*p)))
+                    .collect();
+                ([0.0; 3], standard.vectors, positions)
+            }
+            None => {
+                let mut low = [0.0; 3];
+                let mut high = [0.0; 3];
+                for k in 0..3 {
+                    let values = positions.iter().map(|p| p[k]);
+                    low[k] = values.clone().fold(f64::INFINITY, f64::min);
+                    high[k] = values.fold(f64::NEG_INFINITY, f64::max);
+                    if positions.is_empty() {
+                        (low[k], high[k]) = (0.0, 0.0);
+                    }
+                }
+                let vectors = [
+                    [high[0] - low[0] + 2.0 * BOX_MARGIN, 0.0, 0.0],
+                    [0.0, high[1] - low[1] + 2.0 * BOX_MARGIN, 0.0],
+                    [0.0, 0.0, high[2] - low[2] + 2.0 * BOX_MARGIN],
+                ];
+                (low.map(|value| value - BOX_MARGIN), vectors, positions)
+            }
+        };
+
+        for (k, name) in ["x", "y", "z"].iter().enumerate() {
+            writeln!(
+                writer,
+                "{:>16.8} {:>16.8} {}lo {}hi",
+                origin[k],
+                origin[k] + vectors[k][k],
+                name,
+                name
+            )?;
+        }
+        let tilts = [vectors[1][0], vectors[2][0], vectors[2][1]];
+        if tilts.iter().any(|tilt| *tilt != 0.0) {
+            writeln!(
+                writer,
+                "{:>16.8} {:>16.8} {:>16.8} xy xz yz",
+                tilts[0], tilts[1], tilts[2]
+            )?;
+        }
+
+        writeln!(writer)?;
+        writeln!(writer, "Masses")?;
+        writeln!(writer)?;
+        for (idx, name) in atom_types.keys.iter().enumerate() {
+            let atom_idx = atom_type_ids
+                .iter()
+                .position(|t| *t == idx + 1)
+                .unwrap_or(0);
+            let mass = atomprop::get_atomic_weight(&atoms[atom_idx].symbol).unwrap_or(0.0);
+            writeln!(writer, "{:>6} {:>12.6} # {}", idx + 1, mass, name)?;
+        }
+
+        let molecule_ids: Vec<i64> = match mol.get_atom_property("molecule_id") {
+            Some(AtomProperty::Integers(values)) => values.to_vec(),
+            _ => mol
+                .get_fragment_indices()
+                .iter()
+                .map(|idx| *idx as i64 + 1)
+                .collect(),
+        };
+
+        writeln!(writer)?;
+        writeln!(writer, "Atoms # {}", self.atom_style.get_name())?;
+        writeln!(writer)?;
+        for (idx, atom) in atoms.iter().enumerate() {
+            let p = positions[idx];
+            if full {
+                writeln!(
+                    writer,
+                    "{:>6} {:>6} {:>4} {:>12.6} {:>16.8} {:>16.8} {:>16.8}",
+                    idx + 1,
+                    molecule_ids[idx],
+                    t(idx),
+                    atom.charge,
+                    p[0],
+                    p[1],
+                    p[2]
+                )?;
+            } else {
+                writeln!(
+                    writer,
+                    "{:>6} {:>4} {:>16.8} {:>16.8} {:>16.8}",
+                    idx + 1,
+                    t(idx),
+                    p[0],
+                    p[1],
+                    p[2]
+                )?;
+            }
+        }
+
+        for (section, items) in [
+            (
+                "Bonds",
+                bonds
+                    .iter()
+                    .map(|(t, a)| (*t, a.to_vec()))
+                    .collect::<Vec<_>>(),
+            ),
+            (
+                "Angles",
+                angles.iter().map(|(t, a)| (*t, a.to_vec())).collect(),
+            ),
+            (
+                "Dihedrals",
+                dihedrals.iter().map(|(t, a)| (*t, a.to_vec())).collect(),
+            ),
+        ] {
+            if items.is_empty() {
+                continue;
+            }
+            writeln!(writer)?;
+            writeln!(writer, "{}", section)?;
+            writeln!(writer)?;
+            for (idx, (item_type, indices)) in items.iter().enumerate() {
+                write!(writer, "{:>6} {:>4}", idx + 1, item_type)?;
+                for atom_idx in indices {
+                    write!(writer, " {:>6}", atom_idx + 1)?;
+                }
+                writeln!(writer)?;
+            }
+        }
+
+        return Ok(());
+    }
+}
+
+/** write() writes a molecule as a LAMMPS data file in the full atom style */
+#[allow(dead_code)]
+pub fn write<W: Write>(writer: W, mol: &Molecule) -> Result<(), IoError> {
+    return LammpsWriter::new().write(writer, mol);
+}
+
+/** strip_comment() returns a line without its # comment, and the comment */
+fn strip_comment(line: &str) -> (&str, &str) {
+    match line.split_once('#') {
+        Some((text, comment)) => return (text.trim(), comment.trim()),
+        None => return (line.trim(), ""),
+    }
+}
+
+/** read_section() reads the lines of a section, from its first line of data to a blank line */
+fn read_section<R: BufRead>(reader: &mut LineReader<R>) -> Result<Vec<(String, usize)>, IoError> {
+    let mut lines = Vec::new();
+    while let Some(line) = reader.peek_line()? {
+        if line.trim().is_empty() && !lines.is_empty() {
+            break;
+        }
+        let line = reader.expect_line("a section")?;
+        if !strip_comment(&line).0.is_empty() {
+            lines.push((line, reader.get_line_number()));
+        }
+    }
+    return Ok(lines);
+}
+
+/** read() reads a LAMMPS data file in the full or atomic atom style */
+#[allow(dead_code)]
+pub fn read<R: BufRead>(reader: R) -> Result<Molecule, IoError> {
+    let mut reader = LineReader::new(reader, FORMAT);
+    let title = reader.expect_line("the title")?;
+
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    let mut low = [0.0; 3];
+    let mut high = [0.0; 3];
+    let mut tilts = [0.0; 3];
+
+    // the header, up to the first section
+    while let Some(line) = reader.peek_line()? {
+        let (text, _) = strip_comment(line);
+        let fields: Vec<&str> = text.split_whitespace().collect();
+        let numbers: Vec<f64> = fields.iter().map_while(|f| f.parse().ok()).collect();
+
+        if !fields.is_empty() && numbers.is_empty() {
+            break;
+        }
+        let line = reader.expect_line("the header")?;
+        let (text, _) = strip_comment(&line);
+        let fields: Vec<&str> = text.split_whitespace().collect();
+
+        match fields[numbers.len()..] {
+            [] => {}
+            ["xy", "xz", "yz"] if numbers.len() == 3 => {
+                tilts = [numbers[0], numbers[1], numbers[2]]
+            }
+            [lo, hi] if numbers.len() == 2 && lo.ends_with("lo") && hi.ends_with("hi") => {
+                let k = match &lo[..1] {
+                    "x" => 0,
+                    "y" => 1,
+                    _ => 2,
+                };
+                (low[k], high[k]) = (numbers[0], numbers[1]);
+            }
+            _ if numbers.len() == 1 => {
+                counts.insert(fields[1..].join(" "), numbers[0] as usize);
+            }
+            _ => return Err(reader.error(format!("unknown header line \"{}\"", text))),
+        }
+    }
+
+    let lattice = Lattice::new([
+        [high[0] - low[0], 0.0, 0.0],
+        [tilts[0], high[1] - low[1], 0.0],
+        [tilts[1], tilts[2], high[2] - low[2]],
+    ]);
+
+    // the element and label of each atom type, the element found once per type
+    let mut types: HashMap<i64, (&str, String)> = HashMap::new();
+    let mut atom_lines = Vec::new();
+    let mut bond_lines = Vec::new();
+    let mut style = None;
+
+    while let Some(line) = reader.next_line()? {
+        let (keyword, comment) = strip_comment(&line);
+        match keyword {
+            "" => continue,
+            "Masses" => {
+                for (line, line_number) in read_section(&mut reader)? {
+                    let (text, label) = strip_comment(&line);
+                    let fields: Vec<&str> = text.split_whitespace().collect();
+                    let mass = match fields[..] {
+                        [number, mass] => number.parse().ok().zip(mass.parse().ok()),
+                        _ => None,
+                    };
+                    let (number, mass) = mass.ok_or_else(|| {
+                        IoError::parse(
+                            FORMAT,
+                            line_number,
+                            "expected a type and its mass".to_string(),
+                        )
+                    })?;
+                    let symbol = atomprop::get_atomic_number(label)
+                        .and_then(atomprop::get_element_symbol)
+                        .or_else(|| get_element(mass))
+                        .unwrap_or("X");
+                    types.insert(number, (symbol, label.to_string()));
+                }
+            }
+            "Atoms" => {
+                style = match comment {
+                    "" => None,
+                    "full" => Some(AtomStyle::FULL),
+                    "atomic" => Some(AtomStyle::ATOMIC),
+                    _ => {
+                        return Err(reader.error(format!(
+                            "atom style {} is not supported, only full and atomic",
+                            comment
+                        )))
+                    }
+                };
+                atom_lines = read_section(&mut reader)?;
+            }
+            "Bonds" => bond_lines = read_section(&mut reader)?,
+            _ => {
+                read_section(&mut reader)?;
+            }
+        }
+    }
+
+    let number_of_atoms = counts.get("atoms").copied().unwrap_or(0);
+    if atom_lines.len() != number_of_atoms {
+        return Err(reader.error(format!(
+            "{} atoms in the header, but {} in the Atoms section",
+            number_of_atoms,
+            atom_lines.len()
+        )));
+    }
+
+    let mut atoms = Vec::new();
+    for (line, line_number) in atom_lines.iter() {
+        let error = |message: &str| IoError::parse(FORMAT, *line_number, message.to_string());
+        let (text, _) = strip_comment(line);
+        let fields: Vec<&str> = text.split_whitespace().collect();
+
+        let style = match (style, fields.len()) {
+            (Some(style), _) => style,
+            (None, 5 | 8) => AtomStyle::ATOMIC,
+            (None, 7 | 10) => AtomStyle::FULL,
+            _ => {
+                return Err(error(
+                    "cannot tell the atom style from the number of columns",
+                ))
+            }
+        };
+        let columns = style.get_number_of_columns();
+        if fields.len() != columns && fields.len() != columns + 3 {
+            return Err(error(&format!(
+                "expected {} or {} columns for the {} style",
+                columns,
+                columns + 3,
+                style.get_name()
+            )));
+        }
+
+        let integer = |idx: usize| {
+            fields[idx]
+                .parse::<i64>()
+                .map_err(|_| error("invalid integer"))
+        };
+        let real = |idx: usize| {
+            fields[idx]
+                .parse::<f64>()
+                .map_err(|_| error("invalid number"))
+        };
+
+        let id = integer(0)?;
+        let (molecule_id, atom_type, charge, first) = match style {
+            AtomStyle::FULL => (integer(1)?, integer(2)?, real(3)?, 4),
+            AtomStyle::ATOMIC => (0, integer(1)?, 0.0, 2),
+        };
+        let mut position = [real(first)?, real(first + 1)?, real(first + 2)?];
+        if fields.len() == columns + 3 {
+            let images = [
+                integer(first + 3)?,
+                integer(first + 4)?,
+                integer(first + 5)?,
+            ];
+            for (image, vector) in images.iter().zip(lattice.vectors.iter()) {
+                for (value, component) in position.iter_mut().zip(vector.iter()) {
+                    *value += *image as f64 * component;
+                }
+            }
+        }
+
+        let (symbol, type_name) = match types.get(&atom_type) {
+            Some((symbol, label)) if !label.is_empty() => (*symbol, label.clone()),
+            Some((symbol, _)) => (*symbol, atom_type.to_string()),
+            None => ("X", atom_type.to_string()),
+        };
+
+        atoms.push((
+            id,
+            molecule_id,
+            type_name,
+            Atom {
+                center: Point {
+                    x: position[0] as f32,
+                    y: position[1] as f32,
+                    z: position[2] as f32,
+                },
+                charge: charge as f32,
+                symbol: symbol.to_string(),
+                remark: "".to_string(),
+            },
+        ));
+    }
+    atoms.sort_by_key(|(id, ..)| *id);
+
+    let mut mol = Molecule::new(title.trim().to_string(), "".to_string());
+    let mut indices = HashMap::new();
+    let mut molecule_ids = Vec::new();
+    let mut type_names = Vec::new();
+    for (idx, (id, molecule_id, type_name, atom)) in atoms.into_iter().enumerate() {
+        indices.insert(id, idx);
+        molecule_ids.push(molecule_id);
+        type_names.push(type_name);
+        mol.add_atom(atom);
+    }
+
+    for (line, line_number) in bond_lines.iter() {
+        let error = |message: String| IoError::parse(FORMAT, *line_number, message);
+        let (text, _) = strip_comment(line);
+        let fields: Vec<i64> = text
+            .split_whitespace()
+            .map(|field| field.parse())
+            .collect::<Result<_, _>>()
+            .map_err(|_| error("expected the id, type and atoms of a bond".to_string()))?;
+        if fields.len() != 4 {
+            return Err(error(
+                "expected the id, type and atoms of a bond".to_string(),
+            ));
+        }
+        let atom = |id: i64| {
+            indices
+                .get(&id)
+                .copied()
+                .ok_or_else(|| error(format!("a bond refers to unknown atom {}", id)))
+        };
+        mol.add_bond(atom(fields[2])?, atom(fields[3])?, BondType::SINGLE);
+    }
+
+    mol.set_lattice(Some(lattice));
+    if style != Some(AtomStyle::ATOMIC) && molecule_ids.iter().any(|id| *id != 0) {
+        mol.set_atom_property("molecule_id", AtomProperty::Integers(molecule_ids));
+    }
+    let atoms = mol.get_atoms();
+    if type_names
+        .iter()
+        .zip(atoms.iter())
+        .any(|(name, at)| *name != at.symbol)
+    {
+        mol.set_atom_property("atom_type", AtomProperty::Texts(type_names));
+    }
+
+    return Ok(mol);
+}
+
+/** Unit tests for the above module */
+#[cfg(test)]
+mod tests {
+    use crate::atom::Atom;
+    use crate::atom::AtomOperations;
+    use crate::bond::BondType;
+    use crate::io::lammps::AtomStyle;
+    use crate::io::lammps::LammpsWriter;
+    use crate::io::lammps::BOX_MARGIN;
+    use crate::molecule::Molecule;
+    use crate::point::Point;
+    use crate::property::AtomProperty;
+
+    /** get_methanol_water() returns a methanol (C O H4) and a water molecule */
+    fn get_methanol_water() -> Molecule {
+        let mut mol = Molecule::new("methanol and water".to_string(), "".to_string());
+        let atoms = [
+            ("C", -0.046, 0.663, 0.0),
+            ("O", -0.046, -0.757, 0.0),
+            ("H", -1.086, 0.975, 0.0),
+            ("H", 0.433, 1.08, 0.891),
+            ("H", 0.433, 1.08, -0.891),
+            ("H", 0.892, -1.0, 0.0),
+            ("O", 3.0, 0.0, 0.0),
+            ("H", 3.96, 0.0, 0.0),
+            ("H", 2.76, 0.93, 0.0),
+        ];
+        for (symbol, x, y, z) in atoms {
+            mol.add_atom(Atom {
+                center: Point { x: x, y: y, z: z },
+                charge: if symbol == "O" { -0.5 } else { 0.0 },
+                symbol: symbol.to_string(),
+                remark: "".to_string(),
+            });
+        }
+        for (a, b) in [(0, 1), (0, 2), (0, 3), (0, 4), (1, 5), (6, 7), (6, 8)] {
+            mol.add_bond(a, b, BondType::SINGLE);
+        }
+        return mol;
+    }
+
+    #[test]
+    fn lammps_round_trip() {
+        let mol = get_methanol_water();
+        let mut buffer = Vec::new();
+        super::write(&mut buffer, &mol).unwrap();
+        let text = String::from_utf8(buffer).unwrap();
+
+        let header: Vec<&str> = text.lines().take(12).map(|line| line.trim()).collect();
+        assert_eq!(
+            header,
+            vec![
+                "methanol and water",
+                "",
+                "9 atoms",
+                "7 bonds",
+                "8 angles",
+                "3 dihedrals",
+                "",
+                "3 atom types",
+                "3 bond types",
+                "4 angle types",
+                "1 dihedral types",
+                ""
+            ]
+        );
+        assert!(text.contains("Atoms # full"));
+        let masses: Vec<&str> = text.lines().skip_while(|l| *l != "Masses").collect();
+        assert_eq!(masses[2], "     1    12.009600 # C");
+
+        let read_mol = super::read(text.as_bytes()).unwrap();
+        assert_eq!(read_mol.name, mol.name);
+        assert_eq!(read_mol.get_atoms(), mol.get_atoms());
+        assert_eq!(read_mol.get_bonds().len(), 7);
+        assert_eq!(
+            read_mol.get_atom_property("molecule_id"),
+            Some(&AtomProperty::Integers(vec![1, 1, 1, 1, 1, 1, 2, 2, 2]))
+        );
+        assert_eq!(read_mol.get_atom_property("atom_type"), None);
+
+        let width = read_mol.get_lattice().unwrap().vectors[0][0];
+        assert!((width - (3.96 + 1.086 + 2.0 * BOX_MARGIN)).abs() < 1e-6);
+
+        let mut buffer = Vec::new();
+        LammpsWriter::new()
+            .atom_style(AtomStyle::ATOMIC)
+            .write(&mut buffer, &mol)
+            .unwrap();
+        let text = String::from_utf8(buffer).unwrap();
+        assert!(!text.contains("bonds"));
+        let read_mol = super::read(text.as_bytes()).unwrap();
+        assert_eq!(read_mol.get_atoms()[8].symbol, "H");
+        assert_eq!(read_mol.get_atoms()[1].charge, 0.0);
+        assert_eq!(read_mol.get_bonds().len(), 0);
+    }
+
+    #[test]
+    fn lammps_read_images() {
+        let text = "slab from LAMMPS
+
+2 atoms
+2 atom types
+
+0.0 4.0 xlo xhi
+0.0 4.0 ylo yhi
+0.0 10.0 zlo zhi
+2.0 0.0 0.0 xy xz yz
+
+Masses
+
+1 26.98
+2 15.999
+
+Pair Coeffs # lj/cut
+
+1 0.1 3.0
+2 0.2 3.1
+
+Atoms
+
+2 2 1.0 1.0 1.0 0 0 1
+1 1 0.5 0.5 0.5 1 0 0
+";
+        let mol = super::read(text.as_bytes()).unwrap();
+        let symbols: Vec<&str> = mol
+            .get_atoms()
+            .iter()
+            .map(|at| at.symbol.as_str())
+            .collect();
+        assert_eq!(symbols, vec!["Al", "O"]);
+        assert_eq!(mol.get_atoms()[0].center.x, 4.5);
+        assert_eq!(mol.get_atoms()[1].center.z, 11.0);
+        assert_eq!(mol.get_lattice().unwrap().vectors[1], [2.0, 4.0, 0.0]);
+        assert_eq!(
+            mol.get_atom_property("atom_type"),
+            Some(&AtomProperty::Texts(vec!["1".to_string(), "2".to_string()]))
+        );
+    }
+
+    #[test]
+    fn lammps_read_errors() {
+        let error = |text: &str| super::read(text.as_bytes()).unwrap_err().to_string();
+
+        assert_eq!(
+            error("title\n\n2 atoms\n\nAtoms # full\n\n1 1 1 0.0 0.0 0.0 0.0\n"),
+            "lammps: line 7: 2 atoms in the header, but 1 in the Atoms section"
+        );
+        assert_eq!(
+            error("title\n\n1 atoms\n\nAtoms # charge\n\n1 1 0.0 0.0 0.0 0.0\n"),
+            "lammps: line 5: atom style charge is not supported, only full and atomic"
+        );
+        assert_eq!(
+            error("title\n\n1 atoms\n\nAtoms\n\n1 1 0.0 0.0 0.0 0.0\n"),
+            "lammps: line 7: cannot tell the atom style from the number of columns"
+        );
+        assert_eq!(
+            error("title\n\n1 atoms\n1 bonds\n\nAtoms\n\n1 1 0.0 0.0 0.0\n\nBonds\n\n1 1 1 2\n"),
+            "lammps: line 12: a bond refers to unknown atom 2"
+        );
+    }
+}
@@ -0,0 +1,850 @@
+/**
+ * Author: V. Ganesh
+ * License: MIT
+ */
+
+/**
+ * mdl module, reads and writes MDL molfiles (V2000 and V3000) and SD files.
+ *
+ * The first line of a molfile is the Molecule name and the third line its remark.
+ * Charges are read from the atom block, unless the file has M  CHG lines, which then
+ * replace all atom block charges. Isotopes, radicals and atom parities are kept as the
+ * "isotope", "radical" and "parity" atom properties. Bond wedges are kept as the
+ * "bond_stereo" property, one V3000 CFG code per bond (1 wedge, 2 either, 3 hash), and
+ * the chiral flag as the "chiral" property. The > <field> data items of an SD record
+ * become text properties of the molecule, and the other properties are written back as
+ * data items.
+ *
+ * V3000 enhanced stereo collections become stereo groups named ABS, OR1, AND1 ..., other
+ * V3000 collections become groups named as the collection, e.g. MDLV30/HILITE. A molfile
+ * is written as V3000 when it has more than 999 atoms or bonds, or has stereo groups.
+ *
+ * SD files are read one record at a time through SdfReader, so libraries of any size
+ * are read in constant memory. A record with an error is skipped up to its $$$$ line,
+ * so that the next record can still be read.
+ */
+use std::io::BufRead;
+use std::io::Write;
+
+use crate::atom::Atom;
+use crate::atom::AtomGroup;
+use crate::atom::AtomOperations;
+use crate::atom::GroupKind;
+use crate::bond::BondType;
+use crate::io::IoError;
+use crate::io::LineReader;
+use crate::molecule::Molecule;
+use crate::point::Point;
+use crate::property::AtomProperty;
+use crate::property::Property;
+
+mod v3000;
+
+const FORMAT: &str = "mdl";
+
+/** V2000 counts are three digits wide */
+const MAX_V2000_COUNT: usize = 999;
+
+/** line that ends each record of an SD file */
+const RECORD_END: &str = "$$$$";
+
+/** properties that are part of the molfile, and so are not written as SD data items */
+const MOLFILE_PROPERTIES: [&str; 2] = ["bond_stereo", "chiral"];
+
+/** column() returns the columns start..end (0 based) of a line, short lines give "" */
+fn column(line: &str, start: usize, end: usize) -> &str {
+    let end = end.min(line.len());
+    if start >= end {
+        return "";
+    }
+    return line.get(start..end).unwrap_or("");
+}
+
+/** Ctab - the connection table of a molfile, on its way to or from a Molecule */
+struct Ctab {
+    atoms: Vec<Atom>,
+    bonds: Vec<(usize, usize, BondType)>,
+    bond_stereo: Vec<i64>,
+    isotopes: Vec<i64>,
+    radicals: Vec<i64>,
+    parities: Vec<i64>,
+    chiral: bool,
+    groups: Vec<AtomGroup>,
+}
+
+impl Ctab {
+    fn new() -> Ctab {
+        Ctab {
+            atoms: Vec::new(),
+            bonds: Vec::new(),
+            bond_stereo: Vec::new(),
+            isotopes: Vec::new(),
+            radicals: Vec::new(),
+            parities: Vec::new(),
+            chiral: false,
+            groups: Vec::new(),
+        }
+    }
+
+    fn from_molecule(mol: &Molecule) -> Ctab {
+        let atoms = mol.get_atoms();
+        let bonds = mol.get_bonds();
+
+        let integers = |name: &str| match mol.get_atom_property(name) {
+            Some(AtomProperty::Integers(values)) => values.to_vec(),
+            _ => vec![0; atoms.len()],
+        };
+
+        return Ctab {
+            atoms: atoms.to_vec(),
+            bonds: bonds
+                .iter()
+                .map(|bnd| (bnd.atom_1_idx, bnd.atom_2_idx, bnd.bond_type))
+                .collect(),
+            bond_stereo: match mol.get_property("bond_stereo") {
+                Some(Property::Integers(values)) if values.len() == bonds.len() => values.to_vec(),
+                _ => vec![0; bonds.len()],
+            },
+            isotopes: integers("isotope"),
+            radicals: integers("radical"),
+            parities: integers("parity"),
+            chiral: mol.get_property("chiral") == Some(&Property::Logical(true)),
+            groups: mol
+                .get_groups()
+                .iter()
+                .filter(|g| g.kind == GroupKind::STEREO || g.name.starts_with("MDLV30/"))
+                .cloned()
+                .collect(),
+        };
+    }
+
+    fn into_molecule(self, name: String, remark: String) -> Molecule {
+        let mut mol = Molecule::new(name, remark);
+
+        for atom in self.atoms {
+            mol.add_atom(atom);
+        }
+        for (atom_1_idx, atom_2_idx, bond_type) in self.bonds {
+            mol.add_bond(atom_1_idx, atom_2_idx, bond_type);
+        }
+
+        for (name, values) in [
+            ("isotope", self.isotopes),
+            ("radical", self.radicals),
+            ("parity", self.parities),
+        ] {
+            if values.iter().any(|value| *value != 0) {
+                mol.set_atom_property(name, AtomProperty::Integers(values));
+            }
+        }
+
+        if self.bond_stereo.iter().any(|value| *value != 0) {
+            mol.set_property("bond_stereo", Property::Integers(self.bond_stereo));
+        }
+        if self.chiral {
+            mol.set_property("chiral", Property::Logical(true));
+        }
+
+        for group in self.groups {
+            mol.add_group(group);
+        }
+
+        return mol;
+    }
+
+    /** needs_v3000() checks if the table can only be written as V3000 */
+    fn needs_v3000(&self) -> bool {
+        return self.atoms.len() > MAX_V2000_COUNT
+            || self.bonds.len() > MAX_V2000_COUNT
+            || !self.groups.is_empty();
+    }
+}
+
+/** formal_charge() returns the charge of an atom if it is a whole number, else 0 */
+fn formal_charge(atom: &Atom) -> i64 {
+    if atom.charge.fract() == 0.0 {
+        return atom.charge as i64;
+    }
+    return 0;
+}
+
+/** SdfReader - reads the records of an SD file (or a single molfile) one at a time */
+#[allow(dead_code)]
+pub struct SdfReader<R: BufRead> {
+    reader: LineReader<R>,
+    failed: bool,
+}
+
+#[allow(dead_code)]
+impl<R: BufRead> SdfReader<R> {
+    pub fn new(reader: R) -> SdfReader<R> {
+        SdfReader {
+            reader: LineReader::new(reader, FORMAT),
+            failed: false,
+        }
+    }
+
+    /** read_record() reads the next record, or returns None at the end of the file */
+    pub fn read_record(&mut self) -> Result<Option<Molecule>, IoError> {
+        let name = match self.reader.next_line()? {
+            Some(line) => line,
+            None => return Ok(None),
+        };
+
+        // a blank line after the last record
+        if name.trim().is_empty() && self.reader.peek_line()?.is_none() {
+            return Ok(None);
+        }
+
+        let record = self.read_molfile(name).and_then(|mut mol| {
+            self.read_data_items(&mut mol)?;
+            return Ok(mol);
+        });
+
+        if let Err(IoError::Parse { .. }) = record {
+            self.skip_record()?;
+        }
+
+        return record.map(Some);
+    }
+
+    /** skip_record() skips the lines up to and including the next $$$$ */
+    fn skip_record(&mut self) -> Result<(), IoError> {
+        while let Some(line) = self.reader.next_line()? {
+            if line.trim_end() == RECORD_END {
+                break;
+            }
+        }
+
+        return Ok(());
+    }
+
+    fn read_molfile(&mut self, name: String) -> Result<Molecule, IoError> {
+        self.reader.expect_line("the program line")?;
+        let remark = self.reader.expect_line("the comment line")?;
+        let counts = self.reader.expect_line("the counts line")?;
+
+        let ctab = if column(&counts, 33, 39).trim().eq_ignore_ascii_case("V3000") {
+            v3000::read_ctab(&mut self.reader)?
+        } else {
+            self.read_v2000_ctab(&counts)?
+        };
+
+        return Ok(ctab.into_molecule(name.trim().to_string(), remark.trim().to_string()));
+    }
+
+    fn read_v2000_ctab(&mut self, counts: &str) -> Result<Ctab, IoError> {
+        let count = |start: usize, what: &str| {
+            return column(counts, start, start + 3)
+                .trim()
+                .parse::<usize>()
+                .map_err(|_| format!("expected the number of {} in the counts line", what));
+        };
+        let number_of_atoms = count(0, "atoms").map_err(|message| self.reader.error(message))?;
+        let number_of_bonds = count(3, "bonds").map_err(|message| self.reader.error(message))?;
+
+        let mut ctab = Ctab::new();
+        ctab.chiral = column(counts, 12, 15).trim() == "1";
+
+        for _ in 0..number_of_atoms {
+            let line = self.reader.expect_line("an atom line")?;
+            let (atom, parity) = parse_atom(&line).map_err(|message| self.reader.error(message))?;
+            ctab.atoms.push(atom);
+            ctab.parities.push(parity);
+        }
+
+        for _ in 0..number_of_bonds {
+            let line = self.reader.expect_line("a bond line")?;
+            let (atom_1_idx, atom_2_idx, bond_type, stereo) =
+                parse_bond(&line, number_of_atoms).map_err(|message| self.reader.error(message))?;
+            ctab.bonds.push((atom_1_idx, atom_2_idx, bond_type));
+            ctab.bond_stereo.push(stereo);
+        }
+
+        ctab.isotopes = vec![0; number_of_atoms];
+        ctab.radicals = vec![0; number_of_atoms];
+        let mut has_charge_lines = false;
+
+        // the properties block, older files may leave out the M  END line
+        while let Some(line) = self.reader.peek_line()? {
+            if line.starts_with('>') || line.trim_end() == RECORD_END {
+                break;
+            }
+
+            let line = self.reader.next_line()?.unwrap_or_default();
+            let key = column(&line, 0, 6);
+            if key == "M  END" {
+                break;
+            }
+
+            let pairs = match key {
+                "M  CHG" | "M  ISO" | "M  RAD" => parse_pairs(&line, number_of_atoms)
+                    .map_err(|message| self.reader.error(message))?,
+                _ => continue,
+            };
+
+            if key == "M  CHG" && !has_charge_lines {
+                has_charge_lines = true;
+                for atom in ctab.atoms.iter_mut() {
+                    atom.charge = 0.0;
+                }
+            }
+
+            for (idx, value) in pairs {
+                match key {
+                    "M  CHG" => ctab.atoms[idx].charge = value as f32,
+                    "M  ISO" => ctab.isotopes[idx] = value,
+                    _ => ctab.radicals[idx] = value,
+                }
+            }
+        }
+
+        return Ok(ctab);
+    }
+
+    /** read_data_items() reads the > <field> items of an SD record, up to its $$$$ line */
+    fn read_data_items(&mut self, mol: &mut Molecule) -> Result<(), IoError> {
+        let mut field: Option<(String, Vec<String>)> = None;
+
+        while let Some(line) = self.reader.next_line()? {
+            if line.trim_end() == RECORD_END {
+                break;
+            }
+
+            if field.is_none() {
+                if line.starts_with('>') {
+                    field = Some((parse_field_name(&line), Vec::new()));
+                }
+                continue;
+            }
+
+            if line.trim().is_empty() {
+                if let Some((name, value)) = field.take() {
+                    mol.set_property(&name, Property::Text(value.join("\n")));
+                }
+            } else if let Some((_, value)) = field.as_mut() {
+                value.push(line);
+            }
+        }
+
+        // the last item of a file may lack its blank line
+        if let Some((name, value)) = field.take() {
+            mol.set_property(&name, Property::Text(value.join("\n")));
+        }
+
+        return Ok(());
+    }
+}
+
+impl<R: BufRead> Iterator for SdfReader<R> {
+    type Item = Result<Molecule, IoError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+
+        let record = self.read_record();
+        self.failed = matches!(record, Err(IoError::Io(_)));
+        return record.transpose();
+    }
+}
+
+/** read() reads a molfile, or the first record of an SD file */
+#[allow(dead_code)]
+pub fn read<R: BufRead>(reader: R) -> Result<Molecule, IoError> {
+    match SdfReader::new(reader).read_record()? {
+        Some(mol) => return Ok(mol),
+        None => return Err(IoError::parse(FORMAT, 1, "the file is empty".to_string())),
+    }
+}
+
+/** parse_atom() reads a line of the atom block, into the atom and its parity */
+fn parse_atom(line: &str) -> Result<(Atom, i64), String> {
+    let fixed = (
+        column(line, 0, 10).trim().parse::<f32>(),
+        column(line, 10, 20).trim().parse::<f32>(),
+        column(line, 20, 30).trim().parse::<f32>(),
+    );
+
+    // the columns are fixed, but some programs only separate the fields by spaces
+    let (x, y, z, symbol, charge_code, parity) = match fixed {
+        (Ok(x), Ok(y), Ok(z)) => (
+            x,
+            y,
+            z,
+            column(line, 31, 34).trim(),
+            column(line, 36, 39).trim(),
+            column(line, 39, 42).trim(),
+        ),
+        _ => {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.len() < 4 {
+                return Err(format!(
+                    "expected coordinates and a symbol, found \"{}\"",
+                    line
+                ));
+            }
+
+            let real = |field: &str| {
+                return field
+                    .parse::<f32>()
+                    .map_err(|_| format!("expected a coordinate, found \"{}\"", field));
+            };
+            (
+                real(fields[0])?,
+                real(fields[1])?,
+                real(fields[2])?,
+                fields[3],
+                fields.get(5).copied().unwrap_or("0"),
+                fields.get(6).copied().unwrap_or("0"),
+            )
+        }
+    };
+
+    if symbol.is_empty() {
+        return Err("expected an atom symbol in columns 32-34".to_string());
+    }
+
+    let charge = match charge_code {
+        "1" => 3.0,
+        "2" => 2.0,
+        "3" => 1.0,
+        "5" => -1.0,
+        "6" => -2.0,
+        "7" => -3.0,
+        _ => 0.0,
+    };
+
+    let atom = Atom {
+        center: Point { x: x, y: y, z: z },
+        charge: charge,
+        symbol: symbol.to_string(),
+        remark: "".to_string(),
+    };
+    return Ok((atom, parity.parse().unwrap_or(0)));
+}
+
+/**
+ * parse_bond() reads a line of the bond block into 0 based atom indices, the bond type
+ * and the bond stereo as a V3000 CFG code
+ */
+fn parse_bond(line: &str, number_of_atoms: usize) -> Result<(usize, usize, BondType, i64), String> {
+    let mut fields = [column(line, 0, 3), column(line, 3, 6), column(line, 6, 9)]
+        .map(|field| field.trim().parse::<usize>());
+
+    if fields.iter().any(|field| field.is_err()) {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        if words.len() >= 3 {
+            fields = [words[0], words[1], words[2]].map(|word| word.parse::<usize>());
+        }
+    }
+
+    let [atom_1, atom_2, code] = fields;
+    let (atom_1, atom_2, code) = match (atom_1, atom_2, code) {
+        (Ok(atom_1), Ok(atom_2), Ok(code)) => (atom_1, atom_2, code),
+        _ => {
+            return Err(format!(
+                "expected two atom numbers and a bond type, found \"{}\"",
+                line
+            ))
+        }
+    };
+
+    for atom in [atom_1, atom_2] {
+        if atom == 0 || atom > number_of_atoms {
+            return Err(format!(
+                "bond refers to atom {}, but there are {} atoms",
+                atom, number_of_atoms
+            ));
+        }
+    }
+
+    let bond_type = u8::try_from(code)
+        .ok()
+        .and_then(BondType::from_mdl_code)
+        .ok_or_else(|| format!("unsupported bond type {}", code))?;
+
+    let stereo = match column(line, 9, 12).trim() {
+        "1" => 1,
+        "3" | "4" => 2,
+        "6" => 3,
+        _ => 0,
+    };
+
+    return Ok((atom_1 - 1, atom_2 - 1, bond_type, stereo));
+}
+
+/** parse_pairs() reads the atom number and value pairs of an M  CHG, M  ISO or M  RAD line */
+fn parse_pairs(line: &str, number_of_atoms: usize) -> Result<Vec<(usize, i64)>, String> {
+    let numbers: Vec<i64> = column(line, 6, line.len())
+        .split_whitespace()
+        .map(|word| word.parse::<i64>())
+        .collect::<Result<_, _>>()
+        .map_err(|_| format!("expected numbers in \"{}\"", line.trim()))?;
+
+    let count = numbers.first().copied().unwrap_or(0).max(0) as usize;
+    if numbers.len() < 1 + 2 * count {
+        return Err(format!("expected {} atom and value pairs", count));
+    }
+
+    let mut pairs = Vec::new();
+    for pair in numbers[1..1 + 2 * count].chunks(2) {
+        if pair[0] < 1 || pair[0] as usize > number_of_atoms {
+            return Err(format!(
+                "refers to atom {}, but there are {} atoms",
+                pair[0], number_of_atoms
+            ));
+        }
+        pairs.push((pair[0] as usize - 1, pair[1]));
+    }
+
+    return Ok(pairs);
+}
+
+/** parse_field_name() reads the name of a data item from a line like >  <MW>  (1) */
+fn parse_field_name(line: &str) -> String {
+    let text = &line[1..];
+    match (text.find('<'), text.rfind('>')) {
+        (Some(start), Some(end)) if end > start => return text[start + 1..end].to_string(),
+        _ => return text.trim().to_string(),
+    }
+}
+
+/**
+ * write() writes a molecule as a molfile, in V2000 unless it needs V3000. Only whole
+ * number charges can be written.
+ */
+#[allow(dead_code)]
+pub fn write<W: Write>(writer: W, mol: &Molecule) -> Result<(), IoError> {
+    let ctab = Ctab::from_molecule(mol);
+    let is_v3000 = ctab.needs_v3000();
+    return write_molfile(writer, mol, ctab, is_v3000);
+}
+
+/** write_v3000() writes a molecule as a V3000 molfile */
+#[allow(dead_code)]
+pub fn write_v3000<W: Write>(writer: W, mol: &Molecule) -> Result<(), IoError> {
+    return write_molfile(writer, mol, Ctab::from_molecule(mol), true);
+}
+
+fn write_molfile<W: Write>(
+    mut writer: W,
+    mol: &Molecule,
+    ctab: Ctab,
+    is_v3000: bool,
+) -> Result<(), IoError> {
+    writeln!(writer, "{}", mol.name.replace(['\n', '\r'], " "))?;
+    writeln!(writer, "  {:<8}{:10}3D", "fekit", "")?;
+    writeln!(writer, "{}", mol.remark.replace(['\n', '\r'], " "))?;
+
+    if is_v3000 {
+        writeln!(writer, "  0  0  0     0  0            999 V3000")?;
+        v3000::write_ctab(&mut writer, &ctab)?;
+    } else {
+        write_v2000_ctab(&mut writer, &ctab)?;
+    }
+
+    writeln!(writer, "M  END")?;
+    return Ok(());
+}
+
+/** write_v2000_ctab() writes the counts line, atom and bond blocks and M lines of V2000 */
+fn write_v2000_ctab<W: Write>(writer: &mut W, ctab: &Ctab) -> Result<(), IoError> {
+    writeln!(
+        writer,
+        "{:>3}{:>3}  0  0{:>3}  0  0  0  0  0999 V2000",
+        ctab.atoms.len(),
+        ctab.bonds.len(),
+        if ctab.chiral { 1 } else { 0 }
+    )?;
+
+    let mut charges = Vec::new();
+    for (idx, atom) in ctab.atoms.iter().enumerate() {
+        let charge = formal_charge(atom);
+        if charge != 0 {
+            charges.push((idx, charge));
+        }
+
+        let charge_code = match charge {
+            3 => 1,
+            2 => 2,
+            1 => 3,
+            -1 => 5,
+            -2 => 6,
+            -3 => 7,
+            _ => 0,
+        };
+
+        writeln!(
+            writer,
+            "{:10.4}{:10.4}{:10.4} {:<3} 0{:>3}{:>3}{}",
+            atom.center.x,
+            atom.center.y,
+            atom.center.z,
+            atom.symbol,
+            charge_code,
+            ctab.parities[idx],
+            "  0".repeat(9)
+        )?;
+    }
+
+    for (idx, (atom_1_idx, atom_2_idx, bond_type)) in ctab.bonds.iter().enumerate() {
+        let stereo = match (ctab.bond_stereo[idx], bond_type) {
+            (1, _) => 1,
+            (2, BondType::DOUBLE) => 3,
+            (2, _) => 4,
+            (3, _) => 6,
+            _ => 0,
+        };
+
+        writeln!(
+            writer,
+            "{:>3}{:>3}{:>3}{:>3}",
+            atom_1_idx + 1,
+            atom_2_idx + 1,
+            bond_type.get_mdl_code(),
+            stereo
+        )?;
+    }
+
+    let nonzero = |values: &[i64]| {
+        return values
+            .iter()
+            .enumerate()
+            .filter(|(_, value)| **value != 0)
+            .map(|(idx, value)| (idx, *value))
+            .collect::<Vec<(usize, i64)>>();
+    };
+
+    for (key, pairs) in [
+        ("M  CHG", charges),
+        ("M  ISO", nonzero(&ctab.isotopes)),
+        ("M  RAD", nonzero(&ctab.radicals)),
+    ] {
+        for chunk in pairs.chunks(8) {
+            write!(writer, "{}{:>3}", key, chunk.len())?;
+            for (idx, value) in chunk {
+                write!(writer, " {:>3} {:>3}", idx + 1, value)?;
+            }
+            writeln!(writer)?;
+        }
+    }
+
+    return Ok(());
+}
+
+/** write_record() writes a molecule as an SD record, its properties become data items */
+#[allow(dead_code)]
+pub fn write_record<W: Write>(mut writer: W, mol: &Molecule) -> Result<(), IoError> {
+    write(&mut writer, mol)?;
+
+    for (name, value) in mol.get_properties().iter() {
+        if MOLFILE_PROPERTIES.contains(&name.as_str()) {
+            continue;
+        }
+        writeln!(writer, "> <{}>", name)?;
+
+        // a blank line would end the item, so blank lines of a value are left out
+        for line in value.to_string().lines().filter(|l| !l.trim().is_empty()) {
+            writeln!(writer, "{}", line)?;
+        }
+        writeln!(writer)?;
+    }
+
+    writeln!(writer, "{}", RECORD_END)?;
+    return Ok(());
+}
+
+/** Unit tests for the above module */
+#[cfg(test)]
+mod tests {
+    use super::SdfReader;
+    use crate::atom::AtomOperations;
+    use crate::bond::BondType;
+    use crate::io::IoError;
+    use crate::molecule::Molecule;
+    use crate::property::AtomProperty;
+    use crate::property::Property;
+
+    const ACETATE: &str = "\
+acetate
+  fekit             3D
+acetate anion
+  4  3  0  0  0  0  0  0  0  0999 V2000
+    0.0000    0.0000    0.0000 C   0  0  0  0  0  0  0  0  0  0  0  0
+    1.5200    0.0000    0.0000 C   0  0  0  0  0  0  0  0  0  0  0  0
+    2.1500    1.0900    0.0000 O   0  0  0  0  0  0  0  0  0  0  0  0
+    2.1500   -1.0900    0.0000 O   0  5  0  0  0  0  0  0  0  0  0  0
+  1  2  1  0
+  2  3  2  0
+  2  4  1  0
+M  END
+";
+
+    #[test]
+    fn mdl_read_molfile() {
+        let mut mol = super::read(ACETATE.as_bytes()).unwrap();
+
+        assert_eq!(mol.name, "acetate".to_string());
+        assert_eq!(mol.remark, "acetate anion".to_string());
+        assert_eq!(mol.get_number_of_atoms(), 4);
+        assert_eq!(mol.get_atom(3).charge, -1.0);
+        assert_eq!(mol.get_atom(2).center.y, 1.09);
+        assert_eq!(mol.get_bond_type(1, 2), BondType::DOUBLE);
+        assert!(mol.get_properties().is_empty());
+    }
+
+    #[test]
+    fn mdl_charge_lines() {
+        // M  CHG replaces the atom block charges, the -1 of atom 4 is dropped
+        let text = ACETATE.replace(
+            "M  END",
+            "M  CHG  2   1   1   3  -1\nM  ISO  1   1  13\nM  END",
+        );
+        let mut mol = super::read(text.as_bytes()).unwrap();
+
+        assert_eq!(mol.get_atom(0).charge, 1.0);
+        assert_eq!(mol.get_atom(2).charge, -1.0);
+        assert_eq!(mol.get_atom(3).charge, 0.0);
+        assert_eq!(
+            mol.get_atom_property("isotope"),
+            Some(&AtomProperty::Integers(vec![13, 0, 0, 0]))
+        );
+
+        let mut buffer = Vec::new();
+        super::write(&mut buffer, &mol).unwrap();
+        let text = String::from_utf8(buffer).unwrap();
+        assert!(text.contains("M  CHG  2   1   1   3  -1\n"));
+        assert!(text.contains("M  ISO  1   1  13\n"));
+
+        let read_mol = super::read(text.as_bytes()).unwrap();
+        assert_eq!(read_mol.get_atoms(), mol.get_atoms());
+        assert_eq!(read_mol.get_bonds(), mol.get_bonds());
+        assert_eq!(read_mol.get_atom_properties(), mol.get_atom_properties());
+    }
+
+    #[test]
+    fn mdl_sdf_records() {
+        let mut mol = super::read(ACETATE.as_bytes()).unwrap();
+        mol.set_property("MW", Property::Real(59.04));
+        mol.set_property("SMILES", Property::Text("CC(=O)[O-]".to_string()));
+
+        let mut buffer = Vec::new();
+        super::write_record(&mut buffer, &mol).unwrap();
+        buffer.extend_from_slice(b"broken\n\n\n  1  0  0  0  0  0  0  0  0  0999 V2000\n    x\nM  END\n> <MW>\n1\n\n$$$$\n");
+        mol.name = "second".to_string();
+        super::write_record(&mut buffer, &mol).unwrap();
+
+        let records: Vec<Result<Molecule, IoError>> = SdfReader::new(buffer.as_slice()).collect();
+        assert_eq!(records.len(), 3);
+
+        let first = records[0].as_ref().unwrap();
+        assert_eq!(
+            first.get_property("MW"),
+            Some(&Property::Text("59.04".to_string()))
+        );
+        assert_eq!(
+            first.get_property("SMILES").unwrap().get_text(),
+            Some("CC(=O)[O-]")
+        );
+        assert_eq!(first.get_bonds(), mol.get_bonds());
+
+        match &records[1] {
+            // the first record takes 20 lines, the atom line of the second is bad
+            Err(IoError::Parse { line_number, .. }) => assert_eq!(*line_number, 25),
+            _ => panic!("expected a parse error"),
+        }
+        assert_eq!(records[2].as_ref().unwrap().name, "second".to_string());
+    }
+
+    #[test]
+    fn mdl_data_items() {
+        let text = format!(
+            "{}>  <NAME>  (1)\nacetic acid\nconjugate base\n\n> 25 <ID>\nCHEMBL1\n$$$$\n\n",
+            ACETATE
+        );
+        let mut reader = SdfReader::new(text.as_bytes());
+
+        let mol = reader.next().unwrap().unwrap();
+        assert_eq!(
+            mol.get_property("NAME").unwrap().get_text(),
+            Some("acetic acid\nconjugate base")
+        );
+        assert_eq!(mol.get_property("ID").unwrap().get_text(), Some("CHEMBL1"));
+        assert!(reader.next().is_none());
+    }
+
+    #[test]
+    fn mdl_v2000_stereo() {
+        let text = ACETATE
+            .replace("  4  3  0  0  0", "  4  3  0  0  1")
+            .replace("C   0  0  0  0", "C   0  0  1  0")
+            .replace("  2  4  1  0", "  2  4  1  6");
+        let mol = super::read(text.as_bytes()).unwrap();
+
+        assert_eq!(mol.get_property("chiral"), Some(&Property::Logical(true)));
+        assert_eq!(
+            mol.get_property("bond_stereo"),
+            Some(&Property::Integers(vec![0, 0, 3]))
+        );
+        assert_eq!(
+            mol.get_atom_property("parity"),
+            Some(&AtomProperty::Integers(vec![1, 1, 0, 0]))
+        );
+
+        let mut buffer = Vec::new();
+        super::write_record(&mut buffer, &mol).unwrap();
+        let text = String::from_utf8(buffer).unwrap();
+        assert!(text.contains("  2  4  1  6\n"));
+        assert!(!text.contains("> <bond_stereo>"));
+
+        let read_mol = super::read(text.as_bytes()).unwrap();
+        assert_eq!(read_mol.get_properties(), mol.get_properties());
+        assert_eq!(read_mol.get_atom_properties(), mol.get_atom_properties());
+    }
+
+    #[test]
+    fn mdl_v2000_limit() {
+        let mut mol = Molecule::new("chain".to_string(), "".to_string());
+        for idx in 0..1000 {
+            mol.add_atom(crate::atom::Atom {
+                center: crate::point::Point {
+                    x: idx as f32,
+                    y: 0.0,
+                    z: 0.0,
+                },
+                charge: 0.0,
+                symbol: "C".to_string(),
+                remark: "".to_string(),
+            });
+        }
+
+        // more than 999 atoms do not fit V2000, so V3000 is written
+        let mut buffer = Vec::new();
+        super::write(&mut buffer, &mol).unwrap();
+        assert!(String::from_utf8(buffer)
+            .unwrap()
+            .lines()
+            .nth(3)
+            .unwrap()
+            .ends_with("V3000"));
+
+        let mut buffer = Vec::new();
+        mol.add_atom(crate::atom::Atom {
+            center: crate::point::Point {
+                x: 0.0,
+                y: 1.0,
+                z: 0.0,
+            },
+            charge: 0.0,
+            symbol: "O".to_string(),
+            remark: "".to_string(),
+        });
+        super::write_v3000(&mut buffer, &mol).unwrap();
+        assert_eq!(
+            super::read(buffer.as_slice()).unwrap().get_atoms(),
+            mol.get_atoms()
+        );
+    }
+}
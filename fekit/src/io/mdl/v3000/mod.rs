@@ -0,0 +1,585 @@
+/**
+ * Author: V. Ganesh
+ * License: MIT
+ */
+
+/**
+ * v3000 module, the V3000 connection table of MDL molfiles.
+ *
+ * Every line starts with "M  V30 ", a line ending in '-' continues on the next one.
+ * Atoms and bonds are numbered in the file, the numbers need not be consecutive.
+ * Blocks that fekit has no use for (SGROUP, OBJ3D, TEMPLATE ...) are skipped.
+ */
+use std::collections::HashMap;
+use std::io::BufRead;
+use std::io::Write;
+
+use super::formal_charge;
+use super::Ctab;
+use super::RECORD_END;
+use crate::atom::Atom;
+use crate::atom::AtomGroup;
+use crate::atom::GroupKind;
+use crate::bond::BondType;
+use crate::io::IoError;
+use crate::io::LineReader;
+use crate::point::Point;
+
+const PREFIX: &str = "M  V30";
+
+/** longest text after the prefix on a written line, so lines stay within 80 columns */
+const MAX_LINE_TEXT: usize = 72;
+
+/** Block - the block of the connection table being read */
+#[derive(PartialEq)]
+enum Block {
+    None,
+    Atom,
+    Bond,
+    Collection,
+}
+
+/** next_v30_line() reads the text of the next M  V30 line, joining continued lines */
+fn next_v30_line<R: BufRead>(reader: &mut LineReader<R>) -> Result<String, IoError> {
+    let mut text = String::new();
+
+    loop {
+        let line = reader.expect_line("an M  V30 line")?;
+        let content = match line.strip_prefix(PREFIX) {
+            Some(content) => content.strip_prefix(' ').unwrap_or(content),
+            None => {
+                return Err(reader.error(format!(
+                    "expected an M  V30 line before END CTAB, found \"{}\"",
+                    line.trim_end()
+                )))
+            }
+        };
+
+        match content.trim_end().strip_suffix('-') {
+            Some(continued) => text.push_str(continued),
+            None => {
+                text.push_str(content.trim_end());
+                return Ok(text);
+            }
+        }
+    }
+}
+
+/** tokenize() splits a line on spaces, keeping (lists) and "quoted text" in one token */
+fn tokenize(text: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut token = String::new();
+    let mut depth = 0;
+    let mut in_quotes = false;
+
+    for c in text.chars() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            '(' if !in_quotes => depth += 1,
+            ')' if !in_quotes && depth > 0 => depth -= 1,
+            _ => {}
+        }
+
+        if c.is_whitespace() && depth == 0 && !in_quotes {
+            if !token.is_empty() {
+                tokens.push(std::mem::take(&mut token));
+            }
+        } else {
+            token.push(c);
+        }
+    }
+
+    if !token.is_empty() {
+        tokens.push(token);
+    }
+    return tokens;
+}
+
+/** parse_list() reads a list such as (3 1 2 5), where the first number is the count */
+fn parse_list(text: &str) -> Result<Vec<usize>, String> {
+    let inner = text
+        .strip_prefix('(')
+        .and_then(|t| t.strip_suffix(')'))
+        .ok_or_else(|| format!("expected a list in parentheses, found \"{}\"", text))?;
+
+    let numbers: Vec<usize> = inner
+        .split_whitespace()
+        .map(|word| word.parse::<usize>())
+        .collect::<Result<_, _>>()
+        .map_err(|_| format!("expected numbers in the list \"{}\"", text))?;
+
+    match numbers.split_first() {
+        Some((count, values)) if *count == values.len() => return Ok(values.to_vec()),
+        _ => return Err(format!("the count of the list \"{}\" is wrong", text)),
+    }
+}
+
+/** group_of_collection() returns the group name and kind for a collection name */
+fn group_of_collection(name: &str) -> (String, GroupKind) {
+    let upper = name.to_uppercase();
+
+    if upper == "MDLV30/STEABS" {
+        return ("ABS".to_string(), GroupKind::STEREO);
+    } else if let Some(number) = upper.strip_prefix("MDLV30/STEREL") {
+        return (format!("OR{}", number), GroupKind::STEREO);
+    } else if let Some(number) = upper.strip_prefix("MDLV30/STERAC") {
+        return (format!("AND{}", number), GroupKind::STEREO);
+    }
+
+    return (name.to_string(), GroupKind::GENERIC);
+}
+
+/** collection_of_group() returns the collection name of a group, the reverse of the above */
+fn collection_of_group(group: &AtomGroup) -> String {
+    if group.kind == GroupKind::STEREO {
+        let name = group.name.to_uppercase();
+
+        if name == "ABS" {
+            return "MDLV30/STEABS".to_string();
+        } else if let Some(number) = name.strip_prefix("OR") {
+            return format!("MDLV30/STEREL{}", number);
+        } else if let Some(number) = name.strip_prefix("AND") {
+            return format!("MDLV30/STERAC{}", number);
+        }
+    }
+
+    return group.name.to_string();
+}
+
+/** read_ctab() reads a V3000 connection table, from BEGIN CTAB to the M  END line */
+pub(super) fn read_ctab<R: BufRead>(reader: &mut LineReader<R>) -> Result<Ctab, IoError> {
+    let mut ctab = Ctab::new();
+    let mut atom_numbers: HashMap<usize, usize> = HashMap::new();
+    let mut block = Block::None;
+
+    loop {
+        let text = next_v30_line(reader)?;
+        let tokens = tokenize(&text);
+        let words: Vec<String> = tokens.iter().take(2).map(|t| t.to_uppercase()).collect();
+        let words: Vec<&str> = words.iter().map(|w| w.as_str()).collect();
+
+        match words.as_slice() {
+            ["BEGIN", "CTAB"] => continue,
+            ["END", "CTAB"] => break,
+            ["COUNTS", ..] => ctab.chiral = tokens.get(5).map(|t| t.as_str()) == Some("1"),
+            ["BEGIN", "ATOM"] => block = Block::Atom,
+            ["BEGIN", "BOND"] => block = Block::Bond,
+            ["BEGIN", "COLLECTION"] => block = Block::Collection,
+            ["BEGIN", other] => {
+                let end = format!("END {}", other);
+                while next_v30_line(reader)?.trim().to_uppercase() != end {}
+            }
+            ["END", _] => block = Block::None,
+            _ => {
+                let result = match block {
+                    Block::Atom => read_atom(&tokens, &mut ctab, &mut atom_numbers),
+                    Block::Bond => read_bond(&tokens, &mut ctab, &atom_numbers),
+                    Block::Collection => read_collection(&tokens, &mut ctab, &atom_numbers),
+                    Block::None => Ok(()),
+                };
+                result.map_err(|message| reader.error(message))?;
+            }
+        }
+    }
+
+    // lines after the connection table, older files may leave out the M  END line
+    while let Some(line) = reader.peek_line()? {
+        if line.starts_with('>') || line.trim_end() == RECORD_END {
+            break;
+        }
+        if reader
+            .next_line()?
+            .unwrap_or_default()
+            .starts_with("M  END")
+        {
+            break;
+        }
+    }
+
+    return Ok(ctab);
+}
+
+fn read_atom(
+    tokens: &[String],
+    ctab: &mut Ctab,
+    atom_numbers: &mut HashMap<usize, usize>,
+) -> Result<(), String> {
+    if tokens.len() < 5 {
+        return Err(format!(
+            "expected an atom number, type and coordinates, found \"{}\"",
+            tokens.join(" ")
+        ));
+    }
+
+    let number: usize = tokens[0]
+        .parse()
+        .map_err(|_| format!("expected an atom number, found \"{}\"", tokens[0]))?;
+    let real = |field: &str| {
+        return field
+            .parse::<f32>()
+            .map_err(|_| format!("expected a coordinate, found \"{}\"", field));
+    };
+
+    let mut atom = Atom {
+        center: Point {
+            x: real(&tokens[2])?,
+            y: real(&tokens[3])?,
+            z: real(&tokens[4])?,
+        },
+        charge: 0.0,
+        symbol: tokens[1].trim_matches('"').to_string(),
+        remark: "".to_string(),
+    };
+    let (mut isotope, mut radical, mut parity) = (0, 0, 0);
+
+    for token in tokens.iter().skip(6) {
+        let (key, value) = match token.split_once('=') {
+            Some(pair) => pair,
+            None => continue,
+        };
+        let integer = || {
+            return value
+                .parse::<i64>()
+                .map_err(|_| format!("expected a number for {}, found \"{}\"", key, value));
+        };
+
+        match key.to_uppercase().as_str() {
+            "CHG" => atom.charge = integer()? as f32,
+            "MASS" => isotope = integer()?,
+            "RAD" => radical = integer()?,
+            "CFG" => parity = integer()?,
+            _ => {}
+        }
+    }
+
+    atom_numbers.insert(number, ctab.atoms.len());
+    ctab.atoms.push(atom);
+    ctab.isotopes.push(isotope);
+    ctab.radicals.push(radical);
+    ctab.parities.push(parity);
+
+    return Ok(());
+}
+
+fn read_bond(
+    tokens: &[String],
+    ctab: &mut Ctab,
+    atom_numbers: &HashMap<usize, usize>,
+) -> Result<(), String> {
+    if tokens.len() < 4 {
+        return Err(format!(
+            "expected a bond number, type and two atoms, found \"{}\"",
+            tokens.join(" ")
+        ));
+    }
+
+    let code = tokens[1].parse::<u8>().ok();
+    let bond_type = code
+        .and_then(BondType::from_mdl_code)
+        .ok_or_else(|| format!("unsupported bond type {}", tokens[1]))?;
+
+    let atom_of = |token: &String| {
+        return token
+            .parse::<usize>()
+            .ok()
+            .and_then(|number| atom_numbers.get(&number).copied())
+            .ok_or_else(|| format!("bond refers to a missing atom {}", token));
+    };
+    let (atom_1_idx, atom_2_idx) = (atom_of(&tokens[2])?, atom_of(&tokens[3])?);
+
+    let mut stereo = 0;
+    for token in tokens.iter().skip(4) {
+        if let Some((key, value)) = token.split_once('=') {
+            if key.eq_ignore_ascii_case("CFG") {
+                stereo = value
+                    .parse()
+                    .map_err(|_| format!("expected a number for CFG, found \"{}\"", value))?;
+            }
+        }
+    }
+
+    ctab.bonds.push((atom_1_idx, atom_2_idx, bond_type));
+    ctab.bond_stereo.push(stereo);
+
+    return Ok(());
+}
+
+fn read_collection(
+    tokens: &[String],
+    ctab: &mut Ctab,
+    atom_numbers: &HashMap<usize, usize>,
+) -> Result<(), String> {
+    let (name, kind) = match tokens.first() {
+        Some(name) => group_of_collection(name),
+        None => return Ok(()),
+    };
+    let mut group = AtomGroup::new(name, "".to_string()).with_kind(kind);
+
+    for token in tokens.iter().skip(1) {
+        if let Some((key, value)) = token.split_once('=') {
+            if !key.eq_ignore_ascii_case("ATOMS") {
+                continue;
+            }
+
+            for number in parse_list(value)? {
+                let idx = atom_numbers
+                    .get(&number)
+                    .ok_or_else(|| format!("collection refers to a missing atom {}", number))?;
+                group.add_atom_index(*idx);
+            }
+        }
+    }
+
+    ctab.groups.push(group);
+    return Ok(());
+}
+
+/** write_line() writes an M  V30 line, continuing it on more lines if it is long */
+fn write_line<W: Write>(writer: &mut W, text: &str) -> Result<(), IoError> {
+    let chars: Vec<char> = text.chars().collect();
+    let chunks: Vec<&[char]> = chars.chunks(MAX_LINE_TEXT).collect();
+
+    for (idx, chunk) in chunks.iter().enumerate() {
+        let chunk: String = chunk.iter().collect();
+        let continued = if idx + 1 < chunks.len() { "-" } else { "" };
+        writeln!(writer, "{} {}{}", PREFIX, chunk, continued)?;
+    }
+
+    return Ok(());
+}
+
+/** write_ctab() writes a V3000 connection table, from BEGIN CTAB to END CTAB */
+pub(super) fn write_ctab<W: Write>(writer: &mut W, ctab: &Ctab) -> Result<(), IoError> {
+    write_line(writer, "BEGIN CTAB")?;
+    write_line(
+        writer,
+        &format!(
+            "COUNTS {} {} 0 0 {}",
+            ctab.atoms.len(),
+            ctab.bonds.len(),
+            if ctab.chiral { 1 } else { 0 }
+        ),
+    )?;
+
+    write_line(writer, "BEGIN ATOM")?;
+    for (idx, atom) in ctab.atoms.iter().enumerate() {
+        let symbol = if atom.symbol.is_empty() || atom.symbol.contains(char::is_whitespace) {
+            format!("\"{}\"", atom.symbol)
+        } else {
+            atom.symbol.to_string()
+        };
+
+        let mut line = format!(
+            "{} {} {} {} {} 0",
+            idx + 1,
+            symbol,
+            atom.center.x,
+            atom.center.y,
+            atom.center.z
+        );
+        for (key, value) in [
+            ("CHG", formal_charge(atom)),
+            ("RAD", ctab.radicals[idx]),
+            ("CFG", ctab.parities[idx]),
+            ("MASS", ctab.isotopes[idx]),
+        ] {
+            if value != 0 {
+                line.push_str(&format!(" {}={}", key, value));
+            }
+        }
+        write_line(writer, &line)?;
+    }
+    write_line(writer, "END ATOM")?;
+
+    if !ctab.bonds.is_empty() {
+        write_line(writer, "BEGIN BOND")?;
+        for (idx, (atom_1_idx, atom_2_idx, bond_type)) in ctab.bonds.iter().enumerate() {
+            let mut line = format!(
+                "{} {} {} {}",
+                idx + 1,
+                bond_type.get_mdl_code(),
+                atom_1_idx + 1,
+                atom_2_idx + 1
+            );
+            if ctab.bond_stereo[idx] != 0 {
+                line.push_str(&format!(" CFG={}", ctab.bond_stereo[idx]));
+            }
+            write_line(writer, &line)?;
+        }
+        write_line(writer, "END BOND")?;
+    }
+
+    if !ctab.groups.is_empty() {
+        write_line(writer, "BEGIN COLLECTION")?;
+        for group in ctab.groups.iter() {
+            let indices = group.get_all_atom_indices();
+            let numbers: Vec<String> = indices.iter().map(|idx| (idx + 1).to_string()).collect();
+            write_line(
+                writer,
+                &format!(
+                    "{} ATOMS=({} {})",
+                    collection_of_group(group),
+                    indices.len(),
+                    numbers.join(" ")
+                ),
+            )?;
+        }
+        write_line(writer, "END COLLECTION")?;
+    }
+
+    write_line(writer, "END CTAB")?;
+    return Ok(());
+}
+
+/** Unit tests for the above module */
+#[cfg(test)]
+mod tests {
+    use crate::atom::Atom;
+    use crate::atom::AtomGroup;
+    use crate::atom::AtomOperations;
+    use crate::atom::GroupKind;
+    use crate::bond::BondType;
+    use crate::molecule::Molecule;
+    use crate::point::Point;
+    use crate::property::AtomProperty;
+    use crate::property::Property;
+
+    // 2-butanol and alanine, the butanol centre is absolute, the alanine one is racemic
+    const ENHANCED_STEREO: &str = "\
+stereo
+  fekit             2D
+
+  0  0  0     0  0            999 V3000
+M  V30 BEGIN CTAB
+M  V30 COUNTS 8 6 0 0 0
+M  V30 BEGIN ATOM
+M  V30 2 C 0.0 0.0 0.0 0
+M  V30 4 C 1.3 0.75 0.0 0 CFG=1
+M  V30 6 O 1.3 2.25 0.0 0
+M  V30 8 C 2.6 0.0 0.0 0
+M  V30 10 C 5.0 0.0 0.0 0 CFG=2
+M  V30 12 N 5.0 1.5 0.0 0 CHG=1
+M  V30 14 C 6.3 -0.75 0.0 0
+M  V30 16 O 6.3 -2.25 0.0 0 CHG=-1 -
+M  V30 MASS=18
+M  V30 END ATOM
+M  V30 BEGIN BOND
+M  V30 1 1 2 4
+M  V30 2 1 4 6 CFG=1
+M  V30 3 1 4 8
+M  V30 4 1 10 12 CFG=3
+M  V30 5 1 10 14
+M  V30 6 2 14 16
+M  V30 END BOND
+M  V30 BEGIN SGROUP
+M  V30 1 SUP 0 ATOMS=(1 16) LABEL=X
+M  V30 END SGROUP
+M  V30 BEGIN COLLECTION
+M  V30 MDLV30/STEABS ATOMS=(1 4)
+M  V30 MDLV30/STERAC1 ATOMS=(1 10)
+M  V30 END COLLECTION
+M  V30 END CTAB
+M  END
+";
+
+    #[test]
+    fn v3000_read() {
+        let mut mol = crate::io::mdl::read(ENHANCED_STEREO.as_bytes()).unwrap();
+
+        assert_eq!(mol.get_number_of_atoms(), 8);
+        assert_eq!(mol.get_atom(5).charge, 1.0);
+        assert_eq!(mol.get_atom(7).charge, -1.0);
+        assert_eq!(mol.get_atom(6).center.y, -0.75);
+        assert_eq!(mol.get_bond_type(6, 7), BondType::DOUBLE);
+        assert_eq!(
+            mol.get_property("bond_stereo"),
+            Some(&Property::Integers(vec![0, 1, 0, 3, 0, 0]))
+        );
+        assert_eq!(
+            mol.get_atom_property("isotope"),
+            Some(&AtomProperty::Integers(vec![0, 0, 0, 0, 0, 0, 0, 18]))
+        );
+        assert_eq!(
+            mol.get_atom_property("parity"),
+            Some(&AtomProperty::Integers(vec![0, 1, 0, 0, 2, 0, 0, 0]))
+        );
+
+        let groups = mol.get_groups();
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].name, "ABS".to_string());
+        assert_eq!(groups[1].name, "AND1".to_string());
+        assert_eq!(groups[1].kind, GroupKind::STEREO);
+        assert_eq!(groups[1].get_atom_indices(), &[4]);
+
+        let mut buffer = Vec::new();
+        crate::io::mdl::write(&mut buffer, &mol).unwrap();
+        let text = String::from_utf8(buffer).unwrap();
+        assert!(text.contains(" V3000\n"));
+        assert!(text.contains("M  V30 MDLV30/STERAC1 ATOMS=(1 5)\n"));
+
+        let read_mol = crate::io::mdl::read(text.as_bytes()).unwrap();
+        assert_eq!(read_mol.get_atoms(), mol.get_atoms());
+        assert_eq!(read_mol.get_bonds(), mol.get_bonds());
+        assert_eq!(read_mol.get_groups(), mol.get_groups());
+        assert_eq!(read_mol.get_properties(), mol.get_properties());
+        assert_eq!(read_mol.get_atom_properties(), mol.get_atom_properties());
+    }
+
+    #[test]
+    fn v3000_macrocycle() {
+        let number_of_atoms = 1500;
+        let mut mol = Molecule::new("cyclo-C1500".to_string(), "".to_string());
+        for idx in 0..number_of_atoms {
+            let angle = 2.0 * std::f32::consts::PI * idx as f32 / number_of_atoms as f32;
+            mol.add_atom(Atom {
+                center: Point {
+                    x: 360.0 * angle.cos(),
+                    y: 360.0 * angle.sin(),
+                    z: 0.1 / (idx + 1) as f32,
+                },
+                charge: if idx % 100 == 0 { -1.0 } else { 0.0 },
+                symbol: "C".to_string(),
+                remark: "".to_string(),
+            });
+        }
+        for idx in 0..number_of_atoms {
+            let bond_type = if idx % 2 == 0 {
+                BondType::DOUBLE
+            } else {
+                BondType::SINGLE
+            };
+            mol.add_bond(idx, (idx + 1) % number_of_atoms, bond_type);
+        }
+
+        let mut ring = AtomGroup::new("MDLV30/HILITE".to_string(), "".to_string());
+        for idx in 0..number_of_atoms {
+            ring.add_atom_index(idx);
+        }
+        mol.add_group(ring);
+
+        let mut buffer = Vec::new();
+        crate::io::mdl::write(&mut buffer, &mol).unwrap();
+        let text = String::from_utf8(buffer).unwrap();
+        assert!(text.lines().all(|line| line.len() <= 80));
+
+        let read_mol = crate::io::mdl::read(text.as_bytes()).unwrap();
+        assert_eq!(read_mol.name, mol.name);
+        assert_eq!(read_mol.get_atoms(), mol.get_atoms());
+        assert_eq!(read_mol.get_bonds(), mol.get_bonds());
+        assert_eq!(read_mol.get_groups(), mol.get_groups());
+    }
+
+    #[test]
+    fn v3000_errors() {
+        let text = ENHANCED_STEREO.replace("M  V30 6 2 14 16", "M  V30 6 2 14 17");
+        assert_eq!(
+            crate::io::mdl::read(text.as_bytes())
+                .unwrap_err()
+                .to_string(),
+            "mdl: line 24: bond refers to a missing atom 17"
+        );
+
+        let text = ENHANCED_STEREO.replace("M  V30 END CTAB\n", "");
+        assert!(crate::io::mdl::read(text.as_bytes()).is_err());
+    }
+}
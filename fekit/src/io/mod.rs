@@ -0,0 +1,292 @@
+/**
+ * Author: V. Ganesh
+ * License: MIT
+ */
+
+/**
+ * io module, readers and writers for chemical file formats.
+ *
+ * Each format has its own module, read() and write() below choose the module from the
+ * file name or the contents, see the registry module. Compressed files are read and
+ * written through the compress module.
+ */
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::io::BufRead;
+use std::path::Path;
+
+use crate::atomprop;
+use crate::molecule::Molecule;
+use crate::property::Property;
+
+pub mod cif;
+#[cfg(feature = "serde")]
+pub mod cjson;
+pub mod cml;
+pub mod compress;
+pub mod cube;
+pub mod gamess;
+pub mod gaussian;
+pub mod gromacs;
+pub mod lammps;
+pub mod mdl;
+pub mod mol2;
+pub mod molden;
+pub mod pdb;
+pub mod qm;
+pub mod registry;
+pub mod smiles;
+pub mod vasp;
+pub mod xml;
+pub mod xyz;
+
+/** read() reads the first molecule of a file, in the format of its name or contents */
+#[allow(dead_code)]
+pub fn read<P: AsRef<Path>>(path: P) -> Result<Molecule, IoError> {
+    return registry::get_registry().read(path);
+}
+
+/** read_as() reads the first molecule of a file in the named format, e.g. "xyz" */
+#[allow(dead_code)]
+pub fn read_as<P: AsRef<Path>>(path: P, format: &str) -> Result<Molecule, IoError> {
+    return registry::get_registry().read_as(path, format);
+}
+
+/** read_all() reads all the molecules of a file, in the format of its name or contents */
+#[allow(dead_code)]
+pub fn read_all<P: AsRef<Path>>(path: P) -> Result<Vec<Molecule>, IoError> {
+    return registry::get_registry().read_all(path);
+}
+
+/** write() writes a molecule to a file, in the format of its name */
+#[allow(dead_code)]
+pub fn write<P: AsRef<Path>>(path: P, mol: &Molecule) -> Result<(), IoError> {
+    return registry::get_registry().write(path, mol);
+}
+
+/** write_as() writes a molecule to a file in the named format */
+#[allow(dead_code)]
+pub fn write_as<P: AsRef<Path>>(path: P, mol: &Molecule, format: &str) -> Result<(), IoError> {
+    return registry::get_registry().write_as(path, mol, format);
+}
+
+/** register_format() adds a format that read() and write() can choose */
+#[allow(dead_code)]
+pub fn register_format(format: Box<dyn registry::MoleculeFormat>) {
+    registry::register_format(format);
+}
+
+/** length of the bohr, the atomic unit of length used by QM programs, in angstrom */
+pub const BOHR: f64 = 0.529177210903;
+
+/** IoError - the error returned by all readers and writers */
+#[allow(dead_code)]
+#[derive(Debug)]
+pub enum IoError {
+    /** the underlying file or stream failed */
+    Io(io::Error),
+    /** the contents are not valid for the format, line_number is 1 based, 0 if not known */
+    Parse {
+        format: String,
+        line_number: usize,
+        message: String,
+    },
+}
+
+#[allow(dead_code)]
+impl IoError {
+    /** parse() creates a parse error for the given format and line */
+    pub fn parse(format: &str, line_number: usize, message: String) -> IoError {
+        IoError::Parse {
+            format: format.to_string(),
+            line_number: line_number,
+            message: message,
+        }
+    }
+}
+
+impl fmt::Display for IoError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            IoError::Io(err) => write!(f, "{}", err),
+            IoError::Parse {
+                format,
+                line_number: 0,
+                message,
+            } => write!(f, "{}: {}", format, message),
+            IoError::Parse {
+                format,
+                line_number,
+                message,
+            } => write!(f, "{}: line {}: {}", format, line_number, message),
+        }
+    }
+}
+
+impl Error for IoError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            IoError::Io(err) => return Some(err),
+            IoError::Parse { .. } => return None,
+        }
+    }
+}
+
+impl From<io::Error> for IoError {
+    fn from(err: io::Error) -> Self {
+        IoError::Io(err)
+    }
+}
+
+/** LineReader - reads a text format line by line, keeping count of the line number for errors */
+#[allow(dead_code)]
+pub struct LineReader<R: BufRead> {
+    reader: R,
+    format: &'static str,
+    line_number: usize,
+    buffer: String,
+    peeked: Option<String>,
+}
+
+#[allow(dead_code)]
+impl<R: BufRead> LineReader<R> {
+    pub fn new(reader: R, format: &'static str) -> LineReader<R> {
+        LineReader {
+            reader: reader,
+            format: format,
+            line_number: 0,
+            buffer: String::new(),
+            peeked: None,
+        }
+    }
+
+    /** next_line() returns the next line without its line ending, or None at the end of input */
+    pub fn next_line(&mut self) -> Result<Option<String>, IoError> {
+        if let Some(line) = self.peeked.take() {
+            self.line_number += 1;
+            return Ok(Some(line));
+        }
+
+        self.buffer.clear();
+        if self.reader.read_line(&mut self.buffer)? == 0 {
+            return Ok(None);
+        }
+
+        self.line_number += 1;
+        let line = self.buffer.trim_end_matches(['\n', '\r']);
+        return Ok(Some(line.to_string()));
+    }
+
+    /** expect_line() returns the next line, it is an error if the input ends instead */
+    pub fn expect_line(&mut self, what: &str) -> Result<String, IoError> {
+        match self.next_line()? {
+            Some(line) => return Ok(line),
+            None => {
+                return Err(IoError::parse(
+                    self.format,
+                    self.line_number + 1,
+                    format!("unexpected end of file, expected {}", what),
+                ))
+            }
+        }
+    }
+
+    /** peek_line() returns the next line without consuming it */
+    pub fn peek_line(&mut self) -> Result<Option<&str>, IoError> {
+        if self.peeked.is_none() {
+            match self.next_line()? {
+                Some(line) => {
+                    self.line_number -= 1;
+                    self.peeked = Some(line);
+                }
+                None => return Ok(None),
+            }
+        }
+
+        return Ok(self.peeked.as_deref());
+    }
+
+    /** get_line_number() returns the 1 based number of the line last returned */
+    pub fn get_line_number(&self) -> usize {
+        return self.line_number;
+    }
+
+    /** error() creates a parse error for the line last returned */
+    pub fn error(&self, message: String) -> IoError {
+        return IoError::parse(self.format, self.line_number, message);
+    }
+}
+
+/**
+ * get_total_charge() returns the total charge of a molecule, its "charge" property if it
+ * has one, else the sum of the atom charges
+ */
+pub(crate) fn get_total_charge(mol: &Molecule) -> i64 {
+    if let Some(Property::Integer(charge)) = mol.get_property("charge") {
+        return *charge;
+    }
+
+    let charge: f32 = mol.get_atoms().iter().map(|atom| atom.charge).sum();
+    return charge.round() as i64;
+}
+
+/**
+ * get_multiplicity() returns the spin multiplicity of a molecule with the given total
+ * charge, its "multiplicity" property if it has one, else a singlet for an even number of
+ * electrons and a doublet for an odd number
+ */
+pub(crate) fn get_multiplicity(mol: &Molecule, charge: i64) -> i64 {
+    if let Some(Property::Integer(multiplicity)) = mol.get_property("multiplicity") {
+        return *multiplicity;
+    }
+
+    let electrons: i64 = mol
+        .get_atoms()
+        .iter()
+        .map(|atom| atomprop::get_atomic_number(&atom.symbol).unwrap_or(0) as i64)
+        .sum::<i64>()
+        - charge;
+
+    if electrons.rem_euclid(2) == 0 {
+        return 1;
+    }
+    return 2;
+}
+
+/** Unit tests for the above module */
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn io_error_display() {
+        let err = super::IoError::parse("xyz", 3, "expected 3 coordinates".to_string());
+        assert_eq!(err.to_string(), "xyz: line 3: expected 3 coordinates");
+
+        let err = super::IoError::parse("cjson", 0, "missing atoms".to_string());
+        assert_eq!(err.to_string(), "cjson: missing atoms");
+    }
+
+    #[test]
+    fn io_line_reader() {
+        let mut reader = super::LineReader::new("first\r\nsecond\n\nlast".as_bytes(), "test");
+
+        assert_eq!(reader.next_line().unwrap(), Some("first".to_string()));
+        assert_eq!(reader.peek_line().unwrap(), Some("second"));
+        assert_eq!(reader.get_line_number(), 1);
+        assert_eq!(reader.next_line().unwrap(), Some("second".to_string()));
+        assert_eq!(reader.get_line_number(), 2);
+        assert_eq!(reader.expect_line("a blank line").unwrap(), "".to_string());
+        assert_eq!(reader.next_line().unwrap(), Some("last".to_string()));
+        assert_eq!(reader.next_line().unwrap(), None);
+
+        let err = reader.expect_line("a title").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "test: line 5: unexpected end of file, expected a title"
+        );
+        assert_eq!(
+            reader.error("bad".to_string()).to_string(),
+            "test: line 4: bad"
+        );
+    }
+}
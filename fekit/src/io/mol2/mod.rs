@@ -0,0 +1,819 @@
+/**
+ * Author: V. Ganesh
+ * License: MIT
+ */
+
+/**
+ * mol2 module, reads and writes Tripos MOL2 files.
+ *
+ * Partial charges are read into the atom charges, atom names and SYBYL atom types are
+ * kept as the "atom_name" and "sybyl_type" atom properties, and substructures become
+ * residue groups (inside chain groups, if the SUBSTRUCTURE section names a chain). Amide
+ * bonds (am) are read as single bonds, dummy bonds (du) as weak bonds.
+ *
+ * When writing, atoms without a SYBYL type are typed from their perceived hybridisation,
+ * using the bond orders, or the number of bonded atoms if the molecule only has single
+ * bonds and includes its hydrogens (e.g. bonds computed from an XYZ file).
+ *
+ * A file may hold several molecules, Mol2Reader reads them one at a time.
+ */
+use std::collections::HashMap;
+use std::io::BufRead;
+use std::io::Write;
+
+use crate::atom::Atom;
+use crate::atom::AtomGroup;
+use crate::atom::AtomOperations;
+use crate::atom::GroupKind;
+use crate::atomprop;
+use crate::bond::BondType;
+use crate::io::IoError;
+use crate::io::LineReader;
+use crate::molecule::Molecule;
+use crate::point::Point;
+use crate::property::AtomProperty;
+use crate::property::Property;
+
+const FORMAT: &str = "mol2";
+
+const SECTION_PREFIX: &str = "@<TRIPOS>";
+
+/** name written for an unnamed molecule or substructure, and read back as empty */
+const NO_NAME: &str = "****";
+
+/** SYBYL type of atoms that are not elements */
+const DUMMY_TYPE: &str = "Du";
+
+/** Mol2Reader - reads the molecules of a MOL2 file one at a time */
+#[allow(dead_code)]
+pub struct Mol2Reader<R: BufRead> {
+    reader: LineReader<R>,
+    failed: bool,
+}
+
+#[allow(dead_code)]
+impl<R: BufRead> Mol2Reader<R> {
+    pub fn new(reader: R) -> Mol2Reader<R> {
+        Mol2Reader {
+            reader: LineReader::new(reader, FORMAT),
+            failed: false,
+        }
+    }
+
+    /** next_data_line() returns the next line of the current section, skipping comments */
+    fn next_data_line(&mut self) -> Result<Option<String>, IoError> {
+        while let Some(line) = self.reader.peek_line()? {
+            if line.starts_with(SECTION_PREFIX) {
+                return Ok(None);
+            }
+
+            let line = self.reader.next_line()?.unwrap_or_default();
+            if !line.trim().is_empty() && !line.starts_with('#') {
+                return Ok(Some(line));
+            }
+        }
+
+        return Ok(None);
+    }
+
+    /** read_molecule() reads the next molecule, or returns None at the end of the file */
+    pub fn read_molecule(&mut self) -> Result<Option<Molecule>, IoError> {
+        // anything before the first MOLECULE section is skipped
+        loop {
+            match self.reader.next_line()? {
+                Some(line) if line.trim().eq_ignore_ascii_case("@<TRIPOS>MOLECULE") => break,
+                Some(_) => continue,
+                None => return Ok(None),
+            }
+        }
+
+        let mut mol = Molecule::new("".to_string(), "".to_string());
+
+        // the MOLECULE section keeps its blank lines, the remark may follow an empty status
+        let mut header = Vec::new();
+        while let Some(line) = self.reader.peek_line()? {
+            if line.starts_with(SECTION_PREFIX) {
+                break;
+            }
+            header.push(self.reader.next_line()?.unwrap_or_default());
+        }
+        while header.last().is_some_and(|line| line.trim().is_empty()) {
+            header.pop();
+        }
+
+        let name = header.first().map(|line| line.trim()).unwrap_or("");
+        if name != NO_NAME && name != "*****" {
+            mol.name = name.to_string();
+        }
+        for (idx, key) in [(2, "mol_type"), (3, "charge_type")] {
+            if let Some(value) = header.get(idx).map(|line| line.trim()) {
+                if !value.is_empty() {
+                    mol.set_property(key, Property::Text(value.to_string()));
+                }
+            }
+        }
+        if let Some(remark) = header.get(5) {
+            mol.remark = remark.trim().to_string();
+        }
+
+        let mut atom_ids: HashMap<String, usize> = HashMap::new();
+        let mut atom_names = Vec::new();
+        let mut sybyl_types = Vec::new();
+        let mut substructures: Vec<(String, String)> = Vec::new();
+        let mut atom_substructures: Vec<Option<usize>> = Vec::new();
+        let mut chains: HashMap<String, String> = HashMap::new();
+        let mut has_substructure_section = false;
+
+        while let Some(line) = self.reader.peek_line()? {
+            let section = line.trim().to_uppercase();
+            if section == "@<TRIPOS>MOLECULE" {
+                break;
+            }
+            self.reader.next_line()?;
+
+            match section.as_str() {
+                "@<TRIPOS>ATOM" => {
+                    while let Some(line) = self.next_data_line()? {
+                        let words: Vec<&str> = line.split_whitespace().collect();
+                        let (atom, sybyl_type) =
+                            parse_atom(&words).map_err(|message| self.reader.error(message))?;
+
+                        atom_ids.insert(words[0].to_string(), mol.get_atoms().len());
+                        atom_names.push(words[1].to_string());
+                        sybyl_types.push(sybyl_type);
+                        mol.add_atom(atom);
+
+                        atom_substructures.push(words.get(6).map(|id| {
+                            let name = words.get(7).copied().unwrap_or(id);
+                            match substructures.iter().position(|(i, _)| i == id) {
+                                Some(position) => position,
+                                None => {
+                                    substructures.push((id.to_string(), name.to_string()));
+                                    substructures.len() - 1
+                                }
+                            }
+                        }));
+                    }
+                }
+                "@<TRIPOS>BOND" => {
+                    while let Some(line) = self.next_data_line()? {
+                        let words: Vec<&str> = line.split_whitespace().collect();
+                        if let Some((atom_1_idx, atom_2_idx, bond_type)) =
+                            parse_bond(&words, &atom_ids)
+                                .map_err(|message| self.reader.error(message))?
+                        {
+                            mol.add_bond(atom_1_idx, atom_2_idx, bond_type);
+                        }
+                    }
+                }
+                "@<TRIPOS>SUBSTRUCTURE" => {
+                    has_substructure_section = true;
+                    while let Some(line) = self.next_data_line()? {
+                        let words: Vec<&str> = line.split_whitespace().collect();
+                        if let (Some(id), Some(chain)) = (words.first(), words.get(5)) {
+                            if !chain.starts_with('*') {
+                                chains.insert(id.to_string(), chain.to_string());
+                            }
+                        }
+                    }
+                }
+                _ => while self.next_data_line()?.is_some() {},
+            }
+        }
+
+        // a single substructure without a SUBSTRUCTURE section is only a placeholder
+        if has_substructure_section || substructures.len() > 1 {
+            add_substructures(&mut mol, &substructures, &atom_substructures, &chains);
+        }
+
+        mol.set_atom_property("atom_name", AtomProperty::Texts(atom_names));
+        mol.set_atom_property("sybyl_type", AtomProperty::Texts(sybyl_types));
+
+        return Ok(Some(mol));
+    }
+}
+
+impl<R: BufRead> Iterator for Mol2Reader<R> {
+    type Item = Result<Molecule, IoError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+
+        let molecule = self.read_molecule();
+        self.failed = molecule.is_err();
+        return molecule.transpose();
+    }
+}
+
+/** read() reads the first molecule of a MOL2 file */
+#[allow(dead_code)]
+pub fn read<R: BufRead>(reader: R) -> Result<Molecule, IoError> {
+    match Mol2Reader::new(reader).read_molecule()? {
+        Some(mol) => return Ok(mol),
+        None => {
+            return Err(IoError::parse(
+                FORMAT,
+                0,
+                "no @<TRIPOS>MOLECULE section".to_string(),
+            ))
+        }
+    }
+}
+
+/** parse_atom() reads the words of an atom line into the atom and its SYBYL type */
+fn parse_atom(words: &[&str]) -> Result<(Atom, String), String> {
+    if words.len() < 6 {
+        return Err(format!(
+            "expected an atom id, name, coordinates and type, found \"{}\"",
+            words.join(" ")
+        ));
+    }
+
+    let real = |word: &str| {
+        return word
+            .parse::<f32>()
+            .map_err(|_| format!("expected a number, found \"{}\"", word));
+    };
+
+    let sybyl_type = words[5];
+    let element = sybyl_type.split('.').next().unwrap_or(sybyl_type);
+    let symbol = match atomprop::get_atomic_number(element) {
+        Some(number) => atomprop::get_element_symbol(number).unwrap_or(element),
+        None => element,
+    };
+
+    let atom = Atom {
+        center: Point {
+            x: real(words[2])?,
+            y: real(words[3])?,
+            z: real(words[4])?,
+        },
+        charge: match words.get(8) {
+            Some(word) => real(word)?,
+            None => 0.0,
+        },
+        symbol: symbol.to_string(),
+        remark: "".to_string(),
+    };
+
+    return Ok((atom, sybyl_type.to_string()));
+}
+
+/** parse_bond() reads the words of a bond line, bonds of type nc (not connected) give None */
+fn parse_bond(
+    words: &[&str],
+    atom_ids: &HashMap<String, usize>,
+) -> Result<Option<(usize, usize, BondType)>, String> {
+    if words.len() < 4 {
+        return Err(format!(
+            "expected a bond id, two atom ids and a type, found \"{}\"",
+            words.join(" ")
+        ));
+    }
+
+    let atom_of = |id: &str| {
+        return atom_ids
+            .get(id)
+            .copied()
+            .ok_or_else(|| format!("bond refers to a missing atom {}", id));
+    };
+
+    let bond_type = match words[3].to_lowercase().as_str() {
+        "1" | "am" | "un" => BondType::SINGLE,
+        "2" => BondType::DOUBLE,
+        "3" => BondType::TRIPLE,
+        "ar" => BondType::AROMATIC,
+        "du" => BondType::WEAK,
+        "nc" => return Ok(None),
+        other => return Err(format!("unknown bond type {}", other)),
+    };
+
+    return Ok(Some((atom_of(words[1])?, atom_of(words[2])?, bond_type)));
+}
+
+/** add_substructures() adds a residue group for each substructure, in chain groups if named */
+fn add_substructures(
+    mol: &mut Molecule,
+    substructures: &[(String, String)],
+    atom_substructures: &[Option<usize>],
+    chains: &HashMap<String, String>,
+) {
+    let mut residues: Vec<AtomGroup> = substructures
+        .iter()
+        .map(|(_, name)| {
+            AtomGroup::new(name.to_string(), "".to_string()).with_kind(GroupKind::RESIDUE)
+        })
+        .collect();
+
+    for (idx, substructure) in atom_substructures.iter().enumerate() {
+        if let Some(position) = substructure {
+            residues[*position].add_atom_index(idx);
+        }
+    }
+
+    let mut chain_groups: Vec<AtomGroup> = Vec::new();
+    for ((id, _), residue) in substructures.iter().zip(residues) {
+        match chains.get(id) {
+            Some(chain) => match chain_groups.iter_mut().find(|g| g.name == *chain) {
+                Some(group) => group.add_group(residue),
+                None => {
+                    let mut group = AtomGroup::new(chain.to_string(), "".to_string())
+                        .with_kind(GroupKind::CHAIN);
+                    group.add_group(residue);
+                    chain_groups.push(group);
+                }
+            },
+            None => mol.add_group(residue),
+        }
+    }
+
+    for group in chain_groups {
+        mol.add_group(group);
+    }
+}
+
+/** collect_residues() lists the residue groups of the molecule, with the chain they are in */
+fn collect_residues<'a>(
+    groups: &'a [AtomGroup],
+    chain: &str,
+    residues: &mut Vec<(&'a AtomGroup, String)>,
+) {
+    for group in groups.iter() {
+        match group.kind {
+            GroupKind::RESIDUE => residues.push((group, chain.to_string())),
+            GroupKind::CHAIN => collect_residues(group.get_groups(), &group.name, residues),
+            _ => collect_residues(group.get_groups(), chain, residues),
+        }
+    }
+}
+
+/** get_sybyl_types() assigns SYBYL atom types from the perceived hybridisation of each atom */
+pub fn get_sybyl_types(mol: &Molecule) -> Vec<String> {
+    let atoms = mol.get_atoms();
+    let neighbours = mol.get_adjacency_list();
+
+    let elements: Vec<&str> = atoms
+        .iter()
+        .map(|at| {
+            atomprop::get_atomic_number(&at.symbol)
+                .and_then(atomprop::get_element_symbol)
+                .unwrap_or(DUMMY_TYPE)
+        })
+        .collect();
+
+    let mut pi_bonds = vec![0; atoms.len()];
+    let mut aromatic = vec![false; atoms.len()];
+    let mut double_bonded: Vec<Vec<usize>> = vec![Vec::new(); atoms.len()];
+    for bond in mol.get_bonds().iter() {
+        let (a, b) = (bond.atom_1_idx, bond.atom_2_idx);
+        match bond.bond_type {
+            BondType::DOUBLE => {
+                pi_bonds[a] += 1;
+                pi_bonds[b] += 1;
+                double_bonded[a].push(b);
+                double_bonded[b].push(a);
+            }
+            BondType::TRIPLE => {
+                pi_bonds[a] += 2;
+                pi_bonds[b] += 2;
+            }
+            BondType::AROMATIC => {
+                aromatic[a] = true;
+                aromatic[b] = true;
+            }
+            _ => {}
+        }
+    }
+
+    // with only single bonds and explicit hydrogens, missing neighbours stand for pi bonds
+    let from_geometry =
+        pi_bonds.iter().all(|p| *p == 0) && !aromatic.contains(&true) && elements.contains(&"H");
+    let pi_of = |idx: usize| {
+        if !from_geometry {
+            return pi_bonds[idx];
+        }
+        let valence: usize = match elements[idx] {
+            "C" => 4,
+            "N" => 3,
+            "O" | "S" => 2,
+            _ => return 0,
+        };
+        return valence.saturating_sub(neighbours[idx].len());
+    };
+
+    let is_terminal_oxygen = |idx: usize| elements[idx] == "O" && neighbours[idx].len() == 1;
+    let is_carbonyl_carbon = |idx: usize| {
+        return elements[idx] == "C"
+            && neighbours[idx].iter().any(|other| {
+                matches!(elements[*other], "O" | "S")
+                    && (double_bonded[idx].contains(other)
+                        || (from_geometry && neighbours[*other].len() == 1))
+            });
+    };
+
+    let mut types = Vec::new();
+    for idx in 0..atoms.len() {
+        let number_of_neighbours = neighbours[idx].len();
+        let pi = pi_of(idx);
+
+        let sybyl_type = match elements[idx] {
+            "C" if aromatic[idx] => "C.ar",
+            "C" if pi >= 2 => "C.1",
+            "C" if pi == 1 => "C.2",
+            "C" => "C.3",
+            "N" if aromatic[idx] => "N.ar",
+            "N" if number_of_neighbours == 4 => "N.4",
+            "N" if pi == 0 && neighbours[idx].iter().any(|o| is_carbonyl_carbon(*o)) => "N.am",
+            "N" if pi >= 2 => "N.1",
+            "N" if pi == 1 && number_of_neighbours == 3 => "N.pl3",
+            "N" if pi == 1 => "N.2",
+            "N" if number_of_neighbours == 3
+                && neighbours[idx]
+                    .iter()
+                    .any(|o| pi_of(*o) > 0 || aromatic[*o]) =>
+            {
+                "N.pl3"
+            }
+            "N" => "N.3",
+            "O" if number_of_neighbours == 1
+                && neighbours[idx].iter().any(|o| {
+                    matches!(elements[*o], "C" | "P")
+                        && neighbours[*o]
+                            .iter()
+                            .filter(|t| is_terminal_oxygen(**t))
+                            .count()
+                            >= 2
+                }) =>
+            {
+                "O.co2"
+            }
+            "O" if pi >= 1 || aromatic[idx] => "O.2",
+            "O" => "O.3",
+            "S" if number_of_neighbours == 4
+                && neighbours[idx]
+                    .iter()
+                    .filter(|o| is_terminal_oxygen(**o))
+                    .count()
+                    >= 2 =>
+            {
+                "S.O2"
+            }
+            "S" if number_of_neighbours == 3
+                && neighbours[idx].iter().any(|o| is_terminal_oxygen(*o)) =>
+            {
+                "S.O"
+            }
+            "S" if pi >= 1 || aromatic[idx] => "S.2",
+            "S" => "S.3",
+            "P" => "P.3",
+            element => element,
+        };
+        types.push(sybyl_type.to_string());
+    }
+
+    return types;
+}
+
+/** get_bond_code() returns the MOL2 bond type, a single bond from a carbonyl carbon to N is am */
+fn get_bond_code(
+    mol: &Molecule,
+    types: &[String],
+    atom_1_idx: usize,
+    atom_2_idx: usize,
+    bond_type: BondType,
+) -> &'static str {
+    match bond_type {
+        BondType::DOUBLE => return "2",
+        BondType::TRIPLE => return "3",
+        BondType::AROMATIC => return "ar",
+        BondType::WEAK => return "du",
+        BondType::SINGLE | BondType::COORDINATE => {}
+    }
+
+    for (carbon, nitrogen) in [(atom_1_idx, atom_2_idx), (atom_2_idx, atom_1_idx)] {
+        if types[nitrogen] == "N.am" && types[carbon] == "C.2" {
+            let is_carbonyl = mol.get_bonds().iter().any(|bnd| {
+                bnd.bond_type == BondType::DOUBLE
+                    && (bnd.atom_1_idx == carbon || bnd.atom_2_idx == carbon)
+                    && types[bnd.atom_1_idx + bnd.atom_2_idx - carbon].starts_with('O')
+            });
+            if is_carbonyl {
+                return "am";
+            }
+        }
+    }
+
+    return "1";
+}
+
+/**
+ * write() writes a molecule in MOL2 format, atoms without a "sybyl_type" atom property
+ * (or with an empty one) are typed by get_sybyl_types()
+ */
+#[allow(dead_code)]
+pub fn write<W: Write>(mut writer: W, mol: &Molecule) -> Result<(), IoError> {
+    let atoms = mol.get_atoms();
+    let bonds = mol.get_bonds();
+
+    let texts_of = |name: &str| match mol.get_atom_property(name) {
+        Some(AtomProperty::Texts(values)) => values.to_vec(),
+        _ => vec!["".to_string(); atoms.len()],
+    };
+    let atom_names = texts_of("atom_name");
+    let perceived = get_sybyl_types(mol);
+    let types: Vec<String> = texts_of("sybyl_type")
+        .into_iter()
+        .zip(perceived)
+        .map(|(given, perceived)| if given.is_empty() { perceived } else { given })
+        .collect();
+
+    let mut residues = Vec::new();
+    collect_residues(mol.get_groups(), "", &mut residues);
+
+    let mut atom_residues = vec![None; atoms.len()];
+    for (position, (residue, _)) in residues.iter().enumerate() {
+        for idx in residue.get_all_atom_indices() {
+            atom_residues[idx] = Some(position);
+        }
+    }
+
+    let text_property = |name: &str, default: &str| match mol.get_property(name) {
+        Some(Property::Text(value)) => value.to_string(),
+        _ => default.to_string(),
+    };
+    let has_charges = atoms.iter().any(|at| at.charge != 0.0);
+
+    writeln!(writer, "@<TRIPOS>MOLECULE")?;
+    writeln!(
+        writer,
+        "{}",
+        if mol.name.is_empty() {
+            NO_NAME
+        } else {
+            &mol.name
+        }
+    )?;
+    writeln!(
+        writer,
+        "{:>5} {:>5} {:>5} {:>5} {:>5}",
+        atoms.len(),
+        bonds.len(),
+        residues.len(),
+        0,
+        0
+    )?;
+    writeln!(writer, "{}", text_property("mol_type", "SMALL"))?;
+    writeln!(
+        writer,
+        "{}",
+        text_property(
+            "charge_type",
+            if has_charges {
+                "USER_CHARGES"
+            } else {
+                "NO_CHARGES"
+            }
+        )
+    )?;
+    if !mol.remark.is_empty() {
+        writeln!(writer, "{}", NO_NAME)?;
+        writeln!(writer, "{}", mol.remark.replace(['\n', '\r'], " "))?;
+    }
+    writeln!(writer)?;
+
+    writeln!(writer, "@<TRIPOS>ATOM")?;
+    for (idx, atom) in atoms.iter().enumerate() {
+        let name = if atom_names[idx].is_empty() {
+            format!("{}{}", atom.symbol, idx + 1)
+        } else {
+            atom_names[idx].to_string()
+        };
+        let (residue_id, residue_name) = match atom_residues[idx] {
+            Some(position) => (position + 1, residues[position].0.name.to_string()),
+            None => (1, "UNL1".to_string()),
+        };
+
+        writeln!(
+            writer,
+            "{:>7} {:<8} {:>10.4} {:>10.4} {:>10.4} {:<6} {:>5} {:<8} {:>10.4}",
+            idx + 1,
+            name,
+            atom.center.x,
+            atom.center.y,
+            atom.center.z,
+            types[idx],
+            residue_id,
+            residue_name,
+            atom.charge
+        )?;
+    }
+
+    writeln!(writer, "@<TRIPOS>BOND")?;
+    for (idx, bond) in bonds.iter().enumerate() {
+        writeln!(
+            writer,
+            "{:>6} {:>5} {:>5} {}",
+            idx + 1,
+            bond.atom_1_idx + 1,
+            bond.atom_2_idx + 1,
+            get_bond_code(
+                mol,
+                &types,
+                bond.atom_1_idx,
+                bond.atom_2_idx,
+                bond.bond_type
+            )
+        )?;
+    }
+
+    if !residues.is_empty() {
+        writeln!(writer, "@<TRIPOS>SUBSTRUCTURE")?;
+        for (position, (residue, chain)) in residues.iter().enumerate() {
+            let root = residue
+                .get_all_atom_indices()
+                .first()
+                .map(|idx| idx + 1)
+                .unwrap_or(1);
+            writeln!(
+                writer,
+                "{:>6} {:<8} {:>6} RESIDUE {:>4} {:<4}",
+                position + 1,
+                if residue.name.is_empty() {
+                    NO_NAME
+                } else {
+                    &residue.name
+                },
+                root,
+                1,
+                if chain.is_empty() { NO_NAME } else { chain }
+            )?;
+        }
+    }
+
+    return Ok(());
+}
+
+/** Unit tests for the above module */
+#[cfg(test)]
+mod tests {
+    use super::Mol2Reader;
+    use crate::atom::AtomOperations;
+    use crate::atom::GroupKind;
+    use crate::bond::BondType;
+    use crate::molecule::builder::MoleculeBuilder;
+    use crate::property::AtomProperty;
+    use crate::property::Property;
+
+    const ACETANILIDE: &str = "\
+# written by hand
+@<TRIPOS>MOLECULE
+acetanilide
+ 12 12 2 0 0
+SMALL
+GASTEIGER
+****
+N-phenylacetamide, no hydrogens
+
+@<TRIPOS>ATOM
+      1 C1          0.0000     1.3950     0.0000 C.ar      1 PHE1      -0.0500
+      2 C2          1.2081     0.6975     0.0000 C.ar      1 PHE1      -0.0500
+      3 C3          1.2081    -0.6975     0.0000 C.ar      1 PHE1      -0.0500
+      4 C4          0.0000    -1.3950     0.0000 C.ar      1 PHE1       0.0400
+      5 C5         -1.2081    -0.6975     0.0000 C.ar      1 PHE1      -0.0500
+      6 C6         -1.2081     0.6975     0.0000 C.ar      1 PHE1      -0.0500
+      7 N1          0.0000    -2.8050     0.0000 N.am      2 ACE2      -0.3100
+      8 C7          1.1500    -3.5500     0.0000 C.2       2 ACE2       0.2200
+      9 O1          2.2500    -3.0000     0.0000 O.2       2 ACE2      -0.2700
+     10 C8          1.0000    -5.0500     0.0000 C.3       2 ACE2       0.0300
+     11 Du1        -3.0000     0.0000     0.0000 Du        2 ACE2       0.0000
+     12 Cl1         3.0000     3.0000     0.0000 Cl        2 ACE2      -0.0100
+@<TRIPOS>BOND
+     1     1     2 ar
+     2     2     3 ar
+     3     3     4 ar
+     4     4     5 ar
+     5     5     6 ar
+     6     6     1 ar
+     7     4     7 1
+     8     7     8 am
+     9     8     9 2
+    10     8    10 1
+    11     6    11 nc
+    12     2    12 1
+@<TRIPOS>SUBSTRUCTURE
+     1 PHE1        1 RESIDUE    1 A
+     2 ACE2        7 RESIDUE    1 ****
+";
+
+    #[test]
+    fn mol2_read() {
+        let mut mol = super::read(ACETANILIDE.as_bytes()).unwrap();
+
+        assert_eq!(mol.name, "acetanilide".to_string());
+        assert_eq!(mol.remark, "N-phenylacetamide, no hydrogens".to_string());
+        assert_eq!(
+            mol.get_property("charge_type"),
+            Some(&Property::Text("GASTEIGER".to_string()))
+        );
+        assert_eq!(mol.get_number_of_atoms(), 12);
+        assert_eq!(mol.get_atom(6).charge, -0.31);
+        assert_eq!(mol.get_atom(11).symbol, "Cl".to_string());
+        assert_eq!(mol.get_atom(10).symbol, "Du".to_string());
+        assert_eq!(mol.get_number_of_bonds(), 11);
+        assert_eq!(mol.get_bond_type(0, 1), BondType::AROMATIC);
+        assert_eq!(mol.get_bond_type(6, 7), BondType::SINGLE);
+
+        let groups = mol.get_groups();
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].name, "ACE2".to_string());
+        assert_eq!(groups[0].kind, GroupKind::RESIDUE);
+        assert_eq!(groups[1].name, "A".to_string());
+        assert_eq!(groups[1].kind, GroupKind::CHAIN);
+        assert_eq!(groups[1].get_all_atom_indices(), vec![0, 1, 2, 3, 4, 5]);
+
+        match mol.get_atom_property("sybyl_type") {
+            Some(AtomProperty::Texts(values)) => assert_eq!(values[6], "N.am"),
+            _ => panic!("expected SYBYL types"),
+        }
+    }
+
+    #[test]
+    fn mol2_round_trip() {
+        let mut mol = super::read(ACETANILIDE.as_bytes()).unwrap();
+
+        // without the given types, the perceived ones are the same
+        mol.remove_atom_property("sybyl_type");
+
+        let mut buffer = Vec::new();
+        super::write(&mut buffer, &mol).unwrap();
+        let text = String::from_utf8(buffer).unwrap();
+        assert!(text.contains("     8     7     8 am\n"));
+
+        let read_mol = super::read(text.as_bytes()).unwrap();
+        assert_eq!(read_mol.name, mol.name);
+        assert_eq!(read_mol.remark, mol.remark);
+        assert_eq!(read_mol.get_atoms(), mol.get_atoms());
+        assert_eq!(read_mol.get_bonds(), mol.get_bonds());
+        assert_eq!(read_mol.get_groups(), mol.get_groups());
+        assert_eq!(
+            read_mol.get_atom_property("sybyl_type"),
+            super::read(ACETANILIDE.as_bytes())
+                .unwrap()
+                .get_atom_property("sybyl_type")
+        );
+    }
+
+    #[test]
+    fn mol2_sybyl_types() {
+        // acetate, methylamine and acetonitrile, with bond orders
+        let mol = MoleculeBuilder::new("mixture")
+            .atom("C", 0.0, 0.0, 0.0)
+            .atom("C", 1.5, 0.0, 0.0)
+            .atom("O", 2.1, 1.1, 0.0)
+            .atom("O", 2.1, -1.1, 0.0)
+            .atom("N", 5.0, 0.0, 0.0)
+            .atom("C", 6.5, 0.0, 0.0)
+            .atom("C", 9.0, 0.0, 0.0)
+            .atom("C", 10.5, 0.0, 0.0)
+            .atom("N", 11.7, 0.0, 0.0)
+            .bonds(&[(0, 1), (1, 3), (4, 5), (6, 7)])
+            .bond(1, 2, BondType::DOUBLE)
+            .bond(7, 8, BondType::TRIPLE)
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            super::get_sybyl_types(&mol),
+            vec!["C.3", "C.2", "O.co2", "O.co2", "N.3", "C.3", "C.3", "C.1", "N.1"]
+        );
+
+        // formaldehyde from coordinates only, the missing neighbours make it sp2
+        let mut mol = MoleculeBuilder::new("formaldehyde")
+            .xyz("C 0.0 0.0 0.0\nO 0.0 0.0 1.21\nH 0.0 0.94 -0.59\nH 0.0 -0.94 -0.59")
+            .build()
+            .unwrap();
+        mol.compute_covalent_bonds(crate::molecule::DEFAULT_BOND_TOLERANCE);
+        assert_eq!(super::get_sybyl_types(&mol), vec!["C.2", "O.2", "H", "H"]);
+    }
+
+    #[test]
+    fn mol2_molecules() {
+        let text = format!(
+            "{}{}",
+            ACETANILIDE,
+            ACETANILIDE.replace("acetanilide\n", "second\n")
+        );
+        let names: Vec<String> = Mol2Reader::new(text.as_bytes())
+            .map(|mol| mol.unwrap().name)
+            .collect();
+        assert_eq!(names, vec!["acetanilide".to_string(), "second".to_string()]);
+
+        let text = ACETANILIDE.replace("    10     8    10 1", "    10     8    13 1");
+        assert_eq!(
+            super::read(text.as_bytes()).unwrap_err().to_string(),
+            "mol2: line 33: bond refers to a missing atom 13"
+        );
+    }
+}
@@ -0,0 +1,705 @@
+/**
+ * Author: V. Ganesh
+ * License: MIT
+ */
+
+/**
+ * molden module, reads Molden (.molden) files.
+ *
+ * A Molden file is a list of sections, each starting with a [Name] line. The [Atoms]
+ * section gives the geometry (in bohr with AU, in angstrom with Angs), [GTO] the
+ * contracted Gaussian shells on each atom, [MO] the molecular orbitals as coefficients of
+ * the basis functions, and [FREQ], [FR-COORD], [FR-NORM-COORD] and [INT] the vibrational
+ * frequencies (cm^-1), the geometry they belong to (bohr), the normal modes and their IR
+ * intensities. The [5D], [5D7F], [5D10F], [7F] and [9G] lines mark spherical functions,
+ * the basis is Cartesian without them. Other sections are skipped.
+ *
+ * The molecule is read from [Atoms], or from [FR-COORD] if there is no [Atoms] section,
+ * and named after the first line of [Title]. The [MO] section must come after [GTO], the
+ * coefficients of an orbital are numbered up to the number of basis functions and the
+ * ones left out are 0.
+ */
+use std::io::BufRead;
+
+use crate::atom::Atom;
+use crate::atom::AtomOperations;
+use crate::atomprop;
+use crate::io::gaussian::log::VibrationalMode;
+use crate::io::IoError;
+use crate::io::LineReader;
+use crate::io::BOHR;
+use crate::molecule::Molecule;
+use crate::point::Point;
+
+const FORMAT: &str = "molden";
+
+/** Shell - a contracted Gaussian shell on an atom */
+#[allow(dead_code)]
+#[derive(Clone, Default, PartialEq, Debug)]
+pub struct Shell {
+    /** the index of the atom the shell is on, 0 based */
+    pub atom: usize,
+    /** the angular momentum, "s", "p", "sp", "d", "f" or "g" */
+    pub kind: String,
+    pub exponents: Vec<f64>,
+    pub coefficients: Vec<f64>,
+    /** the p coefficients of an sp shell, empty for other shells */
+    pub p_coefficients: Vec<f64>,
+}
+
+#[allow(dead_code)]
+impl Shell {
+    /** get_angular_momentum() returns the angular momentum, None for an sp shell */
+    pub fn get_angular_momentum(&self) -> Option<usize> {
+        return ["s", "p", "d", "f", "g"]
+            .iter()
+            .position(|kind| *kind == self.kind);
+    }
+}
+
+/** BasisSet - the shells of a Molden file, and which of them are spherical */
+#[allow(dead_code)]
+#[derive(Clone, Default, PartialEq, Debug)]
+pub struct BasisSet {
+    pub shells: Vec<Shell>,
+    pub spherical_d: bool,
+    pub spherical_f: bool,
+    pub spherical_g: bool,
+}
+
+#[allow(dead_code)]
+impl BasisSet {
+    /** get_number_of_functions() returns the number of basis functions, as the [MO] coefficients count them */
+    pub fn get_number_of_functions(&self) -> usize {
+        return self
+            .shells
+            .iter()
+            .map(|shell| match shell.kind.as_str() {
+                "sp" => 4,
+                "d" if self.spherical_d => 5,
+                "f" if self.spherical_f => 7,
+                "g" if self.spherical_g => 9,
+                _ => {
+                    // a Cartesian shell of angular momentum l has (l + 1)(l + 2)/2 functions
+                    let l = shell.get_angular_momentum().unwrap_or(0);
+                    (l + 1) * (l + 2) / 2
+                }
+            })
+            .sum();
+    }
+}
+
+/** Spin - the spin of a molecular orbital */
+#[allow(dead_code)]
+#[derive(Default, Clone, Copy, PartialEq, Debug)]
+pub enum Spin {
+    #[default]
+    ALPHA,
+    BETA,
+}
+
+/** Orbital - a molecular orbital, with a coefficient for each basis function */
+#[allow(dead_code)]
+#[derive(Clone, Default, PartialEq, Debug)]
+pub struct Orbital {
+    pub energy: f64,
+    pub symmetry: String,
+    pub spin: Spin,
+    pub occupation: f64,
+    pub coefficients: Vec<f64>,
+}
+
+/**
+ * Molden - the contents of a Molden file. The normal modes have a frequency, an IR
+ * intensity if there is an [INT] section and the displacement of each atom, the other
+ * fields of VibrationalMode are not in Molden files and are left empty
+ */
+#[allow(dead_code)]
+#[derive(Clone, Debug)]
+pub struct Molden {
+    pub molecule: Molecule,
+    pub basis: BasisSet,
+    pub orbitals: Vec<Orbital>,
+    pub modes: Vec<VibrationalMode>,
+}
+
+#[allow(dead_code)]
+impl Molden {
+    /** get_orbitals() returns the orbitals of the given spin */
+    pub fn get_orbitals(&self, spin: Spin) -> Vec<&Orbital> {
+        return self
+            .orbitals
+            .iter()
+            .filter(|orbital| orbital.spin == spin)
+            .collect();
+    }
+
+    /** get_homo() returns the index of the highest occupied orbital of the given spin */
+    pub fn get_homo(&self, spin: Spin) -> Option<usize> {
+        return self
+            .orbitals
+            .iter()
+            .enumerate()
+            .filter(|(_, orbital)| orbital.spin == spin && orbital.occupation > 0.0)
+            .max_by(|(_, a), (_, b)| a.energy.total_cmp(&b.energy))
+            .map(|(idx, _)| idx);
+    }
+}
+
+/** parse_number() parses a number, which may have a Fortran D exponent */
+fn parse_number(text: &str) -> Option<f64> {
+    return text.replace(['D', 'd'], "E").parse().ok();
+}
+
+/** parse_numbers() parses the numbers of a line, from the given field on */
+fn parse_numbers(line: &str, skip: usize) -> Result<Vec<f64>, String> {
+    return line
+        .split_whitespace()
+        .skip(skip)
+        .map(|field| parse_number(field).ok_or_else(|| format!("invalid number \"{}\"", field)))
+        .collect();
+}
+
+/** get_section() returns the lowercase name of a [Name] line and the text after it */
+fn get_section(line: &str) -> Option<(String, String)> {
+    let line = line.trim();
+    let rest = line.strip_prefix('[')?;
+    let end = rest.find(']')?;
+    return Some((
+        rest[..end].trim().to_lowercase(),
+        rest[end + 1..].to_string(),
+    ));
+}
+
+/** next_section_line() returns the next line of the section, None at the next section */
+fn next_section_line<R: BufRead>(reader: &mut LineReader<R>) -> Result<Option<String>, IoError> {
+    match reader.peek_line()? {
+        Some(line) if get_section(line).is_some() => return Ok(None),
+        Some(_) => return reader.next_line(),
+        None => return Ok(None),
+    }
+}
+
+/** new_atom() returns an atom at a position given in the units of the scale */
+fn new_atom(symbol: &str, position: &[f64], scale: f64) -> Atom {
+    return Atom {
+        center: Point {
+            x: (position[0] * scale) as f32,
+            y: (position[1] * scale) as f32,
+            z: (position[2] * scale) as f32,
+        },
+        charge: 0.0,
+        symbol: symbol.to_string(),
+        remark: "".to_string(),
+    };
+}
+
+/** read_atoms() reads the [Atoms] section, lines of name, number, atomic number and position */
+fn read_atoms<R: BufRead>(
+    reader: &mut LineReader<R>,
+    units: &str,
+    mol: &mut Molecule,
+) -> Result<(), IoError> {
+    let scale = if units.to_lowercase().contains("au") {
+        BOHR
+    } else {
+        1.0
+    };
+
+    while let Some(line) = next_section_line(reader)? {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() < 6 {
+            return Err(reader.error(format!(
+                "expected a name, number, atomic number and position, found \"{}\"",
+                line.trim()
+            )));
+        }
+        let symbol = fields[2]
+            .parse::<u8>()
+            .ok()
+            .and_then(atomprop::get_element_symbol)
+            .ok_or_else(|| reader.error(format!("invalid atomic number \"{}\"", fields[2])))?;
+        let position = parse_numbers(&line, 3).map_err(|message| reader.error(message))?;
+        mol.add_atom(new_atom(symbol, &position, scale));
+    }
+
+    return Ok(());
+}
+
+/** read_basis() reads the shells of the [GTO] section, atom by atom */
+fn read_basis<R: BufRead>(reader: &mut LineReader<R>, basis: &mut BasisSet) -> Result<(), IoError> {
+    let mut atom: Option<usize> = None;
+
+    while let Some(line) = next_section_line(reader)? {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.is_empty() {
+            continue;
+        }
+
+        // a line with the 1 based atom number starts the shells of an atom
+        if let Ok(number) = fields[0].parse::<usize>() {
+            if number == 0 {
+                return Err(reader.error("atoms are numbered from 1".to_string()));
+            }
+            atom = Some(number - 1);
+            continue;
+        }
+
+        let atom = atom.ok_or_else(|| reader.error("a shell comes before its atom".to_string()))?;
+        let kind = fields[0].to_lowercase();
+        if !["s", "p", "sp", "d", "f", "g"].contains(&kind.as_str()) {
+            return Err(reader.error(format!("unknown shell \"{}\"", fields[0])));
+        }
+        let primitives = fields
+            .get(1)
+            .and_then(|field| field.parse::<usize>().ok())
+            .ok_or_else(|| reader.error(format!("expected a shell, found \"{}\"", line.trim())))?;
+
+        let mut shell = Shell {
+            atom: atom,
+            kind: kind,
+            ..Default::default()
+        };
+        let columns = if shell.kind == "sp" { 3 } else { 2 };
+        for _ in 0..primitives {
+            let line = reader.expect_line("a primitive of the shell")?;
+            let numbers = parse_numbers(&line, 0).map_err(|message| reader.error(message))?;
+            if numbers.len() < columns {
+                return Err(reader.error(format!(
+                    "expected {} numbers, found \"{}\"",
+                    columns,
+                    line.trim()
+                )));
+            }
+            shell.exponents.push(numbers[0]);
+            shell.coefficients.push(numbers[1]);
+            if columns == 3 {
+                shell.p_coefficients.push(numbers[2]);
+            }
+        }
+        basis.shells.push(shell);
+    }
+
+    return Ok(());
+}
+
+/**
+ * read_orbitals() reads the [MO] section, keyword lines followed by coefficient lines
+ * numbered from 1 to the number of basis functions
+ */
+fn read_orbitals<R: BufRead>(
+    reader: &mut LineReader<R>,
+    functions: usize,
+    orbitals: &mut Vec<Orbital>,
+) -> Result<(), IoError> {
+    let mut orbital: Option<Orbital> = None;
+    let mut has_coefficients = false;
+
+    while let Some(line) = next_section_line(reader)? {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        if let Some((key, value)) = line.split_once('=') {
+            // the first keyword after the coefficients starts the next orbital
+            if has_coefficients || orbital.is_none() {
+                orbitals.extend(orbital.take());
+                orbital = Some(Orbital::default());
+                has_coefficients = false;
+            }
+            let current = orbital.as_mut().unwrap();
+            let value = value.trim();
+            let number = || {
+                parse_number(value)
+                    .ok_or_else(|| reader.error(format!("invalid {} \"{}\"", key.trim(), value)))
+            };
+            match key.trim().to_lowercase().as_str() {
+                "sym" => current.symmetry = value.to_string(),
+                "ene" => current.energy = number()?,
+                "occup" => current.occupation = number()?,
+                "spin" if value.eq_ignore_ascii_case("beta") => current.spin = Spin::BETA,
+                "spin" => current.spin = Spin::ALPHA,
+                _ => {}
+            }
+            continue;
+        }
+
+        let current = orbital
+            .as_mut()
+            .ok_or_else(|| reader.error("a coefficient comes before its orbital".to_string()))?;
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let index = fields.first().and_then(|field| field.parse::<usize>().ok());
+        let coefficient = fields.get(1).and_then(|field| parse_number(field));
+        match (index, coefficient) {
+            (Some(index), Some(_)) if index > functions => {
+                return Err(reader.error(format!(
+                    "function {} is beyond the {} basis functions",
+                    index, functions
+                )))
+            }
+            (Some(index), Some(coefficient)) if index > 0 => {
+                // small coefficients may be left out
+                if current.coefficients.len() < index {
+                    current.coefficients.resize(index, 0.0);
+                }
+                current.coefficients[index - 1] = coefficient;
+                has_coefficients = true;
+            }
+            _ => {
+                return Err(reader.error(format!(
+                    "expected a function number and coefficient, found \"{}\"",
+                    line.trim()
+                )))
+            }
+        }
+    }
+    orbitals.extend(orbital);
+
+    return Ok(());
+}
+
+/** read_single_values() reads the first number of each line, for [FREQ] and [INT] */
+fn read_single_values<R: BufRead>(reader: &mut LineReader<R>) -> Result<Vec<f64>, IoError> {
+    let mut values = Vec::new();
+    while let Some(line) = next_section_line(reader)? {
+        if let Some(field) = line.split_whitespace().next() {
+            let value = parse_number(field)
+                .ok_or_else(|| reader.error(format!("invalid number \"{}\"", field)))?;
+            values.push(value);
+        }
+    }
+    return Ok(values);
+}
+
+/** read_normal_modes() reads the [FR-NORM-COORD] section, a vibration line and a displacement per atom */
+fn read_normal_modes<R: BufRead>(
+    reader: &mut LineReader<R>,
+) -> Result<Vec<Vec<[f64; 3]>>, IoError> {
+    let mut modes: Vec<Vec<[f64; 3]>> = Vec::new();
+
+    while let Some(line) = next_section_line(reader)? {
+        if line.trim().is_empty() {
+            continue;
+        }
+        if line.trim().to_lowercase().starts_with("vibration") {
+            modes.push(Vec::new());
+            continue;
+        }
+
+        let numbers = parse_numbers(&line, 0).map_err(|message| reader.error(message))?;
+        let mode = modes
+            .last_mut()
+            .ok_or_else(|| reader.error("a displacement comes before its vibration".to_string()))?;
+        if numbers.len() < 3 {
+            return Err(reader.error(format!(
+                "expected a displacement, found \"{}\"",
+                line.trim()
+            )));
+        }
+        mode.push([numbers[0], numbers[1], numbers[2]]);
+    }
+
+    return Ok(modes);
+}
+
+/** read() reads a Molden file */
+pub fn read<R: BufRead>(reader: R) -> Result<Molden, IoError> {
+    let mut reader = LineReader::new(reader, FORMAT);
+    let mut molden = Molden {
+        molecule: Molecule::new("".to_string(), "".to_string()),
+        basis: BasisSet::default(),
+        orbitals: Vec::new(),
+        modes: Vec::new(),
+    };
+    let mut title = String::new();
+    let mut has_atoms = false;
+    let mut frequency_atoms: Vec<Atom> = Vec::new();
+    let mut frequencies = Vec::new();
+    let mut intensities = Vec::new();
+    let mut displacements = Vec::new();
+
+    while let Some(line) = reader.next_line()? {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let (section, rest) = get_section(&line).ok_or_else(|| {
+            reader.error(format!("expected a section, found \"{}\"", line.trim()))
+        })?;
+
+        match section.as_str() {
+            "title" => {
+                while let Some(line) = next_section_line(&mut reader)? {
+                    if title.is_empty() {
+                        title = line.trim().to_string();
+                    }
+                }
+            }
+            "atoms" => {
+                read_atoms(&mut reader, &rest, &mut molden.molecule)?;
+                has_atoms = true;
+            }
+            "gto" => read_basis(&mut reader, &mut molden.basis)?,
+            "mo" => {
+                let functions = molden.basis.get_number_of_functions();
+                if functions == 0 {
+                    return Err(reader.error("the [MO] section comes before [GTO]".to_string()));
+                }
+                read_orbitals(&mut reader, functions, &mut molden.orbitals)?;
+            }
+            "5d" | "5d7f" => {
+                molden.basis.spherical_d = true;
+                molden.basis.spherical_f = true;
+            }
+            "5d10f" => molden.basis.spherical_d = true,
+            "7f" => molden.basis.spherical_f = true,
+            "9g" => molden.basis.spherical_g = true,
+            "freq" => frequencies = read_single_values(&mut reader)?,
+            "int" => intensities = read_single_values(&mut reader)?,
+            "fr-coord" => {
+                while let Some(line) = next_section_line(&mut reader)? {
+                    let fields: Vec<&str> = line.split_whitespace().collect();
+                    if fields.is_empty() {
+                        continue;
+                    }
+                    let position =
+                        parse_numbers(&line, 1).map_err(|message| reader.error(message))?;
+                    if position.len() < 3 {
+                        return Err(reader.error(format!(
+                            "expected a symbol and position, found \"{}\"",
+                            line.trim()
+                        )));
+                    }
+                    let symbol = atomprop::get_atomic_number(fields[0])
+                        .and_then(atomprop::get_element_symbol)
+                        .ok_or_else(|| {
+                            reader.error(format!("unknown element \"{}\"", fields[0]))
+                        })?;
+                    frequency_atoms.push(new_atom(symbol, &position, BOHR));
+                }
+            }
+            "fr-norm-coord" => displacements = read_normal_modes(&mut reader)?,
+            _ => while next_section_line(&mut reader)?.is_some() {},
+        }
+    }
+
+    if displacements.len() != frequencies.len() {
+        return Err(IoError::parse(
+            FORMAT,
+            0,
+            format!(
+                "{} frequencies but {} normal modes",
+                frequencies.len(),
+                displacements.len()
+            ),
+        ));
+    }
+    for (idx, (frequency, displacements)) in frequencies.iter().zip(displacements).enumerate() {
+        molden.modes.push(VibrationalMode {
+            frequency: *frequency,
+            ir_intensity: intensities.get(idx).copied().unwrap_or(0.0),
+            displacements: displacements,
+            ..Default::default()
+        });
+    }
+
+    // [5D] and the like may follow [MO], the number of functions is known at the end
+    let functions = molden.basis.get_number_of_functions();
+    for orbital in molden.orbitals.iter_mut() {
+        if orbital.coefficients.len() > functions {
+            return Err(IoError::parse(
+                FORMAT,
+                0,
+                format!(
+                    "an orbital has {} coefficients but there are {} basis functions",
+                    orbital.coefficients.len(),
+                    functions
+                ),
+            ));
+        }
+        orbital.coefficients.resize(functions, 0.0);
+    }
+
+    if !has_atoms {
+        for atom in frequency_atoms {
+            molden.molecule.add_atom(atom);
+        }
+    }
+    molden.molecule.name = title;
+
+    return Ok(molden);
+}
+
+/** Unit tests for the above module */
+#[cfg(test)]
+mod tests {
+    use crate::io::molden::Spin;
+    use crate::io::BOHR;
+
+    const WATER: &str = "[Molden Format]
+[Title]
+ water HF/STO-3G
+
+[Atoms] AU
+O     1    8     0.0000000000     0.0000000000     0.2217000000
+H     2    1     0.0000000000     1.4305000000    -0.8866000000
+H     3    1     0.0000000000    -1.4305000000    -0.8866000000
+[GTO]
+  1 0
+ s    3 1.00
+  0.1307093214D+03  0.1543289673D+00
+  0.2380886605D+02  0.5353281423D+00
+  0.6443608313D+01  0.4446345422D+00
+ sp   3 1.00
+  0.5033151319D+01 -0.9996722919D-01  0.1559162750D+00
+  0.1169596125D+01  0.3995128261D+00  0.6076837186D+00
+  0.3803889600D+00  0.7001154689D+00  0.3919573931D+00
+
+  2 0
+ s    3 1.00
+  0.3425250914D+01  0.1543289673D+00
+  0.6239137298D+00  0.5353281423D+00
+  0.1688554040D+00  0.4446345422D+00
+
+  3 0
+ s    3 1.00
+  0.3425250914D+01  0.1543289673D+00
+  0.6239137298D+00  0.5353281423D+00
+  0.1688554040D+00  0.4446345422D+00
+
+[MO]
+ Sym= A1
+ Ene= -20.2516
+ Spin= Alpha
+ Occup= 2.0
+   1  0.994123
+   2  0.025835
+   7 -0.005838
+ Sym= B2
+ Ene= 0.6036
+ Spin= Alpha
+ Occup= 0.0
+   4  0.952
+   6  0.703
+   7 -0.703
+ Sym= A1
+ Ene= -20.10
+ Spin= Beta
+ Occup= 1.0
+   1  1.0
+[FREQ]
+ 1750.12
+ 4140.33
+[FR-COORD]
+ O     0.0000000000     0.0000000000     0.2217000000
+ H     0.0000000000     1.4305000000    -0.8866000000
+ H     0.0000000000    -1.4305000000    -0.8866000000
+[FR-NORM-COORD]
+ vibration      1
+  0.000  0.000 -0.070
+  0.000 -0.430  0.560
+  0.000  0.430  0.560
+ vibration      2
+  0.000  0.000  0.050
+  0.000  0.580 -0.400
+  0.000 -0.580 -0.400
+[INT]
+ 80.1
+ 2.3
+";
+
+    #[test]
+    fn molden_read() {
+        let molden = super::read(WATER.as_bytes()).unwrap();
+        let mol = &molden.molecule;
+        assert_eq!(mol.name, "water HF/STO-3G");
+        assert_eq!(mol.get_atoms().len(), 3);
+        assert_eq!(mol.get_atoms()[0].symbol, "O");
+        assert!((mol.get_atoms()[1].center.y as f64 - 1.4305 * BOHR).abs() < 1e-6);
+
+        let basis = &molden.basis;
+        assert_eq!(basis.shells.len(), 4);
+        assert_eq!(basis.shells[1].kind, "sp");
+        assert_eq!(basis.shells[1].get_angular_momentum(), None);
+        assert_eq!(basis.shells[1].p_coefficients.len(), 3);
+        assert_eq!(basis.shells[3].atom, 2);
+        assert!((basis.shells[0].exponents[0] - 130.7093214).abs() < 1e-9);
+        assert_eq!(basis.get_number_of_functions(), 7);
+
+        assert_eq!(molden.orbitals.len(), 3);
+        let orbital = &molden.orbitals[1];
+        assert_eq!(orbital.symmetry, "B2");
+        assert_eq!(
+            orbital.coefficients,
+            vec![0.0, 0.0, 0.0, 0.952, 0.0, 0.703, -0.703]
+        );
+        assert_eq!(molden.orbitals[0].coefficients.len(), 7);
+        assert_eq!(
+            molden.orbitals[2].coefficients,
+            vec![1.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0]
+        );
+        assert_eq!(molden.get_orbitals(Spin::BETA).len(), 1);
+        assert_eq!(molden.get_homo(Spin::ALPHA), Some(0));
+        assert_eq!(molden.get_homo(Spin::BETA), Some(2));
+
+        assert_eq!(molden.modes.len(), 2);
+        assert_eq!(molden.modes[1].frequency, 4140.33);
+        assert_eq!(molden.modes[0].ir_intensity, 80.1);
+        assert_eq!(molden.modes[1].displacements[2], [0.0, -0.58, -0.4]);
+    }
+
+    #[test]
+    fn molden_frequencies_only() {
+        // ORCA style files may only have the vibrations, with spherical functions
+        let start = WATER.find("[FREQ]").unwrap();
+        let text = format!("[Molden Format]\n[5D7F]\n[9G]\n{}", &WATER[start..]);
+        let molden = super::read(text.as_bytes()).unwrap();
+        assert_eq!(molden.molecule.get_atoms().len(), 3);
+        assert_eq!(molden.molecule.get_atoms()[1].symbol, "H");
+        assert!(molden.basis.spherical_d && molden.basis.spherical_f && molden.basis.spherical_g);
+        assert_eq!(molden.modes.len(), 2);
+
+        let text = WATER.replace(" 4140.33\n", "");
+        let err = super::read(text.as_bytes()).unwrap_err();
+        assert_eq!(err.to_string(), "molden: 1 frequencies but 2 normal modes");
+
+        let text = WATER.replace(" sp   3 1.00", " h    3 1.00");
+        let err = super::read(text.as_bytes()).unwrap_err();
+        assert_eq!(err.to_string(), "molden: line 15: unknown shell \"h\"");
+
+        let text = WATER.replace("   2  0.025835", "   2  0.02x");
+        let err = super::read(text.as_bytes()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "molden: line 38: expected a function number and coefficient, found \"2  0.02x\""
+        );
+
+        let text = WATER.replace("   7 -0.005838", "   9000000000 -0.005838");
+        let err = super::read(text.as_bytes()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "molden: line 39: function 9000000000 is beyond the 7 basis functions"
+        );
+
+        let start = WATER.find("[MO]").unwrap();
+        let text = format!("[Molden Format]\n{}", &WATER[start..]);
+        let err = super::read(text.as_bytes()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "molden: line 2: the [MO] section comes before [GTO]"
+        );
+
+        // a [5D] after [MO] leaves fewer functions than the coefficients numbered
+        let text = WATER
+            .replace(" sp   3", " d    3")
+            .replace("   7 -0.703", "   9 -0.703")
+            .replace("[FREQ]", "[5D]\n[FREQ]");
+        let err = super::read(text.as_bytes()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "molden: an orbital has 9 coefficients but there are 8 basis functions"
+        );
+    }
+}
@@ -0,0 +1,843 @@
+/**
+ * Author: V. Ganesh
+ * License: MIT
+ */
+
+/**
+ * pdb module, reads and writes Protein Data Bank files.
+ *
+ * Each MODEL becomes a model group, holding chain groups, holding residue groups
+ * (named by the residue name, with the residue number and insertion code as remark).
+ * The atom name, alternate location, occupancy, B-factor and whether an atom is a
+ * HETATM are kept as atom properties. CONECT records become bonds, a bonded atom listed
+ * twice or three times in a record makes a double or triple bond.
+ *
+ * Files from older software are read as far as possible: short lines, missing element
+ * columns (the element is then taken from the atom name), missing ENDMDL or END records,
+ * and CONECT records naming atoms that are not in the file.
+ */
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::io::BufRead;
+use std::io::Write;
+
+use crate::atom::Atom;
+use crate::atom::AtomGroup;
+use crate::atom::AtomOperations;
+use crate::atom::GroupKind;
+use crate::atomprop;
+use crate::bond::BondType;
+use crate::io::IoError;
+use crate::io::LineReader;
+use crate::lattice::Lattice;
+use crate::molecule::Molecule;
+use crate::point::Point;
+use crate::property::AtomProperty;
+use crate::property::Property;
+
+const FORMAT: &str = "pdb";
+
+/** residue name written for atoms that are not in a residue group (unknown ligand) */
+pub(crate) const UNKNOWN_RESIDUE: &str = "UNL";
+
+/** column() returns the columns start..end (0 based) of a line, short lines give "" */
+pub(crate) fn column(line: &str, start: usize, end: usize) -> &str {
+    let end = end.min(line.len());
+    if start >= end {
+        return "";
+    }
+    return line.get(start..end).unwrap_or("");
+}
+
+/** PdbBuilder - collects the atoms, groups and bonds of the records read so far */
+struct PdbBuilder {
+    mol: Molecule,
+    title: Vec<String>,
+    atom_names: Vec<String>,
+    alt_locs: Vec<String>,
+    occupancies: Vec<f64>,
+    b_factors: Vec<f64>,
+    hetatms: Vec<bool>,
+    models: Vec<AtomGroup>,
+    model_name: Option<String>,
+    chains: Vec<AtomGroup>,
+    residue: Option<(String, String, String, AtomGroup)>,
+    serials: HashMap<i64, Vec<usize>>,
+    connections: BTreeMap<(i64, i64), usize>,
+}
+
+impl PdbBuilder {
+    fn new() -> PdbBuilder {
+        PdbBuilder {
+            mol: Molecule::new("".to_string(), "".to_string()),
+            title: Vec::new(),
+            atom_names: Vec::new(),
+            alt_locs: Vec::new(),
+            occupancies: Vec::new(),
+            b_factors: Vec::new(),
+            hetatms: Vec::new(),
+            models: Vec::new(),
+            model_name: None,
+            chains: Vec::new(),
+            residue: None,
+            serials: HashMap::new(),
+            connections: BTreeMap::new(),
+        }
+    }
+
+    /** end_residue() moves the current residue into its chain */
+    fn end_residue(&mut self) {
+        if let Some((chain_name, _, _, residue)) = self.residue.take() {
+            match self.chains.iter_mut().find(|c| c.name == chain_name) {
+                Some(chain) => chain.add_group(residue),
+                None => {
+                    let mut chain =
+                        AtomGroup::new(chain_name, "".to_string()).with_kind(GroupKind::CHAIN);
+                    chain.add_group(residue);
+                    self.chains.push(chain);
+                }
+            }
+        }
+    }
+
+    /** end_model() moves the chains read so far into the current model, if there is one */
+    fn end_model(&mut self) {
+        self.end_residue();
+
+        if let Some(name) = self.model_name.take() {
+            let mut model = AtomGroup::new(name, "".to_string()).with_kind(GroupKind::MODEL);
+            for chain in self.chains.drain(..) {
+                model.add_group(chain);
+            }
+            self.models.push(model);
+        }
+    }
+
+    fn add_atom(&mut self, line: &str, is_hetatm: bool) -> Result<(), String> {
+        let coordinate = |start: usize, end: usize| {
+            let field = column(line, start, end).trim();
+            return field.parse::<f32>().map_err(|_| {
+                format!(
+                    "expected a coordinate in columns {}-{}, found \"{}\"",
+                    start + 1,
+                    end,
+                    field
+                )
+            });
+        };
+
+        let center = Point {
+            x: coordinate(30, 38)?,
+            y: coordinate(38, 46)?,
+            z: coordinate(46, 54)?,
+        };
+
+        let name = column(line, 12, 16);
+        let residue_name = column(line, 17, 21).trim().to_string();
+        let chain_name = column(line, 21, 22).trim().to_string();
+        let residue_number = column(line, 22, 27).trim().to_string();
+
+        let index = self.mol.get_atoms().len();
+        self.mol.add_atom(Atom {
+            center: center,
+            charge: parse_charge(column(line, 78, 80)),
+            symbol: guess_element(column(line, 76, 78), name, is_hetatm),
+            remark: "".to_string(),
+        });
+
+        self.atom_names.push(name.trim().to_string());
+        self.alt_locs.push(column(line, 16, 17).trim().to_string());
+        self.occupancies
+            .push(column(line, 54, 60).trim().parse().unwrap_or(1.0));
+        self.b_factors
+            .push(column(line, 60, 66).trim().parse().unwrap_or(0.0));
+        self.hetatms.push(is_hetatm);
+
+        if let Ok(serial) = column(line, 6, 11).trim().parse::<i64>() {
+            self.serials.entry(serial).or_default().push(index);
+        }
+
+        let same_residue = match &self.residue {
+            Some((chain, name, number, _)) => {
+                *chain == chain_name && *name == residue_name && *number == residue_number
+            }
+            None => false,
+        };
+
+        if !same_residue {
+            self.end_residue();
+            let residue = AtomGroup::new(residue_name.to_string(), residue_number.to_string())
+                .with_kind(GroupKind::RESIDUE);
+            self.residue = Some((chain_name, residue_name, residue_number, residue));
+        }
+
+        if let Some((_, _, _, residue)) = self.residue.as_mut() {
+            residue.add_atom_index(index);
+        }
+
+        return Ok(());
+    }
+
+    fn add_connections(&mut self, line: &str) {
+        let atom = match column(line, 6, 11).trim().parse::<i64>() {
+            Ok(serial) => serial,
+            Err(_) => return,
+        };
+
+        let mut counts: BTreeMap<i64, usize> = BTreeMap::new();
+        for start in [11, 16, 21, 26] {
+            if let Ok(serial) = column(line, start, start + 5).trim().parse::<i64>() {
+                *counts.entry(serial).or_default() += 1;
+            }
+        }
+
+        for (serial, count) in counts {
+            if serial != atom {
+                let key = (atom.min(serial), atom.max(serial));
+                let order = self.connections.entry(key).or_default();
+                *order = (*order).max(count);
+            }
+        }
+    }
+
+    fn build(mut self) -> Molecule {
+        self.end_model();
+
+        let mut mol = self.mol;
+        mol.remark = self.title.join(" ");
+
+        for model in self.models {
+            mol.add_group(model);
+        }
+        for chain in self.chains {
+            mol.add_group(chain);
+        }
+
+        let mut bonds = Vec::new();
+        for ((serial_1, serial_2), count) in self.connections {
+            if let (Some(atoms_1), Some(atoms_2)) =
+                (self.serials.get(&serial_1), self.serials.get(&serial_2))
+            {
+                // serials restart in each model of an ensemble, so bond the atoms model by model
+                for (atom_1, atom_2) in atoms_1.iter().zip(atoms_2.iter()) {
+                    bonds.push((*atom_1.min(atom_2), *atom_1.max(atom_2), count));
+                }
+            }
+        }
+
+        bonds.sort();
+        for (atom_1, atom_2, count) in bonds {
+            let bond_type = match count {
+                1 => BondType::SINGLE,
+                2 => BondType::DOUBLE,
+                _ => BondType::TRIPLE,
+            };
+            mol.add_bond(atom_1, atom_2, bond_type);
+        }
+
+        mol.set_atom_property("atom_name", AtomProperty::Texts(self.atom_names));
+        mol.set_atom_property("alt_loc", AtomProperty::Texts(self.alt_locs));
+        mol.set_atom_property("occupancy", AtomProperty::Reals(self.occupancies));
+        mol.set_atom_property("b_factor", AtomProperty::Reals(self.b_factors));
+        mol.set_atom_property("hetatm", AtomProperty::Logicals(self.hetatms));
+
+        return mol;
+    }
+}
+
+/** read() reads a PDB file, all models of an ensemble are read into the one molecule */
+#[allow(dead_code)]
+pub fn read<R: BufRead>(reader: R) -> Result<Molecule, IoError> {
+    let mut reader = LineReader::new(reader, FORMAT);
+    let mut builder = PdbBuilder::new();
+
+    while let Some(line) = reader.next_line()? {
+        let record = column(&line, 0, 6).trim().to_uppercase();
+
+        match record.as_str() {
+            "HEADER" => {
+                let id_code = column(&line, 62, 66).trim();
+                builder.mol.name = if id_code.is_empty() {
+                    column(&line, 10, 50).trim().to_string()
+                } else {
+                    id_code.to_string()
+                };
+            }
+            "TITLE" => builder.title.push(column(&line, 10, 80).trim().to_string()),
+            "CRYST1" => {
+                let parameters: Vec<f64> =
+                    [(6, 15), (15, 24), (24, 33), (33, 40), (40, 47), (47, 54)]
+                        .iter()
+                        .map(|(start, end)| column(&line, *start, *end).trim().parse())
+                        .collect::<Result<_, _>>()
+                        .map_err(|_| reader.error("expected six cell parameters".to_string()))?;
+
+                // a 1 x 1 x 1 cell is the placeholder used by NMR and modelled structures
+                if parameters[..3] != [1.0, 1.0, 1.0] {
+                    builder.mol.set_lattice(Some(Lattice::from_parameters(
+                        parameters[0],
+                        parameters[1],
+                        parameters[2],
+                        parameters[3],
+                        parameters[4],
+                        parameters[5],
+                    )));
+
+                    let space_group = column(&line, 55, 66).trim();
+                    if !space_group.is_empty() {
+                        builder
+                            .mol
+                            .set_property("space_group", Property::Text(space_group.to_string()));
+                    }
+                }
+            }
+            "MODEL" => {
+                builder.end_model();
+                let number = column(&line, 10, 14).trim();
+                builder.model_name = Some(if number.is_empty() {
+                    (builder.models.len() + 1).to_string()
+                } else {
+                    number.to_string()
+                });
+            }
+            "ENDMDL" => builder.end_model(),
+            "ATOM" | "HETATM" => builder
+                .add_atom(&line, record == "HETATM")
+                .map_err(|message| reader.error(message))?,
+            "TER" => builder.end_residue(),
+            "CONECT" => builder.add_connections(&line),
+            "END" => break,
+            _ => {}
+        }
+    }
+
+    return Ok(builder.build());
+}
+
+/** guess_element() returns the element column, or else the element guessed from the atom name */
+pub(crate) fn guess_element(element: &str, name: &str, is_hetatm: bool) -> String {
+    let normalize = |text: &str| {
+        let mut chars = text.chars();
+        return match chars.next() {
+            Some(first) => first.to_ascii_uppercase().to_string() + &chars.as_str().to_lowercase(),
+            None => "".to_string(),
+        };
+    };
+
+    let element = element.trim();
+    if !element.is_empty() && element.chars().all(|c| c.is_ascii_alphabetic()) {
+        return normalize(element);
+    }
+
+    // two letter elements start in column 13, only for HETATM since a misaligned "CA" of an
+    // amino acid is far more likely than calcium in a polymer
+    let chars: Vec<char> = name.chars().collect();
+    if is_hetatm
+        && chars.len() >= 2
+        && chars[0].is_ascii_alphabetic()
+        && chars[1].is_ascii_alphabetic()
+        && !(name.trim().len() == 4 && chars[0].eq_ignore_ascii_case(&'H'))
+    {
+        let two = normalize(&name[..2]);
+        if atomprop::get_atomic_number(&two).is_some() {
+            return two;
+        }
+    }
+
+    match chars.iter().find(|c| c.is_ascii_alphabetic()) {
+        Some(first) => return first.to_ascii_uppercase().to_string(),
+        None => return "X".to_string(),
+    }
+}
+
+/** parse_charge() reads the charge column, written as 2+ or 1- (or +2, -1) */
+fn parse_charge(text: &str) -> f32 {
+    let text = text.trim();
+    let (digits, sign) = if let Some(digits) = text.strip_suffix('+') {
+        (digits, 1.0)
+    } else if let Some(digits) = text.strip_suffix('-') {
+        (digits, -1.0)
+    } else {
+        return text.parse().unwrap_or(0.0);
+    };
+
+    return digits.parse::<f32>().map(|c| sign * c).unwrap_or(0.0);
+}
+
+/** AtomRecord - where an atom sits in the model, chain and residue groups */
+#[derive(Clone)]
+pub(crate) struct AtomRecord {
+    pub(crate) chain: String,
+    pub(crate) residue_name: String,
+    pub(crate) residue_number: String,
+}
+
+pub(crate) fn collect_records(
+    groups: &[AtomGroup],
+    record: &AtomRecord,
+    records: &mut [AtomRecord],
+) {
+    for group in groups.iter() {
+        let mut record = record.clone();
+        match group.kind {
+            GroupKind::CHAIN => record.chain = group.name.to_string(),
+            GroupKind::RESIDUE => {
+                record.residue_name = group.name.to_string();
+                record.residue_number = group.remark.to_string();
+            }
+            _ => {}
+        }
+
+        for idx in group.get_atom_indices() {
+            records[*idx] = record.clone();
+        }
+        collect_records(group.get_groups(), &record, records);
+    }
+}
+
+/** split_residue_number() splits "12A" into the residue number and insertion code */
+pub(crate) fn split_residue_number(text: &str) -> (i64, String) {
+    let text = text.trim();
+    let split = text
+        .char_indices()
+        .find(|(i, c)| c.is_ascii_alphabetic() && *i > 0)
+        .map(|(i, _)| i)
+        .unwrap_or(text.len());
+
+    return (
+        text[..split].trim().parse().unwrap_or(1),
+        text[split..].chars().take(1).collect(),
+    );
+}
+
+/**
+ * wrap_residue_number() keeps a residue number within the 4 columns of the field, numbers
+ * beyond -999 to 9999 wrap around as in large GROMACS systems
+ */
+fn wrap_residue_number(number: i64) -> i64 {
+    if (-999..=9999).contains(&number) {
+        return number;
+    }
+    return number.rem_euclid(10000);
+}
+
+fn format_atom_name(name: &str, symbol: &str) -> String {
+    let name: String = name.chars().take(4).collect();
+    if name.len() == 4 || symbol.len() == 2 {
+        return format!("{:<4}", name);
+    }
+    return format!(" {:<3}", name);
+}
+
+fn format_charge(charge: f32) -> String {
+    if charge == 0.0 || charge.fract() != 0.0 || charge.abs() > 9.0 {
+        return "  ".to_string();
+    }
+    return format!("{}{}", charge.abs(), if charge > 0.0 { '+' } else { '-' });
+}
+
+/**
+ * write() writes a molecule in PDB format, with a CONECT record for every bonded atom.
+ * Atoms are numbered from 1 in the order they are written, model groups are written as
+ * MODEL records.
+ */
+#[allow(dead_code)]
+pub fn write<W: Write>(mut writer: W, mol: &Molecule) -> Result<(), IoError> {
+    let atoms = mol.get_atoms();
+    let number_of_atoms = atoms.len();
+
+    let text_of = |name: &str, idx: usize| match mol.get_atom_property(name) {
+        Some(AtomProperty::Texts(values)) => values[idx].to_string(),
+        _ => "".to_string(),
+    };
+    let real_of = |name: &str, idx: usize, default: f64| match mol.get_atom_property(name) {
+        Some(AtomProperty::Reals(values)) => values[idx],
+        _ => default,
+    };
+
+    let default_record = AtomRecord {
+        chain: "".to_string(),
+        residue_name: UNKNOWN_RESIDUE.to_string(),
+        residue_number: "1".to_string(),
+    };
+    let mut records = vec![default_record.clone(); number_of_atoms];
+    collect_records(mol.get_groups(), &default_record, &mut records);
+
+    let is_hetatm = |idx: usize| match mol.get_atom_property("hetatm") {
+        Some(AtomProperty::Logicals(values)) => values[idx],
+        _ => records[idx].residue_name == UNKNOWN_RESIDUE,
+    };
+
+    if !mol.name.is_empty() {
+        writeln!(writer, "{:<80}", format!("HEADER    {:<40}", mol.name))?;
+    }
+
+    let remark: Vec<char> = mol.remark.replace(['\n', '\r'], " ").chars().collect();
+    for (line_idx, chunk) in remark.chunks(69).enumerate() {
+        let text: String = chunk.iter().collect();
+        let continuation = if line_idx == 0 {
+            "  ".to_string()
+        } else {
+            format!("{:>2}", line_idx + 1)
+        };
+        writeln!(
+            writer,
+            "{:<80}",
+            format!("TITLE   {} {}", continuation, text)
+        )?;
+    }
+
+    if let Some(lattice) = mol.get_lattice() {
+        let p = lattice.get_parameters();
+        let space_group = match mol.get_property("space_group") {
+            Some(Property::Text(space_group)) => space_group.to_string(),
+            _ => "P 1".to_string(),
+        };
+        writeln!(
+            writer,
+            "{:<80}",
+            format!(
+                "CRYST1{:9.3}{:9.3}{:9.3}{:7.2}{:7.2}{:7.2} {:<11}{:>4}",
+                p[0], p[1], p[2], p[3], p[4], p[5], space_group, 1
+            )
+        )?;
+    }
+
+    let models: Vec<&AtomGroup> = mol
+        .get_groups()
+        .iter()
+        .filter(|g| g.kind == GroupKind::MODEL)
+        .collect();
+
+    let mut sections: Vec<(Option<String>, Vec<usize>)> = Vec::new();
+    let mut in_model = vec![false; number_of_atoms];
+    for model in models.iter() {
+        let mut indices = model.get_all_atom_indices();
+        indices.sort();
+        for idx in indices.iter() {
+            in_model[*idx] = true;
+        }
+        sections.push((Some(model.name.to_string()), indices));
+    }
+    sections.push((
+        None,
+        (0..number_of_atoms).filter(|idx| !in_model[*idx]).collect(),
+    ));
+
+    let mut serials = vec![0; number_of_atoms];
+    let mut serial = 0;
+
+    for (model_idx, (model_name, indices)) in sections.iter().enumerate() {
+        if let Some(name) = model_name {
+            let number = name.trim().parse().unwrap_or(model_idx + 1);
+            writeln!(writer, "{:<80}", format!("MODEL     {:>4}", number))?;
+        }
+
+        for (position, idx) in indices.iter().enumerate() {
+            let idx = *idx;
+            let atom = &atoms[idx];
+            let record = &records[idx];
+            let (residue_number, insertion_code) = split_residue_number(&record.residue_number);
+            let residue_number = wrap_residue_number(residue_number);
+            let chain: String = record.chain.chars().take(1).collect();
+            let residue_name: String = record.residue_name.chars().take(3).collect();
+            let alt_loc: String = text_of("alt_loc", idx).chars().take(1).collect();
+
+            let mut name = text_of("atom_name", idx);
+            if name.is_empty() {
+                name = atom.symbol.to_string();
+            }
+
+            serial += 1;
+            serials[idx] = serial;
+
+            writeln!(
+                writer,
+                "{:<6}{:>5} {}{:1}{:>3} {:1}{:>4}{:1}   {:8.3}{:8.3}{:8.3}{:6.2}{:6.2}          {:>2}{:2}",
+                if is_hetatm(idx) { "HETATM" } else { "ATOM" },
+                serial % 100000,
+                format_atom_name(&name, &atom.symbol),
+                alt_loc,
+                residue_name,
+                chain,
+                residue_number,
+                insertion_code,
+                atom.center.x,
+                atom.center.y,
+                atom.center.z,
+                real_of("occupancy", idx, 1.0),
+                real_of("b_factor", idx, 0.0),
+                atom.symbol.to_uppercase(),
+                format_charge(atom.charge),
+            )?;
+
+            // a chain of polymer residues ends with a TER record
+            let chain_continues = match indices.get(position + 1) {
+                Some(next) => !is_hetatm(*next) && records[*next].chain == record.chain,
+                None => false,
+            };
+            if !is_hetatm(idx) && !chain_continues {
+                serial += 1;
+                writeln!(
+                    writer,
+                    "{:<80}",
+                    format!(
+                        "TER   {:>5}      {:>3} {:1}{:>4}{:1}",
+                        serial % 100000,
+                        residue_name,
+                        chain,
+                        residue_number,
+                        insertion_code
+                    )
+                )?;
+            }
+        }
+
+        if model_name.is_some() {
+            writeln!(writer, "{:<80}", "ENDMDL")?;
+        }
+    }
+
+    let mut bonded: Vec<Vec<usize>> = vec![Vec::new(); number_of_atoms];
+    for bond in mol.get_bonds().iter() {
+        let order = match bond.bond_type {
+            BondType::DOUBLE => 2,
+            BondType::TRIPLE => 3,
+            _ => 1,
+        };
+        for _ in 0..order {
+            bonded[bond.atom_1_idx].push(serials[bond.atom_2_idx]);
+            bonded[bond.atom_2_idx].push(serials[bond.atom_1_idx]);
+        }
+    }
+
+    let mut order: Vec<usize> = (0..number_of_atoms).collect();
+    order.sort_by_key(|idx| serials[*idx]);
+
+    for idx in order {
+        for chunk in bonded[idx].chunks(4) {
+            let mut line = format!("CONECT{:>5}", serials[idx] % 100000);
+            for serial in chunk {
+                line.push_str(&format!("{:>5}", serial % 100000));
+            }
+            writeln!(writer, "{:<80}", line)?;
+        }
+    }
+
+    writeln!(writer, "{:<80}", "END")?;
+    return Ok(());
+}
+
+/** Unit tests for the above module */
+#[cfg(test)]
+mod tests {
+    use crate::atom::AtomGroup;
+    use crate::atom::AtomOperations;
+    use crate::atom::GroupKind;
+    use crate::bond::BondType;
+    use crate::io::IoError;
+    use crate::property::AtomProperty;
+    use crate::property::Property;
+    use crate::select;
+    use crate::testutil;
+
+    const PEPTIDE: &str = "\
+HEADER    PEPTIDE                                 01-JAN-00   1ABC
+TITLE     GLYCINE DIPEPTIDE WITH
+TITLE    2 ACETATE AND WATER
+CRYST1   20.000   21.000   22.000  90.00  90.00  90.00 P 21 21 21    4
+ATOM      1  N   GLY A   1      -1.195   0.847   0.000  1.00 10.00           N
+ATOM      2  CA  GLY A   1       0.000   0.000   0.000  1.00 11.00           C
+ATOM      3  C   GLY A   1       1.263   0.847   0.000  0.50 12.00           C
+ATOM      4  O  AGLY A   1       1.263   2.075   0.000  0.60 13.00           O
+ATOM      5  O  BGLY A   1       1.300   2.100   0.100  0.40 13.00           O
+ATOM      6  N   GLY A   2       2.400   0.150   0.000  1.00 14.00           N
+ATOM      7  CA  GLY A   2       3.700   0.800   0.000  1.00 15.00           C
+TER       8      GLY A   2
+HETATM    9  C1  ACT A 101       6.000   0.000   0.000  1.00 20.00           C
+HETATM   10  O1  ACT A 101       6.600   1.050   0.000  1.00 20.00           O1-
+HETATM   11  O   HOH B 201       9.000   9.000   9.000  1.00 30.00           O
+CONECT    9   10   10
+CONECT   10    9    9
+END
+";
+
+    #[test]
+    fn pdb_read() {
+        let mut mol = super::read(PEPTIDE.as_bytes()).unwrap();
+
+        assert_eq!(mol.name, "1ABC".to_string());
+        assert_eq!(
+            mol.remark,
+            "GLYCINE DIPEPTIDE WITH ACETATE AND WATER".to_string()
+        );
+        assert_eq!(mol.get_number_of_atoms(), 10);
+        assert_eq!(mol.get_atom(8).symbol, "O".to_string());
+        assert_eq!(mol.get_atom(8).charge, -1.0);
+        assert_eq!(mol.get_atom(1).center.x, 0.0);
+        assert_eq!(mol.get_atom(6).center.y, 0.8);
+
+        let parameters = mol.get_lattice().unwrap().get_parameters();
+        assert!((parameters[1] - 21.0).abs() < 1e-9);
+        assert_eq!(
+            mol.get_property("space_group"),
+            Some(&Property::Text("P 21 21 21".to_string()))
+        );
+
+        let chains = mol.get_groups();
+        assert_eq!(chains.len(), 2);
+        assert_eq!(chains[0].name, "A".to_string());
+        assert_eq!(chains[0].kind, GroupKind::CHAIN);
+        assert_eq!(chains[0].get_groups().len(), 3);
+        assert_eq!(chains[0].get_groups()[1].remark, "2".to_string());
+        assert_eq!(
+            chains[0].get_groups()[0].get_atom_indices(),
+            &[0, 1, 2, 3, 4]
+        );
+        assert_eq!(
+            select::select(&mol, "resname HOH")
+                .unwrap()
+                .get_atom_indices(),
+            &[9]
+        );
+
+        match mol.get_atom_property("alt_loc") {
+            Some(AtomProperty::Texts(values)) => assert_eq!(values[3..5], ["A", "B"]),
+            _ => panic!("expected alternate locations"),
+        }
+        match mol.get_atom_property("occupancy") {
+            Some(AtomProperty::Reals(values)) => assert_eq!(values[2..5], [0.5, 0.6, 0.4]),
+            _ => panic!("expected occupancies"),
+        }
+        match mol.get_atom_property("atom_name") {
+            Some(AtomProperty::Texts(values)) => assert_eq!(values[1], "CA"),
+            _ => panic!("expected atom names"),
+        }
+
+        assert_eq!(mol.get_number_of_bonds(), 1);
+        assert_eq!(mol.get_bond_type(7, 8), BondType::DOUBLE);
+    }
+
+    #[test]
+    fn pdb_round_trip() {
+        let mol = super::read(PEPTIDE.as_bytes()).unwrap();
+
+        let mut buffer = Vec::new();
+        super::write(&mut buffer, &mol).unwrap();
+        let text = String::from_utf8(buffer).unwrap();
+
+        assert!(text.lines().all(|line| line.len() == 80));
+        assert!(text.contains(
+            "ATOM      4  O  AGLY A   1       1.263   2.075   0.000  0.60 13.00           O  \n"
+        ));
+        assert!(text.contains(
+            "HETATM   10  O1  ACT A 101       6.600   1.050   0.000  1.00 20.00           O1-\n"
+        ));
+        assert!(text.contains("TER       8      GLY A   2"));
+        assert!(text.contains("CONECT    9   10   10"));
+
+        let read_mol = super::read(text.as_bytes()).unwrap();
+        assert_eq!(read_mol.name, mol.name);
+        assert_eq!(read_mol.remark, mol.remark);
+        assert_eq!(read_mol.get_atoms(), mol.get_atoms());
+        assert_eq!(read_mol.get_bonds(), mol.get_bonds());
+        assert_eq!(read_mol.get_groups(), mol.get_groups());
+        assert_eq!(read_mol.get_atom_properties(), mol.get_atom_properties());
+        assert_eq!(
+            read_mol.get_property("space_group"),
+            mol.get_property("space_group")
+        );
+    }
+
+    #[test]
+    fn pdb_write_long_fields() {
+        // fields longer than their columns are cut or wrapped, the columns after stay put
+        let mut mol = testutil::get_water();
+        let mut residue =
+            AtomGroup::new("HOHX".to_string(), "12345".to_string()).with_kind(GroupKind::RESIDUE);
+        for idx in 0..3 {
+            residue.add_atom_index(idx);
+        }
+        mol.add_group(residue);
+        mol.set_atom_property(
+            "alt_loc",
+            AtomProperty::Texts(vec!["AB".to_string(), "".to_string(), "".to_string()]),
+        );
+
+        let mut buffer = Vec::new();
+        super::write(&mut buffer, &mol).unwrap();
+        let text = String::from_utf8(buffer).unwrap();
+        assert!(text.lines().all(|line| line.len() == 80));
+        assert!(text.contains(
+            "ATOM      1  O  AHOH  2345       0.000   0.000   0.117  1.00  0.00           O  \n"
+        ));
+        assert!(text.contains("TER       4      HOH  2345"));
+
+        let read_mol = super::read(text.as_bytes()).unwrap();
+        let symbols: Vec<&str> = read_mol
+            .get_atoms()
+            .iter()
+            .map(|a| a.symbol.as_str())
+            .collect();
+        assert_eq!(symbols, vec!["O", "H", "H"]);
+
+        assert_eq!(super::wrap_residue_number(-999), -999);
+        assert_eq!(super::wrap_residue_number(-1000), 9000);
+        assert_eq!(super::wrap_residue_number(10000), 0);
+    }
+
+    #[test]
+    fn pdb_read_ensemble() {
+        // an older file: no element columns, no ENDMDL for the last model, no END
+        let text = "\
+CRYST1    1.000    1.000    1.000  90.00  90.00  90.00 P 1           1
+MODEL        1
+ATOM      1  CA  ALA     1       0.000   0.000   0.000
+ATOM      2  HA  ALA     1       1.090   0.000   0.000
+ENDMDL
+MODEL        2
+ATOM      1  CA  ALA     1       0.000   0.100   0.000
+ATOM      2  HA  ALA     1       1.090   0.100   0.000
+HETATM    3 FE   HEM     2       5.000   0.000   0.000
+CONECT    1    2
+CONECT    1   99
+";
+        let mut mol = super::read(text.as_bytes()).unwrap();
+
+        assert!(mol.get_lattice().is_none());
+        assert_eq!(mol.get_number_of_atoms(), 5);
+        assert_eq!(mol.get_atom(0).symbol, "C".to_string());
+        assert_eq!(mol.get_atom(1).symbol, "H".to_string());
+        assert_eq!(mol.get_atom(4).symbol, "Fe".to_string());
+
+        let models = mol.get_groups();
+        assert_eq!(models.len(), 2);
+        assert_eq!(models[1].kind, GroupKind::MODEL);
+        assert_eq!(models[1].get_all_atom_indices(), vec![2, 3, 4]);
+        assert_eq!(
+            select::select(&mol, "model 2 and resname ALA")
+                .unwrap()
+                .get_atom_indices(),
+            &[2, 3]
+        );
+
+        assert_eq!(mol.get_number_of_bonds(), 2);
+        assert_eq!(mol.get_bond_index(0, 1), 0);
+        assert_eq!(mol.get_bond_index(2, 3), 1);
+
+        let mut buffer = Vec::new();
+        super::write(&mut buffer, &mol).unwrap();
+        let read_mol = super::read(buffer.as_slice()).unwrap();
+        assert_eq!(read_mol.get_groups(), mol.get_groups());
+        assert_eq!(read_mol.get_bonds(), mol.get_bonds());
+    }
+
+    #[test]
+    fn pdb_read_errors() {
+        let text = "ATOM      1  N   GLY A   1      -1.195   0.847   0.000\nATOM      2  CA  GLY A   1       0.000   x.xxx   0.000\n";
+        match super::read(text.as_bytes()) {
+            Err(IoError::Parse { line_number, .. }) => assert_eq!(line_number, 2),
+            _ => panic!("expected a parse error"),
+        }
+    }
+}
@@ -0,0 +1,273 @@
+/**
+ * Author: V. Ganesh
+ * License: MIT
+ */
+
+/**
+ * qm module, inputs for quantum chemistry programs from one description of the job.
+ *
+ * A QmJob gives the method, basis set, charge, multiplicity, task and constraints in
+ * terms that do not depend on the program, and a QmInputGenerator turns a molecule and a
+ * job into the input of one program. The orca, nwchem and psi4 modules are generators for
+ * those programs, other programs can be added by implementing the trait:
+ *
+ *   let job = QmJob::new("B3LYP", "6-31G*").task(Task::OPTIMIZE);
+ *   for generator in [&OrcaInput as &dyn QmInputGenerator, &Psi4Input] {
+ *       let input = generator.get_input(&mol, &job)?;
+ *   }
+ *
+ * Atom indices in constraints are 0 based, generators convert them for their program.
+ */
+use std::io::Write;
+
+use crate::io;
+use crate::io::IoError;
+use crate::molecule::Molecule;
+
+pub mod nwchem;
+pub mod orca;
+pub mod psi4;
+
+/** Task - what the program is asked to do with the molecule */
+#[allow(dead_code)]
+#[derive(Default, Clone, Copy, PartialEq, Debug)]
+pub enum Task {
+    /** the energy at the given geometry */
+    #[default]
+    ENERGY,
+    /** optimize the geometry */
+    OPTIMIZE,
+    /** the vibrational frequencies at the given geometry */
+    FREQUENCY,
+}
+
+/** Constraint - a coordinate kept at its starting value during an optimization */
+#[allow(dead_code)]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Constraint {
+    /** the Cartesian position of an atom */
+    ATOM(usize),
+    /** the distance between two atoms */
+    DISTANCE(usize, usize),
+    /** the angle made by three atoms */
+    ANGLE(usize, usize, usize),
+    /** the dihedral angle made by four atoms */
+    DIHEDRAL(usize, usize, usize, usize),
+}
+
+#[allow(dead_code)]
+impl Constraint {
+    /** get_atom_indices() returns the indices of the atoms the constraint involves */
+    pub fn get_atom_indices(&self) -> Vec<usize> {
+        match *self {
+            Constraint::ATOM(a) => return vec![a],
+            Constraint::DISTANCE(a, b) => return vec![a, b],
+            Constraint::ANGLE(a, b, c) => return vec![a, b, c],
+            Constraint::DIHEDRAL(a, b, c, d) => return vec![a, b, c, d],
+        }
+    }
+}
+
+/** QmJob - a quantum chemistry job, independent of the program that runs it */
+#[allow(dead_code)]
+#[derive(Clone, Default, PartialEq, Debug)]
+pub struct QmJob {
+    method: String,
+    basis: String,
+    charge: Option<i64>,
+    multiplicity: Option<i64>,
+    task: Task,
+    constraints: Vec<Constraint>,
+}
+
+#[allow(dead_code)]
+impl QmJob {
+    /** new() creates an energy job with the given method (e.g. HF, B3LYP, MP2) and basis set */
+    pub fn new(method: &str, basis: &str) -> QmJob {
+        QmJob {
+            method: method.trim().to_string(),
+            basis: basis.trim().to_string(),
+            ..Default::default()
+        }
+    }
+
+    /** charge() sets the total charge, by default the sum of the atom charges */
+    pub fn charge(mut self, charge: i64) -> QmJob {
+        self.charge = Some(charge);
+        return self;
+    }
+
+    /**
+     * multiplicity() sets the spin multiplicity, by default a singlet for an even number
+     * of electrons and a doublet for an odd number
+     */
+    pub fn multiplicity(mut self, multiplicity: i64) -> QmJob {
+        self.multiplicity = Some(multiplicity);
+        return self;
+    }
+
+    /** task() sets what the program is asked to do */
+    pub fn task(mut self, task: Task) -> QmJob {
+        self.task = task;
+        return self;
+    }
+
+    /** constraint() adds a constraint, only optimizations may have them */
+    pub fn constraint(mut self, constraint: Constraint) -> QmJob {
+        self.constraints.push(constraint);
+        return self;
+    }
+
+    pub fn get_method(&self) -> &str {
+        return &self.method;
+    }
+
+    pub fn get_basis(&self) -> &str {
+        return &self.basis;
+    }
+
+    /** get_charge() returns the total charge of the job on a molecule */
+    pub fn get_charge(&self, mol: &Molecule) -> i64 {
+        return self.charge.unwrap_or_else(|| io::get_total_charge(mol));
+    }
+
+    /** get_multiplicity() returns the multiplicity of the job on a molecule */
+    pub fn get_multiplicity(&self, mol: &Molecule) -> i64 {
+        return self
+            .multiplicity
+            .unwrap_or_else(|| io::get_multiplicity(mol, self.get_charge(mol)));
+    }
+
+    pub fn get_task(&self) -> Task {
+        return self.task;
+    }
+
+    pub fn get_constraints(&self) -> &[Constraint] {
+        return &self.constraints;
+    }
+
+    /**
+     * check() checks that the job can be run on a molecule: it has a method, constraints
+     * are only given for optimizations and refer to atoms of the molecule
+     */
+    pub fn check(&self, mol: &Molecule, program: &str) -> Result<(), IoError> {
+        let error = |message: String| IoError::parse(program, 0, message);
+
+        if self.method.is_empty() {
+            return Err(error("the job has no method".to_string()));
+        }
+        if !self.constraints.is_empty() && self.task != Task::OPTIMIZE {
+            return Err(error(
+                "constraints are only allowed in optimizations".to_string(),
+            ));
+        }
+
+        let number_of_atoms = mol.get_atoms().len();
+        for constraint in self.constraints.iter() {
+            let indices = constraint.get_atom_indices();
+            if indices.iter().any(|idx| *idx >= number_of_atoms) {
+                return Err(error(format!(
+                    "the constraint {:?} refers to a missing atom, the molecule has {} atoms",
+                    constraint, number_of_atoms
+                )));
+            }
+        }
+
+        return Ok(());
+    }
+}
+
+/** QmInputGenerator - writes the input of a quantum chemistry program for a job */
+#[allow(dead_code)]
+pub trait QmInputGenerator {
+    /** get_program() returns the name of the program */
+    fn get_program(&self) -> &'static str;
+
+    /** write_input() writes the input of the job on the molecule */
+    fn write_input(
+        &self,
+        writer: &mut dyn Write,
+        mol: &Molecule,
+        job: &QmJob,
+    ) -> Result<(), IoError>;
+
+    /** get_input() returns the input of the job on the molecule */
+    fn get_input(&self, mol: &Molecule, job: &QmJob) -> Result<String, IoError> {
+        let mut input = Vec::new();
+        self.write_input(&mut input, mol, job)?;
+        return Ok(String::from_utf8_lossy(&input).to_string());
+    }
+}
+
+/** get_title() returns the title of a molecule on one line, its remark or else its name */
+pub(crate) fn get_title(mol: &Molecule) -> String {
+    let title = if !mol.remark.trim().is_empty() {
+        mol.remark.as_str()
+    } else {
+        mol.name.as_str()
+    };
+    return title.trim().replace(['\n', '\r'], " ");
+}
+
+/** write_atoms() writes a line with the symbol and coordinates of each atom */
+pub(crate) fn write_atoms(
+    writer: &mut dyn Write,
+    mol: &Molecule,
+    indent: &str,
+) -> Result<(), IoError> {
+    for atom in mol.get_atoms().iter() {
+        writeln!(
+            writer,
+            "{}{:<2} {:16.8} {:16.8} {:16.8}",
+            indent, atom.symbol, atom.center.x, atom.center.y, atom.center.z
+        )?;
+    }
+    return Ok(());
+}
+
+/** Unit tests for the above module */
+#[cfg(test)]
+mod tests {
+    use crate::io::qm::nwchem::NwchemInput;
+    use crate::io::qm::orca::OrcaInput;
+    use crate::io::qm::psi4::Psi4Input;
+    use crate::io::qm::Constraint;
+    use crate::io::qm::QmInputGenerator;
+    use crate::io::qm::QmJob;
+    use crate::io::qm::Task;
+    use crate::testutil;
+
+    #[test]
+    fn qm_job() {
+        let water = testutil::get_water();
+
+        let job = QmJob::new("B3LYP", "6-31G*").charge(1);
+        assert_eq!(job.get_charge(&water), 1);
+        assert_eq!(job.get_multiplicity(&water), 2);
+        assert_eq!(job.get_task(), Task::ENERGY);
+
+        let job = QmJob::new("HF", "STO-3G").constraint(Constraint::DISTANCE(0, 1));
+        let generators: [&dyn QmInputGenerator; 3] = [&OrcaInput, &NwchemInput, &Psi4Input];
+        for generator in generators {
+            let err = generator.get_input(&water, &job).unwrap_err();
+            assert_eq!(
+                err.to_string(),
+                format!(
+                    "{}: constraints are only allowed in optimizations",
+                    generator.get_program()
+                )
+            );
+        }
+
+        let job = job
+            .task(Task::OPTIMIZE)
+            .constraint(Constraint::ANGLE(0, 1, 3));
+        assert_eq!(
+            OrcaInput.get_input(&water, &job).unwrap_err().to_string(),
+            "orca: the constraint ANGLE(0, 1, 3) refers to a missing atom, the molecule has 3 atoms"
+        );
+        assert!(Psi4Input
+            .get_input(&water, &QmJob::new("", "STO-3G"))
+            .is_err());
+    }
+}
@@ -0,0 +1,208 @@
+/**
+ * Author: V. Ganesh
+ * License: MIT
+ */
+
+/**
+ * nwchem module, NWChem inputs for a QmJob.
+ *
+ * The method picks the NWChem module: HF (or RHF, UHF, SCF) runs scf, MP2, CCSD and
+ * CCSD(T) run on an scf reference, and any other method is taken as a DFT functional.
+ * The ccsd module only takes closed shells, so CCSD and CCSD(T) of open shells run in
+ * the tensor contraction engine (tce) on a UHF or ROHF reference.
+ * Frozen atoms are written in a constraints block, frozen distances, angles and
+ * dihedrals as constant internal coordinates (zcoord) of the geometry. NWChem numbers
+ * atoms from 1.
+ */
+use std::io::Write;
+
+use crate::io::qm;
+use crate::io::qm::Constraint;
+use crate::io::qm::QmInputGenerator;
+use crate::io::qm::QmJob;
+use crate::io::qm::Task;
+use crate::io::IoError;
+use crate::molecule::Molecule;
+
+const PROGRAM: &str = "nwchem";
+
+/** NwchemInput - writes NWChem inputs */
+#[allow(dead_code)]
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub struct NwchemInput;
+
+/** get_module() returns the NWChem module running the method, and whether it needs an scf block */
+fn get_module(method: &str) -> (String, bool) {
+    match method.to_lowercase().as_str() {
+        "hf" | "scf" | "rhf" | "uhf" | "rohf" => return ("scf".to_string(), true),
+        "mp2" => return ("mp2".to_string(), true),
+        "ccsd" => return ("ccsd".to_string(), true),
+        "ccsd(t)" => return ("ccsd(t)".to_string(), true),
+        _ => return ("dft".to_string(), false),
+    }
+}
+
+/** get_start_name() returns the file prefix of a job, the molecule name without spaces */
+fn get_start_name(mol: &Molecule) -> String {
+    let name: String = mol
+        .name
+        .trim()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+
+    if name.is_empty() {
+        return "job".to_string();
+    }
+    return name;
+}
+
+impl QmInputGenerator for NwchemInput {
+    fn get_program(&self) -> &'static str {
+        return PROGRAM;
+    }
+
+    fn write_input(
+        &self,
+        writer: &mut dyn Write,
+        mol: &Molecule,
+        job: &QmJob,
+    ) -> Result<(), IoError> {
+        job.check(mol, PROGRAM)?;
+
+        let multiplicity = job.get_multiplicity(mol);
+        writeln!(writer, "start {}", get_start_name(mol))?;
+        writeln!(writer, "title \"{}\"", qm::get_title(mol).replace('"', "'"))?;
+        writeln!(writer, "charge {}", job.get_charge(mol))?;
+        writeln!(writer)?;
+
+        writeln!(writer, "geometry units angstrom")?;
+        qm::write_atoms(writer, mol, "  ")?;
+
+        let internals: Vec<&Constraint> = job
+            .get_constraints()
+            .iter()
+            .filter(|constraint| !matches!(constraint, Constraint::ATOM(_)))
+            .collect();
+        if !internals.is_empty() {
+            writeln!(writer, "  zcoord")?;
+            for constraint in internals {
+                let kind = match constraint {
+                    Constraint::DISTANCE(..) => "bond",
+                    Constraint::ANGLE(..) => "angle",
+                    _ => "torsion",
+                };
+                let indices: Vec<String> = constraint
+                    .get_atom_indices()
+                    .iter()
+                    .map(|idx| (idx + 1).to_string())
+                    .collect();
+                writeln!(writer, "    {} {} constant", kind, indices.join(" "))?;
+            }
+            writeln!(writer, "  end")?;
+        }
+        writeln!(writer, "end")?;
+        writeln!(writer)?;
+
+        let frozen: Vec<String> = job
+            .get_constraints()
+            .iter()
+            .filter_map(|constraint| match constraint {
+                Constraint::ATOM(idx) => Some((idx + 1).to_string()),
+                _ => None,
+            })
+            .collect();
+        if !frozen.is_empty() {
+            writeln!(writer, "constraints")?;
+            writeln!(writer, "  fix atom {}", frozen.join(" "))?;
+            writeln!(writer, "end")?;
+            writeln!(writer)?;
+        }
+
+        writeln!(writer, "basis")?;
+        writeln!(writer, "  * library {}", job.get_basis())?;
+        writeln!(writer, "end")?;
+        writeln!(writer)?;
+
+        let (mut module, needs_scf) = get_module(job.get_method());
+        if needs_scf {
+            let reference = match job.get_method().to_lowercase().as_str() {
+                "rohf" => "rohf",
+                _ if multiplicity > 1 || job.get_method().eq_ignore_ascii_case("uhf") => "uhf",
+                _ => "rhf",
+            };
+            writeln!(writer, "scf")?;
+            writeln!(writer, "  {}", reference)?;
+            writeln!(writer, "  nopen {}", multiplicity - 1)?;
+            writeln!(writer, "end")?;
+
+            if module.starts_with("ccsd") && reference != "rhf" {
+                writeln!(writer)?;
+                writeln!(writer, "tce")?;
+                writeln!(writer, "  {}", module)?;
+                writeln!(writer, "end")?;
+                module = "tce".to_string();
+            }
+        } else {
+            writeln!(writer, "dft")?;
+            writeln!(writer, "  xc {}", job.get_method().to_lowercase())?;
+            writeln!(writer, "  mult {}", multiplicity)?;
+            writeln!(writer, "end")?;
+        }
+        writeln!(writer)?;
+
+        let task = match job.get_task() {
+            Task::ENERGY => "energy",
+            Task::OPTIMIZE => "optimize",
+            Task::FREQUENCY => "frequencies",
+        };
+        writeln!(writer, "task {} {}", module, task)?;
+
+        return Ok(());
+    }
+}
+
+/** Unit tests for the above module */
+#[cfg(test)]
+mod tests {
+    use crate::io::qm::nwchem::NwchemInput;
+    use crate::io::qm::Constraint;
+    use crate::io::qm::QmInputGenerator;
+    use crate::io::qm::QmJob;
+    use crate::io::qm::Task;
+    use crate::testutil;
+
+    #[test]
+    fn nwchem_input() {
+        let mut water = testutil::get_water();
+        // the space of the name is replaced in the start directive
+        water.name = "water dimer".to_string();
+        let job = QmJob::new("B3LYP", "6-31G*")
+            .task(Task::OPTIMIZE)
+            .constraint(Constraint::ANGLE(1, 0, 2))
+            .constraint(Constraint::ATOM(0));
+
+        let input = NwchemInput.get_input(&water, &job).unwrap();
+        assert!(input.starts_with("start water_dimer\ntitle \"water dimer\"\ncharge 0\n\n"));
+        assert!(input.contains("  zcoord\n    angle 2 1 3 constant\n  end\nend\n"));
+        assert!(input.contains("constraints\n  fix atom 1\nend\n"));
+        assert!(input.contains("basis\n  * library 6-31G*\nend\n"));
+        assert!(input.contains("dft\n  xc b3lyp\n  mult 1\nend\n"));
+        assert!(input.ends_with("task dft optimize\n"));
+
+        let job = QmJob::new("CCSD(T)", "cc-pVTZ")
+            .charge(1)
+            .task(Task::FREQUENCY);
+        let input = NwchemInput.get_input(&water, &job).unwrap();
+        assert!(input.contains("charge 1\n"));
+        assert!(input.contains("scf\n  uhf\n  nopen 1\nend\n\ntce\n  ccsd(t)\nend\n"));
+        assert!(input.ends_with("task tce frequencies\n"));
+        assert!(!input.contains("zcoord"));
+
+        let job = QmJob::new("CCSD", "cc-pVTZ");
+        let input = NwchemInput.get_input(&water, &job).unwrap();
+        assert!(input.contains("scf\n  rhf\n  nopen 0\nend\n"));
+        assert!(!input.contains("tce"));
+        assert!(input.ends_with("task ccsd energy\n"));
+    }
+}
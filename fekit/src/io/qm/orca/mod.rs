@@ -0,0 +1,129 @@
+/**
+ * Author: V. Ganesh
+ * License: MIT
+ */
+
+/**
+ * orca module, ORCA inputs for a QmJob.
+ *
+ * The method, basis set and task go on the ! line, constraints in a %geom block, and the
+ * geometry in a * xyz block with the charge and multiplicity. ORCA numbers atoms from 0.
+ */
+use std::io::Write;
+
+use crate::io::qm;
+use crate::io::qm::Constraint;
+use crate::io::qm::QmInputGenerator;
+use crate::io::qm::QmJob;
+use crate::io::qm::Task;
+use crate::io::IoError;
+use crate::molecule::Molecule;
+
+const PROGRAM: &str = "orca";
+
+/** OrcaInput - writes ORCA inputs */
+#[allow(dead_code)]
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub struct OrcaInput;
+
+impl QmInputGenerator for OrcaInput {
+    fn get_program(&self) -> &'static str {
+        return PROGRAM;
+    }
+
+    fn write_input(
+        &self,
+        writer: &mut dyn Write,
+        mol: &Molecule,
+        job: &QmJob,
+    ) -> Result<(), IoError> {
+        job.check(mol, PROGRAM)?;
+
+        let title = qm::get_title(mol);
+        if !title.is_empty() {
+            writeln!(writer, "# {}", title)?;
+        }
+
+        let task = match job.get_task() {
+            Task::ENERGY => "SP",
+            Task::OPTIMIZE => "Opt",
+            Task::FREQUENCY => "Freq",
+        };
+        writeln!(
+            writer,
+            "! {} {} {}",
+            job.get_method(),
+            job.get_basis(),
+            task
+        )?;
+
+        if !job.get_constraints().is_empty() {
+            writeln!(writer, "%geom")?;
+            writeln!(writer, "  Constraints")?;
+            for constraint in job.get_constraints().iter() {
+                let code = match constraint {
+                    Constraint::ATOM(_) => "C",
+                    Constraint::DISTANCE(..) => "B",
+                    Constraint::ANGLE(..) => "A",
+                    Constraint::DIHEDRAL(..) => "D",
+                };
+                let indices: Vec<String> = constraint
+                    .get_atom_indices()
+                    .iter()
+                    .map(|idx| idx.to_string())
+                    .collect();
+                writeln!(writer, "    {{ {} {} C }}", code, indices.join(" "))?;
+            }
+            writeln!(writer, "  end")?;
+            writeln!(writer, "end")?;
+        }
+
+        writeln!(writer)?;
+        writeln!(
+            writer,
+            "* xyz {} {}",
+            job.get_charge(mol),
+            job.get_multiplicity(mol)
+        )?;
+        qm::write_atoms(writer, mol, "")?;
+        writeln!(writer, "*")?;
+
+        return Ok(());
+    }
+}
+
+/** Unit tests for the above module */
+#[cfg(test)]
+mod tests {
+    use crate::io::qm::orca::OrcaInput;
+    use crate::io::qm::Constraint;
+    use crate::io::qm::QmInputGenerator;
+    use crate::io::qm::QmJob;
+    use crate::io::qm::Task;
+    use crate::testutil;
+
+    #[test]
+    fn orca_input() {
+        let water = testutil::get_water();
+        let job = QmJob::new("B3LYP", "def2-SVP")
+            .task(Task::OPTIMIZE)
+            .constraint(Constraint::DISTANCE(0, 1))
+            .constraint(Constraint::ATOM(2));
+
+        let input = OrcaInput.get_input(&water, &job).unwrap();
+        let lines: Vec<&str> = input.lines().collect();
+        assert_eq!(lines[0], "# water");
+        assert_eq!(lines[1], "! B3LYP def2-SVP Opt");
+        assert_eq!(lines[2], "%geom");
+        assert_eq!(lines[4], "    { B 0 1 C }");
+        assert_eq!(lines[5], "    { C 2 C }");
+        assert_eq!(lines[9], "* xyz 0 1");
+        assert!(lines[10].starts_with("O "));
+        assert_eq!(lines[13], "*");
+
+        let input = OrcaInput
+            .get_input(&water, &QmJob::new("MP2", "cc-pVDZ").multiplicity(3))
+            .unwrap();
+        assert!(input.contains("! MP2 cc-pVDZ SP\n\n* xyz 0 3\n"));
+    }
+}
@@ -0,0 +1,181 @@
+/**
+ * Author: V. Ganesh
+ * License: MIT
+ */
+
+/**
+ * psi4 module, Psi4 inputs for a QmJob.
+ *
+ * The molecule block holds the charge, multiplicity and geometry, the basis set and the
+ * reference (UHF for open shells) are set as options, and constraints are frozen
+ * coordinates of the optking optimizer. Psi4 numbers atoms from 1.
+ */
+use std::io::Write;
+
+use crate::io::qm;
+use crate::io::qm::Constraint;
+use crate::io::qm::QmInputGenerator;
+use crate::io::qm::QmJob;
+use crate::io::qm::Task;
+use crate::io::IoError;
+use crate::molecule::Molecule;
+
+const PROGRAM: &str = "psi4";
+
+/** Psi4Input - writes Psi4 inputs */
+#[allow(dead_code)]
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub struct Psi4Input;
+
+/** write_frozen() writes an optking option freezing the given constraints, if there are any */
+fn write_frozen(
+    writer: &mut dyn Write,
+    option: &str,
+    constraints: &[Vec<usize>],
+    suffix: &str,
+) -> Result<(), IoError> {
+    if constraints.is_empty() {
+        return Ok(());
+    }
+
+    writeln!(writer, "  {} = (\"", option)?;
+    for indices in constraints.iter() {
+        let numbers: Vec<String> = indices.iter().map(|idx| (idx + 1).to_string()).collect();
+        writeln!(writer, "    {}{}", numbers.join(" "), suffix)?;
+    }
+    writeln!(writer, "  \")")?;
+    return Ok(());
+}
+
+impl QmInputGenerator for Psi4Input {
+    fn get_program(&self) -> &'static str {
+        return PROGRAM;
+    }
+
+    fn write_input(
+        &self,
+        writer: &mut dyn Write,
+        mol: &Molecule,
+        job: &QmJob,
+    ) -> Result<(), IoError> {
+        job.check(mol, PROGRAM)?;
+
+        let title = qm::get_title(mol);
+        if !title.is_empty() {
+            writeln!(writer, "# {}", title)?;
+            writeln!(writer)?;
+        }
+
+        let multiplicity = job.get_multiplicity(mol);
+        writeln!(writer, "molecule {{")?;
+        writeln!(writer, "  {} {}", job.get_charge(mol), multiplicity)?;
+        qm::write_atoms(writer, mol, "  ")?;
+        writeln!(writer, "  units angstrom")?;
+        if !job.get_constraints().is_empty() {
+            // keep the frozen coordinates where they are given
+            writeln!(writer, "  no_reorient")?;
+            writeln!(writer, "  no_com")?;
+        }
+        writeln!(writer, "}}")?;
+        writeln!(writer)?;
+
+        writeln!(writer, "set {{")?;
+        writeln!(writer, "  basis {}", job.get_basis())?;
+        if multiplicity > 1 {
+            writeln!(writer, "  reference uhf")?;
+        }
+        writeln!(writer, "}}")?;
+        writeln!(writer)?;
+
+        if !job.get_constraints().is_empty() {
+            let of_kind = |kind: fn(&Constraint) -> bool| -> Vec<Vec<usize>> {
+                job.get_constraints()
+                    .iter()
+                    .filter(|constraint| kind(constraint))
+                    .map(|constraint| constraint.get_atom_indices())
+                    .collect()
+            };
+
+            writeln!(writer, "set optking {{")?;
+            write_frozen(
+                writer,
+                "frozen_distance",
+                &of_kind(|c| matches!(c, Constraint::DISTANCE(..))),
+                "",
+            )?;
+            write_frozen(
+                writer,
+                "frozen_bend",
+                &of_kind(|c| matches!(c, Constraint::ANGLE(..))),
+                "",
+            )?;
+            write_frozen(
+                writer,
+                "frozen_dihedral",
+                &of_kind(|c| matches!(c, Constraint::DIHEDRAL(..))),
+                "",
+            )?;
+            write_frozen(
+                writer,
+                "frozen_cartesian",
+                &of_kind(|c| matches!(c, Constraint::ATOM(_))),
+                " xyz",
+            )?;
+            writeln!(writer, "}}")?;
+            writeln!(writer)?;
+        }
+
+        let function = match job.get_task() {
+            Task::ENERGY => "energy",
+            Task::OPTIMIZE => "optimize",
+            Task::FREQUENCY => "frequency",
+        };
+        writeln!(
+            writer,
+            "{}('{}')",
+            function,
+            job.get_method().to_lowercase()
+        )?;
+
+        return Ok(());
+    }
+}
+
+/** Unit tests for the above module */
+#[cfg(test)]
+mod tests {
+    use crate::io::qm::psi4::Psi4Input;
+    use crate::io::qm::Constraint;
+    use crate::io::qm::QmInputGenerator;
+    use crate::io::qm::QmJob;
+    use crate::io::qm::Task;
+    use crate::testutil;
+
+    #[test]
+    fn psi4_input() {
+        let mut water = testutil::get_water();
+        water.remark = "water".to_string();
+
+        let job = QmJob::new("MP2", "cc-pVDZ")
+            .task(Task::OPTIMIZE)
+            .constraint(Constraint::DISTANCE(0, 1))
+            .constraint(Constraint::DISTANCE(0, 2))
+            .constraint(Constraint::ATOM(0));
+        let input = Psi4Input.get_input(&water, &job).unwrap();
+        assert!(input.starts_with("# water\n\nmolecule {\n  0 1\n  O "));
+        assert!(input.contains("  units angstrom\n  no_reorient\n  no_com\n}\n"));
+        assert!(input.contains("set {\n  basis cc-pVDZ\n}\n"));
+        assert!(input.contains("  frozen_distance = (\"\n    1 2\n    1 3\n  \")\n"));
+        assert!(input.contains("  frozen_cartesian = (\"\n    1 xyz\n  \")\n"));
+        assert!(!input.contains("frozen_bend"));
+        assert!(input.ends_with("optimize('mp2')\n"));
+
+        let input = Psi4Input
+            .get_input(&water, &QmJob::new("B3LYP", "6-31G*").charge(-1))
+            .unwrap();
+        assert!(input.contains("molecule {\n  -1 2\n"));
+        assert!(input.contains("  reference uhf\n"));
+        assert!(!input.contains("optking"));
+        assert!(input.ends_with("energy('b3lyp')\n"));
+    }
+}
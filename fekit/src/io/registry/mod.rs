@@ -0,0 +1,931 @@
+/**
+ * Author: V. Ganesh
+ * License: MIT
+ */
+
+/**
+ * registry module, reads and writes molecule files by path, choosing the format from the
+ * file name or the contents.
+ *
+ * A FormatRegistry holds MoleculeFormats, the built-in formats to start with. The format
+ * of a file is the first format whose extensions match the file name, e.g. water.sdf,
+ * POSCAR or data.water; when none does, the first format that recognises the start of the
+ * contents. Other crates add formats by implementing the trait:
+ *
+ *   fekit::io::register_format(Box::new(MyFormat));
+ *   let mol = fekit::io::read("water.my")?;
+ *   let mol = fekit::io::read_as("water.txt", "xyz")?;
+ *
+ * Formats added later come first, so they can replace a built-in format. Parse errors of
+ * a file name it and quote the line that could not be read. Files are read and written
+ * through the compress module, so water.sdf.gz is a gzip compressed SD file.
+ */
+use std::io::BufRead;
+use std::io::Write;
+use std::path::Path;
+use std::sync::OnceLock;
+use std::sync::RwLock;
+use std::sync::RwLockReadGuard;
+
+use crate::io;
+use crate::io::compress;
+use crate::io::IoError;
+use crate::molecule::Molecule;
+
+/** longest part of a line quoted in a parse error */
+const MAX_QUOTED_LENGTH: usize = 80;
+
+/** MoleculeFormat - a file format that molecules are read from and written to */
+#[allow(dead_code)]
+pub trait MoleculeFormat: Send + Sync {
+    /** get_name() returns the name of the format, e.g. "xyz" */
+    fn get_name(&self) -> &str;
+
+    /**
+     * get_extensions() returns the file extensions of the format in lower case, they are
+     * also matched against the whole file name and its part before the first '.'
+     */
+    fn get_extensions(&self) -> &[&str];
+
+    /** sniff() returns true if the start of a file looks like this format */
+    fn sniff(&self, _head: &str) -> bool {
+        return false;
+    }
+
+    /** read() reads the first molecule of a file */
+    fn read(&self, reader: &mut dyn BufRead) -> Result<Molecule, IoError>;
+
+    /** read_all() reads all the molecules of a file */
+    fn read_all(&self, reader: &mut dyn BufRead) -> Result<Vec<Molecule>, IoError> {
+        return Ok(vec![self.read(reader)?]);
+    }
+
+    /** can_write() returns true if molecules can be written in the format */
+    fn can_write(&self) -> bool {
+        return false;
+    }
+
+    /** write() writes a molecule */
+    fn write(&self, _writer: &mut dyn Write, _mol: &Molecule) -> Result<(), IoError> {
+        return Err(IoError::parse(
+            self.get_name(),
+            0,
+            "molecules cannot be written in this format".to_string(),
+        ));
+    }
+
+    /**
+     * write_all() writes molecules to one file, formats holding one molecule a file write
+     * it alone and it is an error to give them more
+     */
+    fn write_all(&self, writer: &mut dyn Write, mols: &[Molecule]) -> Result<(), IoError> {
+        if mols.len() > 1 {
+            return Err(get_one_molecule_error(self.get_name(), mols.len()));
+        }
+        for mol in mols.iter() {
+            self.write(writer, mol)?;
+        }
+        return Ok(());
+    }
+}
+
+/** get_one_molecule_error() returns the error of writing several molecules in a one molecule format */
+fn get_one_molecule_error(name: &str, count: usize) -> IoError {
+    return IoError::parse(
+        name,
+        0,
+        format!(
+            "a file holds one molecule in this format, {} were given",
+            count
+        ),
+    );
+}
+
+/** the functions of a built-in format */
+type ReadFn = fn(&mut dyn BufRead) -> Result<Molecule, IoError>;
+type ReadAllFn = fn(&mut dyn BufRead) -> Result<Vec<Molecule>, IoError>;
+type WriteFn = fn(&mut dyn Write, &Molecule) -> Result<(), IoError>;
+type WriteAllFn = fn(&mut dyn Write, &[Molecule]) -> Result<(), IoError>;
+
+/** Builtin - a format of the io module */
+struct Builtin {
+    name: &'static str,
+    extensions: &'static [&'static str],
+    sniff: fn(&[&str]) -> bool,
+    read: ReadFn,
+    read_all: ReadAllFn,
+    write: Option<WriteFn>,
+    /** writes several molecules to one file, None if a file holds one molecule */
+    write_all: Option<WriteAllFn>,
+}
+
+impl MoleculeFormat for Builtin {
+    fn get_name(&self) -> &str {
+        return self.name;
+    }
+
+    fn get_extensions(&self) -> &[&str] {
+        return self.extensions;
+    }
+
+    fn sniff(&self, head: &str) -> bool {
+        let lines: Vec<&str> = head.lines().collect();
+        return (self.sniff)(&lines);
+    }
+
+    fn read(&self, reader: &mut dyn BufRead) -> Result<Molecule, IoError> {
+        return (self.read)(reader);
+    }
+
+    fn read_all(&self, reader: &mut dyn BufRead) -> Result<Vec<Molecule>, IoError> {
+        return (self.read_all)(reader);
+    }
+
+    fn can_write(&self) -> bool {
+        return self.write.is_some();
+    }
+
+    fn write(&self, writer: &mut dyn Write, mol: &Molecule) -> Result<(), IoError> {
+        match self.write {
+            Some(write) => return write(writer, mol),
+            None => {
+                return Err(IoError::parse(
+                    self.name,
+                    0,
+                    "molecules cannot be written in this format".to_string(),
+                ))
+            }
+        }
+    }
+
+    fn write_all(&self, writer: &mut dyn Write, mols: &[Molecule]) -> Result<(), IoError> {
+        match self.write_all {
+            Some(write_all) => return write_all(writer, mols),
+            _ if mols.len() > 1 => return Err(get_one_molecule_error(self.name, mols.len())),
+            _ => {
+                for mol in mols.iter() {
+                    self.write(writer, mol)?;
+                }
+                return Ok(());
+            }
+        }
+    }
+}
+
+/** write_each() writes molecules one after the other, for formats of several records */
+fn write_each(writer: &mut dyn Write, mols: &[Molecule], write: WriteFn) -> Result<(), IoError> {
+    for mol in mols.iter() {
+        write(writer, mol)?;
+    }
+    return Ok(());
+}
+
+/** are_numbers() returns true if the line has the given number of fields, all numbers */
+fn are_numbers(line: &str, count: usize) -> bool {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    return fields.len() == count && fields.iter().all(|field| field.parse::<f64>().is_ok());
+}
+
+/** is_count() returns true if the line is a number of atoms */
+fn is_count(line: &str) -> bool {
+    return line.trim().parse::<usize>().is_ok();
+}
+
+/** sniff_none() is the sniff of formats only known by their extension */
+fn sniff_none(_lines: &[&str]) -> bool {
+    return false;
+}
+
+fn sniff_cml(lines: &[&str]) -> bool {
+    let head = lines.join("\n");
+    return head.trim_start().starts_with('<')
+        && (head.contains("<cml") || head.contains("<molecule"));
+}
+
+#[cfg(feature = "serde")]
+fn sniff_cjson(lines: &[&str]) -> bool {
+    let head = lines.join("\n");
+    return head.trim_start().starts_with('{') && head.contains("\"atoms\"");
+}
+
+fn sniff_mol2(lines: &[&str]) -> bool {
+    return lines.iter().any(|line| line.starts_with("@<TRIPOS>"));
+}
+
+fn sniff_sdf(lines: &[&str]) -> bool {
+    return lines.len() > 3 && (lines[3].contains("V2000") || lines[3].contains("V3000"));
+}
+
+fn sniff_cif(lines: &[&str]) -> bool {
+    return lines.iter().any(|line| line.starts_with("data_"))
+        && lines.iter().any(|line| {
+            let line = line.trim_start();
+            line.starts_with("_atom_site") || line.starts_with("_cell_length")
+        });
+}
+
+fn sniff_lammps(lines: &[&str]) -> bool {
+    let has_atoms = lines.iter().any(|line| {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        fields.len() == 2 && is_count(fields[0]) && fields[1] == "atoms"
+    });
+    return has_atoms
+        && lines
+            .iter()
+            .any(|line| line.trim_end().ends_with("xlo xhi"));
+}
+
+fn sniff_vasp(lines: &[&str]) -> bool {
+    return lines.len() > 5
+        && (are_numbers(lines[1], 1) || are_numbers(lines[1], 3))
+        && lines[2..5].iter().all(|line| are_numbers(line, 3));
+}
+
+fn sniff_gaussian(lines: &[&str]) -> bool {
+    match lines
+        .iter()
+        .map(|line| line.trim())
+        .find(|line| !line.is_empty() && !line.starts_with('%'))
+    {
+        Some(line) => return line.starts_with('#'),
+        None => return false,
+    }
+}
+
+fn sniff_pdb(lines: &[&str]) -> bool {
+    const RECORDS: [&str; 8] = [
+        "HEADER", "TITLE ", "COMPND", "REMARK", "CRYST1", "MODEL ", "ATOM  ", "HETATM",
+    ];
+    return lines
+        .iter()
+        .any(|line| RECORDS.iter().any(|record| line.starts_with(record)))
+        && lines
+            .iter()
+            .all(|line| line.len() >= 6 || line.trim().is_empty() || line.starts_with("END"));
+}
+
+fn sniff_xyz(lines: &[&str]) -> bool {
+    if lines.len() < 3 || !is_count(lines[0]) {
+        return false;
+    }
+    let fields: Vec<&str> = lines[2].split_whitespace().collect();
+    return fields.len() >= 4 && fields[1..4].iter().all(|f| f.parse::<f64>().is_ok());
+}
+
+fn sniff_gro(lines: &[&str]) -> bool {
+    if lines.len() < 3 || !is_count(lines[1]) {
+        return false;
+    }
+    let fields: Vec<&str> = lines[2].split_whitespace().collect();
+    return lines[2].len() >= 44
+        && fields.len() >= 6
+        && fields[fields.len() - 3..]
+            .iter()
+            .all(|f| f.parse::<f64>().is_ok());
+}
+
+/** get_builtins() returns the formats of the io module, in the order they are sniffed */
+fn get_builtins() -> Vec<Builtin> {
+    let mut builtins = vec![Builtin {
+        name: "cml",
+        extensions: &["cml"],
+        sniff: sniff_cml,
+        read: |reader| io::cml::read(reader),
+        read_all: |reader| io::cml::read_all(reader),
+        write: Some(|writer, mol| io::cml::write(writer, mol)),
+        write_all: Some(|writer, mols| io::cml::CmlWriter::new().write_all(writer, mols)),
+    }];
+
+    #[cfg(feature = "serde")]
+    builtins.push(Builtin {
+        name: "cjson",
+        extensions: &["cjson"],
+        sniff: sniff_cjson,
+        read: |reader| io::cjson::read(reader),
+        read_all: |reader| Ok(vec![io::cjson::read(reader)?]),
+        write: Some(|writer, mol| io::cjson::write(writer, mol)),
+        write_all: None,
+    });
+
+    builtins.extend([
+        Builtin {
+            name: "mol2",
+            extensions: &["mol2"],
+            sniff: sniff_mol2,
+            read: |reader| io::mol2::read(reader),
+            read_all: |reader| io::mol2::Mol2Reader::new(reader).collect(),
+            write: Some(|writer, mol| io::mol2::write(writer, mol)),
+            write_all: Some(|writer, mols| {
+                write_each(writer, mols, |writer, mol| io::mol2::write(writer, mol))
+            }),
+        },
+        Builtin {
+            name: "sdf",
+            extensions: &["sdf", "sd"],
+            sniff: sniff_sdf,
+            read: |reader| io::mdl::read(reader),
+            read_all: |reader| io::mdl::SdfReader::new(reader).collect(),
+            write: Some(|writer, mol| io::mdl::write_record(writer, mol)),
+            write_all: Some(|writer, mols| {
+                write_each(writer, mols, |writer, mol| {
+                    io::mdl::write_record(writer, mol)
+                })
+            }),
+        },
+        Builtin {
+            name: "mol",
+            extensions: &["mol", "mdl"],
+            sniff: sniff_none,
+            read: |reader| io::mdl::read(reader),
+            read_all: |reader| io::mdl::SdfReader::new(reader).collect(),
+            write: Some(|writer, mol| io::mdl::write(writer, mol)),
+            write_all: None,
+        },
+        Builtin {
+            name: "cif",
+            extensions: &["cif", "mmcif"],
+            sniff: sniff_cif,
+            read: |reader| io::cif::read(reader),
+            read_all: |reader| io::cif::CifReader::new(reader).collect(),
+            write: None,
+            write_all: None,
+        },
+        Builtin {
+            name: "lammps",
+            extensions: &["lmp", "data"],
+            sniff: sniff_lammps,
+            read: |reader| io::lammps::read(reader),
+            read_all: |reader| Ok(vec![io::lammps::read(reader)?]),
+            write: Some(|writer, mol| io::lammps::write(writer, mol)),
+            write_all: None,
+        },
+        Builtin {
+            name: "vasp",
+            extensions: &["vasp", "poscar", "contcar"],
+            sniff: sniff_vasp,
+            read: |reader| io::vasp::read(reader),
+            read_all: |reader| Ok(vec![io::vasp::read(reader)?]),
+            write: Some(|writer, mol| io::vasp::write(writer, mol)),
+            write_all: None,
+        },
+        Builtin {
+            name: "gaussian",
+            extensions: &["gjf", "com", "gau"],
+            sniff: sniff_gaussian,
+            read: |reader| io::gaussian::read(reader),
+            read_all: |reader| Ok(vec![io::gaussian::read(reader)?]),
+            write: Some(|writer, mol| io::gaussian::write(writer, mol)),
+            write_all: None,
+        },
+        Builtin {
+            name: "pdb",
+            extensions: &["pdb", "ent"],
+            sniff: sniff_pdb,
+            read: |reader| io::pdb::read(reader),
+            read_all: |reader| Ok(vec![io::pdb::read(reader)?]),
+            write: Some(|writer, mol| io::pdb::write(writer, mol)),
+            write_all: None,
+        },
+        Builtin {
+            name: "xyz",
+            extensions: &["xyz", "extxyz"],
+            sniff: sniff_xyz,
+            read: |reader| io::xyz::read(reader),
+            read_all: |reader| io::xyz::XyzReader::new(reader).collect(),
+            write: Some(|writer, mol| io::xyz::write(writer, mol)),
+            write_all: Some(|writer, mols| {
+                write_each(writer, mols, |writer, mol| io::xyz::write(writer, mol))
+            }),
+        },
+        Builtin {
+            name: "gro",
+            extensions: &["gro"],
+            sniff: sniff_gro,
+            read: |reader| io::gromacs::read(reader),
+            read_all: |reader| Ok(vec![io::gromacs::read(reader)?]),
+            write: Some(|writer, mol| io::gromacs::write(writer, mol)),
+            write_all: None,
+        },
+        Builtin {
+            name: "smiles",
+            extensions: &["smi", "smiles"],
+            sniff: sniff_none,
+            read: |reader| io::smiles::read(reader),
+            read_all: |reader| io::smiles::SmilesReader::new(reader).collect(),
+            write: Some(|writer, mol| io::smiles::write(writer, mol)),
+            write_all: Some(|writer, mols| {
+                write_each(writer, mols, |writer, mol| io::smiles::write(writer, mol))
+            }),
+        },
+    ]);
+
+    return builtins;
+}
+
+/** FormatRegistry - the formats that files are read and written in */
+#[allow(dead_code)]
+pub struct FormatRegistry {
+    formats: Vec<Box<dyn MoleculeFormat>>,
+}
+
+#[allow(dead_code)]
+impl Default for FormatRegistry {
+    fn default() -> Self {
+        return FormatRegistry::new();
+    }
+}
+
+#[allow(dead_code)]
+impl FormatRegistry {
+    /** new() creates a registry of the built-in formats */
+    pub fn new() -> FormatRegistry {
+        let mut formats: Vec<Box<dyn MoleculeFormat>> = Vec::new();
+        for builtin in get_builtins() {
+            formats.push(Box::new(builtin));
+        }
+        FormatRegistry { formats: formats }
+    }
+
+    /** empty() creates a registry without any formats */
+    pub fn empty() -> FormatRegistry {
+        FormatRegistry {
+            formats: Vec::new(),
+        }
+    }
+
+    /** register() adds a format, it is tried before the formats added earlier */
+    pub fn register(&mut self, format: Box<dyn MoleculeFormat>) {
+        self.formats.insert(0, format);
+    }
+
+    /** get_names() returns the names of the formats, in the order they are tried */
+    pub fn get_names(&self) -> Vec<&str> {
+        return self
+            .formats
+            .iter()
+            .map(|format| format.get_name())
+            .collect();
+    }
+
+    /** get_format() returns the format with the given name or extension */
+    pub fn get_format(&self, name: &str) -> Option<&dyn MoleculeFormat> {
+        let name = name.to_lowercase();
+        let format = self
+            .formats
+            .iter()
+            .find(|format| format.get_name().to_lowercase() == name)
+            .or_else(|| {
+                self.formats
+                    .iter()
+                    .find(|format| format.get_extensions().contains(&name.as_str()))
+            });
+        return format.map(|format| format.as_ref());
+    }
+
+    /**
+     * get_format_of_path() returns the format matching the file name: its extension, else
+     * the whole name (POSCAR), else its part before the first '.' (data.water). A
+     * compression extension is left out, water.sdf.gz is an SD file.
+     */
+    pub fn get_format_of_path(&self, path: &Path) -> Option<&dyn MoleculeFormat> {
+        let path = compress::strip_extension(path);
+        let file_name = match path.file_name() {
+            Some(file_name) => file_name.to_string_lossy().to_lowercase(),
+            None => return None,
+        };
+
+        let mut names = Vec::new();
+        if let Some((_, extension)) = file_name.rsplit_once('.') {
+            names.push(extension.to_string());
+        }
+        names.push(file_name.clone());
+        if let Some((stem, _)) = file_name.split_once('.') {
+            names.push(stem.to_string());
+        }
+
+        for name in names.iter() {
+            let format = self
+                .formats
+                .iter()
+                .find(|format| format.get_extensions().contains(&name.as_str()));
+            if let Some(format) = format {
+                return Some(format.as_ref());
+            }
+        }
+        return None;
+    }
+
+    /** sniff() returns the first format that recognises the start of a file */
+    pub fn sniff(&self, head: &str) -> Option<&dyn MoleculeFormat> {
+        let format = self.formats.iter().find(|format| format.sniff(head));
+        return format.map(|format| format.as_ref());
+    }
+
+    /** detect() returns the format of a file from its name, else from its contents */
+    pub fn detect(&self, path: &Path) -> Result<&dyn MoleculeFormat, IoError> {
+        if let Some(format) = self.get_format_of_path(path) {
+            return Ok(format);
+        }
+
+        let mut reader = compress::open(path)?;
+        let head = String::from_utf8_lossy(reader.fill_buf()?).to_string();
+        match self.sniff(&head) {
+            Some(format) => return Ok(format),
+            None => {
+                return Err(IoError::parse(
+                    &path.display().to_string(),
+                    0,
+                    "cannot tell the format of the file, give the format explicitly".to_string(),
+                ))
+            }
+        }
+    }
+
+    /** get_named_format() returns the format with the given name, it is an error if none has it */
+    fn get_named_format(&self, name: &str) -> Result<&dyn MoleculeFormat, IoError> {
+        match self.get_format(name) {
+            Some(format) => return Ok(format),
+            None => {
+                return Err(IoError::parse(
+                    name,
+                    0,
+                    format!(
+                        "unknown format, known formats are {}",
+                        self.get_names().join(", ")
+                    ),
+                ))
+            }
+        }
+    }
+
+    /** read() reads the first molecule of a file in the format detected */
+    pub fn read<P: AsRef<Path>>(&self, path: P) -> Result<Molecule, IoError> {
+        let path = path.as_ref();
+        return read_with(self.detect(path)?, path);
+    }
+
+    /** read_as() reads the first molecule of a file in the named format */
+    pub fn read_as<P: AsRef<Path>>(&self, path: P, format: &str) -> Result<Molecule, IoError> {
+        return read_with(self.get_named_format(format)?, path.as_ref());
+    }
+
+    /** read_all() reads all the molecules of a file in the format detected */
+    pub fn read_all<P: AsRef<Path>>(&self, path: P) -> Result<Vec<Molecule>, IoError> {
+        let path = path.as_ref();
+        return read_all_with(self.detect(path)?, path);
+    }
+
+    /** read_all_as() reads all the molecules of a file in the named format */
+    pub fn read_all_as<P: AsRef<Path>>(
+        &self,
+        path: P,
+        format: &str,
+    ) -> Result<Vec<Molecule>, IoError> {
+        return read_all_with(self.get_named_format(format)?, path.as_ref());
+    }
+
+    /** write() writes a molecule to a file in the format of its name */
+    pub fn write<P: AsRef<Path>>(&self, path: P, mol: &Molecule) -> Result<(), IoError> {
+        let path = path.as_ref();
+        return write_with(
+            self.get_writable_format(path)?,
+            path,
+            std::slice::from_ref(mol),
+        );
+    }
+
+    /** write_as() writes a molecule to a file in the named format */
+    pub fn write_as<P: AsRef<Path>>(
+        &self,
+        path: P,
+        mol: &Molecule,
+        format: &str,
+    ) -> Result<(), IoError> {
+        return write_with(
+            self.get_named_format(format)?,
+            path.as_ref(),
+            std::slice::from_ref(mol),
+        );
+    }
+
+    /**
+     * write_all() writes molecules to a file in the format of its name, it is an error to
+     * give several molecules to a format holding one a file, e.g. pdb
+     */
+    pub fn write_all<P: AsRef<Path>>(&self, path: P, mols: &[Molecule]) -> Result<(), IoError> {
+        let path = path.as_ref();
+        return write_with(self.get_writable_format(path)?, path, mols);
+    }
+
+    /** get_writable_format() returns the format of a file name, for writing */
+    fn get_writable_format(&self, path: &Path) -> Result<&dyn MoleculeFormat, IoError> {
+        match self.get_format_of_path(path) {
+            Some(format) => return Ok(format),
+            None => {
+                return Err(IoError::parse(
+                    &path.display().to_string(),
+                    0,
+                    "cannot tell the format from the file name, give the format explicitly"
+                        .to_string(),
+                ))
+            }
+        }
+    }
+}
+
+/** read_with() reads the first molecule of a file in the given format */
+fn read_with(format: &dyn MoleculeFormat, path: &Path) -> Result<Molecule, IoError> {
+    let mut reader = compress::open(path)?;
+    return format
+        .read(&mut reader)
+        .map_err(|err| locate_error(err, format, path));
+}
+
+/** read_all_with() reads all the molecules of a file in the given format */
+fn read_all_with(format: &dyn MoleculeFormat, path: &Path) -> Result<Vec<Molecule>, IoError> {
+    let mut reader = compress::open(path)?;
+    return format
+        .read_all(&mut reader)
+        .map_err(|err| locate_error(err, format, path));
+}
+
+/** write_with() writes molecules to a file in the given format */
+fn write_with(format: &dyn MoleculeFormat, path: &Path, mols: &[Molecule]) -> Result<(), IoError> {
+    if !format.can_write() {
+        return Err(IoError::parse(
+            format.get_name(),
+            0,
+            "molecules cannot be written in this format".to_string(),
+        ));
+    }
+
+    let mut writer = compress::create(path)?;
+    format.write_all(&mut writer, mols)?;
+    return writer.finish();
+}
+
+/**
+ * locate_error() adds the file to a parse error, and quotes the line it is on when the
+ * error has a line number
+ */
+fn locate_error(err: IoError, format: &dyn MoleculeFormat, path: &Path) -> IoError {
+    let (line_number, message) = match err {
+        IoError::Parse {
+            line_number,
+            message,
+            ..
+        } => (line_number, message),
+        err => return err,
+    };
+
+    let location = format!("{} ({})", path.display(), format.get_name());
+    let line = match line_number {
+        0 => None,
+        _ => get_line(path, line_number),
+    };
+
+    match line {
+        Some(line) if !line.trim().is_empty() => {
+            let mut line = line.trim_end().to_string();
+            if let Some((end, _)) = line.char_indices().nth(MAX_QUOTED_LENGTH) {
+                line.truncate(end);
+                line.push_str("...");
+            }
+            return IoError::parse(&location, line_number, format!("{}\n    {}", message, line));
+        }
+        _ => return IoError::parse(&location, line_number, message),
+    }
+}
+
+/** get_line() returns a line of a file, None if the file has fewer lines */
+fn get_line(path: &Path, line_number: usize) -> Option<String> {
+    let reader = compress::open(path).ok()?;
+    return reader.lines().nth(line_number - 1)?.ok();
+}
+
+static REGISTRY: OnceLock<RwLock<FormatRegistry>> = OnceLock::new();
+
+/** get_registry() returns the registry used by the read and write functions of the io module */
+pub(crate) fn get_registry() -> RwLockReadGuard<'static, FormatRegistry> {
+    let registry = REGISTRY.get_or_init(|| RwLock::new(FormatRegistry::new()));
+    return registry.read().unwrap_or_else(|err| err.into_inner());
+}
+
+/** register_format() adds a format to the registry used by the io module */
+pub(crate) fn register_format(format: Box<dyn MoleculeFormat>) {
+    let registry = REGISTRY.get_or_init(|| RwLock::new(FormatRegistry::new()));
+    let mut registry = registry.write().unwrap_or_else(|err| err.into_inner());
+    registry.register(format);
+}
+
+/** Unit tests for the above module */
+#[cfg(test)]
+mod tests {
+    use std::io::BufRead;
+    use std::path::Path;
+
+    use crate::atom::AtomOperations;
+    use crate::io::registry::FormatRegistry;
+    use crate::io::registry::MoleculeFormat;
+    use crate::io::IoError;
+    use crate::molecule::Molecule;
+    use crate::testutil;
+
+    /** Symbols - a format of one element symbol per line, all atoms at the origin */
+    struct Symbols;
+
+    impl MoleculeFormat for Symbols {
+        fn get_name(&self) -> &str {
+            return "symbols";
+        }
+
+        fn get_extensions(&self) -> &[&str] {
+            return &["sym"];
+        }
+
+        fn read(&self, reader: &mut dyn BufRead) -> Result<Molecule, IoError> {
+            let mut mol = Molecule::new("symbols".to_string(), "".to_string());
+            for line in reader.lines() {
+                mol.add_atom(testutil::get_atom(line?.trim(), 0.0, 0.0, 0.0));
+            }
+            return Ok(mol);
+        }
+    }
+
+    #[test]
+    fn registry_detect() {
+        let registry = FormatRegistry::new();
+        let name_of_path = |path: &str| {
+            registry
+                .get_format_of_path(Path::new(path))
+                .map(|format| format.get_name().to_string())
+        };
+
+        assert_eq!(name_of_path("ligands.SDF"), Some("sdf".to_string()));
+        assert_eq!(name_of_path("run/POSCAR"), Some("vasp".to_string()));
+        assert_eq!(name_of_path("data.water"), Some("lammps".to_string()));
+        assert_eq!(name_of_path("water.out"), None);
+        assert_eq!(registry.get_format("GJF").unwrap().get_name(), "gaussian");
+
+        let sniff = |head: &str| registry.sniff(head).map(|format| format.get_name());
+        assert_eq!(sniff("3\nwater\nO 0.0 0.0 0.1173\n"), Some("xyz"));
+        assert_eq!(
+            sniff("water\n  1\n    1SOL     OW    1   0.126   1.624   1.679\n"),
+            Some("gro")
+        );
+        assert_eq!(
+            sniff("water\n  fekit\n\n  3  2  0  0  0  0  0  0  0  0999 V2000\n"),
+            Some("sdf")
+        );
+        assert_eq!(
+            sniff("@<TRIPOS>MOLECULE\nwater\n 3 2 0 0 0\n"),
+            Some("mol2")
+        );
+        assert_eq!(
+            sniff("Si\n1.0\n5.43 0 0\n0 5.43 0\n0 0 5.43\nSi\n2\nDirect\n"),
+            Some("vasp")
+        );
+        assert_eq!(
+            sniff("%chk=water\n#p hf/sto-3g\n\nwater\n"),
+            Some("gaussian")
+        );
+        assert_eq!(
+            sniff(
+                "HETATM    1  O   HOH A   1       0.000   0.000   0.117  1.00  0.00           O\n"
+            ),
+            Some("pdb")
+        );
+        assert_eq!(sniff("CCO ethanol\n"), None);
+    }
+
+    #[test]
+    fn registry_read_write() {
+        let water = testutil::get_water();
+
+        let path = testutil::get_path("registry", "water.sdf");
+        crate::io::write(&path, &water).unwrap();
+        let mol = crate::io::read(&path).unwrap();
+        assert_eq!(mol.name, "water");
+        assert_eq!(mol.get_atoms().len(), 3);
+
+        crate::io::registry::get_registry()
+            .write_all(&path, &[testutil::get_water(), testutil::get_water()])
+            .unwrap();
+        assert_eq!(crate::io::read_all(&path).unwrap().len(), 2);
+        std::fs::remove_file(&path).unwrap();
+
+        // one <cml> root for several molecules, and one molecule a file for pdb
+        let path = testutil::get_path("registry", "waters.cml");
+        let registry = FormatRegistry::new();
+        registry
+            .write_all(&path, &[testutil::get_water(), testutil::get_water()])
+            .unwrap();
+        let contents = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(contents.matches("<cml").count(), 1);
+        assert_eq!(registry.read_all(&path).unwrap().len(), 2);
+        std::fs::remove_file(&path).unwrap();
+
+        let path = testutil::get_path("registry", "waters.pdb");
+        let err = registry
+            .write_all(&path, &[testutil::get_water(), testutil::get_water()])
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "pdb: a file holds one molecule in this format, 2 were given"
+        );
+        registry.write_all(&path, &[testutil::get_water()]).unwrap();
+        assert_eq!(registry.read(&path).unwrap().get_atoms().len(), 3);
+        std::fs::remove_file(&path).unwrap();
+
+        // compressed files, the contents of water.gz are sniffed after decompressing
+        let path = testutil::get_path("registry", "water.xyz.gz");
+        crate::io::write(&path, &water).unwrap();
+        let gzip_path = testutil::get_path("registry", "water.gz");
+        std::fs::rename(&path, &gzip_path).unwrap();
+        let mol = crate::io::read(&gzip_path).unwrap();
+        assert_eq!(mol.get_atoms().len(), 3);
+        std::fs::remove_file(&gzip_path).unwrap();
+
+        // the contents give the format when the name does not
+        let path = testutil::get_path("registry", "water.txt");
+        crate::io::write_as(&path, &water, "xyz").unwrap();
+        let mol = crate::io::read(&path).unwrap();
+        assert_eq!(mol.get_atoms()[0].symbol, "O");
+        let mol = crate::io::read_as(&path, "xyz").unwrap();
+        assert_eq!(mol.get_atoms().len(), 3);
+
+        let err = crate::io::read_as(&path, "abc").unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("abc: unknown format, known formats are"));
+        let err = crate::io::write(&path, &water).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "{}: cannot tell the format from the file name, give the format explicitly",
+                path.display()
+            )
+        );
+        std::fs::remove_file(&path).unwrap();
+
+        let err = crate::io::write_as(testutil::get_path("registry", "water.cif"), &water, "cif")
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "cif: molecules cannot be written in this format"
+        );
+    }
+
+    #[test]
+    fn registry_register() {
+        let path = testutil::get_path("registry", "water.sym");
+        std::fs::write(&path, "O\nH\nH\n").unwrap();
+
+        let mut registry = FormatRegistry::new();
+        assert!(registry.read(&path).is_err());
+        registry.register(Box::new(Symbols));
+        assert_eq!(registry.get_names()[0], "symbols");
+        assert_eq!(registry.read(&path).unwrap().get_atoms().len(), 3);
+
+        crate::io::register_format(Box::new(Symbols));
+        let mol = crate::io::read(&path).unwrap();
+        assert_eq!(mol.get_atoms()[2].symbol, "H");
+        assert!(crate::io::write(&path, &mol).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn registry_read_errors() {
+        let path = testutil::get_path("registry", "bad.xyz");
+        std::fs::write(&path, "3\nwater\nO 0.0 0.0 0.1173\nH 0.0 0.7572\n").unwrap();
+
+        let err = crate::io::read(&path).unwrap_err();
+        match &err {
+            IoError::Parse { line_number, .. } => assert_eq!(*line_number, 4),
+            _ => panic!("expected a parse error"),
+        }
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "{} (xyz): line 4: expected 4 fields in the atom line, found 3\n    H 0.0 0.7572",
+                path.display()
+            )
+        );
+        std::fs::remove_file(&path).unwrap();
+
+        let path = testutil::get_path("registry", "missing.xyz");
+        assert!(matches!(crate::io::read(&path), Err(IoError::Io(_))));
+
+        let path = testutil::get_path("registry", "water.dat");
+        std::fs::write(&path, "water\n").unwrap();
+        let err = crate::io::read(&path).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "{}: cannot tell the format of the file, give the format explicitly",
+                path.display()
+            )
+        );
+        std::fs::remove_file(&path).unwrap();
+    }
+}
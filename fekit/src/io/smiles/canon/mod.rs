@@ -0,0 +1,269 @@
+/**
+ * Author: V. Ganesh
+ * License: MIT
+ */
+
+/**
+ * canon module, canonical ranking of the atoms of a molecular graph.
+ *
+ * Atoms are first ranked by their invariants, then the ranks are refined by the ranks
+ * of their neighbours (and the bonds to them) until no more atoms can be told apart, as
+ * in CANON (Weininger, Weininger and Weininger, 1989). Chiral atoms are also told apart
+ * by their sense with the neighbours in order of rank. Ties left are broken one at a time,
+ * lowest first, by giving one of the tied atoms a rank of its own and refining again. The
+ * tied atoms are symmetry equivalent in most graphs, but not all (e.g. around chiral
+ * atoms whose neighbours are tied), so at each tie every tied atom is tried: the ties
+ * after it are broken by their first atom and the atom giving the lowest key (the SMILES
+ * written) is kept. This takes polynomial time, as only one ranking goes on from each tie.
+ */
+
+/** get_dense_ranks() ranks the keys, equal keys get equal ranks, counting from 0 */
+fn get_dense_ranks<K: Ord + Clone>(keys: &[K]) -> Vec<usize> {
+    let mut sorted: Vec<K> = keys.to_vec();
+    sorted.sort();
+    sorted.dedup();
+
+    return keys
+        .iter()
+        .map(|key| sorted.binary_search(key).unwrap_or(0))
+        .collect();
+}
+
+/**
+ * Chirality - a tetrahedral centre, given by its neighbours in the order its sense refers
+ * to (None for a hydrogen that is not in the graph, it comes before all atoms) and whether,
+ * looking from the first of them, the others are anticlockwise
+ */
+pub(super) struct Chirality {
+    pub neighbours: Vec<Option<usize>>,
+    pub anticlockwise: bool,
+}
+
+/** Graph - the atoms to rank, their invariants, neighbours and chiralities */
+struct Graph<'a> {
+    invariants: &'a [Vec<i64>],
+    neighbours: &'a [Vec<(usize, i64)>],
+    chiralities: &'a [Option<Chirality>],
+}
+
+impl Graph<'_> {
+    /**
+     * get_parity() returns the sense of a chiral atom with its neighbours in order of rank,
+     * 1 for anticlockwise, -1 for clockwise, 0 if the atom is not chiral or two of its
+     * neighbours have the same rank
+     */
+    fn get_parity(&self, atom: usize, ranks: &[usize]) -> i64 {
+        let chirality = match &self.chiralities[atom] {
+            Some(chirality) => chirality,
+            None => return 0,
+        };
+
+        let mut order: Vec<i64> = chirality
+            .neighbours
+            .iter()
+            .map(|n| n.map(|other| ranks[other] as i64).unwrap_or(-1))
+            .collect();
+
+        let mut swaps = 0;
+        for i in 0..order.len() {
+            for j in 0..order.len() - 1 - i {
+                if order[j] == order[j + 1] {
+                    return 0;
+                }
+                if order[j] > order[j + 1] {
+                    order.swap(j, j + 1);
+                    swaps += 1;
+                }
+            }
+        }
+
+        match chirality.anticlockwise == (swaps % 2 == 0) {
+            true => return 1,
+            false => return -1,
+        }
+    }
+
+    /**
+     * refine() splits the ranks by the ranks of the neighbours and the sense of the chiral
+     * atoms, until they no longer change
+     */
+    fn refine(&self, ranks: Vec<usize>) -> Vec<usize> {
+        let mut ranks = ranks;
+        let mut number_of_classes = get_number_of_classes(&ranks);
+
+        loop {
+            let keys: Vec<_> = self
+                .neighbours
+                .iter()
+                .enumerate()
+                .map(|(atom, list)| {
+                    let mut around: Vec<(usize, i64)> = list
+                        .iter()
+                        .map(|(other, bond)| (ranks[*other], *bond))
+                        .collect();
+                    around.sort();
+                    (ranks[atom], around, self.get_parity(atom, &ranks))
+                })
+                .collect();
+
+            let refined = get_dense_ranks(&keys);
+            let refined_classes = get_number_of_classes(&refined);
+            if refined_classes == number_of_classes {
+                return ranks;
+            }
+
+            ranks = refined;
+            number_of_classes = refined_classes;
+        }
+    }
+
+    /**
+     * are_twins() tells whether swapping two atoms leaves the graph as it is: they have the
+     * same invariants and the same neighbours, and neither they nor those neighbours are
+     * chiral. Ranking one of them first gives the same string as ranking the other.
+     */
+    fn are_twins(&self, atom_1: usize, atom_2: usize) -> bool {
+        if self.invariants[atom_1] != self.invariants[atom_2]
+            || self.chiralities[atom_1].is_some()
+            || self.chiralities[atom_2].is_some()
+        {
+            return false;
+        }
+
+        let others_of = |atom: usize, other: usize| {
+            let mut list: Vec<(usize, i64)> = self.neighbours[atom]
+                .iter()
+                .filter(|(n, _)| *n != other)
+                .copied()
+                .collect();
+            list.sort();
+            list
+        };
+        let others = others_of(atom_1, atom_2);
+        return others == others_of(atom_2, atom_1)
+            && others.iter().all(|(n, _)| self.chiralities[*n].is_none());
+    }
+
+    /** break_tie() gives an atom a rank of its own, below the atoms tied with it, and refines */
+    fn break_tie(&self, ranks: &[usize], chosen: usize) -> Vec<usize> {
+        let keys: Vec<(usize, bool)> = ranks
+            .iter()
+            .enumerate()
+            .map(|(atom, rank)| (*rank, atom != chosen))
+            .collect();
+        return self.refine(get_dense_ranks(&keys));
+    }
+
+    /** complete() breaks the ties left by the first atom of each, lowest tie first */
+    fn complete(&self, ranks: Vec<usize>) -> Vec<usize> {
+        let mut ranks = ranks;
+        while let Some(tied) = get_lowest_tie(&ranks) {
+            let chosen = ranks.iter().position(|rank| *rank == tied).unwrap_or(0);
+            ranks = self.break_tie(&ranks, chosen);
+        }
+        return ranks;
+    }
+
+    /**
+     * search() breaks the lowest tie by each of the tied atoms in turn (one of each set of
+     * twins), completes the ranks with the first atom of every tie after it, and goes on
+     * with the atom whose completed ranks have the lowest key, until all atoms differ
+     */
+    fn search<K: Ord, F: FnMut(&[usize]) -> K>(
+        &self,
+        ranks: Vec<usize>,
+        key_of: &mut F,
+    ) -> Vec<usize> {
+        let mut ranks = ranks;
+
+        while let Some(tied) = get_lowest_tie(&ranks) {
+            let mut best: Option<(K, Vec<usize>)> = None;
+            let mut tried: Vec<usize> = Vec::new();
+            for chosen in (0..ranks.len()).filter(|atom| ranks[*atom] == tied) {
+                if tried.iter().any(|atom| self.are_twins(*atom, chosen)) {
+                    continue;
+                }
+                tried.push(chosen);
+
+                let broken = self.break_tie(&ranks, chosen);
+                let key = key_of(&self.complete(broken.clone()));
+                if best.as_ref().is_none_or(|(best_key, _)| key < *best_key) {
+                    best = Some((key, broken));
+                }
+            }
+
+            match best {
+                Some((_, broken)) => ranks = broken,
+                None => break,
+            }
+        }
+
+        return ranks;
+    }
+}
+
+/** get_lowest_tie() returns the lowest rank shared by more than one atom, None if all differ */
+fn get_lowest_tie(ranks: &[usize]) -> Option<usize> {
+    let mut counts = vec![0; ranks.len()];
+    for rank in ranks.iter() {
+        counts[*rank] += 1;
+    }
+    return counts.iter().position(|count| *count > 1);
+}
+
+fn get_number_of_classes(ranks: &[usize]) -> usize {
+    return ranks.iter().max().map(|max| max + 1).unwrap_or(0);
+}
+
+/**
+ * get_canonical_ranks() returns a distinct rank for each atom, which does not depend on
+ * the order of the atoms, given the invariants of each atom, the neighbours of each atom
+ * with a code for the bond to them, and the chiral atoms. Each tie is broken by the atom
+ * giving the lowest key, e.g. the SMILES written with the ranks.
+ */
+pub(super) fn get_canonical_ranks<K: Ord, F: FnMut(&[usize]) -> K>(
+    invariants: &[Vec<i64>],
+    neighbours: &[Vec<(usize, i64)>],
+    chiralities: &[Option<Chirality>],
+    mut key_of: F,
+) -> Vec<usize> {
+    let graph = Graph {
+        invariants: invariants,
+        neighbours: neighbours,
+        chiralities: chiralities,
+    };
+
+    return graph.search(graph.refine(get_dense_ranks(invariants)), &mut key_of);
+}
+
+/** Unit tests for the above module */
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn canon_ranks() {
+        // propane, C-C-C: the middle atom differs, the ends are tied and broken apart
+        let invariants = vec![vec![6], vec![6], vec![6]];
+        let neighbours = vec![vec![(1, 1)], vec![(0, 1), (2, 1)], vec![(1, 1)]];
+        let no_chirality = [None, None, None];
+        let key_of = |ranks: &[usize]| ranks.to_vec();
+        let ranks = super::get_canonical_ranks(&invariants, &neighbours, &no_chirality, key_of);
+        assert_eq!(ranks[1], 2);
+        assert_ne!(ranks[0], ranks[2]);
+
+        // ethanol, the ranks follow the atoms whatever their order
+        let ranks = super::get_canonical_ranks(
+            &[vec![8], vec![6], vec![6]],
+            &[vec![(1, 1)], vec![(0, 1), (2, 1)], vec![(1, 1)]],
+            &no_chirality,
+            key_of,
+        );
+        let reordered = super::get_canonical_ranks(
+            &[vec![6], vec![6], vec![8]],
+            &[vec![(1, 1)], vec![(0, 1), (2, 1)], vec![(1, 1)]],
+            &no_chirality,
+            key_of,
+        );
+        assert_eq!(ranks, vec![2, 1, 0]);
+        assert_eq!(reordered, vec![0, 1, 2]);
+    }
+}
@@ -0,0 +1,1831 @@
+/**
+ * Author: V. Ganesh
+ * License: MIT
+ */
+
+/**
+ * smiles module, reads and writes SMILES strings and SMILES files (one SMILES and name per
+ * line). SmilesWriter writes canonical SMILES, the rest of this describes reading.
+ *
+ * The organic subset (B C N O P S F Cl Br I and aromatic b c n o p s), bracket atoms with
+ * isotope, chirality, hydrogen count, charge and atom class, branches, ring closures
+ * (including %nn) and '.' separated parts are read. Aromatic atoms are read as their
+ * element, bonds between aromatic atoms as aromatic bonds. Directional bonds (/ and \)
+ * are read as single bonds. SMILES has no coordinates, so all atoms are at the origin.
+ *
+ * Hydrogens are kept as counts in the "implicit_hydrogens" atom property, only [H] atoms
+ * are added as atoms. Atoms of the organic subset get the hydrogens needed to reach the
+ * lowest default valence at or above their bond order sum (B 3, C 4, N 3 5, O 2, P 3 5,
+ * S 2 4 6, halogens 1), aromatic atoms up to 3 bonds for c and 2 for the others, with
+ * aromatic bonds counted as 1, so pyrrole must be written with [nH].
+ *
+ * Isotopes are kept in the "isotope" atom property, atom classes in "atom_class", and
+ * tetrahedral chirality in "chirality" as "@" or "@@". As bonds are not stored in SMILES
+ * order, the chirality is not the one written but the one seen with the neighbours in
+ * order of atom index, an implicit hydrogen first: "@" means that looking from the first
+ * neighbour the others are anticlockwise.
+ */
+use std::collections::BTreeMap;
+use std::collections::VecDeque;
+use std::io::BufRead;
+use std::io::Write;
+
+use crate::atom::Atom;
+use crate::atom::AtomOperations;
+use crate::atomprop;
+use crate::bond::BondType;
+use crate::io::IoError;
+use crate::io::LineReader;
+use crate::molecule::Molecule;
+use crate::point::Point;
+use crate::property::AtomProperty;
+
+mod canon;
+
+const FORMAT: &str = "smiles";
+
+/** symbol of the wildcard atom * */
+const WILDCARD: &str = "*";
+
+/** the aromatic symbols allowed in brackets, and (the one letter ones) in the organic subset */
+const AROMATIC_SYMBOLS: [&str; 9] = ["b", "c", "n", "o", "p", "s", "se", "as", "te"];
+
+/** placeholder neighbour of an open ring bond, until it is closed */
+const OPEN_RING: usize = usize::MAX;
+
+/** get_default_valences() returns the valences of an element of the organic subset */
+fn get_default_valences(symbol: &str) -> &'static [i64] {
+    match symbol {
+        "B" => return &[3],
+        "C" => return &[4],
+        "N" | "P" => return &[3, 5],
+        "O" => return &[2],
+        "S" => return &[2, 4, 6],
+        "F" | "Cl" | "Br" | "I" => return &[1],
+        _ => return &[],
+    }
+}
+
+/** get_implicit_hydrogens() returns the hydrogens of an organic subset atom, from its bonds */
+fn get_implicit_hydrogens(symbol: &str, is_aromatic: bool, bond_order_sum: i64) -> i64 {
+    if is_aromatic {
+        // aromatic b and c have valence 3, the other aromatic atoms 2
+        let target = if symbol == "B" || symbol == "C" { 3 } else { 2 };
+        return (target - bond_order_sum).max(0);
+    }
+
+    match get_default_valences(symbol)
+        .iter()
+        .find(|valence| **valence >= bond_order_sum)
+    {
+        Some(valence) => return valence - bond_order_sum,
+        None => return 0,
+    }
+}
+
+/** BracketAtom - the contents of a bracket atom */
+struct BracketAtom {
+    isotope: i64,
+    symbol: String,
+    is_aromatic: bool,
+    chirality: String,
+    hydrogens: i64,
+    charge: i64,
+    atom_class: i64,
+}
+
+/** Parser - reads one SMILES string */
+struct Parser {
+    chars: Vec<char>,
+    position: usize,
+    atoms: Vec<Atom>,
+    bonds: Vec<(usize, usize, BondType)>,
+    is_aromatic: Vec<bool>,
+    hydrogens: Vec<Option<i64>>,
+    isotopes: Vec<i64>,
+    chiralities: Vec<String>,
+    atom_classes: Vec<i64>,
+    neighbours: Vec<Vec<Option<usize>>>,
+    rings: BTreeMap<u32, (usize, usize, Option<BondType>)>,
+}
+
+impl Parser {
+    fn new(smiles: &str) -> Parser {
+        Parser {
+            chars: smiles.chars().collect(),
+            position: 0,
+            atoms: Vec::new(),
+            bonds: Vec::new(),
+            is_aromatic: Vec::new(),
+            hydrogens: Vec::new(),
+            isotopes: Vec::new(),
+            chiralities: Vec::new(),
+            atom_classes: Vec::new(),
+            neighbours: Vec::new(),
+            rings: BTreeMap::new(),
+        }
+    }
+
+    /** error() returns an error message for the current (1 based) position */
+    fn error(&self, message: &str) -> String {
+        return format!("position {}: {}", self.position + 1, message);
+    }
+
+    fn peek(&self) -> Option<char> {
+        return self.chars.get(self.position).copied();
+    }
+
+    /** read_number() reads the digits at the current position, None if there are none */
+    fn read_number(&mut self) -> Option<i64> {
+        let start = self.position;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.position += 1;
+        }
+
+        let digits: String = self.chars[start..self.position].iter().collect();
+        return digits.parse().ok();
+    }
+
+    /** add_atom() adds an atom, hydrogens is None for the organic subset */
+    fn add_atom(&mut self, symbol: &str, is_aromatic: bool, hydrogens: Option<i64>) -> usize {
+        self.atoms.push(Atom {
+            center: Point {
+                x: 0.0,
+                y: 0.0,
+                z: 0.0,
+            },
+            charge: 0.0,
+            symbol: symbol.to_string(),
+            remark: "".to_string(),
+        });
+        self.is_aromatic.push(is_aromatic);
+        self.hydrogens.push(hydrogens);
+        self.isotopes.push(0);
+        self.chiralities.push("".to_string());
+        self.atom_classes.push(0);
+        self.neighbours.push(Vec::new());
+
+        return self.atoms.len() - 1;
+    }
+
+    /** read_organic_atom() reads an atom of the organic subset, or the wildcard */
+    fn read_organic_atom(&mut self) -> Result<usize, String> {
+        let c = self.peek().unwrap_or(' ');
+        let next = self.chars.get(self.position + 1).copied();
+
+        let (symbol, is_aromatic, length) = match (c, next) {
+            ('C', Some('l')) => ("Cl".to_string(), false, 2),
+            ('B', Some('r')) => ("Br".to_string(), false, 2),
+            ('B' | 'C' | 'N' | 'O' | 'P' | 'S' | 'F' | 'I', _) => (c.to_string(), false, 1),
+            ('b' | 'c' | 'n' | 'o' | 'p' | 's', _) => (c.to_ascii_uppercase().to_string(), true, 1),
+            ('*', _) => (WILDCARD.to_string(), false, 1),
+            _ => return Err(self.error(&format!("unexpected character {}", c))),
+        };
+
+        self.position += length;
+        return Ok(self.add_atom(&symbol, is_aromatic, None));
+    }
+
+    /** read_bracket() reads a bracket atom, [isotope symbol chirality hydrogens charge :class] */
+    fn read_bracket(&mut self) -> Result<BracketAtom, String> {
+        self.position += 1;
+        let isotope = self.read_number().unwrap_or(0);
+
+        let (symbol, is_aromatic) = if self.peek() == Some('*') {
+            (WILDCARD.to_string(), false)
+        } else {
+            let letters: Vec<char> = self.chars[self.position..]
+                .iter()
+                .take_while(|c| c.is_ascii_alphabetic())
+                .take(2)
+                .copied()
+                .collect();
+            let first = *letters
+                .first()
+                .ok_or_else(|| self.error("expected an element in the bracket atom"))?;
+            let is_aromatic = first.is_ascii_lowercase();
+
+            // a second lower case letter is part of the symbol if that makes an element
+            let two: String = letters.iter().collect();
+            let symbol = if letters.len() == 2
+                && letters[1].is_ascii_lowercase()
+                && ((is_aromatic && AROMATIC_SYMBOLS.contains(&two.as_str()))
+                    || (!is_aromatic && atomprop::get_atomic_number(&two).is_some()))
+            {
+                two.to_string()
+            } else {
+                first.to_string()
+            };
+
+            if is_aromatic && !AROMATIC_SYMBOLS.contains(&symbol.as_str()) {
+                return Err(self.error(&format!("{} is not an aromatic element", symbol)));
+            }
+            if !is_aromatic && atomprop::get_atomic_number(&symbol).is_none() {
+                let written = if letters.len() == 2 && letters[1].is_ascii_lowercase() {
+                    two
+                } else {
+                    symbol
+                };
+                return Err(self.error(&format!("unknown element {}", written)));
+            }
+            (symbol, is_aromatic)
+        };
+        self.position += symbol.len();
+        let symbol = normalize_symbol(&symbol);
+
+        let mut chirality = "".to_string();
+        if self.peek() == Some('@') {
+            self.position += 1;
+            chirality = "@".to_string();
+            let class: String = self.chars[self.position..].iter().take(2).collect();
+            if self.peek() == Some('@') {
+                self.position += 1;
+                chirality = "@@".to_string();
+            } else if class == "TH" {
+                self.position += 2;
+                match self.read_number() {
+                    Some(1) => chirality = "@".to_string(),
+                    Some(2) => chirality = "@@".to_string(),
+                    _ => return Err(self.error("expected @TH1 or @TH2")),
+                }
+            } else if ["AL", "SP", "TB", "OH"].contains(&class.as_str()) {
+                return Err(self.error("only tetrahedral chirality is supported"));
+            }
+        }
+
+        let mut hydrogens = 0;
+        if self.peek() == Some('H') {
+            self.position += 1;
+            hydrogens = self.read_number().unwrap_or(1);
+        }
+
+        let mut charge = 0;
+        if let Some(sign @ ('+' | '-')) = self.peek() {
+            self.position += 1;
+            let sign = if sign == '+' { 1 } else { -1 };
+            charge = match self.read_number() {
+                Some(number) => sign * number,
+                None => {
+                    // ++ and -- are the old way of writing +2 and -2
+                    let mut count = 1;
+                    while self.peek() == Some(if sign > 0 { '+' } else { '-' }) {
+                        self.position += 1;
+                        count += 1;
+                    }
+                    sign * count
+                }
+            };
+        }
+
+        let mut atom_class = 0;
+        if self.peek() == Some(':') {
+            self.position += 1;
+            atom_class = self
+                .read_number()
+                .ok_or_else(|| self.error("expected an atom class after :"))?;
+        }
+
+        if self.peek() != Some(']') {
+            return Err(self.error("expected ] to close the bracket atom"));
+        }
+        self.position += 1;
+
+        return Ok(BracketAtom {
+            isotope: isotope,
+            symbol: symbol,
+            is_aromatic: is_aromatic,
+            chirality: chirality,
+            hydrogens: hydrogens,
+            charge: charge,
+            atom_class: atom_class,
+        });
+    }
+
+    /** read_atom() reads an organic subset or bracket atom */
+    fn read_atom(&mut self) -> Result<usize, String> {
+        if self.peek() != Some('[') {
+            return self.read_organic_atom();
+        }
+
+        let bracket = self.read_bracket()?;
+        let idx = self.add_atom(
+            &bracket.symbol,
+            bracket.is_aromatic,
+            Some(bracket.hydrogens),
+        );
+        self.atoms[idx].charge = bracket.charge as f32;
+        self.isotopes[idx] = bracket.isotope;
+        self.chiralities[idx] = bracket.chirality;
+        self.atom_classes[idx] = bracket.atom_class;
+
+        return Ok(idx);
+    }
+
+    /** get_bond_type() returns the bond written, or the implicit bond between two atoms */
+    fn get_bond_type(
+        &self,
+        bond: Option<BondType>,
+        atom_1_idx: usize,
+        atom_2_idx: usize,
+    ) -> BondType {
+        match bond {
+            Some(bond_type) => return bond_type,
+            None if self.is_aromatic[atom_1_idx] && self.is_aromatic[atom_2_idx] => {
+                return BondType::AROMATIC
+            }
+            None => return BondType::SINGLE,
+        }
+    }
+
+    /** read_bond() reads a bond symbol, None if there is none at the current position */
+    fn read_bond(&mut self) -> Result<Option<BondType>, String> {
+        let bond_type = match self.peek() {
+            Some('-') | Some('/') | Some('\\') => BondType::SINGLE,
+            Some('=') => BondType::DOUBLE,
+            Some('#') => BondType::TRIPLE,
+            Some(':') => BondType::AROMATIC,
+            Some('$') => return Err(self.error("quadruple bonds are not supported")),
+            _ => return Ok(None),
+        };
+
+        self.position += 1;
+        return Ok(Some(bond_type));
+    }
+
+    /** peek_ring_number() returns the ring closure digit or %nn, and its length, None if there is none */
+    fn peek_ring_number(&self) -> Result<Option<(u32, usize)>, String> {
+        match self.peek() {
+            Some(c) if c.is_ascii_digit() => return Ok(c.to_digit(10).map(|n| (n, 1))),
+            Some('%') => {
+                let digits: String = self.chars[self.position + 1..].iter().take(2).collect();
+                match digits.parse() {
+                    Ok(number) if digits.chars().all(|c| c.is_ascii_digit()) => {
+                        return Ok(Some((number, 3)))
+                    }
+                    _ => return Err(self.error("expected two digits after %")),
+                }
+            }
+            _ => return Ok(None),
+        }
+    }
+
+    /** ring_bond() opens or closes a ring bond at an atom */
+    fn ring_bond(
+        &mut self,
+        number: u32,
+        atom: usize,
+        bond: Option<BondType>,
+    ) -> Result<(), String> {
+        let (other, slot, other_bond) = match self.rings.remove(&number) {
+            Some(ring) => ring,
+            None => {
+                self.rings
+                    .insert(number, (atom, self.neighbours[atom].len(), bond));
+                self.neighbours[atom].push(Some(OPEN_RING));
+                return Ok(());
+            }
+        };
+
+        if other == atom {
+            return Err(self.error(&format!("ring bond {} joins an atom to itself", number)));
+        }
+        if self.neighbours[atom].contains(&Some(other)) {
+            return Err(self.error(&format!("ring bond {} repeats a bond", number)));
+        }
+        let bond = match (bond, other_bond) {
+            (Some(bond), Some(other_bond)) if bond != other_bond => {
+                return Err(self.error(&format!("ring bond {} has two bond types", number)))
+            }
+            (bond, other_bond) => bond.or(other_bond),
+        };
+
+        self.neighbours[other][slot] = Some(atom);
+        self.neighbours[atom].push(Some(other));
+        self.bonds
+            .push((other, atom, self.get_bond_type(bond, other, atom)));
+        return Ok(());
+    }
+
+    /** parse() reads the whole string */
+    fn parse(&mut self) -> Result<(), String> {
+        if self.chars.is_empty() {
+            return Err("empty SMILES".to_string());
+        }
+
+        let mut previous: Option<usize> = None;
+        let mut bond: Option<BondType> = None;
+        let mut branches: Vec<usize> = Vec::new();
+
+        while let Some(c) = self.peek() {
+            match c {
+                '(' => {
+                    match (previous, bond) {
+                        (Some(atom), None) => branches.push(atom),
+                        _ => return Err(self.error("a branch must follow an atom")),
+                    }
+                    self.position += 1;
+                }
+                ')' => {
+                    if bond.is_some() {
+                        return Err(self.error("bond without a following atom"));
+                    }
+                    match branches.pop() {
+                        Some(atom) if previous.is_some() => previous = Some(atom),
+                        Some(_) => return Err(self.error("empty branch")),
+                        None => return Err(self.error("unmatched )")),
+                    }
+                    self.position += 1;
+                }
+                '.' => {
+                    if previous.is_none() || bond.is_some() || !branches.is_empty() {
+                        return Err(self.error(". must separate two complete parts"));
+                    }
+                    previous = None;
+                    self.position += 1;
+                }
+                '0'..='9' | '%' => {
+                    let atom =
+                        previous.ok_or_else(|| self.error("a ring bond must follow an atom"))?;
+                    let (number, length) = self.peek_ring_number()?.unwrap_or((0, 1));
+                    self.ring_bond(number, atom, bond.take())?;
+                    self.position += length;
+                }
+                '-' | '/' | '\\' | '=' | '#' | ':' | '$' => {
+                    if previous.is_none() || bond.is_some() {
+                        return Err(self.error("a bond must follow an atom"));
+                    }
+                    bond = self.read_bond()?;
+                }
+                _ => {
+                    let atom = self.read_atom()?;
+                    if let Some(other) = previous {
+                        self.bonds.push((
+                            other,
+                            atom,
+                            self.get_bond_type(bond.take(), other, atom),
+                        ));
+                        self.neighbours[other].push(Some(atom));
+                        self.neighbours[atom].push(Some(other));
+                    }
+                    // the hydrogens of a bracket atom come right after the preceding atom
+                    if self.hydrogens[atom].is_some_and(|h| h > 0) {
+                        self.neighbours[atom].push(None);
+                    }
+                    previous = Some(atom);
+                }
+            }
+        }
+
+        if bond.is_some() {
+            return Err(self.error("bond without a following atom"));
+        }
+        if !branches.is_empty() {
+            return Err(self.error("missing ) to close a branch"));
+        }
+        if let Some(number) = self.rings.keys().next() {
+            return Err(self.error(&format!("ring bond {} is not closed", number)));
+        }
+
+        return Ok(());
+    }
+
+    /** into_molecule() makes the molecule, resolving the implicit hydrogens and chirality */
+    fn into_molecule(self) -> Molecule {
+        let mut mol = Molecule::new("".to_string(), "".to_string());
+
+        let mut bond_order_sums = vec![0; self.atoms.len()];
+        for (atom_1_idx, atom_2_idx, bond_type) in self.bonds.iter() {
+            let order = match bond_type {
+                BondType::DOUBLE => 2,
+                BondType::TRIPLE => 3,
+                _ => 1,
+            };
+            bond_order_sums[*atom_1_idx] += order;
+            bond_order_sums[*atom_2_idx] += order;
+        }
+
+        let hydrogens: Vec<i64> = self
+            .hydrogens
+            .iter()
+            .enumerate()
+            .map(|(idx, hydrogens)| match hydrogens {
+                Some(hydrogens) => *hydrogens,
+                None => get_implicit_hydrogens(
+                    &self.atoms[idx].symbol,
+                    self.is_aromatic[idx],
+                    bond_order_sums[idx],
+                ),
+            })
+            .collect();
+
+        let chiralities: Vec<String> = self
+            .chiralities
+            .iter()
+            .zip(self.neighbours.iter())
+            .map(|(chirality, neighbours)| to_index_order(chirality, neighbours))
+            .collect();
+
+        for atom in self.atoms {
+            mol.add_atom(atom);
+        }
+        for (atom_1_idx, atom_2_idx, bond_type) in self.bonds {
+            mol.add_bond(atom_1_idx, atom_2_idx, bond_type);
+        }
+
+        mol.set_atom_property("implicit_hydrogens", AtomProperty::Integers(hydrogens));
+        if self.isotopes.iter().any(|isotope| *isotope != 0) {
+            mol.set_atom_property("isotope", AtomProperty::Integers(self.isotopes));
+        }
+        if self.atom_classes.iter().any(|class| *class != 0) {
+            mol.set_atom_property("atom_class", AtomProperty::Integers(self.atom_classes));
+        }
+        if chiralities.iter().any(|chirality| !chirality.is_empty()) {
+            mol.set_atom_property("chirality", AtomProperty::Texts(chiralities));
+        }
+
+        return mol;
+    }
+}
+
+/** normalize_symbol() returns the element symbol of a (possibly aromatic) symbol */
+fn normalize_symbol(symbol: &str) -> String {
+    let mut chars = symbol.chars();
+    match chars.next() {
+        Some(first) => return first.to_ascii_uppercase().to_string() + chars.as_str(),
+        None => return "".to_string(),
+    }
+}
+
+/**
+ * to_index_order() turns the chirality seen with the neighbours in SMILES order into the one
+ * seen with the neighbours in order of atom index (implicit hydrogen first), each swap of
+ * two neighbours inverts it
+ */
+fn to_index_order(chirality: &str, neighbours: &[Option<usize>]) -> String {
+    if chirality.is_empty() {
+        return "".to_string();
+    }
+
+    let mut order: Vec<i64> = neighbours
+        .iter()
+        .map(|n| n.map(|idx| idx as i64).unwrap_or(-1))
+        .collect();
+
+    let mut swaps = 0;
+    for i in 0..order.len() {
+        for j in 0..order.len() - 1 - i {
+            if order[j] > order[j + 1] {
+                order.swap(j, j + 1);
+                swaps += 1;
+            }
+        }
+    }
+
+    match (chirality, swaps % 2 == 0) {
+        ("@", true) | ("@@", false) => return "@".to_string(),
+        _ => return "@@".to_string(),
+    }
+}
+
+/** parse() reads a SMILES string into a molecule */
+#[allow(dead_code)]
+pub fn parse(smiles: &str) -> Result<Molecule, IoError> {
+    let mut parser = Parser::new(smiles.trim());
+    parser
+        .parse()
+        .map_err(|message| IoError::parse(FORMAT, 0, message))?;
+    return Ok(parser.into_molecule());
+}
+
+/** SmilesReader - reads the molecules of a SMILES file, a SMILES and a name per line */
+#[allow(dead_code)]
+pub struct SmilesReader<R: BufRead> {
+    reader: LineReader<R>,
+    failed: bool,
+}
+
+#[allow(dead_code)]
+impl<R: BufRead> SmilesReader<R> {
+    pub fn new(reader: R) -> SmilesReader<R> {
+        SmilesReader {
+            reader: LineReader::new(reader, FORMAT),
+            failed: false,
+        }
+    }
+
+    /** read_molecule() reads the next molecule, or returns None at the end of the file */
+    pub fn read_molecule(&mut self) -> Result<Option<Molecule>, IoError> {
+        let line = loop {
+            match self.reader.next_line()? {
+                Some(line) if line.trim().is_empty() || line.starts_with('#') => continue,
+                Some(line) => break line,
+                None => return Ok(None),
+            }
+        };
+
+        let line = line.trim();
+        let (smiles, name) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+
+        let mut parser = Parser::new(smiles);
+        parser
+            .parse()
+            .map_err(|message| self.reader.error(message))?;
+        let mut mol = parser.into_molecule();
+        mol.name = name.trim().to_string();
+
+        return Ok(Some(mol));
+    }
+}
+
+impl<R: BufRead> Iterator for SmilesReader<R> {
+    type Item = Result<Molecule, IoError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+
+        let molecule = self.read_molecule();
+        self.failed = molecule.is_err();
+        return molecule.transpose();
+    }
+}
+
+/** read() reads the first molecule of a SMILES file */
+#[allow(dead_code)]
+pub fn read<R: BufRead>(reader: R) -> Result<Molecule, IoError> {
+    match SmilesReader::new(reader).read_molecule()? {
+        Some(mol) => return Ok(mol),
+        None => {
+            return Err(IoError::parse(
+                FORMAT,
+                0,
+                "no SMILES in the file".to_string(),
+            ))
+        }
+    }
+}
+
+/** formal_charge() returns the charge of an atom if it is a whole number, else 0 */
+fn formal_charge(atom: &Atom) -> i64 {
+    if atom.charge.fract() == 0.0 {
+        return atom.charge as i64;
+    }
+    return 0;
+}
+
+/** get_bond_order() returns the order a bond adds to the valence of its atoms in SMILES */
+fn get_bond_order(bond_type: BondType) -> i64 {
+    match bond_type {
+        BondType::DOUBLE => return 2,
+        BondType::TRIPLE => return 3,
+        _ => return 1,
+    }
+}
+
+/** get_bond_code() returns the code of a bond type used to rank atoms */
+fn get_bond_code(bond_type: BondType) -> i64 {
+    match bond_type {
+        BondType::AROMATIC => return 4,
+        bond_type => return get_bond_order(bond_type),
+    }
+}
+
+/**
+ * get_kekule_valence() returns the valence an atom of an aromatic ring fills with single and
+ * double bonds, charged atoms take the valence of the element with the same electrons
+ */
+fn get_kekule_valence(symbol: &str, charge: i64) -> i64 {
+    match symbol {
+        "B" => return 3 - charge,
+        "C" => return 4 - charge.abs(),
+        "N" | "P" | "As" => return 3 + charge,
+        "O" | "S" | "Se" | "Te" => return 2 + charge,
+        _ => return 0,
+    }
+}
+
+/**
+ * get_blossom_base() returns the base of the blossom closed by the edge between two outer
+ * atoms, the first atom the paths from them to the root of the tree have in common
+ */
+fn get_blossom_base(
+    atom_1: usize,
+    atom_2: usize,
+    base: &[usize],
+    parent: &[Option<usize>],
+    matched: &[Option<usize>],
+) -> usize {
+    let mut on_path = vec![false; base.len()];
+    let mut atom = atom_1;
+    loop {
+        atom = base[atom];
+        on_path[atom] = true;
+        match matched[atom].and_then(|mate| parent[mate]) {
+            Some(next) => atom = next,
+            None => break,
+        }
+    }
+
+    let mut atom = atom_2;
+    loop {
+        atom = base[atom];
+        if on_path[atom] {
+            return atom;
+        }
+        atom = parent[matched[atom].unwrap()].unwrap();
+    }
+}
+
+/** mark_blossom() marks the atoms from an atom up to the base of its blossom, linking them back */
+fn mark_blossom(
+    atom: usize,
+    blossom_base: usize,
+    child: usize,
+    base: &[usize],
+    parent: &mut [Option<usize>],
+    matched: &[Option<usize>],
+    in_blossom: &mut [bool],
+) {
+    let (mut atom, mut child) = (atom, child);
+    while base[atom] != blossom_base {
+        let mate = matched[atom].unwrap();
+        in_blossom[base[atom]] = true;
+        in_blossom[base[mate]] = true;
+        parent[atom] = Some(child);
+        child = mate;
+        atom = parent[mate].unwrap();
+    }
+}
+
+/**
+ * find_augmenting_path() grows a tree of alternating paths from an unmatched atom,
+ * shrinking odd rings (blossoms) as they close, and returns the unmatched atom reached,
+ * if any, with the parent of each atom on the way to it
+ */
+fn find_augmenting_path(
+    candidates: &[Vec<usize>],
+    matched: &[Option<usize>],
+    root: usize,
+    parent: &mut [Option<usize>],
+) -> Option<usize> {
+    let number_of_atoms = candidates.len();
+    let mut base: Vec<usize> = (0..number_of_atoms).collect();
+    let mut is_outer = vec![false; number_of_atoms];
+    parent.fill(None);
+
+    is_outer[root] = true;
+    let mut queue = VecDeque::from([root]);
+    while let Some(atom) = queue.pop_front() {
+        for other in candidates[atom].iter().copied() {
+            if base[atom] == base[other] || matched[atom] == Some(other) {
+                continue;
+            }
+
+            let closes_blossom =
+                other == root || matched[other].is_some_and(|mate| parent[mate].is_some());
+            if closes_blossom {
+                let blossom_base = get_blossom_base(atom, other, &base, parent, matched);
+                let mut in_blossom = vec![false; number_of_atoms];
+                mark_blossom(
+                    atom,
+                    blossom_base,
+                    other,
+                    &base,
+                    parent,
+                    matched,
+                    &mut in_blossom,
+                );
+                mark_blossom(
+                    other,
+                    blossom_base,
+                    atom,
+                    &base,
+                    parent,
+                    matched,
+                    &mut in_blossom,
+                );
+                for idx in 0..number_of_atoms {
+                    if in_blossom[base[idx]] {
+                        base[idx] = blossom_base;
+                        if !is_outer[idx] {
+                            is_outer[idx] = true;
+                            queue.push_back(idx);
+                        }
+                    }
+                }
+            } else if parent[other].is_none() {
+                parent[other] = Some(atom);
+                match matched[other] {
+                    Some(mate) => {
+                        is_outer[mate] = true;
+                        queue.push_back(mate);
+                    }
+                    None => return Some(other),
+                }
+            }
+        }
+    }
+
+    return None;
+}
+
+/**
+ * find_matching() pairs as many of the atoms that need a double bond as it can, each with
+ * a neighbour that also needs one, by growing the pairs along augmenting paths (Edmonds'
+ * blossom algorithm), in polynomial time whether or not all atoms can be paired
+ */
+fn find_matching(candidates: &[Vec<usize>]) -> Vec<Option<usize>> {
+    let number_of_atoms = candidates.len();
+    let mut matched: Vec<Option<usize>> = vec![None; number_of_atoms];
+
+    // pair greedily first, the augmenting paths then only fix what is left
+    for atom in 0..number_of_atoms {
+        if matched[atom].is_none() {
+            if let Some(other) = candidates[atom].iter().find(|o| matched[**o].is_none()) {
+                matched[atom] = Some(*other);
+                matched[*other] = Some(atom);
+            }
+        }
+    }
+
+    let mut parent = vec![None; number_of_atoms];
+    for root in 0..number_of_atoms {
+        if matched[root].is_some() || candidates[root].is_empty() {
+            continue;
+        }
+        let mut end = find_augmenting_path(candidates, &matched, root, &mut parent);
+        while let Some(atom) = end {
+            let previous = parent[atom].unwrap();
+            let next = matched[previous];
+            matched[atom] = Some(previous);
+            matched[previous] = Some(atom);
+            end = next;
+        }
+    }
+
+    return matched;
+}
+
+/**
+ * SmilesWriter - writes SMILES, by default canonical, aromatic and isomeric.
+ *
+ * Hydrogen atoms bonded to one heavy atom are written as hydrogen counts, together with the
+ * "implicit_hydrogens" atom property. A molecule with neither (e.g. read from a PDB file
+ * without hydrogens) is written with the hydrogens implied by the default valences.
+ *
+ * Canonical SMILES do not depend on the order of the atoms, the atoms are ranked (see
+ * canon) and the string is written from the lowest ranked atom, taking the neighbours in
+ * order of rank. Aromatic bonds are written as given, with lower case atoms, or as
+ * alternating single and double bonds (Kekulé) - aromaticity is not perceived, so a
+ * molecule with Kekulé bonds gives Kekulé SMILES either way. Isomeric SMILES have the
+ * isotopes and the tetrahedral chirality, double bond stereo and atom classes are not
+ * written.
+ */
+#[allow(dead_code)]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct SmilesWriter {
+    canonical: bool,
+    aromatic: bool,
+    isomeric: bool,
+}
+
+impl Default for SmilesWriter {
+    fn default() -> SmilesWriter {
+        SmilesWriter {
+            canonical: true,
+            aromatic: true,
+            isomeric: true,
+        }
+    }
+}
+
+#[allow(dead_code)]
+impl SmilesWriter {
+    pub fn new() -> SmilesWriter {
+        return SmilesWriter::default();
+    }
+
+    /** canonical() sets whether the SMILES are canonical, else atoms are taken in order */
+    pub fn canonical(mut self, canonical: bool) -> SmilesWriter {
+        self.canonical = canonical;
+        return self;
+    }
+
+    /** aromatic() sets whether aromatic bonds are written as such, else in Kekulé form */
+    pub fn aromatic(mut self, aromatic: bool) -> SmilesWriter {
+        self.aromatic = aromatic;
+        return self;
+    }
+
+    /** isomeric() sets whether isotopes and chirality are written */
+    pub fn isomeric(mut self, isomeric: bool) -> SmilesWriter {
+        self.isomeric = isomeric;
+        return self;
+    }
+
+    /** get_smiles() returns the SMILES of a molecule */
+    pub fn get_smiles(&self, mol: &Molecule) -> Result<String, IoError> {
+        let mut graph = SmilesGraph::new(mol, self);
+        if !self.aromatic {
+            graph.kekulize()?;
+        }
+        return graph.write();
+    }
+
+    /** write() writes the SMILES of a molecule and its name as a line of a SMILES file */
+    pub fn write<W: Write>(&self, mut writer: W, mol: &Molecule) -> Result<(), IoError> {
+        let smiles = self.get_smiles(mol)?;
+        if mol.name.is_empty() {
+            writeln!(writer, "{}", smiles)?;
+        } else {
+            writeln!(writer, "{} {}", smiles, mol.name)?;
+        }
+        return Ok(());
+    }
+}
+
+/** write() writes a molecule as a line of a SMILES file, with canonical isomeric SMILES */
+#[allow(dead_code)]
+pub fn write<W: Write>(writer: W, mol: &Molecule) -> Result<(), IoError> {
+    return SmilesWriter::new().write(writer, mol);
+}
+
+/** SmilesGraph - the atoms written in a SMILES and the bonds between them */
+#[derive(Clone)]
+struct SmilesGraph {
+    symbols: Vec<String>,
+    charges: Vec<i64>,
+    isotopes: Vec<i64>,
+    chiralities: Vec<String>,
+    is_written: Vec<bool>,
+    hydrogens: Vec<Option<i64>>,
+    folded_hydrogens: Vec<Vec<usize>>,
+    neighbours: Vec<Vec<(usize, BondType)>>,
+    ranks: Vec<usize>,
+}
+
+impl SmilesGraph {
+    fn new(mol: &Molecule, options: &SmilesWriter) -> SmilesGraph {
+        let atoms = mol.get_atoms();
+        let number_of_atoms = atoms.len();
+
+        let integers_of = |name: &str| match mol.get_atom_property(name) {
+            Some(AtomProperty::Integers(values)) => Some(values.to_vec()),
+            _ => None,
+        };
+        let isotopes = match integers_of("isotope") {
+            Some(isotopes) if options.isomeric => isotopes,
+            _ => vec![0; number_of_atoms],
+        };
+        let chiralities = match mol.get_atom_property("chirality") {
+            Some(AtomProperty::Texts(values)) if options.isomeric => values.to_vec(),
+            _ => vec!["".to_string(); number_of_atoms],
+        };
+
+        // weak bonds (hydrogen bonds) are not part of the structure written
+        let mut neighbours: Vec<Vec<(usize, BondType)>> = vec![Vec::new(); number_of_atoms];
+        for bond in mol.get_bonds().iter() {
+            if bond.bond_type != BondType::WEAK {
+                neighbours[bond.atom_1_idx].push((bond.atom_2_idx, bond.bond_type));
+                neighbours[bond.atom_2_idx].push((bond.atom_1_idx, bond.bond_type));
+            }
+        }
+
+        let mut is_written = vec![true; number_of_atoms];
+        let mut folded_hydrogens = vec![Vec::new(); number_of_atoms];
+        for (idx, atom) in atoms.iter().enumerate() {
+            if atom.symbol == "H"
+                && formal_charge(atom) == 0
+                && isotopes[idx] == 0
+                && neighbours[idx].len() == 1
+            {
+                let (heavy, bond_type) = neighbours[idx][0];
+                if atoms[heavy].symbol != "H" && bond_type == BondType::SINGLE {
+                    is_written[idx] = false;
+                    folded_hydrogens[heavy].push(idx);
+                }
+            }
+        }
+        for list in neighbours.iter_mut() {
+            list.retain(|(other, _)| is_written[*other]);
+        }
+
+        let implicit_hydrogens = integers_of("implicit_hydrogens");
+        let has_hydrogens =
+            implicit_hydrogens.is_some() || atoms.iter().any(|atom| atom.symbol == "H");
+        let hydrogens = (0..number_of_atoms)
+            .map(|idx| {
+                has_hydrogens.then(|| {
+                    let implicit = implicit_hydrogens.as_ref().map(|h| h[idx]).unwrap_or(0);
+                    implicit + folded_hydrogens[idx].len() as i64
+                })
+            })
+            .collect();
+
+        let mut graph = SmilesGraph {
+            symbols: atoms.iter().map(|atom| atom.symbol.to_string()).collect(),
+            charges: atoms.iter().map(formal_charge).collect(),
+            isotopes: isotopes,
+            chiralities: chiralities,
+            is_written: is_written,
+            hydrogens: hydrogens,
+            folded_hydrogens: folded_hydrogens,
+            neighbours: neighbours,
+            ranks: (0..number_of_atoms).collect(),
+        };
+
+        if options.canonical {
+            let ranks = graph.get_canonical_ranks(&mol.get_ring_atoms());
+            graph.set_ranks(ranks);
+        } else {
+            graph.set_ranks((0..number_of_atoms).collect());
+        }
+
+        return graph;
+    }
+
+    /** set_ranks() sets the ranks of the atoms, the neighbours are taken in order of rank */
+    fn set_ranks(&mut self, ranks: Vec<usize>) {
+        self.ranks = ranks;
+        for list in self.neighbours.iter_mut() {
+            list.sort_by_key(|(other, _)| self.ranks[*other]);
+        }
+    }
+
+    /**
+     * get_canonical_ranks() ranks the written atoms by their invariants, bonds and chirality,
+     * of the rankings that break the ties it keeps the one giving the lowest SMILES
+     */
+    fn get_canonical_ranks(&self, in_ring: &[bool]) -> Vec<usize> {
+        let written: Vec<usize> = (0..self.symbols.len())
+            .filter(|idx| self.is_written[*idx])
+            .collect();
+        let mut position = vec![0; self.symbols.len()];
+        for (i, idx) in written.iter().enumerate() {
+            position[*idx] = i;
+        }
+
+        let invariants: Vec<Vec<i64>> = written
+            .iter()
+            .map(|idx| {
+                let symbol = &self.symbols[*idx];
+                let mut invariant = vec![
+                    self.neighbours[*idx].len() as i64,
+                    atomprop::get_atomic_number(symbol).unwrap_or(0) as i64,
+                    self.hydrogens[*idx].unwrap_or(-1),
+                    self.charges[*idx],
+                    self.isotopes[*idx],
+                    self.is_aromatic(*idx) as i64,
+                    in_ring[*idx] as i64,
+                    !self.chiralities[*idx].is_empty() as i64,
+                ];
+                invariant.extend(symbol.bytes().map(|b| b as i64));
+                invariant
+            })
+            .collect();
+        let neighbours: Vec<Vec<(usize, i64)>> = written
+            .iter()
+            .map(|idx| {
+                self.neighbours[*idx]
+                    .iter()
+                    .map(|(other, bond_type)| (position[*other], get_bond_code(*bond_type)))
+                    .collect()
+            })
+            .collect();
+
+        // the chirality is seen with the neighbours in order of atom index, an implicit
+        // hydrogen first, hydrogens are not ranked
+        let chiralities: Vec<Option<canon::Chirality>> = written
+            .iter()
+            .map(|idx| {
+                if self.chiralities[*idx].is_empty() {
+                    return None;
+                }
+                let mut order: Vec<usize> = self.neighbours[*idx]
+                    .iter()
+                    .map(|(other, _)| *other)
+                    .chain(self.folded_hydrogens[*idx].iter().copied())
+                    .collect();
+                order.sort();
+                let implicit =
+                    self.hydrogens[*idx].unwrap_or(0) > self.folded_hydrogens[*idx].len() as i64;
+
+                let mut neighbours = Vec::new();
+                if implicit {
+                    neighbours.push(None);
+                }
+                for other in order {
+                    match self.is_written[other] {
+                        true => neighbours.push(Some(position[other])),
+                        false => neighbours.push(None),
+                    }
+                }
+                Some(canon::Chirality {
+                    neighbours: neighbours,
+                    anticlockwise: self.chiralities[*idx] == "@",
+                })
+            })
+            .collect();
+
+        // atoms written as hydrogen counts come last, they are never visited
+        let expand = |compact_ranks: &[usize]| {
+            let mut ranks = vec![usize::MAX; self.symbols.len()];
+            for (i, idx) in written.iter().enumerate() {
+                ranks[*idx] = compact_ranks[i];
+            }
+            ranks
+        };
+
+        let compact_ranks =
+            canon::get_canonical_ranks(&invariants, &neighbours, &chiralities, |compact_ranks| {
+                let mut graph = self.clone();
+                graph.set_ranks(expand(compact_ranks));
+                match graph.write() {
+                    Ok(smiles) => (false, smiles),
+                    Err(_) => (true, String::new()),
+                }
+            });
+        return expand(&compact_ranks);
+    }
+
+    /** is_aromatic() tells whether an atom is written in lower case */
+    fn is_aromatic(&self, idx: usize) -> bool {
+        return AROMATIC_SYMBOLS.contains(&self.symbols[idx].to_lowercase().as_str())
+            && self.neighbours[idx]
+                .iter()
+                .any(|(_, bond_type)| *bond_type == BondType::AROMATIC);
+    }
+
+    /** get_bond_order_sum() returns the valence used by the bonds of an atom */
+    fn get_bond_order_sum(&self, idx: usize) -> i64 {
+        return self.neighbours[idx]
+            .iter()
+            .map(|(_, bond_type)| get_bond_order(*bond_type))
+            .sum();
+    }
+
+    /** kekulize() turns the aromatic bonds into alternating single and double bonds */
+    fn kekulize(&mut self) -> Result<(), IoError> {
+        let number_of_atoms = self.symbols.len();
+
+        // an aromatic atom needs a double bond if its valence leaves room for one
+        let mut needs_double = vec![false; number_of_atoms];
+        for (idx, needs) in needs_double.iter_mut().enumerate() {
+            if self.is_written[idx] && self.is_aromatic(idx) {
+                let bond_order_sum = self.get_bond_order_sum(idx);
+                let symbol = normalize_symbol(&self.symbols[idx]);
+                let hydrogens = self.hydrogens[idx]
+                    .unwrap_or_else(|| get_implicit_hydrogens(&symbol, true, bond_order_sum));
+                *needs =
+                    bond_order_sum + hydrogens < get_kekule_valence(&symbol, self.charges[idx]);
+            }
+        }
+
+        let candidates: Vec<Vec<usize>> = (0..number_of_atoms)
+            .map(|idx| match needs_double[idx] {
+                true => self.neighbours[idx]
+                    .iter()
+                    .filter(|(other, bond_type)| {
+                        *bond_type == BondType::AROMATIC && needs_double[*other]
+                    })
+                    .map(|(other, _)| *other)
+                    .collect(),
+                false => Vec::new(),
+            })
+            .collect();
+
+        let matched = find_matching(&candidates);
+        let needing = needs_double.iter().filter(|needs| **needs).count();
+        if matched.iter().filter(|m| m.is_some()).count() != needing {
+            return Err(IoError::parse(
+                FORMAT,
+                0,
+                "the aromatic bonds cannot be written as alternating single and double bonds"
+                    .to_string(),
+            ));
+        }
+
+        for (idx, list) in self.neighbours.iter_mut().enumerate() {
+            for (other, bond_type) in list.iter_mut() {
+                if *bond_type == BondType::AROMATIC {
+                    *bond_type = if matched[idx] == Some(*other) {
+                        BondType::DOUBLE
+                    } else {
+                        BondType::SINGLE
+                    };
+                }
+            }
+        }
+
+        return Ok(());
+    }
+
+    /** get_bond_symbol() returns the symbol written for a bond, "" if it is implied */
+    fn get_bond_symbol(
+        &self,
+        atom_1_idx: usize,
+        atom_2_idx: usize,
+        bond_type: BondType,
+    ) -> &'static str {
+        let both_aromatic = self.is_aromatic(atom_1_idx) && self.is_aromatic(atom_2_idx);
+        match bond_type {
+            BondType::DOUBLE => return "=",
+            BondType::TRIPLE => return "#",
+            BondType::AROMATIC if both_aromatic => return "",
+            BondType::AROMATIC => return ":",
+            _ if both_aromatic => return "-",
+            _ => return "",
+        }
+    }
+
+    /** get_atom_text() returns an atom, its neighbours in the order written decide the chirality */
+    fn get_atom_text(&self, idx: usize, written_order: &[Option<usize>]) -> String {
+        let is_aromatic = self.is_aromatic(idx);
+        let symbol = if is_aromatic {
+            self.symbols[idx].to_lowercase()
+        } else {
+            self.symbols[idx].to_string()
+        };
+
+        let is_organic = match is_aromatic {
+            true => symbol.len() == 1,
+            false => !get_default_valences(&symbol).is_empty() || symbol == WILDCARD,
+        };
+        let implied = get_implicit_hydrogens(
+            &self.symbols[idx],
+            is_aromatic,
+            self.get_bond_order_sum(idx),
+        );
+        if is_organic
+            && self.charges[idx] == 0
+            && self.isotopes[idx] == 0
+            && self.chiralities[idx].is_empty()
+            && self.hydrogens[idx].is_none_or(|hydrogens| hydrogens == implied)
+        {
+            return symbol;
+        }
+
+        let mut text = "[".to_string();
+        if self.isotopes[idx] > 0 {
+            text += &self.isotopes[idx].to_string();
+        }
+        text += &symbol;
+        // the same swaps that take the index order to the written order take it back
+        text += &to_index_order(&self.chiralities[idx], written_order);
+        match self.hydrogens[idx] {
+            Some(1) => text += "H",
+            Some(hydrogens) if hydrogens > 1 => text += &format!("H{}", hydrogens),
+            _ => {}
+        }
+        match self.charges[idx] {
+            0 => {}
+            1 => text += "+",
+            -1 => text += "-",
+            charge => text += &format!("{:+}", charge),
+        }
+        text += "]";
+
+        return text;
+    }
+
+    /** write() writes the SMILES, each part from its lowest ranked atom */
+    fn write(&self) -> Result<String, IoError> {
+        let number_of_atoms = self.symbols.len();
+
+        // a depth first traversal finds the tree bonds and the ring bonds, which join an
+        // atom to one of the atoms it was reached from
+        const NEW: u8 = 0;
+        const OPEN: u8 = 1;
+        const DONE: u8 = 2;
+        let mut state = vec![NEW; number_of_atoms];
+        let mut parent: Vec<Option<(usize, BondType)>> = vec![None; number_of_atoms];
+        let mut children: Vec<Vec<usize>> = vec![Vec::new(); number_of_atoms];
+        let mut rings: Vec<(usize, usize, BondType)> = Vec::new();
+        let mut ring_events: Vec<Vec<usize>> = vec![Vec::new(); number_of_atoms];
+        let mut roots = Vec::new();
+
+        let mut by_rank: Vec<usize> = (0..number_of_atoms)
+            .filter(|idx| self.is_written[*idx])
+            .collect();
+        by_rank.sort_by_key(|idx| self.ranks[*idx]);
+
+        for root in by_rank {
+            if state[root] != NEW {
+                continue;
+            }
+            roots.push(root);
+            state[root] = OPEN;
+
+            let mut stack = vec![(root, 0)];
+            while let Some(&mut (atom, ref mut next)) = stack.last_mut() {
+                if *next >= self.neighbours[atom].len() {
+                    state[atom] = DONE;
+                    stack.pop();
+                    continue;
+                }
+
+                let (other, bond_type) = self.neighbours[atom][*next];
+                *next += 1;
+                if parent[atom].is_some_and(|(p, _)| p == other) {
+                    continue;
+                }
+
+                match state[other] {
+                    NEW => {
+                        parent[other] = Some((atom, bond_type));
+                        children[atom].push(other);
+                        state[other] = OPEN;
+                        stack.push((other, 0));
+                    }
+                    OPEN => {
+                        ring_events[other].push(rings.len());
+                        ring_events[atom].push(rings.len());
+                        rings.push((other, atom, bond_type));
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        enum Step {
+            Atom(usize),
+            Text(&'static str),
+        }
+
+        let mut parts = Vec::new();
+        let mut digits: Vec<usize> = vec![0; rings.len()];
+        let mut digit_in_use = [false; 100];
+
+        for root in roots {
+            let mut text = String::new();
+            let mut steps = vec![Step::Atom(root)];
+
+            while let Some(step) = steps.pop() {
+                let atom = match step {
+                    Step::Text(symbol) => {
+                        text += symbol;
+                        continue;
+                    }
+                    Step::Atom(atom) => atom,
+                };
+
+                let mut written_order = Vec::new();
+                if let Some((from, bond_type)) = parent[atom] {
+                    text += self.get_bond_symbol(from, atom, bond_type);
+                    written_order.push(Some(from));
+                }
+                match self.folded_hydrogens[atom].first() {
+                    Some(hydrogen) => written_order.push(Some(*hydrogen)),
+                    None if self.hydrogens[atom].is_some_and(|h| h > 0) => written_order.push(None),
+                    None => {}
+                }
+
+                let mut ring_text = String::new();
+                let mut freed = Vec::new();
+                for ring in ring_events[atom].iter() {
+                    let (opener, closer, bond_type) = rings[*ring];
+                    if opener == atom {
+                        let digit = match (1..100).find(|d| !digit_in_use[*d]) {
+                            Some(digit) => digit,
+                            None => {
+                                return Err(IoError::parse(
+                                    FORMAT,
+                                    0,
+                                    "more than 99 rings are open at once".to_string(),
+                                ))
+                            }
+                        };
+                        digit_in_use[digit] = true;
+                        digits[*ring] = digit;
+                        ring_text += self.get_bond_symbol(opener, closer, bond_type);
+                        written_order.push(Some(closer));
+                    } else {
+                        freed.push(digits[*ring]);
+                        written_order.push(Some(opener));
+                    }
+
+                    match digits[*ring] {
+                        digit if digit < 10 => ring_text += &digit.to_string(),
+                        digit => ring_text += &format!("%{}", digit),
+                    }
+                }
+                for digit in freed {
+                    digit_in_use[digit] = false;
+                }
+
+                written_order.extend(children[atom].iter().map(|child| Some(*child)));
+                text += &self.get_atom_text(atom, &written_order);
+                text += &ring_text;
+
+                // all but the last child are branches
+                if let Some((last, branches)) = children[atom].split_last() {
+                    steps.push(Step::Atom(*last));
+                    for child in branches.iter().rev() {
+                        steps.push(Step::Text(")"));
+                        steps.push(Step::Atom(*child));
+                        steps.push(Step::Text("("));
+                    }
+                }
+            }
+
+            parts.push(text);
+        }
+
+        return Ok(parts.join("."));
+    }
+}
+
+/** Unit tests for the above module */
+#[cfg(test)]
+mod tests {
+    use super::SmilesReader;
+    use super::SmilesWriter;
+    use crate::atom::AtomOperations;
+    use crate::bond::BondType;
+    use crate::molecule::builder::MoleculeBuilder;
+    use crate::molecule::Molecule;
+    use crate::property::AtomProperty;
+
+    fn hydrogens_of(smiles: &str) -> Vec<i64> {
+        match super::parse(smiles)
+            .unwrap()
+            .get_atom_property("implicit_hydrogens")
+        {
+            Some(AtomProperty::Integers(values)) => return values.to_vec(),
+            _ => panic!("expected hydrogen counts"),
+        }
+    }
+
+    #[test]
+    fn smiles_parse() {
+        let mut mol = super::parse("CC(=O)[O-]").unwrap();
+        assert_eq!(mol.get_number_of_atoms(), 4);
+        assert_eq!(mol.get_bond_type(1, 2), BondType::DOUBLE);
+        assert_eq!(mol.get_bond_type(1, 3), BondType::SINGLE);
+        assert_eq!(mol.get_atom(3).charge, -1.0);
+        assert_eq!(hydrogens_of("CC(=O)[O-]"), vec![3, 0, 0, 0]);
+
+        let mut mol = super::parse("c1ccc2ccccc2c1").unwrap();
+        assert_eq!(mol.get_number_of_bonds(), 11);
+        assert_eq!(mol.get_bond_type(0, 9), BondType::AROMATIC);
+        assert_eq!(mol.get_atom(0).symbol, "C".to_string());
+        assert_eq!(
+            hydrogens_of("c1ccc2ccccc2c1"),
+            vec![1, 1, 1, 0, 1, 1, 1, 1, 0, 1]
+        );
+
+        assert_eq!(hydrogens_of("C1=CC=CC=C1"), vec![1; 6]);
+        assert_eq!(hydrogens_of("c1cc[nH]c1"), vec![1, 1, 1, 1, 1]);
+        assert_eq!(hydrogens_of("b1ccccc1"), vec![1; 6]);
+        assert_eq!(hydrogens_of("Cb1cccc1"), vec![3, 0, 1, 1, 1, 1]);
+        assert_eq!(hydrogens_of("C#N"), vec![1, 0]);
+        assert_eq!(hydrogens_of("CS(=O)(=O)C"), vec![3, 0, 0, 0, 3]);
+        assert_eq!(hydrogens_of("ClBr"), vec![0, 0]);
+        assert_eq!(hydrogens_of("C%10CC%10"), vec![2, 2, 2]);
+    }
+
+    #[test]
+    fn smiles_bracket_atoms() {
+        let mut mol = super::parse("[13CH4].[NH4+].[Cl-].[Fe++].[se]1cccc1").unwrap();
+        assert_eq!(mol.get_number_of_atoms(), 9);
+        assert_eq!(mol.get_number_of_bonds(), 5);
+        assert_eq!(mol.get_atom(3).symbol, "Fe".to_string());
+        assert_eq!(mol.get_atom(3).charge, 2.0);
+        assert_eq!(mol.get_atom(4).symbol, "Se".to_string());
+        assert_eq!(
+            mol.get_atom_property("isotope"),
+            Some(&AtomProperty::Integers(vec![13, 0, 0, 0, 0, 0, 0, 0, 0]))
+        );
+        assert_eq!(&hydrogens_of("[13CH4].[NH4+].[Cl-].[Fe++]"), &[4, 4, 0, 0]);
+
+        let mol = super::parse("[CH3:1][OH:2]").unwrap();
+        assert_eq!(
+            mol.get_atom_property("atom_class"),
+            Some(&AtomProperty::Integers(vec![1, 2]))
+        );
+    }
+
+    #[test]
+    fn smiles_chirality() {
+        let chirality_of =
+            |smiles: &str| match super::parse(smiles).unwrap().get_atom_property("chirality") {
+                Some(AtomProperty::Texts(values)) => return values[1].to_string(),
+                _ => panic!("expected chirality"),
+            };
+
+        assert_eq!(chirality_of("N[C@@H](C)C(=O)O"), "@".to_string());
+        assert_eq!(chirality_of("N[C@H](C)C(=O)O"), "@@".to_string());
+        assert_eq!(chirality_of("F[C@TH2](Cl)(Br)I"), "@@".to_string());
+
+        // L-alanine again, with the same atom indices, a ring bond takes the place of its
+        // digit in the neighbour order
+        assert_eq!(chirality_of("N[C@H]1C.C1(=O)O"), "@".to_string());
+    }
+
+    #[test]
+    fn smiles_errors() {
+        let error_of = |smiles: &str| super::parse(smiles).unwrap_err().to_string();
+
+        assert_eq!(error_of(""), "smiles: empty SMILES");
+        assert_eq!(error_of("CC)C"), "smiles: position 3: unmatched )");
+        assert_eq!(
+            error_of("C(C"),
+            "smiles: position 4: missing ) to close a branch"
+        );
+        assert_eq!(
+            error_of("C1CC"),
+            "smiles: position 5: ring bond 1 is not closed"
+        );
+        assert_eq!(
+            error_of("C=1CC-1"),
+            "smiles: position 7: ring bond 1 has two bond types"
+        );
+        assert_eq!(error_of("C[Xx]"), "smiles: position 3: unknown element Xx");
+        assert_eq!(
+            error_of("CC="),
+            "smiles: position 4: bond without a following atom"
+        );
+        assert_eq!(
+            error_of("C.=C"),
+            "smiles: position 3: a bond must follow an atom"
+        );
+        assert_eq!(error_of("Cq"), "smiles: position 2: unexpected character q");
+
+        let text = "# a comment\nCCO ethanol\n\nc1ccccc1 benzene ring\nC(C\n";
+        let mut reader = SmilesReader::new(text.as_bytes());
+        assert_eq!(reader.next().unwrap().unwrap().name, "ethanol".to_string());
+        assert_eq!(
+            reader.next().unwrap().unwrap().name,
+            "benzene ring".to_string()
+        );
+        assert_eq!(
+            reader.next().unwrap().unwrap_err().to_string(),
+            "smiles: line 5: position 4: missing ) to close a branch"
+        );
+        assert!(reader.next().is_none());
+    }
+
+    fn canonical(smiles: &str) -> String {
+        return SmilesWriter::new()
+            .get_smiles(&super::parse(smiles).unwrap())
+            .unwrap();
+    }
+
+    #[test]
+    fn smiles_canonical() {
+        for group in [
+            vec!["CCO", "OCC", "C(O)C"],
+            vec!["CC(=O)O", "OC(C)=O", "O=C(O)C"],
+            vec!["c1ccccc1C", "Cc1ccccc1", "c1cc(C)ccc1"],
+            vec!["C1CC2CCC1CC2", "C12CCC(CC1)CC2"],
+            vec!["[NH4+].[Cl-]", "[Cl-].[NH4+]"],
+        ] {
+            let expected = canonical(group[0]);
+            for smiles in group.iter() {
+                assert_eq!(canonical(smiles), expected, "{}", smiles);
+            }
+        }
+
+        assert_eq!(canonical("OCC"), "CCO".to_string());
+        assert_eq!(canonical("OC(C)=O"), "CC(=O)O".to_string());
+        assert_eq!(canonical("[O-]C(C)=O"), "CC([O-])=O".to_string());
+        assert_eq!(canonical("c1ccncc1"), "c1ccncc1".to_string());
+        assert_eq!(canonical("c1cc[nH]c1"), "c1cc[nH]c1".to_string());
+        assert_eq!(
+            canonical("c1ccccc1-c1ccccc1"),
+            "c1ccc(cc1)-c1ccccc1".to_string()
+        );
+
+        // the atoms in order
+        let mol = super::parse("OCC").unwrap();
+        assert_eq!(
+            SmilesWriter::new()
+                .canonical(false)
+                .get_smiles(&mol)
+                .unwrap(),
+            "OCC".to_string()
+        );
+    }
+
+    /**
+     * shuffle() returns the molecule with its atoms in another order, the chirality is
+     * changed to be seen with the neighbours in the new order of atom index
+     */
+    fn shuffle(mol: &Molecule, seed: u64) -> Molecule {
+        let number_of_atoms = mol.get_atoms().len();
+        let mut order: Vec<usize> = (0..number_of_atoms).collect();
+        let mut state = seed;
+        for i in (1..number_of_atoms).rev() {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            order.swap(i, (state >> 33) as usize % (i + 1));
+        }
+        let mut new_index = vec![0; number_of_atoms];
+        for (new, old) in order.iter().enumerate() {
+            new_index[*old] = new;
+        }
+
+        let mut shuffled = Molecule::new(mol.name.to_string(), "".to_string());
+        for old in order.iter() {
+            shuffled.add_atom(mol.get_atoms()[*old].clone());
+        }
+        for bond in mol.get_bonds().iter() {
+            shuffled.add_bond(
+                new_index[bond.atom_1_idx],
+                new_index[bond.atom_2_idx],
+                bond.bond_type,
+            );
+        }
+
+        let hydrogens = match mol.get_atom_property("implicit_hydrogens") {
+            Some(AtomProperty::Integers(values)) => values.to_vec(),
+            _ => vec![0; number_of_atoms],
+        };
+        for (name, values) in mol.get_atom_properties().iter() {
+            match values {
+                AtomProperty::Integers(values) => {
+                    let values = order.iter().map(|old| values[*old]).collect();
+                    shuffled.set_atom_property(name, AtomProperty::Integers(values));
+                }
+                AtomProperty::Texts(values) if name == "chirality" => {
+                    let values = order
+                        .iter()
+                        .map(|old| {
+                            let mut neighbours: Vec<usize> = mol
+                                .get_bonds()
+                                .iter()
+                                .filter_map(|bond| match *old {
+                                    idx if idx == bond.atom_1_idx => Some(bond.atom_2_idx),
+                                    idx if idx == bond.atom_2_idx => Some(bond.atom_1_idx),
+                                    _ => None,
+                                })
+                                .collect();
+                            neighbours.sort();
+                            let mut in_old_order: Vec<Option<usize>> = Vec::new();
+                            if hydrogens[*old] > 0 {
+                                in_old_order.push(None);
+                            }
+                            in_old_order.extend(neighbours.iter().map(|n| Some(new_index[*n])));
+                            super::to_index_order(&values[*old], &in_old_order)
+                        })
+                        .collect();
+                    shuffled.set_atom_property(name, AtomProperty::Texts(values));
+                }
+                _ => panic!("unexpected atom property {}", name),
+            }
+        }
+
+        return shuffled;
+    }
+
+    #[test]
+    fn smiles_canonical_atom_order() {
+        for smiles in [
+            "C[C@@H](O)[C@H](C)O",
+            "O[C@H]1[C@H](O)[C@@H](O)[C@H](O)[C@@H](O)[C@@H]1O",
+            "C[C@H]1CC[C@@H](C)CC1",
+            "C[C@H]1CC[C@H](C)CC1",
+            "C12C3C4C5C1C6C7C2C3C4C5C67",
+            "N[C@@H](C)C(=O)O",
+            "CC(C)(C)C(C(C)(C)C)(C(C)(C)C)C(C)(C)C",
+        ] {
+            let mol = super::parse(smiles).unwrap();
+            let expected = SmilesWriter::new().get_smiles(&mol).unwrap();
+            for seed in 0..50 {
+                let shuffled = shuffle(&mol, seed);
+                assert_eq!(
+                    SmilesWriter::new().get_smiles(&shuffled).unwrap(),
+                    expected,
+                    "{} shuffled with seed {}",
+                    smiles,
+                    seed
+                );
+            }
+        }
+
+        // the two isomers stay apart
+        assert_ne!(
+            canonical("C[C@H]1CC[C@@H](C)CC1"),
+            canonical("C[C@H]1CC[C@H](C)CC1")
+        );
+    }
+
+    #[test]
+    fn smiles_canonical_symmetric() {
+        // symmetric molecules take polynomial time, not a search of every tie break
+        let chain = vec!["c1ccc(cc1)"; 15].join("-");
+        let start = std::time::Instant::now();
+        for smiles in ["c1ccccc1.c1ccccc1.c1ccccc1.c1ccccc1", chain.as_str()] {
+            let mol = super::parse(smiles).unwrap();
+            let expected = SmilesWriter::new().get_smiles(&mol).unwrap();
+            assert_eq!(canonical(&expected), expected);
+            for seed in 0..3 {
+                let shuffled = shuffle(&mol, seed);
+                assert_eq!(SmilesWriter::new().get_smiles(&shuffled).unwrap(), expected);
+            }
+        }
+        assert_eq!(
+            canonical("c1ccccc1.c1ccccc1.c1ccccc1.c1ccccc1"),
+            "c1ccccc1.c1ccccc1.c1ccccc1.c1ccccc1".to_string()
+        );
+        assert!(
+            start.elapsed().as_secs_f64() < 5.0,
+            "took {:?}",
+            start.elapsed()
+        );
+
+        // no Kekulé form is found as quickly
+        let start = std::time::Instant::now();
+        let mol = super::parse(&format!("{}.c1cccc1", chain)).unwrap();
+        assert!(SmilesWriter::new()
+            .aromatic(false)
+            .get_smiles(&mol)
+            .is_err());
+        assert!(
+            start.elapsed().as_secs_f64() < 5.0,
+            "took {:?}",
+            start.elapsed()
+        );
+    }
+
+    #[test]
+    fn smiles_write_options() {
+        let kekule = |smiles: &str| {
+            return SmilesWriter::new()
+                .aromatic(false)
+                .get_smiles(&super::parse(smiles).unwrap())
+                .unwrap();
+        };
+        assert_eq!(kekule("c1ccccc1"), "C1=CC=CC=C1".to_string());
+        assert_eq!(kekule("c1cc[nH]c1"), "C=1C=CNC1".to_string());
+        assert_eq!(kekule("Cn1ccnc1"), "CN1C=CN=C1".to_string());
+        assert_eq!(kekule("O=c1cc[nH]cc1"), "O=C1C=CNC=C1".to_string());
+        // odd rings in the aromatic system, the pairs are found through them
+        for smiles in [
+            "c1ccc2cccc2cc1",
+            "c1cc2ccc3cccc4ccc(c1)c2c34",
+            "c1ccc2c(c1)c1ccccc1c1ccccc21",
+        ] {
+            let written = kekule(smiles);
+            assert!(!written.contains('c'), "{}", written);
+            assert_eq!(canonical(&written), kekule(&written));
+        }
+        assert!(SmilesWriter::new()
+            .aromatic(false)
+            .get_smiles(&super::parse("c1cccc1").unwrap())
+            .is_err());
+
+        // isotopes and chirality, the same molecule written from either end
+        assert_eq!(canonical("[13CH4]"), "[13CH4]".to_string());
+        assert_eq!(canonical("N[C@@H](C)C(=O)O"), canonical("OC(=O)[C@@H](N)C"));
+        assert_eq!(canonical("N[C@@H](C)C(=O)O"), canonical("C[C@H](N)C(O)=O"));
+        assert_ne!(canonical("N[C@@H](C)C(=O)O"), canonical("N[C@H](C)C(=O)O"));
+
+        let mol = super::parse("N[C@@H](C)C(=O)O").unwrap();
+        let plain = SmilesWriter::new()
+            .isomeric(false)
+            .get_smiles(&mol)
+            .unwrap();
+        assert!(!plain.contains('@'));
+        // reading canonical SMILES back gives them again
+        let written = canonical("N[C@@H](C)C(=O)O");
+        assert_eq!(canonical(&written), written);
+    }
+
+    #[test]
+    fn smiles_write_explicit_hydrogens() {
+        // water and formaldehyde from coordinates, the hydrogens become hydrogen counts
+        let mut mol = MoleculeBuilder::new("formaldehyde")
+            .xyz("C 0.0 0.0 0.0\nO 0.0 0.0 1.21\nH 0.0 0.94 -0.59\nH 0.0 -0.94 -0.59")
+            .build()
+            .unwrap();
+        mol.compute_covalent_bonds(crate::molecule::DEFAULT_BOND_TOLERANCE);
+        mol.set_bond_type(0, 1, BondType::DOUBLE);
+        assert_eq!(
+            SmilesWriter::new().get_smiles(&mol).unwrap(),
+            "C=O".to_string()
+        );
+
+        let mut buffer = Vec::new();
+        super::write(&mut buffer, &mol).unwrap();
+        assert_eq!(
+            String::from_utf8(buffer).unwrap(),
+            "C=O formaldehyde\n".to_string()
+        );
+
+        // without any hydrogens, the default valences imply them
+        let mol = MoleculeBuilder::new("")
+            .atom("C", 0.0, 0.0, 0.0)
+            .atom("N", 1.5, 0.0, 0.0)
+            .bonds(&[(0, 1)])
+            .build()
+            .unwrap();
+        assert_eq!(
+            SmilesWriter::new().get_smiles(&mol).unwrap(),
+            "CN".to_string()
+        );
+        assert_eq!(canonical("[H][H]"), "[H][H]".to_string());
+    }
+}
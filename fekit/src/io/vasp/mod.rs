@@ -0,0 +1,446 @@
+/**
+ * Author: V. Ganesh
+ * License: MIT
+ */
+
+/**
+ * vasp module, reads and writes the VASP structure files POSCAR and CONTCAR.
+ *
+ * The comment line is the molecule name and the scaled cell vectors its lattice. The
+ * scaling factor is one factor, a negative cell volume, or a factor per cartesian axis.
+ * The species come from the species line (VASP 5) or, in VASP 4 files that have none,
+ * from the comment line; POTCAR suffixes such as "Fe_pv" are dropped. Positions are read
+ * in Direct (fractional) or Cartesian coordinates. Selective dynamics flags are kept as
+ * the "selective_dynamics" atom property, a vector with 1 for a coordinate that is free
+ * to move (T) and 0 for one that is fixed (F). Velocities after the positions are not
+ * read.
+ *
+ * The writer keeps the order of the atoms, with a species entry for each run of the same
+ * element. It writes selective dynamics from the "selective_dynamics" atom property, or
+ * else with all the coordinates of the "frozen" atoms fixed.
+ */
+use std::io::BufRead;
+use std::io::Write;
+
+use crate::atom::Atom;
+use crate::atom::AtomOperations;
+use crate::atomprop;
+use crate::io::IoError;
+use crate::io::LineReader;
+use crate::lattice::Lattice;
+use crate::molecule::Molecule;
+use crate::point::Point;
+use crate::property::AtomProperty;
+
+pub mod outcar;
+
+const FORMAT: &str = "vasp";
+
+/** get_species() returns the element of a species name, without a POTCAR suffix */
+pub(crate) fn get_species(name: &str) -> Option<&'static str> {
+    let element = name.split(['_', '/', '.']).next().unwrap_or(name);
+    return atomprop::get_atomic_number(element).and_then(atomprop::get_element_symbol);
+}
+
+/** parse_numbers() parses the first count fields of a line as numbers */
+fn parse_numbers<R: BufRead>(
+    reader: &LineReader<R>,
+    line: &str,
+    count: usize,
+    what: &str,
+) -> Result<Vec<f64>, IoError> {
+    let values: Vec<f64> = line
+        .split_whitespace()
+        .take(count)
+        .map_while(|field| field.parse().ok())
+        .collect();
+    if values.len() != count {
+        return Err(reader.error(format!("expected {} numbers for {}", count, what)));
+    }
+    return Ok(values);
+}
+
+/** parse_flag() parses a selective dynamics flag, 1 for T (free) and 0 for F (fixed) */
+fn parse_flag(field: &str) -> Option<f64> {
+    match field {
+        "T" | "t" => return Some(1.0),
+        "F" | "f" => return Some(0.0),
+        _ => return None,
+    }
+}
+
+/** read() reads a structure in POSCAR or CONTCAR format */
+#[allow(dead_code)]
+pub fn read<R: BufRead>(reader: R) -> Result<Molecule, IoError> {
+    let mut reader = LineReader::new(reader, FORMAT);
+
+    let comment = reader.expect_line("the comment line")?.trim().to_string();
+
+    let line = reader.expect_line("the scaling factor")?;
+    let scale: Vec<f64> = line
+        .split_whitespace()
+        .map_while(|field| field.parse().ok())
+        .collect();
+    if scale.len() != 1 && scale.len() != 3 {
+        return Err(reader.error("expected one or three scaling factors".to_string()));
+    }
+
+    let mut vectors = [[0.0; 3]; 3];
+    for vector in vectors.iter_mut() {
+        let line = reader.expect_line("a lattice vector")?;
+        let values = parse_numbers(&reader, &line, 3, "a lattice vector")?;
+        vector.copy_from_slice(&values);
+    }
+
+    // a negative factor is the volume of the cell
+    let factors = match scale[..] {
+        [volume] if volume < 0.0 => {
+            let factor = (-volume / Lattice::new(vectors).get_volume()).cbrt();
+            [factor; 3]
+        }
+        [factor] => [factor; 3],
+        _ => [scale[0], scale[1], scale[2]],
+    };
+    for vector in vectors.iter_mut() {
+        for k in 0..3 {
+            vector[k] *= factors[k];
+        }
+    }
+    let lattice = Lattice::new(vectors);
+
+    let mut line = reader.expect_line("the species or their counts")?;
+    let names: Vec<String> = if line
+        .split_whitespace()
+        .next()
+        .is_some_and(|field| field.parse::<usize>().is_err())
+    {
+        let names = line.split_whitespace().map(|s| s.to_string()).collect();
+        line = reader.expect_line("the number of atoms of each species")?;
+        names
+    } else {
+        comment.split_whitespace().map(|s| s.to_string()).collect()
+    };
+
+    let counts: Vec<usize> = line
+        .split_whitespace()
+        .map_while(|field| field.parse().ok())
+        .collect();
+    if counts.is_empty() {
+        return Err(reader.error("expected the number of atoms of each species".to_string()));
+    }
+    if names.len() < counts.len() {
+        return Err(reader.error(format!(
+            "{} species counts, but {} species are named",
+            counts.len(),
+            names.len()
+        )));
+    }
+
+    let mut symbols = Vec::new();
+    for (name, count) in names.iter().zip(counts.iter()) {
+        let symbol =
+            get_species(name).ok_or_else(|| reader.error(format!("unknown species {}", name)))?;
+        symbols.extend(std::iter::repeat_n(symbol, *count));
+    }
+
+    let mut line = reader.expect_line("the coordinate mode")?;
+    let selective = line.trim_start().starts_with(['S', 's']);
+    if selective {
+        line = reader.expect_line("the coordinate mode")?;
+    }
+    let cartesian = line.trim_start().starts_with(['C', 'c', 'K', 'k']);
+
+    let mut mol = Molecule::new(comment, "".to_string());
+    let mut flags = Vec::new();
+
+    for symbol in symbols.iter() {
+        let line = reader.expect_line("an atom position")?;
+        let values = parse_numbers(&reader, &line, 3, "an atom position")?;
+
+        let position = if cartesian {
+            [
+                values[0] * factors[0],
+                values[1] * factors[1],
+                values[2] * factors[2],
+            ]
+        } else {
+            lattice.to_cartesian([values[0], values[1], values[2]])
+        };
+
+        if selective {
+            let fields: Vec<Option<f64>> = line
+                .split_whitespace()
+                .skip(3)
+                .take(3)
+                .map(parse_flag)
+                .collect();
+            match fields[..] {
+                [Some(x), Some(y), Some(z)] => flags.push([x, y, z]),
+                _ => {
+                    return Err(
+                        reader.error("expected three T or F selective dynamics flags".to_string())
+                    )
+                }
+            }
+        }
+
+        mol.add_atom(Atom {
+            center: Point {
+                x: position[0] as f32,
+                y: position[1] as f32,
+                z: position[2] as f32,
+            },
+            charge: 0.0,
+            symbol: symbol.to_string(),
+            remark: "".to_string(),
+        });
+    }
+
+    mol.set_lattice(Some(lattice));
+    if selective {
+        mol.set_atom_property("selective_dynamics", AtomProperty::Vectors(flags));
+    }
+
+    return Ok(mol);
+}
+
+/** PoscarWriter - writes POSCAR files, in Direct coordinates by default */
+#[allow(dead_code)]
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct PoscarWriter {
+    cartesian: bool,
+}
+
+#[allow(dead_code)]
+impl PoscarWriter {
+    pub fn new() -> PoscarWriter {
+        return PoscarWriter::default();
+    }
+
+    /** cartesian() sets whether positions are written in Cartesian coordinates, else Direct */
+    pub fn cartesian(mut self, cartesian: bool) -> PoscarWriter {
+        self.cartesian = cartesian;
+        return self;
+    }
+
+    /** get_flags() returns the selective dynamics flags of the atoms, if any are set */
+    fn get_flags(mol: &Molecule) -> Option<Vec<[bool; 3]>> {
+        if let Some(AtomProperty::Vectors(values)) = mol.get_atom_property("selective_dynamics") {
+            return Some(values.iter().map(|v| v.map(|flag| flag != 0.0)).collect());
+        }
+        match mol.get_atom_property("frozen") {
+            Some(AtomProperty::Logicals(values)) => {
+                return Some(values.iter().map(|frozen| [!frozen; 3]).collect())
+            }
+            _ => return None,
+        }
+    }
+
+    /** write() writes a molecule, which must have a lattice, as a POSCAR file */
+    pub fn write<W: Write>(&self, mut writer: W, mol: &Molecule) -> Result<(), IoError> {
+        let lattice = mol.get_lattice().ok_or_else(|| {
+            IoError::parse(FORMAT, 0, "a POSCAR file needs a lattice".to_string())
+        })?;
+        let atoms = mol.get_atoms();
+
+        let mut species: Vec<(&str, usize)> = Vec::new();
+        for atom in atoms.iter() {
+            match species.last_mut() {
+                Some((symbol, count)) if *symbol == atom.symbol => *count += 1,
+                _ => species.push((&atom.symbol, 1)),
+            }
+        }
+
+        let comment = if mol.name.is_empty() {
+            let names: Vec<&str> = species.iter().map(|(symbol, _)| *symbol).collect();
+            names.join(" ")
+        } else {
+            mol.name.replace('\n', " ")
+        };
+        writeln!(writer, "{}", comment)?;
+        writeln!(writer, "   1.00000000000000")?;
+        for vector in lattice.vectors.iter() {
+            writeln!(
+                writer,
+                "  {:20.16}  {:20.16}  {:20.16}",
+                vector[0], vector[1], vector[2]
+            )?;
+        }
+        for (symbol, _) in species.iter() {
+            write!(writer, "   {:>2}", symbol)?;
+        }
+        writeln!(writer)?;
+        for (_, count) in species.iter() {
+            write!(writer, " {:>4}", count)?;
+        }
+        writeln!(writer)?;
+
+        let flags = PoscarWriter::get_flags(mol);
+        if flags.is_some() {
+            writeln!(writer, "Selective dynamics")?;
+        }
+        writeln!(
+            writer,
+            "{}",
+            if self.cartesian {
+                "Cartesian"
+            } else {
+                "Direct"
+            }
+        )?;
+
+        for (idx, atom) in atoms.iter().enumerate() {
+            let mut position = [
+                atom.center.x as f64,
+                atom.center.y as f64,
+                atom.center.z as f64,
+            ];
+            if !self.cartesian {
+                position = lattice.to_fractional(position);
+            }
+            write!(
+                writer,
+                "  {:20.16}  {:20.16}  {:20.16}",
+                position[0], position[1], position[2]
+            )?;
+            if let Some(flags) = &flags {
+                let flag = flags.get(idx).copied().unwrap_or([true; 3]);
+                for free in flag {
+                    write!(writer, "   {}", if free { "T" } else { "F" })?;
+                }
+            }
+            writeln!(writer)?;
+        }
+
+        return Ok(());
+    }
+}
+
+/** write() writes a molecule, which must have a lattice, as a POSCAR file in Direct coordinates */
+#[allow(dead_code)]
+pub fn write<W: Write>(writer: W, mol: &Molecule) -> Result<(), IoError> {
+    return PoscarWriter::new().write(writer, mol);
+}
+
+/** Unit tests for the above module */
+#[cfg(test)]
+mod tests {
+    use crate::io::vasp::PoscarWriter;
+    use crate::property::AtomProperty;
+
+    const POSCAR: &str = "Cu slab with O
+   3.6
+     1.0000000000000000    0.0000000000000000    0.0000000000000000
+     0.0000000000000000    1.0000000000000000    0.0000000000000000
+     0.0000000000000000    0.0000000000000000    2.0000000000000000
+   Cu_pv O
+     2     1
+Selective dynamics
+Direct
+  0.0000000000000000  0.0000000000000000  0.0000000000000000   F   F   F
+  0.5000000000000000  0.5000000000000000  0.0000000000000000   F   F   F
+  0.2500000000000000  0.2500000000000000  0.1000000000000000   T   T   T
+
+  0.00000000E+00  0.00000000E+00  0.00000000E+00
+";
+
+    #[test]
+    fn vasp_read() {
+        let mol = super::read(POSCAR.as_bytes()).unwrap();
+        assert_eq!(mol.name, "Cu slab with O");
+        assert_eq!(mol.get_lattice().unwrap().vectors[2], [0.0, 0.0, 7.2]);
+
+        let atoms = mol.get_atoms();
+        let symbols: Vec<&str> = atoms.iter().map(|at| at.symbol.as_str()).collect();
+        assert_eq!(symbols, vec!["Cu", "Cu", "O"]);
+        assert_eq!(atoms[1].center.x, 1.8);
+        assert!((atoms[2].center.z - 0.72).abs() < 1e-6);
+        assert_eq!(
+            mol.get_atom_property("selective_dynamics"),
+            Some(&AtomProperty::Vectors(vec![
+                [0.0, 0.0, 0.0],
+                [0.0, 0.0, 0.0],
+                [1.0, 1.0, 1.0]
+            ]))
+        );
+
+        // VASP 4, species in the comment, cartesian positions and the cell volume as scale
+        let poscar = "O H H
+  -8.0
+  1.0 0.0 0.0
+  0.0 1.0 0.0
+  0.0 0.0 1.0
+  1 2
+Cartesian
+  0.0 0.0 0.0
+  0.5 0.0 0.0
+  0.0 0.5 0.0
+";
+        let mol = super::read(poscar.as_bytes()).unwrap();
+        assert_eq!(mol.get_lattice().unwrap().get_volume(), 8.0);
+        assert_eq!(mol.get_atoms()[2].symbol, "H");
+        assert_eq!(mol.get_atoms()[2].center.y, 1.0);
+        assert_eq!(mol.get_atom_property("selective_dynamics"), None);
+    }
+
+    #[test]
+    fn vasp_round_trip() {
+        let mol = super::read(POSCAR.as_bytes()).unwrap();
+
+        for writer in [PoscarWriter::new(), PoscarWriter::new().cartesian(true)] {
+            let mut buffer = Vec::new();
+            writer.write(&mut buffer, &mol).unwrap();
+            let text = String::from_utf8(buffer).unwrap();
+            let lines: Vec<&str> = text.lines().collect();
+            assert_eq!(lines[5].split_whitespace().collect::<Vec<_>>(), ["Cu", "O"]);
+            assert_eq!(lines[7], "Selective dynamics");
+
+            let read_mol = super::read(text.as_bytes()).unwrap();
+            assert_eq!(read_mol.name, mol.name);
+            assert_eq!(read_mol.get_lattice(), mol.get_lattice());
+            for (read_atom, atom) in read_mol.get_atoms().iter().zip(mol.get_atoms()) {
+                assert_eq!(read_atom.symbol, atom.symbol);
+                assert!((read_atom.center.z - atom.center.z).abs() < 1e-6);
+            }
+            assert_eq!(
+                read_mol.get_atom_property("selective_dynamics"),
+                mol.get_atom_property("selective_dynamics")
+            );
+        }
+
+        let mut mol = mol;
+        mol.remove_atom_property("selective_dynamics");
+        mol.set_atom_property("frozen", AtomProperty::Logicals(vec![true, false, false]));
+        let mut buffer = Vec::new();
+        super::write(&mut buffer, &mol).unwrap();
+        let text = String::from_utf8(buffer).unwrap();
+        assert!(text.lines().nth(9).unwrap().ends_with("F   F   F"));
+        assert!(text.lines().nth(10).unwrap().ends_with("T   T   T"));
+
+        mol.set_lattice(None);
+        let err = super::write(&mut Vec::new(), &mol).unwrap_err();
+        assert_eq!(err.to_string(), "vasp: a POSCAR file needs a lattice");
+    }
+
+    #[test]
+    fn vasp_read_errors() {
+        let error = |text: &str| super::read(text.as_bytes()).unwrap_err().to_string();
+
+        assert_eq!(
+            error("water\n1.0\n1 0 0\n0 1\n"),
+            "vasp: line 4: expected 3 numbers for a lattice vector"
+        );
+        assert_eq!(
+            error("water\n1.0\n1 0 0\n0 1 0\n0 0 1\n1 2\nDirect\n"),
+            "vasp: line 6: 2 species counts, but 1 species are named"
+        );
+        assert_eq!(
+            error("cell\n1.0\n1 0 0\n0 1 0\n0 0 1\nXx\n1\nDirect\n0 0 0\n"),
+            "vasp: line 7: unknown species Xx"
+        );
+        assert_eq!(
+            error("cell\n1.0\n1 0 0\n0 1 0\n0 0 1\nO\n1\nSelective\nDirect\n0 0 0 T T\n"),
+            "vasp: line 10: expected three T or F selective dynamics flags"
+        );
+    }
+}
@@ -0,0 +1,353 @@
+/**
+ * Author: V. Ganesh
+ * License: MIT
+ */
+
+/**
+ * outcar module, reads the results of a VASP run from its OUTCAR file.
+ *
+ * Each ionic step gives a frame, with the positions and the cell of that step, along with
+ * its free energy (TOTEN), energy without entropy and energy for sigma -> 0 (in eV), the
+ * forces on the atoms (eV/Å) and the number of electronic steps it took. The species come
+ * from the TITEL lines of the POTCARs and the "ions per type" line; the last Fermi energy
+ * is kept as well.
+ *
+ * A run that was cut short still gives the ionic steps read up to that point, with the
+ * reason in Outcar.error.
+ */
+use std::io::BufRead;
+
+use crate::atom::Atom;
+use crate::atom::AtomOperations;
+use crate::io::vasp::get_species;
+use crate::io::IoError;
+use crate::io::LineReader;
+use crate::lattice::Lattice;
+use crate::molecule::Molecule;
+use crate::point::Point;
+
+const FORMAT: &str = "outcar";
+
+/** Outcar - the results read from a VASP OUTCAR file, one entry per ionic step */
+#[allow(dead_code)]
+#[derive(Default, Debug)]
+pub struct Outcar {
+    pub frames: Vec<Molecule>,
+    pub free_energies: Vec<f64>,
+    pub energies_without_entropy: Vec<f64>,
+    pub energies_sigma_0: Vec<f64>,
+    pub forces: Vec<Vec<[f64; 3]>>,
+    pub electronic_steps: Vec<usize>,
+    pub fermi_energy: Option<f64>,
+    /** why the results are incomplete: the file ending before the timing summary of the run */
+    pub error: Option<IoError>,
+}
+
+#[allow(dead_code)]
+impl Outcar {
+    /** get_final_frame() returns the last geometry of the run */
+    pub fn get_final_frame(&self) -> Option<&Molecule> {
+        return self.frames.last();
+    }
+
+    /** get_final_energy() returns the free energy (TOTEN) of the last ionic step */
+    pub fn get_final_energy(&self) -> Option<f64> {
+        return self.free_energies.last().copied();
+    }
+
+    /** get_number_of_ionic_steps() returns the number of ionic steps read */
+    pub fn get_number_of_ionic_steps(&self) -> usize {
+        return self.frames.len();
+    }
+
+    /** is_complete() checks whether the run finished */
+    pub fn is_complete(&self) -> bool {
+        return self.error.is_none();
+    }
+}
+
+/** get_value_after() returns the first number after the given text on a line */
+fn get_value_after(line: &str, text: &str) -> Option<f64> {
+    let idx = line.find(text)?;
+    return line[idx + text.len()..]
+        .split_whitespace()
+        .next()
+        .and_then(|field| field.parse().ok());
+}
+
+/** OutcarParser - reads the sections of an OUTCAR file into an Outcar */
+struct OutcarParser<R: BufRead> {
+    reader: LineReader<R>,
+    outcar: Outcar,
+    species: Vec<&'static str>,
+    counts: Vec<usize>,
+    lattice: Option<Lattice>,
+    electronic_steps: usize,
+    in_energy_block: bool,
+}
+
+impl<R: BufRead> OutcarParser<R> {
+    /** get_symbols() returns the symbol of each atom, from the species and their counts */
+    fn get_symbols(&self) -> Result<Vec<&'static str>, IoError> {
+        if self.species.len() < self.counts.len() {
+            return Err(self.reader.error(format!(
+                "{} species counts, but {} POTCARs",
+                self.counts.len(),
+                self.species.len()
+            )));
+        }
+
+        let mut symbols = Vec::new();
+        for (symbol, count) in self.species.iter().zip(self.counts.iter()) {
+            symbols.extend(std::iter::repeat_n(*symbol, *count));
+        }
+        return Ok(symbols);
+    }
+
+    /** read_lattice() reads the three direct lattice vectors after their header */
+    fn read_lattice(&mut self) -> Result<(), IoError> {
+        let mut vectors = [[0.0; 3]; 3];
+        for vector in vectors.iter_mut() {
+            let line = self.reader.expect_line("a lattice vector")?;
+            let values: Vec<f64> = line
+                .split_whitespace()
+                .take(3)
+                .filter_map(|field| field.parse().ok())
+                .collect();
+            if values.len() != 3 {
+                return Err(self.reader.error("expected a lattice vector".to_string()));
+            }
+            vector.copy_from_slice(&values);
+        }
+        self.lattice = Some(Lattice::new(vectors));
+        return Ok(());
+    }
+
+    /** read_positions() reads the positions and forces of an ionic step after their header */
+    fn read_positions(&mut self) -> Result<(), IoError> {
+        let symbols = self.get_symbols()?;
+        self.reader
+            .expect_line("the table of positions and forces")?;
+
+        let mut mol = Molecule::new(
+            format!("ionic step {}", self.outcar.frames.len() + 1),
+            "".to_string(),
+        );
+        let mut forces = Vec::new();
+
+        for symbol in symbols.iter() {
+            let line = self.reader.expect_line("a position and force")?;
+            let values: Vec<f64> = line
+                .split_whitespace()
+                .map_while(|field| field.parse().ok())
+                .collect();
+            if values.len() != 6 {
+                return Err(self
+                    .reader
+                    .error("expected a position and a force, six numbers".to_string()));
+            }
+
+            mol.add_atom(Atom {
+                center: Point {
+                    x: values[0] as f32,
+                    y: values[1] as f32,
+                    z: values[2] as f32,
+                },
+                charge: 0.0,
+                symbol: symbol.to_string(),
+                remark: "".to_string(),
+            });
+            forces.push([values[3], values[4], values[5]]);
+        }
+
+        mol.set_lattice(self.lattice);
+        self.outcar.frames.push(mol);
+        self.outcar.forces.push(forces);
+        self.outcar.electronic_steps.push(self.electronic_steps);
+        self.electronic_steps = 0;
+        return Ok(());
+    }
+
+    /** read_line() reads the section that starts at a line, if any */
+    fn read_line(&mut self, line: &str) -> Result<(), IoError> {
+        let trimmed = line.trim();
+
+        if let Some(titel) = trimmed.strip_prefix("TITEL") {
+            let name = titel
+                .trim_start_matches([' ', '='])
+                .split_whitespace()
+                .nth(1);
+            let name = name.unwrap_or("");
+            let symbol = get_species(name)
+                .ok_or_else(|| self.reader.error(format!("unknown species {}", name)))?;
+            self.species.push(symbol);
+        } else if let Some(counts) = trimmed.strip_prefix("ions per type =") {
+            self.counts = counts
+                .split_whitespace()
+                .filter_map(|field| field.parse().ok())
+                .collect();
+        } else if trimmed.starts_with("direct lattice vectors") {
+            self.read_lattice()?;
+        } else if trimmed.starts_with("POSITION") && trimmed.contains("TOTAL-FORCE") {
+            self.read_positions()?;
+        } else if trimmed.contains("Iteration") && trimmed.starts_with("---") {
+            self.electronic_steps += 1;
+        } else if trimmed.starts_with("FREE ENERGIE OF THE ION-ELECTRON SYSTEM") {
+            self.in_energy_block = true;
+        } else if self.in_energy_block && trimmed.starts_with("free  energy   TOTEN") {
+            if let Some(energy) = get_value_after(trimmed, "=") {
+                self.outcar.free_energies.push(energy);
+            }
+        } else if self.in_energy_block && trimmed.starts_with("energy  without entropy") {
+            if let Some(energy) = get_value_after(trimmed, "entropy=") {
+                self.outcar.energies_without_entropy.push(energy);
+            }
+            if let Some(energy) = get_value_after(trimmed, "energy(sigma->0) =") {
+                self.outcar.energies_sigma_0.push(energy);
+            }
+            self.in_energy_block = false;
+        } else if trimmed.starts_with("E-fermi :") {
+            self.outcar.fermi_energy = get_value_after(trimmed, "E-fermi :");
+        }
+
+        return Ok(());
+    }
+}
+
+/** read() reads the results of a VASP OUTCAR file, it fails only if the file cannot be read */
+#[allow(dead_code)]
+pub fn read<R: BufRead>(reader: R) -> Result<Outcar, IoError> {
+    let mut parser = OutcarParser {
+        reader: LineReader::new(reader, FORMAT),
+        outcar: Outcar::default(),
+        species: Vec::new(),
+        counts: Vec::new(),
+        lattice: None,
+        electronic_steps: 0,
+        in_energy_block: false,
+    };
+    let mut finished = false;
+
+    while let Some(line) = parser.reader.next_line()? {
+        if line.contains("General timing and accounting informations for this job") {
+            finished = true;
+        }
+
+        match parser.read_line(&line) {
+            Ok(()) => {}
+            Err(IoError::Io(err)) => return Err(IoError::Io(err)),
+            Err(err) => {
+                parser.outcar.error = Some(err);
+                return Ok(parser.outcar);
+            }
+        }
+    }
+
+    if !finished {
+        parser.outcar.error = Some(IoError::parse(
+            FORMAT,
+            parser.reader.get_line_number(),
+            "the run did not finish, there is no timing summary".to_string(),
+        ));
+    }
+
+    return Ok(parser.outcar);
+}
+
+/** Unit tests for the above module */
+#[cfg(test)]
+mod tests {
+    fn get_ionic_step(z: f64, force: f64, energy: f64) -> String {
+        return format!(
+            " ----------------------------------------- Iteration    1(   1)  ---------------------------------------
+  free energy    TOTEN  =        -9.12345678 eV
+ ----------------------------------------- Iteration    1(   2)  ---------------------------------------
+  free energy    TOTEN  =        -9.87654321 eV
+ E-fermi :  -2.5432     XC(G=0):  -7.1234     alpha+bet : -3.1234
+
+  VOLUME and BASIS-vectors are now :
+ -----------------------------------------------------------------------------
+  energy-cutoff  :      400.00
+  volume of cell :     1000.00
+      direct lattice vectors                 reciprocal lattice vectors
+    10.000000000  0.000000000  0.000000000     0.100000000  0.000000000  0.000000000
+     0.000000000 10.000000000  0.000000000     0.000000000  0.100000000  0.000000000
+     0.000000000  0.000000000 10.000000000     0.000000000  0.000000000  0.100000000
+
+ POSITION                                       TOTAL-FORCE (eV/Angst)
+ -----------------------------------------------------------------------------------
+      5.00000      5.00000      {z:.5}         0.000000      0.000000     {force:.6}
+      5.00000      5.00000      5.00000         0.000000      0.000000     {minus:.6}
+ -----------------------------------------------------------------------------------
+    total drift:                                0.000000      0.000000      0.000000
+
+  FREE ENERGIE OF THE ION-ELECTRON SYSTEM (eV)
+  ---------------------------------------------------
+  free  energy   TOTEN  =       {energy:.8} eV
+
+  energy  without entropy=      {entropy:.8}  energy(sigma->0) =      {sigma:.8}
+",
+            z = z,
+            force = force,
+            minus = -force,
+            energy = energy,
+            entropy = energy + 0.001,
+            sigma = energy + 0.0005,
+        );
+    }
+
+    fn get_outcar() -> String {
+        return format!(
+            " vasp.6.3.0 18Jan22 (build Mar 11 2022) complex
+   POTCAR:    PAW_PBE O 08Apr2002
+   TITEL  = PAW_PBE O 08Apr2002
+   ions per type =               2
+{}{}",
+            get_ionic_step(6.2, -1.5, -9.8),
+            get_ionic_step(6.21, -0.01, -9.85),
+        );
+    }
+
+    #[test]
+    fn outcar_read() {
+        let text = get_outcar()
+            + "
+ General timing and accounting informations for this job:
+ ========================================================
+";
+        let outcar = super::read(text.as_bytes()).unwrap();
+        assert!(outcar.is_complete());
+        assert_eq!(outcar.get_number_of_ionic_steps(), 2);
+        assert_eq!(outcar.free_energies, vec![-9.8, -9.85]);
+        assert_eq!(outcar.energies_without_entropy, vec![-9.799, -9.849]);
+        assert_eq!(outcar.energies_sigma_0, vec![-9.7995, -9.8495]);
+        assert_eq!(outcar.get_final_energy(), Some(-9.85));
+        assert_eq!(outcar.electronic_steps, vec![2, 2]);
+        assert_eq!(outcar.fermi_energy, Some(-2.5432));
+        assert_eq!(outcar.forces[0], vec![[0.0, 0.0, -1.5], [0.0, 0.0, 1.5]]);
+
+        let mol = outcar.get_final_frame().unwrap();
+        assert_eq!(mol.get_atoms().len(), 2);
+        assert_eq!(mol.get_atoms()[0].symbol, "O");
+        assert_eq!(mol.get_atoms()[0].center.z, 6.21);
+        assert_eq!(mol.get_lattice().unwrap().get_volume(), 1000.0);
+    }
+
+    #[test]
+    fn outcar_read_incomplete() {
+        let outcar = super::read(get_outcar().as_bytes()).unwrap();
+        assert_eq!(outcar.get_number_of_ionic_steps(), 2);
+        assert_eq!(
+            outcar.error.unwrap().to_string(),
+            "outcar: line 58: the run did not finish, there is no timing summary"
+        );
+
+        let text = get_outcar().replace("ions per type =               2", "ions per type = 2 1");
+        let outcar = super::read(text.as_bytes()).unwrap();
+        assert_eq!(outcar.get_number_of_ionic_steps(), 0);
+        assert_eq!(
+            outcar.error.unwrap().to_string(),
+            "outcar: line 20: 2 species counts, but 1 POTCARs"
+        );
+    }
+}
@@ -0,0 +1,507 @@
+/**
+ * Author: V. Ganesh
+ * License: MIT
+ */
+
+/**
+ * xml module, a small pull parser for the XML based formats.
+ *
+ * XmlReader returns the document one event at a time: the start of an element with its
+ * attributes, its end, and the text between elements. An empty element (<atom/>) gives a
+ * start and an end event. The XML declaration, processing instructions, comments and the
+ * DOCTYPE are skipped, CDATA sections are returned as text, and the predefined and
+ * numeric character references are decoded. Text that is only whitespace is dropped.
+ *
+ * The parser checks that elements are closed in order, it does not read DTDs, so custom
+ * entities are not expanded, and it does not resolve namespaces: names keep their prefix,
+ * local_name() removes it.
+ */
+use std::io::BufRead;
+use std::io::Bytes;
+
+use crate::io::IoError;
+
+/** XmlEvent - one step through an XML document */
+#[allow(dead_code)]
+#[derive(Clone, PartialEq, Debug)]
+pub enum XmlEvent {
+    StartElement {
+        name: String,
+        attributes: Vec<(String, String)>,
+    },
+    EndElement {
+        name: String,
+    },
+    Text(String),
+}
+
+/** XmlReader - reads the events of an XML document */
+#[allow(dead_code)]
+pub struct XmlReader<R: BufRead> {
+    bytes: Bytes<R>,
+    peeked: Option<u8>,
+    format: &'static str,
+    line_number: usize,
+    /** the elements opened and not closed yet */
+    open: Vec<String>,
+    /** the end of an empty element, returned after its start */
+    pending_end: Option<String>,
+}
+
+/** get_attribute() returns the value of an attribute, by its name without a prefix */
+#[allow(dead_code)]
+pub fn get_attribute<'a>(attributes: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    return attributes
+        .iter()
+        .find(|(key, _)| local_name(key) == name)
+        .map(|(_, value)| value.as_str());
+}
+
+/** local_name() returns a name without its namespace prefix, "cml:atom" is "atom" */
+#[allow(dead_code)]
+pub fn local_name(name: &str) -> &str {
+    return name.rsplit(':').next().unwrap_or(name);
+}
+
+/** escape() replaces the characters that are special in text and attribute values */
+#[allow(dead_code)]
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    return escaped;
+}
+
+/** decode_reference() returns the character of an entity or character reference, without & and ; */
+fn decode_reference(reference: &str) -> Option<char> {
+    match reference {
+        "amp" => return Some('&'),
+        "lt" => return Some('<'),
+        "gt" => return Some('>'),
+        "quot" => return Some('"'),
+        "apos" => return Some('\''),
+        _ => {}
+    }
+
+    let code = if let Some(hex) = reference
+        .strip_prefix("#x")
+        .or_else(|| reference.strip_prefix("#X"))
+    {
+        u32::from_str_radix(hex, 16).ok()?
+    } else {
+        reference.strip_prefix('#')?.parse().ok()?
+    };
+    return char::from_u32(code);
+}
+
+fn is_name_end(byte: u8) -> bool {
+    return byte.is_ascii_whitespace() || b"/>=<".contains(&byte);
+}
+
+#[allow(dead_code)]
+impl<R: BufRead> XmlReader<R> {
+    /** new() creates a reader, errors are reported for the given format */
+    pub fn new(reader: R, format: &'static str) -> XmlReader<R> {
+        XmlReader {
+            bytes: reader.bytes(),
+            peeked: None,
+            format: format,
+            line_number: 1,
+            open: Vec::new(),
+            pending_end: None,
+        }
+    }
+
+    /** get_line_number() returns the line the reader is on, 1 based */
+    pub fn get_line_number(&self) -> usize {
+        return self.line_number;
+    }
+
+    /** error() creates a parse error for the line the reader is on */
+    pub fn error(&self, message: String) -> IoError {
+        return IoError::parse(self.format, self.line_number, message);
+    }
+
+    fn peek_byte(&mut self) -> Result<Option<u8>, IoError> {
+        if self.peeked.is_none() {
+            self.peeked = self.bytes.next().transpose()?;
+        }
+        return Ok(self.peeked);
+    }
+
+    fn next_byte(&mut self) -> Result<Option<u8>, IoError> {
+        let byte = match self.peeked.take() {
+            Some(byte) => Some(byte),
+            None => self.bytes.next().transpose()?,
+        };
+        if byte == Some(b'\n') {
+            self.line_number += 1;
+        }
+        return Ok(byte);
+    }
+
+    /** expect_byte() returns the next byte, failing at the end of the document */
+    fn expect_byte(&mut self, what: &str) -> Result<u8, IoError> {
+        return self
+            .next_byte()?
+            .ok_or_else(|| self.error(format!("unexpected end of file, expected {}", what)));
+    }
+
+    fn skip_whitespace(&mut self) -> Result<(), IoError> {
+        while let Some(byte) = self.peek_byte()? {
+            if !byte.is_ascii_whitespace() {
+                break;
+            }
+            self.next_byte()?;
+        }
+        return Ok(());
+    }
+
+    /** read_until() returns the bytes up to the terminator, which is consumed */
+    fn read_until(&mut self, terminator: &[u8], what: &str) -> Result<Vec<u8>, IoError> {
+        let mut bytes = Vec::new();
+        while !bytes.ends_with(terminator) {
+            bytes.push(self.expect_byte(what)?);
+        }
+        bytes.truncate(bytes.len() - terminator.len());
+        return Ok(bytes);
+    }
+
+    /** read_name() reads an element or attribute name */
+    fn read_name(&mut self) -> Result<String, IoError> {
+        let mut bytes = Vec::new();
+        while let Some(byte) = self.peek_byte()? {
+            if is_name_end(byte) {
+                break;
+            }
+            bytes.push(byte);
+            self.next_byte()?;
+        }
+        if bytes.is_empty() {
+            return Err(self.error("expected a name".to_string()));
+        }
+        return Ok(String::from_utf8_lossy(&bytes).to_string());
+    }
+
+    /** decode() converts bytes to text, replacing the character references */
+    fn decode(&self, bytes: &[u8]) -> Result<String, IoError> {
+        let text = String::from_utf8_lossy(bytes);
+        let mut decoded = String::with_capacity(text.len());
+        let mut rest = text.as_ref();
+
+        while let Some(start) = rest.find('&') {
+            decoded.push_str(&rest[..start]);
+            let end = rest[start..]
+                .find(';')
+                .ok_or_else(|| self.error("a reference has no closing ;".to_string()))?;
+            let reference = &rest[start + 1..start + end];
+            let c = decode_reference(reference)
+                .ok_or_else(|| self.error(format!("unknown reference &{};", reference)))?;
+            decoded.push(c);
+            rest = &rest[start + end + 1..];
+        }
+        decoded.push_str(rest);
+
+        return Ok(decoded);
+    }
+
+    /** read_start_element() reads the name and attributes of a tag, after its < */
+    fn read_start_element(&mut self) -> Result<XmlEvent, IoError> {
+        let name = self.read_name()?;
+        let mut attributes = Vec::new();
+
+        loop {
+            self.skip_whitespace()?;
+            match self.peek_byte()? {
+                Some(b'>') => {
+                    self.next_byte()?;
+                    self.open.push(name.clone());
+                    break;
+                }
+                Some(b'/') => {
+                    self.next_byte()?;
+                    if self.expect_byte("the end of the tag")? != b'>' {
+                        return Err(self.error(format!("expected /> to end <{}", name)));
+                    }
+                    self.pending_end = Some(name.clone());
+                    break;
+                }
+                Some(_) => {
+                    let key = self.read_name()?;
+                    self.skip_whitespace()?;
+                    if self.expect_byte("an attribute value")? != b'=' {
+                        return Err(self.error(format!("expected = after attribute {}", key)));
+                    }
+                    self.skip_whitespace()?;
+                    let quote = self.expect_byte("an attribute value")?;
+                    if quote != b'"' && quote != b'\'' {
+                        return Err(self.error(format!("the value of {} is not quoted", key)));
+                    }
+                    let value = self.read_until(&[quote], "the end of an attribute value")?;
+                    attributes.push((key, self.decode(&value)?));
+                }
+                None => {
+                    return Err(self.error(format!("unexpected end of file in <{}", name)));
+                }
+            }
+        }
+
+        return Ok(XmlEvent::StartElement {
+            name: name,
+            attributes: attributes,
+        });
+    }
+
+    /** read_end_element() reads an end tag after its </, checking it closes the last open element */
+    fn read_end_element(&mut self) -> Result<XmlEvent, IoError> {
+        let name = self.read_name()?;
+        self.skip_whitespace()?;
+        if self.expect_byte("the end of the tag")? != b'>' {
+            return Err(self.error(format!("expected > to end </{}", name)));
+        }
+
+        match self.open.pop() {
+            Some(open) if open == name => return Ok(XmlEvent::EndElement { name: name }),
+            Some(open) => {
+                return Err(self.error(format!("expected </{}>, found </{}>", open, name)))
+            }
+            None => return Err(self.error(format!("</{}> closes no element", name))),
+        }
+    }
+
+    /** skip_declaration() skips a DOCTYPE or other declaration after its <!, with any internal subset */
+    fn skip_declaration(&mut self) -> Result<(), IoError> {
+        let mut depth = 0;
+        loop {
+            match self.expect_byte("the end of a declaration")? {
+                b'[' => depth += 1,
+                b']' => depth -= 1,
+                b'>' if depth <= 0 => return Ok(()),
+                _ => {}
+            }
+        }
+    }
+
+    /**
+     * next_event() returns the next event of the document, None at its end. The end of
+     * the document with elements still open is an error
+     */
+    pub fn next_event(&mut self) -> Result<Option<XmlEvent>, IoError> {
+        if let Some(name) = self.pending_end.take() {
+            return Ok(Some(XmlEvent::EndElement { name: name }));
+        }
+
+        let mut text: Vec<u8> = Vec::new();
+        loop {
+            match self.peek_byte()? {
+                None => {
+                    if let Some(open) = self.open.last() {
+                        return Err(
+                            self.error(format!("unexpected end of file, <{}> is not closed", open))
+                        );
+                    }
+                    break;
+                }
+                Some(b'<') => {
+                    // markup ends the text before it, if there is any
+                    let decoded = self.decode(&text)?;
+                    if !decoded.trim().is_empty() {
+                        return Ok(Some(XmlEvent::Text(decoded)));
+                    }
+                    text.clear();
+
+                    self.next_byte()?;
+                    match self.peek_byte()? {
+                        Some(b'?') => {
+                            self.read_until(b"?>", "the end of a processing instruction")?;
+                        }
+                        Some(b'!') => {
+                            self.next_byte()?;
+                            if self.peek_byte()? == Some(b'-') {
+                                self.read_until(b"--", "the start of a comment")?;
+                                self.read_until(b"-->", "the end of a comment")?;
+                            } else if self.peek_byte()? == Some(b'[') {
+                                let start = self.read_until(b"[CDATA[", "a CDATA section")?;
+                                if !start.is_empty() {
+                                    return Err(self.error("invalid CDATA section".to_string()));
+                                }
+                                let bytes =
+                                    self.read_until(b"]]>", "the end of a CDATA section")?;
+                                let cdata = String::from_utf8_lossy(&bytes).to_string();
+                                if cdata.trim().is_empty() {
+                                    continue;
+                                }
+                                // the CDATA text joins the text that follows it
+                                let following = self.read_text_bytes()?;
+                                let decoded = cdata + &self.decode(&following)?;
+                                return Ok(Some(XmlEvent::Text(decoded)));
+                            } else {
+                                self.skip_declaration()?;
+                            }
+                        }
+                        Some(b'/') => {
+                            self.next_byte()?;
+                            return Ok(Some(self.read_end_element()?));
+                        }
+                        _ => return Ok(Some(self.read_start_element()?)),
+                    }
+                }
+                Some(_) => {
+                    text.extend(self.read_text_bytes()?);
+                }
+            }
+        }
+
+        return Ok(None);
+    }
+
+    /** read_text_bytes() reads the bytes up to the next markup */
+    fn read_text_bytes(&mut self) -> Result<Vec<u8>, IoError> {
+        let mut bytes = Vec::new();
+        while let Some(byte) = self.peek_byte()? {
+            if byte == b'<' {
+                break;
+            }
+            bytes.push(byte);
+            self.next_byte()?;
+        }
+        return Ok(bytes);
+    }
+
+    /**
+     * read_text() returns the text of the element just started, up to and including its
+     * end, the text of nested elements included
+     */
+    pub fn read_text(&mut self) -> Result<String, IoError> {
+        let mut text = String::new();
+        let mut depth = 1;
+
+        while let Some(event) = self.next_event()? {
+            match event {
+                XmlEvent::StartElement { .. } => depth += 1,
+                XmlEvent::EndElement { .. } => {
+                    depth -= 1;
+                    if depth == 0 {
+                        break;
+                    }
+                }
+                XmlEvent::Text(value) => text.push_str(&value),
+            }
+        }
+
+        return Ok(text);
+    }
+
+    /** skip_element() skips the rest of the element just started, up to and including its end */
+    pub fn skip_element(&mut self) -> Result<(), IoError> {
+        self.read_text()?;
+        return Ok(());
+    }
+}
+
+/** Unit tests for the above module */
+#[cfg(test)]
+mod tests {
+    use crate::io::xml::XmlEvent;
+    use crate::io::xml::XmlReader;
+
+    fn get_events(text: &str) -> Vec<XmlEvent> {
+        let mut reader = XmlReader::new(text.as_bytes(), "xml");
+        let mut events = Vec::new();
+        while let Some(event) = reader.next_event().unwrap() {
+            events.push(event);
+        }
+        return events;
+    }
+
+    #[test]
+    fn xml_events() {
+        let events = get_events(
+            "<?xml version=\"1.0\"?>
+<!DOCTYPE cml [ <!ENTITY x \"y\"> ]>
+<!-- a comment -- with dashes -->
+<cml:list xmlns:cml='http://www.xml-cml.org/schema'>
+  <atom id=\"a1\" title='&lt;O&gt; &amp; &#72;&#x32;'/>
+  <name>water &quot;dimer&quot;</name>
+  <scalar><![CDATA[1 < 2]]> &amp; more</scalar>
+</cml:list>",
+        );
+
+        assert_eq!(events.len(), 10);
+        match &events[0] {
+            XmlEvent::StartElement { name, attributes } => {
+                assert_eq!(name, "cml:list");
+                assert_eq!(super::local_name(name), "list");
+                assert_eq!(
+                    super::get_attribute(attributes, "cml"),
+                    Some("http://www.xml-cml.org/schema")
+                );
+            }
+            event => panic!("unexpected {:?}", event),
+        }
+        match &events[1] {
+            XmlEvent::StartElement { attributes, .. } => {
+                assert_eq!(super::get_attribute(attributes, "title"), Some("<O> & H2"));
+            }
+            event => panic!("unexpected {:?}", event),
+        }
+        assert_eq!(
+            events[2],
+            XmlEvent::EndElement {
+                name: "atom".to_string()
+            }
+        );
+        assert_eq!(events[4], XmlEvent::Text("water \"dimer\"".to_string()));
+        assert_eq!(events[7], XmlEvent::Text("1 < 2 & more".to_string()));
+
+        let mut reader = XmlReader::new("<a><b>x<c>y</c></b>z</a>".as_bytes(), "xml");
+        reader.next_event().unwrap();
+        reader.next_event().unwrap();
+        assert_eq!(reader.read_text().unwrap(), "xy");
+        assert_eq!(
+            reader.next_event().unwrap(),
+            Some(XmlEvent::Text("z".to_string()))
+        );
+
+        assert_eq!(super::escape("a<b & \"c\""), "a&lt;b &amp; &quot;c&quot;");
+    }
+
+    #[test]
+    fn xml_errors() {
+        let error = |text: &str| {
+            let mut reader = XmlReader::new(text.as_bytes(), "xml");
+            loop {
+                match reader.next_event() {
+                    Ok(Some(_)) => {}
+                    Ok(None) => panic!("no error in {}", text),
+                    Err(err) => return err.to_string(),
+                }
+            }
+        };
+
+        assert_eq!(
+            error("<a>\n<b>\n</a>"),
+            "xml: line 3: expected </b>, found </a>"
+        );
+        assert_eq!(
+            error("<a>\n<b/>"),
+            "xml: line 2: unexpected end of file, <a> is not closed"
+        );
+        assert_eq!(
+            error("<a x=1/>"),
+            "xml: line 1: the value of x is not quoted"
+        );
+        assert_eq!(
+            error("<a>&nbsp;</a>"),
+            "xml: line 1: unknown reference &nbsp;"
+        );
+    }
+}
@@ -0,0 +1,728 @@
+/**
+ * Author: V. Ganesh
+ * License: MIT
+ */
+
+/**
+ * xyz module, reads and writes XYZ files, including multi-frame trajectories and the
+ * extended XYZ comment line written by ASE.
+ *
+ * In extended XYZ the comment line is a list of key=value pairs. Lattice sets the
+ * Molecule lattice, Properties describes the columns of the atom lines, name and
+ * comment set the Molecule name and remark, and any other key becomes a Molecule
+ * property. A charges column is read into the atom charges, other columns become atom
+ * properties, e.g. forces:R:3.
+ */
+use std::io::BufRead;
+use std::io::Write;
+
+use crate::atom::Atom;
+use crate::atom::AtomOperations;
+use crate::atomprop;
+use crate::io::IoError;
+use crate::io::LineReader;
+use crate::lattice::Lattice;
+use crate::molecule::Molecule;
+use crate::molecule::DEFAULT_BOND_TOLERANCE;
+use crate::point::Point;
+use crate::property::AtomProperty;
+use crate::property::Property;
+
+const FORMAT: &str = "xyz";
+
+/** symbol used for atoms given by the atomic number 0 (dummy atoms) */
+const DUMMY_SYMBOL: &str = "X";
+
+/** Column - a column of the atom lines, as described by the Properties key */
+enum Column {
+    Species,
+    Position,
+    Charge,
+    Values(String, AtomProperty),
+}
+
+impl Column {
+    /** get_width() returns the number of fields taken by the column */
+    fn get_width(&self) -> usize {
+        match self {
+            Column::Position => return 3,
+            Column::Values(_, AtomProperty::Vectors(_)) => return 3,
+            _ => return 1,
+        }
+    }
+}
+
+/** XyzReader - reads the frames of an XYZ file one at a time */
+#[allow(dead_code)]
+pub struct XyzReader<R: BufRead> {
+    reader: LineReader<R>,
+    perceive_bonds: bool,
+    failed: bool,
+}
+
+#[allow(dead_code)]
+impl<R: BufRead> XyzReader<R> {
+    pub fn new(reader: R) -> XyzReader<R> {
+        XyzReader {
+            reader: LineReader::new(reader, FORMAT),
+            perceive_bonds: false,
+            failed: false,
+        }
+    }
+
+    /** perceive_bonds() sets whether bonds are computed from covalent radii for each frame */
+    pub fn perceive_bonds(mut self, perceive_bonds: bool) -> XyzReader<R> {
+        self.perceive_bonds = perceive_bonds;
+        return self;
+    }
+
+    /** read_frame() reads the next frame, or returns None at the end of the file */
+    pub fn read_frame(&mut self) -> Result<Option<Molecule>, IoError> {
+        let count_line = loop {
+            match self.reader.next_line()? {
+                Some(line) if line.trim().is_empty() => continue,
+                Some(line) => break line,
+                None => return Ok(None),
+            }
+        };
+
+        let number_of_atoms: usize = count_line.trim().parse().map_err(|_| {
+            self.reader.error(format!(
+                "expected the number of atoms, found \"{}\"",
+                count_line.trim()
+            ))
+        })?;
+
+        let comment = self.reader.expect_line("the comment line")?;
+        let mut mol = Molecule::new("".to_string(), "".to_string());
+        let mut columns =
+            parse_comment(&comment, &mut mol).map_err(|message| self.reader.error(message))?;
+
+        for _ in 0..number_of_atoms {
+            let line = self.reader.expect_line("an atom line")?;
+            let atom =
+                parse_atom(&line, &mut columns).map_err(|message| self.reader.error(message))?;
+            mol.add_atom(atom);
+        }
+
+        for column in columns {
+            if let Column::Values(name, values) = column {
+                mol.set_atom_property(&name, values);
+            }
+        }
+
+        if self.perceive_bonds {
+            mol.compute_covalent_bonds(DEFAULT_BOND_TOLERANCE);
+        }
+
+        return Ok(Some(mol));
+    }
+}
+
+impl<R: BufRead> Iterator for XyzReader<R> {
+    type Item = Result<Molecule, IoError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+
+        let frame = self.read_frame();
+        self.failed = frame.is_err();
+        return frame.transpose();
+    }
+}
+
+/** read() reads the first frame of an XYZ file */
+#[allow(dead_code)]
+pub fn read<R: BufRead>(reader: R) -> Result<Molecule, IoError> {
+    match XyzReader::new(reader).read_frame()? {
+        Some(mol) => return Ok(mol),
+        None => return Err(IoError::parse(FORMAT, 1, "the file is empty".to_string())),
+    }
+}
+
+/**
+ * write() writes a molecule as one XYZ frame, call it once per molecule to write a
+ * trajectory. The extended comment line is written when the molecule has a lattice,
+ * properties, atom properties or charges, otherwise the remark is the comment line.
+ * Whitespace in text atom properties is written as '_'.
+ */
+#[allow(dead_code)]
+pub fn write<W: Write>(mut writer: W, mol: &Molecule) -> Result<(), IoError> {
+    let atoms = mol.get_atoms();
+    let has_charges = atoms.iter().any(|at| at.charge != 0.0);
+    let is_extended = has_charges
+        || mol.get_lattice().is_some()
+        || !mol.get_properties().is_empty()
+        || !mol.get_atom_properties().is_empty();
+
+    writeln!(writer, "{}", atoms.len())?;
+
+    if is_extended {
+        writeln!(writer, "{}", format_comment(mol, has_charges))?;
+    } else if mol.remark.is_empty() {
+        writeln!(writer, "{}", mol.name.replace(['\n', '\r'], " "))?;
+    } else {
+        writeln!(writer, "{}", mol.remark.replace(['\n', '\r'], " "))?;
+    }
+
+    for (idx, atom) in atoms.iter().enumerate() {
+        write!(
+            writer,
+            "{:<2} {:16.8} {:16.8} {:16.8}",
+            atom.symbol, atom.center.x, atom.center.y, atom.center.z
+        )?;
+
+        if has_charges {
+            write!(writer, " {:16.8}", atom.charge)?;
+        }
+
+        for values in mol.get_atom_properties().values() {
+            match values {
+                AtomProperty::Texts(values) => write!(writer, " {}", format_text(&values[idx]))?,
+                AtomProperty::Integers(values) => write!(writer, " {:>8}", values[idx])?,
+                AtomProperty::Reals(values) => write!(writer, " {:>16}", values[idx])?,
+                AtomProperty::Logicals(values) => {
+                    write!(writer, " {}", if values[idx] { "T" } else { "F" })?
+                }
+                AtomProperty::Vectors(values) => {
+                    let v = values[idx];
+                    write!(writer, " {:>16} {:>16} {:>16}", v[0], v[1], v[2])?
+                }
+            }
+        }
+
+        writeln!(writer)?;
+    }
+
+    return Ok(());
+}
+
+/**
+ * parse_comment() reads the comment line into the molecule, and returns the columns of
+ * the atom lines. The line is taken as extended XYZ if it has a Lattice or Properties key,
+ * or if every word in it is a key=value pair.
+ */
+fn parse_comment(comment: &str, mol: &mut Molecule) -> Result<Vec<Column>, String> {
+    let mut columns = vec![Column::Species, Column::Position];
+
+    let pairs = match split_key_values(comment) {
+        Some(pairs)
+            if pairs.iter().all(|(_, value)| value.is_some())
+                || pairs.iter().any(|(key, _)| {
+                    key.eq_ignore_ascii_case("lattice") || key.eq_ignore_ascii_case("properties")
+                }) =>
+        {
+            pairs
+        }
+        _ => {
+            mol.remark = comment.trim().to_string();
+            return Ok(columns);
+        }
+    };
+
+    for (key, value) in pairs {
+        let value = match value {
+            Some(value) => value,
+            None => {
+                mol.set_property(&key, Property::Logical(true));
+                continue;
+            }
+        };
+
+        match key.to_lowercase().as_str() {
+            "lattice" => {
+                let numbers = parse_reals(&value)
+                    .filter(|numbers| numbers.len() == 9)
+                    .ok_or_else(|| format!("Lattice needs 9 numbers, found \"{}\"", value))?;
+
+                mol.set_lattice(Some(Lattice::new([
+                    [numbers[0], numbers[1], numbers[2]],
+                    [numbers[3], numbers[4], numbers[5]],
+                    [numbers[6], numbers[7], numbers[8]],
+                ])));
+            }
+            "properties" => columns = parse_columns(&value)?,
+            "name" => mol.name = value,
+            "comment" => mol.remark = value,
+            _ => mol.set_property(&key, parse_value(&value)),
+        }
+    }
+
+    return Ok(columns);
+}
+
+/**
+ * split_key_values() splits a comment line into keys, each with an optional value.
+ * Values may be quoted with ", where \ escapes the next character. Returns None if the
+ * line is not a list of key=value pairs.
+ */
+fn split_key_values(line: &str) -> Option<Vec<(String, Option<String>)>> {
+    let mut pairs = Vec::new();
+    let mut chars = line.chars().peekable();
+
+    let read_word = |chars: &mut std::iter::Peekable<std::str::Chars>, is_key: bool| {
+        let mut word = String::new();
+
+        if chars.peek() == Some(&'"') {
+            chars.next();
+            loop {
+                match chars.next()? {
+                    '"' => break,
+                    '\\' => word.push(chars.next()?),
+                    c => word.push(c),
+                }
+            }
+        } else {
+            while let Some(&c) = chars.peek() {
+                if c.is_whitespace() || (is_key && c == '=') {
+                    break;
+                }
+                word.push(c);
+                chars.next();
+            }
+        }
+
+        return Some(word);
+    };
+
+    loop {
+        while chars.peek().is_some_and(|c| c.is_whitespace()) {
+            chars.next();
+        }
+        if chars.peek().is_none() {
+            break;
+        }
+
+        let key = read_word(&mut chars, true)?;
+        if key.is_empty() {
+            return None;
+        }
+
+        while chars.peek().is_some_and(|c| c.is_whitespace()) {
+            chars.next();
+        }
+
+        if chars.peek() == Some(&'=') {
+            chars.next();
+            while chars.peek().is_some_and(|c| c.is_whitespace()) {
+                chars.next();
+            }
+            pairs.push((key, Some(read_word(&mut chars, false)?)));
+        } else {
+            pairs.push((key, None));
+        }
+    }
+
+    return Some(pairs);
+}
+
+/** parse_columns() reads the value of the Properties key, e.g. species:S:1:pos:R:3 */
+fn parse_columns(value: &str) -> Result<Vec<Column>, String> {
+    let fields: Vec<&str> = value.split(':').collect();
+    if !fields.len().is_multiple_of(3) {
+        return Err(format!(
+            "Properties needs name:type:count triples, found \"{}\"",
+            value
+        ));
+    }
+
+    let mut columns = Vec::new();
+    for triple in fields.chunks(3) {
+        let (name, kind, count) = (triple[0], triple[1], triple[2]);
+
+        let column = match (name.to_lowercase().as_str(), kind, count) {
+            ("species", "S", "1") => Column::Species,
+            ("pos", "R", "3") => Column::Position,
+            ("charges" | "charge", "R", "1") => Column::Charge,
+            (_, "S", "1") => Column::Values(name.to_string(), AtomProperty::Texts(Vec::new())),
+            (_, "I", "1") => Column::Values(name.to_string(), AtomProperty::Integers(Vec::new())),
+            (_, "R", "1") => Column::Values(name.to_string(), AtomProperty::Reals(Vec::new())),
+            (_, "L", "1") => Column::Values(name.to_string(), AtomProperty::Logicals(Vec::new())),
+            (_, "R", "3") => Column::Values(name.to_string(), AtomProperty::Vectors(Vec::new())),
+            _ => {
+                return Err(format!(
+                    "unsupported column {}:{}:{} in Properties",
+                    name, kind, count
+                ))
+            }
+        };
+        columns.push(column);
+    }
+
+    for (column, name) in [(Column::Species, "species"), (Column::Position, "pos")] {
+        if !columns
+            .iter()
+            .any(|c| std::mem::discriminant(c) == std::mem::discriminant(&column))
+        {
+            return Err(format!("Properties has no {} column", name));
+        }
+    }
+
+    return Ok(columns);
+}
+
+/** parse_atom() reads an atom line, appending the values of atom properties to the columns */
+fn parse_atom(line: &str, columns: &mut [Column]) -> Result<Atom, String> {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    let width: usize = columns.iter().map(|c| c.get_width()).sum();
+
+    if fields.len() < width {
+        return Err(format!(
+            "expected {} fields in the atom line, found {}",
+            width,
+            fields.len()
+        ));
+    }
+
+    let real = |field: &str| {
+        return field
+            .parse::<f64>()
+            .map_err(|_| format!("expected a number, found \"{}\"", field));
+    };
+
+    let mut atom = Atom {
+        center: Point {
+            x: 0.0,
+            y: 0.0,
+            z: 0.0,
+        },
+        charge: 0.0,
+        symbol: "".to_string(),
+        remark: "".to_string(),
+    };
+
+    let mut position = 0;
+    for column in columns.iter_mut() {
+        let field = fields[position];
+
+        match column {
+            Column::Species => {
+                atom.symbol = match field.parse::<u8>() {
+                    Ok(number) => atomprop::get_element_symbol(number)
+                        .unwrap_or(DUMMY_SYMBOL)
+                        .to_string(),
+                    Err(_) => field.to_string(),
+                }
+            }
+            Column::Position => {
+                atom.center = Point {
+                    x: real(field)? as f32,
+                    y: real(fields[position + 1])? as f32,
+                    z: real(fields[position + 2])? as f32,
+                }
+            }
+            Column::Charge => atom.charge = real(field)? as f32,
+            Column::Values(_, AtomProperty::Texts(values)) => values.push(field.to_string()),
+            Column::Values(_, AtomProperty::Integers(values)) => values.push(
+                field
+                    .parse()
+                    .map_err(|_| format!("expected an integer, found \"{}\"", field))?,
+            ),
+            Column::Values(_, AtomProperty::Reals(values)) => values.push(real(field)?),
+            Column::Values(_, AtomProperty::Logicals(values)) => values.push(
+                parse_logical(field)
+                    .ok_or_else(|| format!("expected T or F, found \"{}\"", field))?,
+            ),
+            Column::Values(_, AtomProperty::Vectors(values)) => values.push([
+                real(field)?,
+                real(fields[position + 1])?,
+                real(fields[position + 2])?,
+            ]),
+        }
+
+        position += column.get_width();
+    }
+
+    return Ok(atom);
+}
+
+fn parse_logical(text: &str) -> Option<bool> {
+    match text {
+        "T" | "True" | "true" | "TRUE" => return Some(true),
+        "F" | "False" | "false" | "FALSE" => return Some(false),
+        _ => return None,
+    }
+}
+
+fn parse_reals(text: &str) -> Option<Vec<f64>> {
+    return text
+        .split_whitespace()
+        .map(|word| word.parse().ok())
+        .collect();
+}
+
+/** parse_value() reads a property value as a logical, number, list of those, or text */
+fn parse_value(text: &str) -> Property {
+    let words: Vec<&str> = text.split_whitespace().collect();
+
+    if words.len() == 1 {
+        if let Some(value) = parse_logical(words[0]) {
+            return Property::Logical(value);
+        } else if let Ok(value) = words[0].parse() {
+            return Property::Integer(value);
+        } else if let Ok(value) = words[0].parse() {
+            return Property::Real(value);
+        }
+    } else if words.len() > 1 {
+        if let Some(values) = words.iter().map(|w| parse_logical(w)).collect() {
+            return Property::Logicals(values);
+        } else if let Some(values) = words.iter().map(|w| w.parse().ok()).collect() {
+            return Property::Integers(values);
+        } else if let Some(values) = parse_reals(text) {
+            return Property::Reals(values);
+        }
+    }
+
+    return Property::Text(text.to_string());
+}
+
+/** format_comment() writes the extended XYZ comment line of a molecule */
+fn format_comment(mol: &Molecule, has_charges: bool) -> String {
+    let mut fields = Vec::new();
+
+    if let Some(lattice) = mol.get_lattice() {
+        let numbers: Vec<String> = lattice
+            .vectors
+            .iter()
+            .flatten()
+            .map(|v| v.to_string())
+            .collect();
+        fields.push(format!("Lattice=\"{}\"", numbers.join(" ")));
+    }
+
+    let mut columns = "species:S:1:pos:R:3".to_string();
+    if has_charges {
+        columns.push_str(":charges:R:1");
+    }
+    for (name, values) in mol.get_atom_properties().iter() {
+        let kind = match values {
+            AtomProperty::Texts(_) => "S:1",
+            AtomProperty::Integers(_) => "I:1",
+            AtomProperty::Reals(_) => "R:1",
+            AtomProperty::Logicals(_) => "L:1",
+            AtomProperty::Vectors(_) => "R:3",
+        };
+        columns.push_str(&format!(":{}:{}", name, kind));
+    }
+    fields.push(format!("Properties={}", columns));
+
+    if !mol.name.is_empty() {
+        fields.push(format!("name={}", quote(&mol.name)));
+    }
+    if !mol.remark.is_empty() {
+        fields.push(format!("comment={}", quote(&mol.remark)));
+    }
+
+    for (key, value) in mol.get_properties().iter() {
+        fields.push(format!("{}={}", quote(key), quote(&value.to_string())));
+    }
+
+    return fields.join(" ");
+}
+
+/** quote() quotes a key or value if it is empty or has whitespace, '=' or '"' in it */
+fn quote(text: &str) -> String {
+    if !text.is_empty()
+        && !text
+            .chars()
+            .any(|c| c.is_whitespace() || c == '=' || c == '"' || c == '\\')
+    {
+        return text.to_string();
+    }
+
+    let escaped = text
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace(['\n', '\r'], " ");
+    return format!("\"{}\"", escaped);
+}
+
+fn format_text(text: &str) -> String {
+    if text.is_empty() {
+        return "_".to_string();
+    }
+    return text.replace(char::is_whitespace, "_");
+}
+
+/** Unit tests for the above module */
+#[cfg(test)]
+mod tests {
+    use super::XyzReader;
+    use crate::atom::Atom;
+    use crate::atom::AtomOperations;
+    use crate::io::IoError;
+    use crate::lattice::Lattice;
+    use crate::molecule::Molecule;
+    use crate::point::Point;
+    use crate::property::AtomProperty;
+    use crate::property::Property;
+
+    const WATER: &str = "3
+Water Molecule
+O     0.000000     0.000000     0.000000
+H     0.758602     0.000000     0.504284
+H     0.758602     0.000000    -0.504284
+";
+
+    #[test]
+    fn xyz_read_plain() {
+        let mut mol = super::read(WATER.as_bytes()).unwrap();
+
+        assert_eq!(mol.remark, "Water Molecule".to_string());
+        assert_eq!(mol.get_number_of_atoms(), 3);
+        assert_eq!(mol.get_atom(1).symbol, "H".to_string());
+        assert_eq!(mol.get_atom(2).center.z, -0.504284);
+        assert!(mol.get_properties().is_empty());
+        assert_eq!(mol.get_number_of_bonds(), 0);
+
+        let mut buffer = Vec::new();
+        super::write(&mut buffer, &mol).unwrap();
+
+        let read_mol = super::read(buffer.as_slice()).unwrap();
+        assert_eq!(read_mol.remark, mol.remark);
+        assert_eq!(read_mol.get_atoms(), mol.get_atoms());
+    }
+
+    #[test]
+    fn xyz_frames() {
+        let text = format!(
+            "{}\n{}2\nstep 2\n8 0.0 0.0 0.1\n1 0.0 0.0 1.1\n",
+            WATER, WATER
+        );
+
+        let frames: Vec<Molecule> = XyzReader::new(text.as_bytes())
+            .map(|frame| frame.unwrap())
+            .collect();
+
+        assert_eq!(frames.len(), 3);
+        assert_eq!(frames[2].remark, "step 2".to_string());
+        assert_eq!(frames[2].get_atoms()[0].symbol, "O".to_string());
+        assert_eq!(frames[2].get_atoms()[1].center.z, 1.1);
+
+        let mut buffer = Vec::new();
+        for frame in frames.iter() {
+            super::write(&mut buffer, frame).unwrap();
+        }
+        assert_eq!(XyzReader::new(buffer.as_slice()).count(), 3);
+    }
+
+    #[test]
+    fn xyz_read_extended() {
+        let text = r#"2
+Lattice="5.0 0.0 0.0 0.0 5.0 0.0 0.0 0.0 5.0" Properties=species:S:1:pos:R:3:forces:R:3:fixed:L:1 energy=-14.5 pbc="T T T" config_type=bulk
+Si 0.0 0.0 0.0 0.1 0.0 -0.1 T
+Si 1.3575 1.3575 1.3575 -0.1 0.0 0.1 F
+"#;
+
+        let mol = super::read(text.as_bytes()).unwrap();
+
+        assert_eq!(
+            mol.get_lattice(),
+            Some(&Lattice::new([
+                [5.0, 0.0, 0.0],
+                [0.0, 5.0, 0.0],
+                [0.0, 0.0, 5.0]
+            ]))
+        );
+        assert_eq!(mol.get_property("energy"), Some(&Property::Real(-14.5)));
+        assert_eq!(
+            mol.get_property("pbc"),
+            Some(&Property::Logicals(vec![true, true, true]))
+        );
+        assert_eq!(
+            mol.get_property("config_type").unwrap().get_text(),
+            Some("bulk")
+        );
+        assert_eq!(
+            mol.get_atom_property("forces"),
+            Some(&AtomProperty::Vectors(vec![
+                [0.1, 0.0, -0.1],
+                [-0.1, 0.0, 0.1]
+            ]))
+        );
+        assert_eq!(
+            mol.get_atom_property("fixed"),
+            Some(&AtomProperty::Logicals(vec![true, false]))
+        );
+        assert_eq!(mol.get_atoms()[1].center.x, 1.3575);
+        assert_eq!(mol.remark, "".to_string());
+    }
+
+    #[test]
+    fn xyz_extended_round_trip() {
+        let mut mol = Molecule::new("nacl".to_string(), "rock \"salt\" = NaCl".to_string());
+        for (symbol, x, charge) in [("Na", 0.0, 1.0), ("Cl", 2.82, -1.0)] {
+            mol.add_atom(Atom {
+                center: Point {
+                    x: x,
+                    y: 0.0,
+                    z: 0.0,
+                },
+                charge: charge,
+                symbol: symbol.to_string(),
+                remark: "".to_string(),
+            });
+        }
+        mol.set_lattice(Some(Lattice::from_parameters(
+            5.64, 5.64, 5.64, 90.0, 90.0, 90.0,
+        )));
+        mol.set_property("energy", Property::Real(-7.123456789));
+        mol.set_property("step", Property::Integer(12));
+        mol.set_property("method", Property::Text("PBE D3".to_string()));
+        mol.set_property("kpoints", Property::Integers(vec![4, 4, 4]));
+        mol.set_atom_property(
+            "forces",
+            AtomProperty::Vectors(vec![[0.01, -0.02, 0.0], [-0.01, 0.02, 1e-9]]),
+        );
+        mol.set_atom_property("tag", AtomProperty::Integers(vec![1, 2]));
+        mol.set_atom_property(
+            "label",
+            AtomProperty::Texts(vec!["cation".to_string(), "anion".to_string()]),
+        );
+
+        let mut buffer = Vec::new();
+        super::write(&mut buffer, &mol).unwrap();
+
+        let read_mol = super::read(buffer.as_slice()).unwrap();
+        assert_eq!(read_mol.name, mol.name);
+        assert_eq!(read_mol.remark, mol.remark);
+        assert_eq!(read_mol.get_atoms(), mol.get_atoms());
+        assert_eq!(read_mol.get_lattice(), mol.get_lattice());
+        assert_eq!(read_mol.get_properties(), mol.get_properties());
+        assert_eq!(read_mol.get_atom_properties(), mol.get_atom_properties());
+    }
+
+    #[test]
+    fn xyz_perceive_bonds() {
+        let text = "3\n\nO 0.0 0.0 0.1173\nH 0.0 0.7572 -0.4692\nH 0.0 -0.7572 -0.4692\n";
+        let mut mol = XyzReader::new(text.as_bytes())
+            .perceive_bonds(true)
+            .read_frame()
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(mol.get_number_of_bonds(), 2);
+        assert_eq!(mol.get_bond_index(0, 1), 0);
+        assert_eq!(mol.get_bond_index(0, 2), 1);
+    }
+
+    #[test]
+    fn xyz_read_errors() {
+        let line_of = |text: &str| match super::read(text.as_bytes()) {
+            Err(IoError::Parse { line_number, .. }) => line_number,
+            _ => panic!("expected a parse error"),
+        };
+
+        assert_eq!(line_of("three\ncomment\n"), 1);
+        assert_eq!(line_of("2\ncomment\nH 0.0 0.0 0.0\nH 0.0 0.0\n"), 4);
+        assert_eq!(line_of("2\ncomment\nH 0.0 0.0 0.0\n"), 4);
+        assert_eq!(line_of("1\nLattice=\"1 2 3\"\nH 0.0 0.0 0.0\n"), 2);
+        assert_eq!(line_of(""), 1);
+
+        let mut reader = XyzReader::new("1\n\nH 0 0 x\n1\n\nH 0 0 0\n".as_bytes());
+        assert!(reader.next().unwrap().is_err());
+        assert!(reader.next().is_none());
+    }
+}
@@ -0,0 +1,179 @@
+/**
+ * Author: V. Ganesh
+ * License: MIT
+ */
+
+/** lattice module, the unit cell of a periodic system */
+
+/** Lattice - three cell vectors a, b and c, in angstrom, one per row */
+#[allow(dead_code)]
+#[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Lattice {
+    pub vectors: [[f64; 3]; 3],
+}
+
+fn dot(u: &[f64; 3], v: &[f64; 3]) -> f64 {
+    return u[0] * v[0] + u[1] * v[1] + u[2] * v[2];
+}
+
+fn cross(u: &[f64; 3], v: &[f64; 3]) -> [f64; 3] {
+    return [
+        u[1] * v[2] - u[2] * v[1],
+        u[2] * v[0] - u[0] * v[2],
+        u[0] * v[1] - u[1] * v[0],
+    ];
+}
+
+#[allow(dead_code)]
+impl Lattice {
+    pub fn new(vectors: [[f64; 3]; 3]) -> Lattice {
+        Lattice { vectors: vectors }
+    }
+
+    /**
+     * from_parameters() builds the cell from its lengths and angles (in degrees), with a
+     * along x and b in the xy plane, the convention used by PDB and CIF files
+     */
+    pub fn from_parameters(a: f64, b: f64, c: f64, alpha: f64, beta: f64, gamma: f64) -> Lattice {
+        let (alpha, beta, gamma) = (alpha.to_radians(), beta.to_radians(), gamma.to_radians());
+
+        let c_x = c * beta.cos();
+        let c_y = c * (alpha.cos() - beta.cos() * gamma.cos()) / gamma.sin();
+        let c_z = (c * c - c_x * c_x - c_y * c_y).max(0.0).sqrt();
+
+        return Lattice {
+            vectors: [
+                [a, 0.0, 0.0],
+                [b * gamma.cos(), b * gamma.sin(), 0.0],
+                [c_x, c_y, c_z],
+            ],
+        };
+    }
+
+    /** get_parameters() returns the cell lengths and angles (in degrees): a, b, c, alpha, beta, gamma */
+    pub fn get_parameters(&self) -> [f64; 6] {
+        let [a_vec, b_vec, c_vec] = &self.vectors;
+        let (a, b, c) = (
+            dot(a_vec, a_vec).sqrt(),
+            dot(b_vec, b_vec).sqrt(),
+            dot(c_vec, c_vec).sqrt(),
+        );
+
+        let angle = |u: &[f64; 3], v: &[f64; 3], lu: f64, lv: f64| {
+            return (dot(u, v) / (lu * lv)).clamp(-1.0, 1.0).acos().to_degrees();
+        };
+
+        return [
+            a,
+            b,
+            c,
+            angle(b_vec, c_vec, b, c),
+            angle(a_vec, c_vec, a, c),
+            angle(a_vec, b_vec, a, b),
+        ];
+    }
+
+    /** get_volume() returns the cell volume, in cubic angstrom */
+    pub fn get_volume(&self) -> f64 {
+        let [a_vec, b_vec, c_vec] = &self.vectors;
+        return dot(a_vec, &cross(b_vec, c_vec)).abs();
+    }
+
+    /** to_cartesian() converts fractional coordinates to cartesian ones */
+    pub fn to_cartesian(&self, fractional: [f64; 3]) -> [f64; 3] {
+        let mut cartesian = [0.0; 3];
+
+        for (i, vector) in self.vectors.iter().enumerate() {
+            for k in 0..3 {
+                cartesian[k] += fractional[i] * vector[k];
+            }
+        }
+
+        return cartesian;
+    }
+
+    /** to_fractional() converts cartesian coordinates to fractional ones */
+    pub fn to_fractional(&self, cartesian: [f64; 3]) -> [f64; 3] {
+        let [a_vec, b_vec, c_vec] = &self.vectors;
+        let volume = dot(a_vec, &cross(b_vec, c_vec));
+
+        // rows of the inverse cell matrix are the reciprocal vectors
+        return [
+            dot(&cartesian, &cross(b_vec, c_vec)) / volume,
+            dot(&cartesian, &cross(c_vec, a_vec)) / volume,
+            dot(&cartesian, &cross(a_vec, b_vec)) / volume,
+        ];
+    }
+
+    /**
+     * is_standard_form() checks whether a is along x and b in the xy plane, the form of
+     * the cell used by GROMACS and LAMMPS
+     */
+    pub fn is_standard_form(&self) -> bool {
+        let [a_vec, b_vec, c_vec] = &self.vectors;
+        return a_vec[1] == 0.0
+            && a_vec[2] == 0.0
+            && b_vec[2] == 0.0
+            && a_vec[0] > 0.0
+            && b_vec[1] > 0.0
+            && c_vec[2] > 0.0;
+    }
+
+    /** get_standard_form() returns the same cell turned so that a is along x and b in the xy plane */
+    pub fn get_standard_form(&self) -> Lattice {
+        let [a, b, c, alpha, beta, gamma] = self.get_parameters();
+        return Lattice::from_parameters(a, b, c, alpha, beta, gamma);
+    }
+}
+
+/** Unit tests for the above module */
+#[cfg(test)]
+mod tests {
+    use super::Lattice;
+
+    fn assert_close(a: f64, b: f64) {
+        assert!((a - b).abs() < 1e-8, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn lattice_parameters() {
+        let lattice = Lattice::from_parameters(5.0, 6.0, 7.0, 80.0, 95.0, 110.0);
+        let parameters = lattice.get_parameters();
+
+        for (value, expected) in parameters.iter().zip([5.0, 6.0, 7.0, 80.0, 95.0, 110.0]) {
+            assert_close(*value, expected);
+        }
+
+        let cubic = Lattice::from_parameters(4.0, 4.0, 4.0, 90.0, 90.0, 90.0);
+        assert_close(cubic.get_volume(), 64.0);
+        assert_close(cubic.vectors[1][0], 0.0);
+    }
+
+    #[test]
+    fn lattice_fractional() {
+        let lattice = Lattice::from_parameters(5.0, 6.0, 7.0, 80.0, 95.0, 110.0);
+        let cartesian = lattice.to_cartesian([0.25, 0.5, 0.75]);
+        let fractional = lattice.to_fractional(cartesian);
+
+        assert_close(fractional[0], 0.25);
+        assert_close(fractional[1], 0.5);
+        assert_close(fractional[2], 0.75);
+
+        let orthogonal = Lattice::new([[2.0, 0.0, 0.0], [0.0, 3.0, 0.0], [0.0, 0.0, 4.0]]);
+        assert_eq!(orthogonal.to_cartesian([0.5, 0.5, 0.5]), [1.0, 1.5, 2.0]);
+    }
+
+    #[test]
+    fn lattice_standard_form() {
+        let lattice = Lattice::new([[0.0, 3.0, 0.0], [0.0, 0.0, 4.0], [2.0, 0.0, 0.0]]);
+        assert!(!lattice.is_standard_form());
+
+        let standard = lattice.get_standard_form();
+        assert!(standard.is_standard_form());
+        assert_close(standard.vectors[0][0], 3.0);
+        assert_close(standard.vectors[1][1], 4.0);
+        assert_close(standard.vectors[2][2], 2.0);
+        assert_close(standard.get_volume(), lattice.get_volume());
+    }
+}
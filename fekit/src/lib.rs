@@ -1,11 +1,27 @@
+// The crate is written with explicit returns, `field: field` initialisers and a blank line
+// after the author header of every file, so clippy's lints against them are turned off
+// for the whole crate rather than at each of the hundreds of places they would fire.
+#![allow(
+    clippy::needless_return,
+    clippy::redundant_field_names,
+    clippy::empty_line_after_doc_comments
+)]
 pub mod atom;
+pub mod atomprop;
 pub mod bond;
+pub mod grid;
+pub mod io;
+pub mod lattice;
 pub mod molecule;
 /**
  * Author: V. Ganesh
  * License: MIT
  */
 pub mod point;
+pub mod property;
+pub mod select;
+#[cfg(test)]
+mod testutil;
 
 mod my_math {
     #[allow(dead_code)]
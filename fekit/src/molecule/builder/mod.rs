@@ -0,0 +1,387 @@
+/**
+ * Author: V. Ganesh
+ * License: MIT
+ */
+
+/**
+ * builder module, compact construction of molecules.
+ *
+ * MoleculeBuilder checks bond indices when build() is called:
+ *
+ *   let water = MoleculeBuilder::new("H2O")
+ *       .xyz("O 0.0 0.0 0.0
+ *             H 0.758602 0.0 0.504284
+ *             H 0.758602 0.0 -0.504284")
+ *       .bonds(&[(0, 1), (0, 2)])
+ *       .build()?;
+ *
+ * the molecule! macro checks them when the crate is compiled. Bonds are written as
+ * `a - b` (single), `a = b` (double), `a # b` (triple), `a : b` (aromatic),
+ * `a -> b` (coordinate) or `a ~ b` (weak):
+ *
+ *   let water = molecule! {
+ *       name: "H2O",
+ *       atoms: [
+ *           O 0.0, 0.0, 0.0;
+ *           H 0.758602, 0.0, 0.504284;
+ *           H 0.758602, 0.0, -0.504284;
+ *       ],
+ *       bonds: [0 - 1, 0 - 2],
+ *   };
+ */
+use std::error::Error;
+use std::fmt;
+
+use crate::atom::Atom;
+use crate::atom::AtomOperations;
+use crate::bond::BondIndex;
+use crate::bond::BondType;
+use crate::molecule::Molecule;
+use crate::point::Point;
+
+/** BuildError - the reason a MoleculeBuilder could not build its molecule */
+#[allow(dead_code)]
+#[derive(Clone, PartialEq, Debug)]
+pub enum BuildError {
+    /** a line passed to xyz() is not `symbol x y z`, line_number is 1 based */
+    InvalidXyz { line_number: usize, line: String },
+    /** a bond refers to an atom index that does not exist */
+    BondIndexOutOfRange {
+        atom_1_idx: usize,
+        atom_2_idx: usize,
+        number_of_atoms: usize,
+    },
+    /** a bond joins an atom to itself */
+    SelfBond { atom_idx: usize },
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BuildError::InvalidXyz { line_number, line } => {
+                write!(
+                    f,
+                    "line {}: expected 'symbol x y z', found '{}'",
+                    line_number, line
+                )
+            }
+            BuildError::BondIndexOutOfRange {
+                atom_1_idx,
+                atom_2_idx,
+                number_of_atoms,
+            } => write!(
+                f,
+                "bond {} - {} refers to a missing atom, the molecule has {} atoms",
+                atom_1_idx, atom_2_idx, number_of_atoms
+            ),
+            BuildError::SelfBond { atom_idx } => {
+                write!(f, "bond joins atom {} to itself", atom_idx)
+            }
+        }
+    }
+}
+
+impl Error for BuildError {}
+
+/** MoleculeBuilder - fluent construction of a Molecule */
+#[allow(dead_code)]
+#[derive(Clone, Default, PartialEq, Debug)]
+pub struct MoleculeBuilder {
+    name: String,
+    remark: String,
+    atom_list: Vec<Atom>,
+    bond_list: Vec<BondIndex>,
+    error: Option<BuildError>,
+}
+
+#[allow(dead_code)]
+impl MoleculeBuilder {
+    pub fn new(name: &str) -> MoleculeBuilder {
+        MoleculeBuilder {
+            name: name.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /** remark() sets the remark of the molecule */
+    pub fn remark(mut self, remark: &str) -> MoleculeBuilder {
+        self.remark = remark.to_string();
+        return self;
+    }
+
+    /** atom() adds an uncharged atom */
+    pub fn atom(mut self, symbol: &str, x: f32, y: f32, z: f32) -> MoleculeBuilder {
+        self.atom_list.push(Atom {
+            center: Point { x: x, y: y, z: z },
+            charge: 0.0,
+            symbol: symbol.to_string(),
+            remark: "".to_string(),
+        });
+        return self;
+    }
+
+    /** charge() sets the charge of the most recently added atom */
+    pub fn charge(mut self, charge: f32) -> MoleculeBuilder {
+        if let Some(atom) = self.atom_list.last_mut() {
+            atom.charge = charge;
+        }
+        return self;
+    }
+
+    /** xyz() adds atoms from `symbol x y z` lines, blank lines are skipped */
+    pub fn xyz(mut self, text: &str) -> MoleculeBuilder {
+        for (idx, line) in text.lines().enumerate() {
+            let words: Vec<&str> = line.split_whitespace().collect();
+
+            if words.is_empty() || self.error.is_some() {
+                continue;
+            }
+
+            let coords: Vec<f32> = words[1..].iter().filter_map(|w| w.parse().ok()).collect();
+
+            if words.len() != 4 || coords.len() != 3 {
+                self.error = Some(BuildError::InvalidXyz {
+                    line_number: idx + 1,
+                    line: line.trim().to_string(),
+                });
+                continue;
+            }
+
+            self = self.atom(words[0], coords[0], coords[1], coords[2]);
+        }
+
+        return self;
+    }
+
+    /** bond() adds a bond between two atom indices */
+    pub fn bond(
+        mut self,
+        atom_1_idx: usize,
+        atom_2_idx: usize,
+        bond_type: BondType,
+    ) -> MoleculeBuilder {
+        self.bond_list.push(BondIndex {
+            atom_1_idx: atom_1_idx,
+            atom_2_idx: atom_2_idx,
+            bond_type: bond_type,
+        });
+        return self;
+    }
+
+    /** bonds() adds single bonds between pairs of atom indices */
+    pub fn bonds(mut self, pairs: &[(usize, usize)]) -> MoleculeBuilder {
+        for (atom_1_idx, atom_2_idx) in pairs.iter() {
+            self = self.bond(*atom_1_idx, *atom_2_idx, BondType::SINGLE);
+        }
+        return self;
+    }
+
+    /** build() checks the bond indices and creates the molecule */
+    pub fn build(self) -> Result<Molecule, BuildError> {
+        if let Some(error) = self.error {
+            return Err(error);
+        }
+
+        let number_of_atoms = self.atom_list.len();
+
+        for bond in self.bond_list.iter() {
+            if bond.atom_1_idx >= number_of_atoms || bond.atom_2_idx >= number_of_atoms {
+                return Err(BuildError::BondIndexOutOfRange {
+                    atom_1_idx: bond.atom_1_idx,
+                    atom_2_idx: bond.atom_2_idx,
+                    number_of_atoms: number_of_atoms,
+                });
+            }
+
+            if bond.atom_1_idx == bond.atom_2_idx {
+                return Err(BuildError::SelfBond {
+                    atom_idx: bond.atom_1_idx,
+                });
+            }
+        }
+
+        let mut mol = Molecule::new(self.name, self.remark);
+
+        for atom in self.atom_list {
+            mol.add_atom(atom);
+        }
+
+        for bond in self.bond_list {
+            mol.add_bond(bond.atom_1_idx, bond.atom_2_idx, bond.bond_type);
+        }
+
+        return Ok(mol);
+    }
+}
+
+/** maps the bond notation of molecule! to a BondType */
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __molecule_bond_type {
+    (-) => {
+        $crate::bond::BondType::SINGLE
+    };
+    (=) => {
+        $crate::bond::BondType::DOUBLE
+    };
+    (#) => {
+        $crate::bond::BondType::TRIPLE
+    };
+    (:) => {
+        $crate::bond::BondType::AROMATIC
+    };
+    (->) => {
+        $crate::bond::BondType::COORDINATE
+    };
+    (~) => {
+        $crate::bond::BondType::WEAK
+    };
+}
+
+/** molecule! builds a Molecule from element symbols, coordinates and bonds, see the builder module */
+#[macro_export]
+macro_rules! molecule {
+    (
+        name: $name:expr,
+        $(remark: $remark:expr,)?
+        atoms: [ $( $symbol:ident $x:expr, $y:expr, $z:expr );+ $(;)? ]
+        $(, bonds: [ $( $atom_1_idx:literal $bond:tt $atom_2_idx:literal ),* $(,)? ])?
+        $(,)?
+    ) => {{
+        #[allow(dead_code)]
+        const NUMBER_OF_ATOMS: usize = [$(stringify!($symbol)),+].len();
+        $($(
+            const _: () = assert!(
+                $atom_1_idx < NUMBER_OF_ATOMS && $atom_2_idx < NUMBER_OF_ATOMS,
+                "molecule!: bond refers to an atom index that does not exist"
+            );
+            const _: () = assert!(
+                $atom_1_idx != $atom_2_idx,
+                "molecule!: bond joins an atom to itself"
+            );
+        )*)?
+
+        $crate::molecule::builder::MoleculeBuilder::new($name)
+            $(.remark($remark))?
+            $(.atom(stringify!($symbol), $x as f32, $y as f32, $z as f32))+
+            $($(.bond($atom_1_idx, $atom_2_idx, $crate::__molecule_bond_type!($bond)))*)?
+            .build()
+            .expect("molecule!: bond indices are checked at compile time")
+    }};
+}
+
+/** Unit tests for the above module */
+#[cfg(test)]
+mod tests {
+    use super::BuildError;
+    use super::MoleculeBuilder;
+    use crate::atom::AtomOperations;
+    use crate::bond::BondType;
+
+    #[test]
+    fn builder_water() {
+        let mut mol = MoleculeBuilder::new("H2O")
+            .remark("Water Molecule")
+            .xyz(
+                "O 0.0 0.0 0.0
+                 H 0.758602 0.0 0.504284
+
+                 H 0.758602 0.0 -0.504284",
+            )
+            .bonds(&[(0, 1), (0, 2)])
+            .build()
+            .unwrap();
+
+        assert_eq!(mol.name, "H2O".to_string());
+        assert_eq!(mol.remark, "Water Molecule".to_string());
+        assert_eq!(mol.get_number_of_atoms(), 3);
+        assert_eq!(mol.get_atom(2).symbol, "H".to_string());
+        assert_eq!(mol.get_atom(2).center.z, -0.504284);
+        assert_eq!(mol.get_bond_type(0, 2), BondType::SINGLE);
+    }
+
+    #[test]
+    fn builder_atoms_and_charges() {
+        let mut mol = MoleculeBuilder::new("NaCl")
+            .atom("Na", 0.0, 0.0, 0.0)
+            .charge(1.0)
+            .atom("Cl", 2.36, 0.0, 0.0)
+            .charge(-1.0)
+            .bond(0, 1, BondType::WEAK)
+            .build()
+            .unwrap();
+
+        assert_eq!(mol.get_atom(0).charge, 1.0);
+        assert_eq!(mol.get_atom(1).charge, -1.0);
+        assert_eq!(mol.get_bond_type(0, 1), BondType::WEAK);
+    }
+
+    #[test]
+    fn builder_errors() {
+        let err = MoleculeBuilder::new("H2")
+            .atom("H", 0.0, 0.0, 0.0)
+            .atom("H", 0.74, 0.0, 0.0)
+            .bonds(&[(0, 2)])
+            .build()
+            .unwrap_err();
+        assert_eq!(
+            err,
+            BuildError::BondIndexOutOfRange {
+                atom_1_idx: 0,
+                atom_2_idx: 2,
+                number_of_atoms: 2
+            }
+        );
+
+        let err = MoleculeBuilder::new("H2")
+            .xyz("H 0.0 0.0 0.0\nH 0.74 0.0")
+            .build()
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "line 2: expected 'symbol x y z', found 'H 0.74 0.0'".to_string()
+        );
+
+        let err = MoleculeBuilder::new("H")
+            .atom("H", 0.0, 0.0, 0.0)
+            .bonds(&[(0, 0)])
+            .build()
+            .unwrap_err();
+        assert_eq!(err, BuildError::SelfBond { atom_idx: 0 });
+    }
+
+    #[test]
+    fn builder_macro() {
+        let mut water = crate::molecule! {
+            name: "H2O",
+            remark: "Water Molecule",
+            atoms: [
+                O 0.0, 0.0, 0.0;
+                H 0.758602, 0.0, 0.504284;
+                H 0.758602, 0.0, -0.504284;
+            ],
+            bonds: [0 - 1, 0 - 2],
+        };
+
+        assert_eq!(water.remark, "Water Molecule".to_string());
+        assert_eq!(water.get_number_of_atoms(), 3);
+        assert_eq!(water.get_atom(0).symbol, "O".to_string());
+        assert_eq!(water.get_number_of_bonds(), 2);
+
+        let mut acetylene = crate::molecule! {
+            name: "C2H2",
+            atoms: [
+                H -1.66, 0, 0;
+                C -0.6, 0, 0;
+                C 0.6, 0, 0;
+                H 1.66, 0, 0
+            ],
+            bonds: [0 - 1, 1 # 2, 2 - 3]
+        };
+        assert_eq!(acetylene.get_bond_type(1, 2), BondType::TRIPLE);
+        assert_eq!(acetylene.get_atom(0).center.x, -1.66);
+
+        let mut ion = crate::molecule! { name: "Na+", atoms: [Na 0, 0, 0] };
+        assert_eq!(ion.get_number_of_bonds(), 0);
+    }
+}
@@ -0,0 +1,64 @@
+/**
+ * Author: V. Ganesh
+ * License: MIT
+ */
+
+/**
+ * cells module, sorts points into cubic cells so that the points near a point are found
+ * without measuring the distance to every other point
+ */
+use std::collections::HashMap;
+
+use crate::point::Point;
+
+/** CellGrid - the indices of points, by the cell of the given size that they are in */
+pub(crate) struct CellGrid {
+    size: f32,
+    cells: HashMap<(i64, i64, i64), Vec<usize>>,
+}
+
+impl CellGrid {
+    /** new() creates an empty grid of cells of the given size, which must be positive */
+    pub(crate) fn new(size: f32) -> CellGrid {
+        CellGrid {
+            size: size,
+            cells: HashMap::new(),
+        }
+    }
+
+    /** get_cell() returns the cell a point is in */
+    fn get_cell(&self, pt: &Point) -> (i64, i64, i64) {
+        return (
+            (pt.x / self.size).floor() as i64,
+            (pt.y / self.size).floor() as i64,
+            (pt.z / self.size).floor() as i64,
+        );
+    }
+
+    /** add() adds the index of a point to the cell it is in */
+    pub(crate) fn add(&mut self, index: usize, pt: &Point) {
+        let cell = self.get_cell(pt);
+        self.cells.entry(cell).or_default().push(index);
+    }
+
+    /**
+     * get_neighbours() returns the indices in the cell of a point and the 26 cells around
+     * it, they include every point within the cell size of it
+     */
+    pub(crate) fn get_neighbours(&self, pt: &Point) -> Vec<usize> {
+        let (cx, cy, cz) = self.get_cell(pt);
+        let mut neighbours = Vec::new();
+
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                for dz in -1..=1 {
+                    if let Some(indices) = self.cells.get(&(cx + dx, cy + dy, cz + dz)) {
+                        neighbours.extend(indices.iter().copied());
+                    }
+                }
+            }
+        }
+
+        return neighbours;
+    }
+}
@@ -0,0 +1,292 @@
+/**
+ * Author: V. Ganesh
+ * License: MIT
+ */
+
+/** graph module, treats the atom and bond lists of a Molecule as a connectivity graph */
+use std::collections::HashSet;
+
+use crate::molecule::Molecule;
+
+#[allow(dead_code)]
+impl Molecule {
+    /** get_adjacency_list() returns, for every atom, the indices of the atoms bonded to it */
+    pub fn get_adjacency_list(&self) -> Vec<Vec<usize>> {
+        let mut adjacency_list = vec![Vec::new(); self.atom_list.len()];
+
+        for bond in self.bond_list.iter() {
+            let (a, b) = (bond.atom_1_idx, bond.atom_2_idx);
+
+            if a == b || adjacency_list[a].contains(&b) {
+                continue;
+            }
+
+            adjacency_list[a].push(b);
+            adjacency_list[b].push(a);
+        }
+
+        return adjacency_list;
+    }
+
+    /** get_neighbours() returns the indices of the atoms bonded to the atom at the given index */
+    pub fn get_neighbours(&self, index: usize) -> Vec<usize> {
+        let mut neighbours = Vec::new();
+
+        for bond in self.bond_list.iter() {
+            let other = if bond.atom_1_idx == index {
+                bond.atom_2_idx
+            } else if bond.atom_2_idx == index {
+                bond.atom_1_idx
+            } else {
+                continue;
+            };
+
+            if other != index && !neighbours.contains(&other) {
+                neighbours.push(other);
+            }
+        }
+
+        return neighbours;
+    }
+
+    /**
+     * get_ring_atoms() flags the atoms that are part of at least one ring.
+     *
+     * An atom is in a ring if any of its bonds is not a bridge, bridges being found with
+     * Tarjan's lowest-link numbering over a depth first traversal.
+     */
+    pub fn get_ring_atoms(&self) -> Vec<bool> {
+        let adjacency_list = self.get_adjacency_list();
+        let number_of_atoms = adjacency_list.len();
+
+        let mut order = vec![usize::MAX; number_of_atoms];
+        let mut low_link = vec![0; number_of_atoms];
+        let mut in_ring = vec![false; number_of_atoms];
+        let mut counter = 0;
+
+        for root in 0..number_of_atoms {
+            if order[root] != usize::MAX {
+                continue;
+            }
+
+            order[root] = counter;
+            low_link[root] = counter;
+            counter += 1;
+
+            // stack of (atom, parent, next neighbour position) replaces recursion
+            let mut stack = vec![(root, usize::MAX, 0)];
+
+            while let Some(&mut (atom, parent, ref mut next)) = stack.last_mut() {
+                if *next < adjacency_list[atom].len() {
+                    let other = adjacency_list[atom][*next];
+                    *next += 1;
+
+                    if other == parent {
+                        continue;
+                    }
+
+                    if order[other] == usize::MAX {
+                        order[other] = counter;
+                        low_link[other] = counter;
+                        counter += 1;
+                        stack.push((other, atom, 0));
+                    } else {
+                        // back edge, closes a ring
+                        low_link[atom] = low_link[atom].min(order[other]);
+                        in_ring[atom] = true;
+                        in_ring[other] = true;
+                    }
+                } else {
+                    stack.pop();
+
+                    if parent != usize::MAX {
+                        low_link[parent] = low_link[parent].min(low_link[atom]);
+
+                        // tree edge parent - atom is not a bridge
+                        if low_link[atom] <= order[parent] {
+                            in_ring[atom] = true;
+                            in_ring[parent] = true;
+                        }
+                    }
+                }
+            }
+        }
+
+        return in_ring;
+    }
+
+    /**
+     * get_angles() returns the bond angles i-j-k of the bond graph, with j the central atom
+     * and i < k, ordered by the central atom
+     */
+    pub fn get_angles(&self) -> Vec<[usize; 3]> {
+        let adjacency_list = self.get_adjacency_list();
+        let mut angles = Vec::new();
+
+        for (j, neighbours) in adjacency_list.iter().enumerate() {
+            let mut neighbours = neighbours.to_vec();
+            neighbours.sort();
+
+            for (idx, i) in neighbours.iter().enumerate() {
+                for k in neighbours[idx + 1..].iter() {
+                    angles.push([*i, j, *k]);
+                }
+            }
+        }
+
+        return angles;
+    }
+
+    /**
+     * get_dihedrals() returns the proper dihedrals i-j-k-l of the bond graph, one for each
+     * path of three bonds, ordered by the central bond j-k (with j < k) as in the bond list.
+     * Paths around a three membered ring (i == l) are left out
+     */
+    pub fn get_dihedrals(&self) -> Vec<[usize; 4]> {
+        let adjacency_list = self.get_adjacency_list();
+        let mut dihedrals = Vec::new();
+        let mut seen = HashSet::new();
+
+        for bond in self.bond_list.iter() {
+            let (j, k) = (
+                bond.atom_1_idx.min(bond.atom_2_idx),
+                bond.atom_1_idx.max(bond.atom_2_idx),
+            );
+            if j == k || !seen.insert((j, k)) {
+                continue;
+            }
+
+            for i in adjacency_list[j].iter().filter(|i| **i != k) {
+                for l in adjacency_list[k].iter().filter(|l| **l != j && **l != *i) {
+                    dihedrals.push([*i, j, k, *l]);
+                }
+            }
+        }
+
+        return dihedrals;
+    }
+
+    /**
+     * get_fragment_indices() returns, for every atom, the 0 based index of the fragment
+     * (connected part of the bond graph) it belongs to, fragments numbered by their first atom
+     */
+    pub fn get_fragment_indices(&self) -> Vec<usize> {
+        let adjacency_list = self.get_adjacency_list();
+        let mut fragments = vec![usize::MAX; adjacency_list.len()];
+        let mut count = 0;
+
+        for root in 0..adjacency_list.len() {
+            if fragments[root] != usize::MAX {
+                continue;
+            }
+
+            fragments[root] = count;
+            let mut stack = vec![root];
+            while let Some(atom) = stack.pop() {
+                for other in adjacency_list[atom].iter() {
+                    if fragments[*other] == usize::MAX {
+                        fragments[*other] = count;
+                        stack.push(*other);
+                    }
+                }
+            }
+            count += 1;
+        }
+
+        return fragments;
+    }
+}
+
+/** Unit tests for the above module */
+#[cfg(test)]
+mod tests {
+    use crate::atom::Atom;
+    use crate::atom::AtomOperations;
+    use crate::bond::BondType;
+    use crate::molecule::Molecule;
+
+    fn make_chain(symbols: &[&str]) -> Molecule {
+        let mut mol = Molecule::new("chain".to_string(), "".to_string());
+
+        for symbol in symbols {
+            mol.add_atom(Atom {
+                symbol: symbol.to_string(),
+                ..Default::default()
+            });
+        }
+
+        return mol;
+    }
+
+    #[test]
+    fn graph_neighbours() {
+        let mut mol = make_chain(&["O", "H", "H"]);
+        mol.add_bond(0, 1, BondType::SINGLE);
+        mol.add_bond(0, 2, BondType::SINGLE);
+
+        assert_eq!(mol.get_neighbours(0), vec![1, 2]);
+        assert_eq!(mol.get_neighbours(2), vec![0]);
+        assert_eq!(mol.get_adjacency_list(), vec![vec![1, 2], vec![0], vec![0]]);
+        assert_eq!(mol.get_ring_atoms(), vec![false, false, false]);
+    }
+
+    #[test]
+    fn graph_ring_atoms() {
+        // a three membered ring linked through atom 3 to a fused bicycle, atom 8 dangles
+        let mut mol = make_chain(&["C", "C", "C", "C", "C", "C", "C", "C", "C"]);
+        mol.add_bond(0, 1, BondType::SINGLE);
+        mol.add_bond(1, 2, BondType::SINGLE);
+        mol.add_bond(2, 0, BondType::SINGLE);
+        mol.add_bond(2, 3, BondType::SINGLE);
+        mol.add_bond(3, 4, BondType::SINGLE);
+        mol.add_bond(4, 5, BondType::SINGLE);
+        mol.add_bond(5, 6, BondType::SINGLE);
+        mol.add_bond(6, 4, BondType::SINGLE);
+        mol.add_bond(6, 7, BondType::SINGLE);
+        mol.add_bond(7, 5, BondType::SINGLE);
+        mol.add_bond(7, 8, BondType::SINGLE);
+
+        assert_eq!(
+            mol.get_ring_atoms(),
+            vec![true, true, true, false, true, true, true, true, false]
+        );
+    }
+
+    #[test]
+    fn graph_angles_dihedrals() {
+        // ethanol heavy atoms with one hydrogen on each carbon, plus a separate water
+        let mut mol = make_chain(&["C", "C", "O", "H", "H", "H", "O", "H", "H"]);
+        mol.add_bond(0, 1, BondType::SINGLE);
+        mol.add_bond(1, 2, BondType::SINGLE);
+        mol.add_bond(0, 3, BondType::SINGLE);
+        mol.add_bond(1, 4, BondType::SINGLE);
+        mol.add_bond(2, 5, BondType::SINGLE);
+        mol.add_bond(6, 7, BondType::SINGLE);
+        mol.add_bond(6, 8, BondType::SINGLE);
+
+        assert_eq!(
+            mol.get_angles(),
+            vec![
+                [1, 0, 3],
+                [0, 1, 2],
+                [0, 1, 4],
+                [2, 1, 4],
+                [1, 2, 5],
+                [7, 6, 8]
+            ]
+        );
+        assert_eq!(
+            mol.get_dihedrals(),
+            vec![[3, 0, 1, 2], [3, 0, 1, 4], [0, 1, 2, 5], [4, 1, 2, 5]]
+        );
+        assert_eq!(mol.get_fragment_indices(), vec![0, 0, 0, 0, 0, 0, 1, 1, 1]);
+
+        // no dihedrals around a three membered ring
+        let mut ring = make_chain(&["C", "C", "C"]);
+        ring.add_bond(0, 1, BondType::SINGLE);
+        ring.add_bond(1, 2, BondType::SINGLE);
+        ring.add_bond(2, 0, BondType::SINGLE);
+        assert_eq!(ring.get_angles().len(), 3);
+        assert_eq!(ring.get_dihedrals(), Vec::<[usize; 4]>::new());
+    }
+}
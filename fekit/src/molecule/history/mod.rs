@@ -0,0 +1,566 @@
+/**
+ * Author: V. Ganesh
+ * License: MIT
+ */
+
+/** history module, records edits on a Molecule so that they can be undone and redone */
+use std::collections::BTreeMap;
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fmt;
+
+use crate::atom::Atom;
+use crate::atom::AtomGroup;
+use crate::bond::BondIndex;
+use crate::bond::BondType;
+use crate::molecule::Molecule;
+use crate::point::Point;
+use crate::property::AtomProperty;
+
+/** default number of transactions kept in the undo history */
+pub const DEFAULT_HISTORY_LIMIT: usize = 100;
+
+/** HistoryError - the reason a transaction could not be begun, committed or rolled back */
+#[allow(dead_code)]
+#[derive(Clone, PartialEq, Debug)]
+pub enum HistoryError {
+    /** begin_transaction() was called while a transaction is open */
+    TransactionOpen { name: String },
+    /** commit_transaction() or rollback_transaction() was called without an open transaction */
+    NoTransaction,
+}
+
+impl fmt::Display for HistoryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HistoryError::TransactionOpen { name } => {
+                write!(f, "the transaction \"{}\" is already open", name)
+            }
+            HistoryError::NoTransaction => write!(f, "no transaction is open"),
+        }
+    }
+}
+
+impl Error for HistoryError {}
+
+/** EditOperation - a single, reversible edit made on a Molecule */
+#[allow(dead_code)]
+#[derive(Clone, PartialEq, Debug)]
+pub enum EditOperation {
+    AddAtom {
+        index: usize,
+        atom: Atom,
+    },
+    /** bond_list holds the removed bonds by their position, group_positions the group references */
+    RemoveAtom {
+        index: usize,
+        atom: Atom,
+        atom_property_row: BTreeMap<String, AtomProperty>,
+        bond_list: Vec<(usize, BondIndex)>,
+        group_positions: Vec<(Vec<usize>, usize)>,
+    },
+    MoveAtom {
+        index: usize,
+        old_center: Point,
+        new_center: Point,
+    },
+    AddBond {
+        index: usize,
+        bond: BondIndex,
+    },
+    RemoveBond {
+        index: usize,
+        bond: BondIndex,
+    },
+    SetBondType {
+        index: usize,
+        old_type: BondType,
+        new_type: BondType,
+    },
+    AddGroup {
+        index: usize,
+        group: AtomGroup,
+    },
+    RemoveGroup {
+        index: usize,
+        group: AtomGroup,
+    },
+}
+
+#[allow(dead_code)]
+impl EditOperation {
+    /** apply() (re)does this edit on the given atom and bond lists */
+    pub(crate) fn apply(&self, mol: &mut Molecule) {
+        match self {
+            EditOperation::AddAtom { index, atom } => {
+                mol.insert_atom_at(*index, atom.clone(), &BTreeMap::new())
+            }
+            EditOperation::RemoveAtom { index, .. } => {
+                mol.remove_atom_at(*index);
+            }
+            EditOperation::MoveAtom {
+                index,
+                old_center: _,
+                new_center,
+            } => mol.atom_list[*index].center = *new_center,
+            EditOperation::AddBond { index, bond } => mol.bond_list.insert(*index, bond.clone()),
+            EditOperation::RemoveBond { index, bond: _ } => {
+                mol.bond_list.remove(*index);
+            }
+            EditOperation::SetBondType {
+                index,
+                old_type: _,
+                new_type,
+            } => mol.bond_list[*index].bond_type = *new_type,
+            EditOperation::AddGroup { index, group } => {
+                mol.group_list.insert(*index, group.clone())
+            }
+            EditOperation::RemoveGroup { index, group: _ } => {
+                mol.group_list.remove(*index);
+            }
+        }
+    }
+
+    /** revert() undoes this edit on the given atom and bond lists */
+    pub(crate) fn revert(&self, mol: &mut Molecule) {
+        match self {
+            EditOperation::AddAtom { index, atom: _ } => {
+                mol.remove_atom_at(*index);
+            }
+            EditOperation::RemoveAtom {
+                index,
+                atom,
+                atom_property_row,
+                bond_list,
+                group_positions,
+            } => {
+                mol.insert_atom_at(*index, atom.clone(), atom_property_row);
+                mol.restore_atom_bonds_groups(*index, bond_list, group_positions);
+            }
+            EditOperation::MoveAtom {
+                index,
+                old_center,
+                new_center: _,
+            } => mol.atom_list[*index].center = *old_center,
+            EditOperation::AddBond { index, bond: _ } => {
+                mol.bond_list.remove(*index);
+            }
+            EditOperation::RemoveBond { index, bond } => mol.bond_list.insert(*index, bond.clone()),
+            EditOperation::SetBondType {
+                index,
+                old_type,
+                new_type: _,
+            } => mol.bond_list[*index].bond_type = *old_type,
+            EditOperation::AddGroup { index, group: _ } => {
+                mol.group_list.remove(*index);
+            }
+            EditOperation::RemoveGroup { index, group } => {
+                mol.group_list.insert(*index, group.clone())
+            }
+        }
+    }
+}
+
+/** Transaction - a named group of edits that are undone and redone together */
+#[allow(dead_code)]
+#[derive(Clone, PartialEq, Debug)]
+pub struct Transaction {
+    pub name: String,
+
+    operation_list: Vec<EditOperation>,
+}
+
+#[allow(dead_code)]
+impl Transaction {
+    pub fn new(name: String) -> Transaction {
+        Transaction {
+            name: name,
+            operation_list: Vec::new(),
+        }
+    }
+
+    /** get_operations() returns the edits recorded in this transaction, oldest first */
+    pub fn get_operations(&self) -> &[EditOperation] {
+        return &self.operation_list;
+    }
+
+    /** apply() (re)does all the edits in this transaction */
+    pub(crate) fn apply(&self, mol: &mut Molecule) {
+        for operation in self.operation_list.iter() {
+            operation.apply(mol);
+        }
+    }
+
+    /** revert() undoes all the edits in this transaction, newest first */
+    pub(crate) fn revert(&self, mol: &mut Molecule) {
+        for operation in self.operation_list.iter().rev() {
+            operation.revert(mol);
+        }
+    }
+}
+
+/**
+ * EditHistory - the open transaction, if any, and the bounded undo / redo stacks.
+ *
+ * Edits are only recorded while a transaction is open. An edit made outside a
+ * transaction clears both stacks, as the recorded atom and bond indices would
+ * no longer be valid.
+ */
+#[allow(dead_code)]
+#[derive(Clone, PartialEq, Debug)]
+pub struct EditHistory {
+    limit: usize,
+    current: Option<Transaction>,
+    undo_list: VecDeque<Transaction>,
+    redo_list: VecDeque<Transaction>,
+}
+
+impl Default for EditHistory {
+    fn default() -> Self {
+        EditHistory::new()
+    }
+}
+
+#[allow(dead_code)]
+impl EditHistory {
+    pub fn new() -> EditHistory {
+        EditHistory {
+            limit: DEFAULT_HISTORY_LIMIT,
+            current: None,
+            undo_list: VecDeque::new(),
+            redo_list: VecDeque::new(),
+        }
+    }
+
+    /** record() adds an edit to the open transaction, the edit is only built if one is open */
+    pub(crate) fn record<F: FnOnce() -> EditOperation>(&mut self, operation: F) {
+        match self.current.as_mut() {
+            Some(transaction) => transaction.operation_list.push(operation()),
+            None => self.clear(),
+        }
+    }
+
+    /** begin() opens a new transaction, it is an error if one is open */
+    pub(crate) fn begin(&mut self, name: String) -> Result<(), HistoryError> {
+        if let Some(transaction) = self.current.as_ref() {
+            return Err(HistoryError::TransactionOpen {
+                name: transaction.name.clone(),
+            });
+        }
+
+        self.current = Some(Transaction::new(name));
+        return Ok(());
+    }
+
+    /** commit() closes the open transaction and pushes it on the undo stack */
+    pub(crate) fn commit(&mut self) -> Result<(), HistoryError> {
+        let transaction = self.current.take().ok_or(HistoryError::NoTransaction)?;
+
+        if transaction.operation_list.is_empty() {
+            return Ok(());
+        }
+
+        self.redo_list.clear();
+        self.undo_list.push_back(transaction);
+        self.trim();
+        return Ok(());
+    }
+
+    /** rollback() closes the open transaction and returns it, so that it can be reverted */
+    pub(crate) fn rollback(&mut self) -> Result<Transaction, HistoryError> {
+        return self.current.take().ok_or(HistoryError::NoTransaction);
+    }
+
+    /** pop_undo() returns the transaction to undo, None while a transaction is open */
+    pub(crate) fn pop_undo(&mut self) -> Option<Transaction> {
+        if self.in_transaction() {
+            return None;
+        }
+        return self.undo_list.pop_back();
+    }
+
+    pub(crate) fn push_redo(&mut self, transaction: Transaction) {
+        self.redo_list.push_back(transaction);
+    }
+
+    /** pop_redo() returns the transaction to redo, None while a transaction is open */
+    pub(crate) fn pop_redo(&mut self) -> Option<Transaction> {
+        if self.in_transaction() {
+            return None;
+        }
+        return self.redo_list.pop_back();
+    }
+
+    pub(crate) fn push_undo(&mut self, transaction: Transaction) {
+        self.undo_list.push_back(transaction);
+        self.trim();
+    }
+
+    /** in_transaction() returns true if a transaction is open */
+    pub fn in_transaction(&self) -> bool {
+        return self.current.is_some();
+    }
+
+    /** get_limit() returns the maximum number of transactions kept for undo */
+    pub fn get_limit(&self) -> usize {
+        return self.limit;
+    }
+
+    /** set_limit() sets the maximum number of transactions kept for undo, dropping the oldest ones */
+    pub fn set_limit(&mut self, limit: usize) {
+        self.limit = limit;
+        self.trim();
+    }
+
+    /** get_undo_name() returns the name of the transaction that undo() would revert */
+    pub fn get_undo_name(&self) -> Option<&str> {
+        return self.undo_list.back().map(|t| t.name.as_str());
+    }
+
+    /** get_redo_name() returns the name of the transaction that redo() would reapply */
+    pub fn get_redo_name(&self) -> Option<&str> {
+        return self.redo_list.back().map(|t| t.name.as_str());
+    }
+
+    /** get_undo_count() returns the number of transactions that can be undone */
+    pub fn get_undo_count(&self) -> usize {
+        return self.undo_list.len();
+    }
+
+    /** get_redo_count() returns the number of transactions that can be redone */
+    pub fn get_redo_count(&self) -> usize {
+        return self.redo_list.len();
+    }
+
+    /** clear() forgets all undo and redo history */
+    pub fn clear(&mut self) {
+        self.undo_list.clear();
+        self.redo_list.clear();
+    }
+
+    fn trim(&mut self) {
+        while self.undo_list.len() > self.limit {
+            self.undo_list.pop_front();
+        }
+    }
+}
+
+/** Unit tests for the above module */
+#[cfg(test)]
+mod tests {
+    use crate::atom::AtomGroup;
+    use crate::atom::AtomOperations;
+    use crate::bond::BondType;
+    use crate::molecule::history::HistoryError;
+    use crate::molecule::Molecule;
+    use crate::point::Point;
+    use crate::testutil;
+
+    fn add_water(mol: &mut Molecule) {
+        mol.add_atom(testutil::get_atom("O", 0.0, 0.0, 0.0));
+        mol.add_atom(testutil::get_atom("H", 0.758602, 0.0, 0.504284));
+        mol.add_atom(testutil::get_atom("H", 0.758602, 0.0, -0.504284));
+        mol.add_bond(0, 1, BondType::SINGLE);
+        mol.add_bond(0, 2, BondType::SINGLE);
+    }
+
+    #[test]
+    fn history_undo_redo() {
+        let mut mol = Molecule::new("H2O".to_string(), "Water Molecule".to_string());
+
+        mol.begin_transaction("Add water".to_string()).unwrap();
+        add_water(&mut mol);
+        mol.commit_transaction().unwrap();
+
+        assert_eq!(mol.get_number_of_atoms(), 3);
+        assert_eq!(mol.get_number_of_bonds(), 2);
+        assert_eq!(mol.get_history().get_undo_name(), Some("Add water"));
+
+        assert!(mol.undo());
+        assert_eq!(mol.get_number_of_atoms(), 0);
+        assert_eq!(mol.get_number_of_bonds(), 0);
+        assert!(!mol.undo());
+        assert_eq!(mol.get_history().get_redo_name(), Some("Add water"));
+
+        assert!(mol.redo());
+        assert_eq!(mol.get_number_of_atoms(), 3);
+        assert_eq!(mol.get_number_of_bonds(), 2);
+        assert_eq!(mol.get_atom(1).center.x, 0.758602);
+        assert!(!mol.redo());
+    }
+
+    #[test]
+    fn history_edit_operations() {
+        let mut mol = Molecule::new("H2O".to_string(), "Water Molecule".to_string());
+
+        mol.begin_transaction("Add water".to_string()).unwrap();
+        add_water(&mut mol);
+        mol.commit_transaction().unwrap();
+
+        mol.begin_transaction("Edit water".to_string()).unwrap();
+        mol.set_bond_type(0, 1, BondType::DOUBLE);
+        mol.set_atom_center(
+            2,
+            Point {
+                x: 1.0,
+                y: 1.0,
+                z: 1.0,
+            },
+        );
+        mol.remove_bond(0, 2);
+        mol.remove_atom(2);
+        mol.commit_transaction().unwrap();
+
+        assert_eq!(mol.get_number_of_atoms(), 2);
+        assert_eq!(mol.get_number_of_bonds(), 1);
+        assert_eq!(mol.get_bond_type(0, 1), BondType::DOUBLE);
+
+        assert!(mol.undo());
+        assert_eq!(mol.get_number_of_atoms(), 3);
+        assert_eq!(mol.get_number_of_bonds(), 2);
+        assert_eq!(mol.get_bond_type(0, 1), BondType::SINGLE);
+        assert_eq!(mol.get_bond_type(0, 2), BondType::SINGLE);
+        assert_eq!(mol.get_atom(2).center.x, 0.758602);
+        assert_eq!(mol.get_atom(2).center.z, -0.504284);
+
+        assert!(mol.redo());
+        assert_eq!(mol.get_number_of_atoms(), 2);
+        assert_eq!(mol.get_bond_type(0, 1), BondType::DOUBLE);
+    }
+
+    #[test]
+    fn history_rollback() {
+        let mut mol = Molecule::new("H2O".to_string(), "Water Molecule".to_string());
+
+        mol.begin_transaction("Add water".to_string()).unwrap();
+        add_water(&mut mol);
+        mol.commit_transaction().unwrap();
+
+        mol.begin_transaction("Add hydroxide".to_string()).unwrap();
+        mol.add_atom(testutil::get_atom("O", 3.0, 0.0, 0.0));
+        mol.add_atom(testutil::get_atom("H", 4.0, 0.0, 0.0));
+        mol.add_bond(3, 4, BondType::SINGLE);
+        assert!(mol.get_history().in_transaction());
+
+        // nested transactions, and undo and redo while one is open, are refused
+        assert_eq!(
+            mol.begin_transaction("Nested".to_string()),
+            Err(HistoryError::TransactionOpen {
+                name: "Add hydroxide".to_string()
+            })
+        );
+        assert!(!mol.undo());
+        assert!(!mol.redo());
+        assert_eq!(mol.get_number_of_atoms(), 5);
+        mol.rollback_transaction().unwrap();
+
+        assert!(!mol.get_history().in_transaction());
+        assert_eq!(mol.get_number_of_atoms(), 3);
+        assert_eq!(mol.get_number_of_bonds(), 2);
+        assert_eq!(mol.get_history().get_undo_count(), 1);
+        assert_eq!(mol.get_history().get_undo_name(), Some("Add water"));
+        assert_eq!(mol.commit_transaction(), Err(HistoryError::NoTransaction));
+        assert_eq!(mol.rollback_transaction(), Err(HistoryError::NoTransaction));
+    }
+
+    #[test]
+    fn history_limit_and_invalidation() {
+        let mut mol = Molecule::new("H".to_string(), "Hydrogen atoms".to_string());
+        mol.set_history_limit(2);
+
+        for i in 0..4 {
+            mol.begin_transaction(format!("Add H{}", i)).unwrap();
+            mol.add_atom(testutil::get_atom("H", i as f32, 0.0, 0.0));
+            mol.commit_transaction().unwrap();
+        }
+
+        assert_eq!(mol.get_history().get_undo_count(), 2);
+        assert!(mol.undo());
+        assert!(mol.undo());
+        assert!(!mol.undo());
+        assert_eq!(mol.get_number_of_atoms(), 2);
+
+        // an edit outside a transaction invalidates the recorded history
+        mol.redo();
+        mol.add_atom(testutil::get_atom("H", 9.0, 0.0, 0.0));
+        assert_eq!(mol.get_history().get_undo_count(), 0);
+        assert_eq!(mol.get_history().get_redo_count(), 0);
+
+        // an empty transaction is not recorded
+        mol.begin_transaction("Nothing".to_string()).unwrap();
+        mol.commit_transaction().unwrap();
+        assert_eq!(mol.get_history().get_undo_count(), 0);
+    }
+
+    #[test]
+    fn history_remove_bonded_atom() {
+        let mut mol = Molecule::new("H2O".to_string(), "Water Molecule".to_string());
+        add_water(&mut mol);
+        let mut residue = AtomGroup::new("HOH".to_string(), "Water".to_string());
+        residue.add_atom_index(1);
+        residue.add_atom_index(2);
+        let mut model = AtomGroup::new("MODEL".to_string(), "".to_string());
+        model.add_atom_index(0);
+        model.add_group(residue);
+        mol.add_group(model);
+
+        mol.begin_transaction("Remove H".to_string()).unwrap();
+        mol.remove_atom(1);
+        mol.commit_transaction().unwrap();
+
+        assert_eq!(mol.get_number_of_bonds(), 1);
+        assert_eq!(mol.get_bond_type(0, 1), BondType::SINGLE);
+        let model = mol.find_group(&["MODEL"]).unwrap();
+        assert_eq!(model.get_atom_indices(), &[0]);
+        assert_eq!(model.get_groups()[0].get_atom_indices(), &[1]);
+
+        assert!(mol.undo());
+        assert_eq!(mol.get_number_of_atoms(), 3);
+        let bonds: Vec<(usize, usize)> = mol
+            .get_bonds()
+            .iter()
+            .map(|bond| (bond.atom_1_idx, bond.atom_2_idx))
+            .collect();
+        assert_eq!(bonds, vec![(0, 1), (0, 2)]);
+        let model = mol.find_group(&["MODEL"]).unwrap();
+        assert_eq!(model.get_atom_indices(), &[0]);
+        assert_eq!(model.get_groups()[0].get_atom_indices(), &[1, 2]);
+
+        assert!(mol.redo());
+        assert_eq!(mol.get_number_of_bonds(), 1);
+
+        // outside a transaction too, the bonds of the atom go with it
+        mol.remove_atom(0);
+        assert_eq!(mol.get_number_of_bonds(), 0);
+    }
+
+    #[test]
+    fn history_groups() {
+        let mut mol = Molecule::new("H2O".to_string(), "Water Molecule".to_string());
+        add_water(&mut mol);
+
+        mol.begin_transaction("Group water".to_string()).unwrap();
+        let mut group = AtomGroup::new("HOH".to_string(), "Water".to_string());
+        group.add_atom_index(0);
+        group.add_atom_index(1);
+        group.add_atom_index(2);
+        mol.add_group(group);
+        mol.remove_atom(1);
+        mol.commit_transaction().unwrap();
+
+        assert_eq!(
+            mol.find_group(&["HOH"]).unwrap().get_atom_indices(),
+            &[0, 1]
+        );
+
+        assert!(mol.undo());
+        assert!(mol.get_groups().is_empty());
+        assert_eq!(mol.get_number_of_atoms(), 3);
+
+        assert!(mol.redo());
+        assert_eq!(
+            mol.find_group(&["HOH"]).unwrap().get_atom_indices(),
+            &[0, 1]
+        );
+    }
+}
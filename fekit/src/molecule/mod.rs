@@ -4,19 +4,51 @@
  */
 
 /** molecule module consists of basic structs: Atom, AtomGroup, Molecule */
+use std::collections::BTreeMap;
+use std::collections::HashSet;
+
 use crate::atom::Atom;
+use crate::atom::AtomGroup;
 use crate::atom::AtomOperations;
+use crate::atomprop;
 use crate::bond::Bond;
 use crate::bond::BondIndex;
 use crate::bond::BondType;
+use crate::lattice::Lattice;
+use crate::point::Point;
+use crate::property::AtomProperty;
+use crate::property::Property;
+
+pub mod builder;
+pub(crate) mod cells;
+pub mod graph;
+pub mod history;
+pub mod zmatrix;
+
+use cells::CellGrid;
+use history::EditHistory;
+use history::EditOperation;
+use history::HistoryError;
+
+/** tolerance (in angstrom) added to the sum of covalent radii when perceiving bonds */
+pub const DEFAULT_BOND_TOLERANCE: f32 = 0.45;
 
 #[allow(dead_code)]
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Molecule {
     pub name: String,
     pub remark: String,
 
     atom_list: Vec<Atom>,
     bond_list: Vec<BondIndex>,
+    group_list: Vec<AtomGroup>,
+    lattice: Option<Lattice>,
+    property_list: BTreeMap<String, Property>,
+    atom_property_list: BTreeMap<String, AtomProperty>,
+
+    #[cfg_attr(feature = "serde", serde(skip))]
+    history: EditHistory,
 }
 
 #[allow(dead_code)]
@@ -28,16 +60,27 @@ impl Molecule {
             remark: remark,
             atom_list: Vec::new(),
             bond_list: Vec::new(),
+            group_list: Vec::new(),
+            lattice: None,
+            property_list: BTreeMap::new(),
+            atom_property_list: BTreeMap::new(),
+            history: EditHistory::new(),
         }
     }
 
     /** add_bond() adds an bond to the molecule */
     pub fn add_bond(&mut self, atom_1_idx: usize, atom_2_idx: usize, bond_type: BondType) {
-        self.bond_list.push(BondIndex {
+        let bond = BondIndex {
             atom_1_idx: atom_1_idx,
             atom_2_idx: atom_2_idx,
             bond_type: bond_type,
+        };
+
+        self.history.record(|| EditOperation::AddBond {
+            index: self.bond_list.len(),
+            bond: bond.clone(),
         });
+        self.bond_list.push(bond);
     }
 
     /** get_bond() returns the bond between two atom indices */
@@ -75,6 +118,71 @@ impl Molecule {
         }
     }
 
+    /**
+     * compute_covalent_bonds() adds single bonds between atoms closer than the sum of their
+     * covalent radii plus the tolerance (in angstrom). Atoms of unknown elements and pairs
+     * that are already bonded are skipped. Atoms are binned in a grid of cells, so only
+     * neighbouring cells are searched.
+     */
+    pub fn compute_covalent_bonds(&mut self, tolerance: f32) {
+        const MIN_BOND_DIST: f32 = 0.4;
+
+        let radii: Vec<Option<f32>> = self
+            .atom_list
+            .iter()
+            .map(|at| atomprop::get_covalent_radius(&at.symbol))
+            .collect();
+        let max_radius = radii.iter().flatten().fold(0.0_f32, |a, b| a.max(*b));
+        let cell_size = 2.0 * max_radius + tolerance;
+
+        if cell_size <= 0.0 {
+            return;
+        }
+
+        let mut cells = CellGrid::new(cell_size);
+        for (idx, atom) in self.atom_list.iter().enumerate() {
+            if radii[idx].is_some() {
+                cells.add(idx, &atom.center);
+            }
+        }
+
+        let mut bonded: HashSet<(usize, usize)> = self
+            .bond_list
+            .iter()
+            .map(|bnd| {
+                (
+                    bnd.atom_1_idx.min(bnd.atom_2_idx),
+                    bnd.atom_1_idx.max(bnd.atom_2_idx),
+                )
+            })
+            .collect();
+
+        let mut new_bonds = Vec::new();
+        for i in 0..self.atom_list.len() {
+            let radius_i = match radii[i] {
+                Some(radius) => radius,
+                None => continue,
+            };
+            for j in cells.get_neighbours(&self.atom_list[i].center) {
+                if j <= i {
+                    continue;
+                }
+                let mut center_i = self.atom_list[i].center;
+                let dist = center_i.distance_from(&self.atom_list[j].center);
+                let max_dist = radius_i + radii[j].unwrap() + tolerance;
+
+                if dist > MIN_BOND_DIST && dist < max_dist && bonded.insert((i, j)) {
+                    new_bonds.push((i, j));
+                }
+            }
+        }
+
+        new_bonds.sort();
+        for (i, j) in new_bonds {
+            self.add_bond(i, j, BondType::SINGLE);
+        }
+    }
+
     /** get_number_of_bonds() returns the number of bonds in the molecule */
     pub fn get_number_of_bonds(&mut self) -> usize {
         return self.bond_list.len();
@@ -98,13 +206,24 @@ impl Molecule {
     /** set_bond_type() sets the bond type of the bond between the two atoms */
     pub fn set_bond_type(&mut self, atom_1_idx: usize, atom_2_idx: usize, bond_type: BondType) {
         let bond_idx = self.get_bond_index(atom_1_idx, atom_2_idx);
+
+        self.history.record(|| EditOperation::SetBondType {
+            index: bond_idx,
+            old_type: self.bond_list[bond_idx].bond_type,
+            new_type: bond_type,
+        });
         self.bond_list[bond_idx].bond_type = bond_type;
     }
 
     /** remove_bond() removes the bond between the two atoms */
     pub fn remove_bond(&mut self, atom_1_idx: usize, atom_2_idx: usize) {
         let bond_idx = self.get_bond_index(atom_1_idx, atom_2_idx);
-        self.bond_list.remove(bond_idx);
+        let bond = self.bond_list.remove(bond_idx);
+
+        self.history.record(|| EditOperation::RemoveBond {
+            index: bond_idx,
+            bond: bond,
+        });
     }
 
     /** compute bond order of the bond between the two atoms */
@@ -133,12 +252,318 @@ impl Molecule {
             }
         }
     }
+
+    /** set_atom_center() moves the atom at the given index to a new center */
+    pub fn set_atom_center(&mut self, index: usize, center: Point) {
+        self.history.record(|| EditOperation::MoveAtom {
+            index: index,
+            old_center: self.atom_list[index].center,
+            new_center: center,
+        });
+        self.atom_list[index].center = center;
+    }
+
+    /**
+     * begin_transaction() starts recording edits as a single undoable step, transactions
+     * do not nest so it is an error if one is open
+     */
+    pub fn begin_transaction(&mut self, name: String) -> Result<(), HistoryError> {
+        return self.history.begin(name);
+    }
+
+    /** commit_transaction() ends the open transaction and makes it available to undo() */
+    pub fn commit_transaction(&mut self) -> Result<(), HistoryError> {
+        return self.history.commit();
+    }
+
+    /** rollback_transaction() reverts all edits made since begin_transaction() and discards them */
+    pub fn rollback_transaction(&mut self) -> Result<(), HistoryError> {
+        let transaction = self.history.rollback()?;
+        transaction.revert(self);
+        return Ok(());
+    }
+
+    /**
+     * undo() reverts the last committed transaction, returns false if there is nothing to
+     * undo or a transaction is open
+     */
+    pub fn undo(&mut self) -> bool {
+        match self.history.pop_undo() {
+            Some(transaction) => {
+                transaction.revert(self);
+                self.history.push_redo(transaction);
+                return true;
+            }
+            None => return false,
+        }
+    }
+
+    /**
+     * redo() reapplies the last undone transaction, returns false if there is nothing to
+     * redo or a transaction is open
+     */
+    pub fn redo(&mut self) -> bool {
+        match self.history.pop_redo() {
+            Some(transaction) => {
+                transaction.apply(self);
+                self.history.push_undo(transaction);
+                return true;
+            }
+            None => return false,
+        }
+    }
+
+    /** get_atoms() returns all the atoms of this molecule */
+    pub fn get_atoms(&self) -> &[Atom] {
+        return &self.atom_list;
+    }
+
+    /** get_bonds() returns all the bonds of this molecule */
+    pub fn get_bonds(&self) -> &[BondIndex] {
+        return &self.bond_list;
+    }
+
+    /** add_group() adds a top level group (model, chain, fragment, layer ...) to the molecule */
+    pub fn add_group(&mut self, group: AtomGroup) {
+        if let Some(max_idx) = group.max_atom_index() {
+            if max_idx >= self.atom_list.len() {
+                panic!(
+                    "group {} refers to atom {}, but the molecule has only {} atoms",
+                    group.name,
+                    max_idx,
+                    self.atom_list.len()
+                );
+            }
+        }
+
+        self.history.record(|| EditOperation::AddGroup {
+            index: self.group_list.len(),
+            group: group.clone(),
+        });
+        self.group_list.push(group);
+    }
+
+    /** get_groups() returns the top level groups of this molecule */
+    pub fn get_groups(&self) -> &[AtomGroup] {
+        return &self.group_list;
+    }
+
+    /** find_group() returns the group at the given path of names, e.g. ["A", "ALA12"] */
+    pub fn find_group(&self, path: &[&str]) -> Option<&AtomGroup> {
+        let (first, rest) = path.split_first()?;
+        let mut group = self.group_list.iter().find(|g| g.name == *first)?;
+
+        for name in rest {
+            group = group.get_group(name)?;
+        }
+
+        return Some(group);
+    }
+
+    /** remove_group() removes the top level group with the given name, the atoms are not touched */
+    pub fn remove_group(&mut self, name: &str) -> Option<AtomGroup> {
+        let group_idx = self.group_list.iter().position(|g| g.name == name)?;
+        let group = self.group_list.remove(group_idx);
+
+        self.history.record(|| EditOperation::RemoveGroup {
+            index: group_idx,
+            group: group.clone(),
+        });
+        return Some(group);
+    }
+
+    /** get_groups_of_atom() returns every group containing the atom, each hierarchy outermost first */
+    pub fn get_groups_of_atom(&self, index: usize) -> Vec<&AtomGroup> {
+        let mut groups = Vec::new();
+
+        for group in self.group_list.iter() {
+            group.collect_groups_of_atom(index, &mut groups);
+        }
+
+        return groups;
+    }
+
+    /** get_group_atoms() iterates over the atoms of a group and its sub-groups */
+    pub fn get_group_atoms<'a>(
+        &'a self,
+        group: &'a AtomGroup,
+    ) -> impl Iterator<Item = &'a Atom> + 'a {
+        return group
+            .get_all_atom_indices()
+            .into_iter()
+            .map(move |idx| &self.atom_list[idx]);
+    }
+
+    /** get_lattice() returns the unit cell, for periodic systems */
+    pub fn get_lattice(&self) -> Option<&Lattice> {
+        return self.lattice.as_ref();
+    }
+
+    /** set_lattice() sets, or with None clears, the unit cell */
+    pub fn set_lattice(&mut self, lattice: Option<Lattice>) {
+        self.lattice = lattice;
+    }
+
+    /** set_property() sets a named property of the molecule, such as an energy */
+    pub fn set_property(&mut self, name: &str, value: Property) {
+        self.property_list.insert(name.to_string(), value);
+    }
+
+    /** get_property() returns a named property of the molecule */
+    pub fn get_property(&self, name: &str) -> Option<&Property> {
+        return self.property_list.get(name);
+    }
+
+    /** get_properties() returns all the properties of the molecule, by name */
+    pub fn get_properties(&self) -> &BTreeMap<String, Property> {
+        return &self.property_list;
+    }
+
+    /** remove_property() removes a named property of the molecule */
+    pub fn remove_property(&mut self, name: &str) -> Option<Property> {
+        return self.property_list.remove(name);
+    }
+
+    /** set_atom_property() sets a named column of per atom values, one value for every atom */
+    pub fn set_atom_property(&mut self, name: &str, values: AtomProperty) {
+        if values.len() != self.atom_list.len() {
+            panic!(
+                "atom property {} has {} values, but the molecule has {} atoms",
+                name,
+                values.len(),
+                self.atom_list.len()
+            );
+        }
+
+        self.atom_property_list.insert(name.to_string(), values);
+    }
+
+    /** get_atom_property() returns a named column of per atom values */
+    pub fn get_atom_property(&self, name: &str) -> Option<&AtomProperty> {
+        return self.atom_property_list.get(name);
+    }
+
+    /** get_atom_properties() returns all the per atom properties, by name */
+    pub fn get_atom_properties(&self) -> &BTreeMap<String, AtomProperty> {
+        return &self.atom_property_list;
+    }
+
+    /** remove_atom_property() removes a named column of per atom values */
+    pub fn remove_atom_property(&mut self, name: &str) -> Option<AtomProperty> {
+        return self.atom_property_list.remove(name);
+    }
+
+    /** insert_atom_at() inserts an atom, with its atom property values if known, else defaults */
+    fn insert_atom_at(
+        &mut self,
+        index: usize,
+        atom: Atom,
+        atom_property_row: &BTreeMap<String, AtomProperty>,
+    ) {
+        self.atom_list.insert(index, atom);
+
+        for (name, values) in self.atom_property_list.iter_mut() {
+            match atom_property_row.get(name) {
+                Some(value) if value.is_same_kind(values) => values.insert(index, value),
+                _ => values.insert_default(index),
+            }
+        }
+    }
+
+    /**
+     * remove_atom_at() removes an atom with its atom property values, its bonds and group
+     * references, shifting the indices after it, and returns the edit that reverts this
+     */
+    fn remove_atom_at(&mut self, index: usize) -> EditOperation {
+        let atom = self.atom_list.remove(index);
+        let atom_property_row = self
+            .atom_property_list
+            .iter_mut()
+            .map(|(name, values)| (name.to_string(), values.remove(index)))
+            .collect();
+
+        let mut bond_list = Vec::new();
+        let mut kept_bonds = Vec::with_capacity(self.bond_list.len());
+        for (bond_idx, mut bond) in self.bond_list.drain(..).enumerate() {
+            if bond.atom_1_idx == index || bond.atom_2_idx == index {
+                bond_list.push((bond_idx, bond));
+                continue;
+            }
+            if bond.atom_1_idx > index {
+                bond.atom_1_idx -= 1;
+            }
+            if bond.atom_2_idx > index {
+                bond.atom_2_idx -= 1;
+            }
+            kept_bonds.push(bond);
+        }
+        self.bond_list = kept_bonds;
+
+        let mut group_positions = Vec::new();
+        for (group_idx, group) in self.group_list.iter_mut().enumerate() {
+            for (mut path, position) in group.remove_atom_index(index) {
+                path.insert(0, group_idx);
+                group_positions.push((path, position));
+            }
+        }
+
+        return EditOperation::RemoveAtom {
+            index: index,
+            atom: atom,
+            atom_property_row: atom_property_row,
+            bond_list: bond_list,
+            group_positions: group_positions,
+        };
+    }
+
+    /** restore_atom_bonds_groups() puts back the bonds and group references of a reinserted atom */
+    fn restore_atom_bonds_groups(
+        &mut self,
+        index: usize,
+        bond_list: &[(usize, BondIndex)],
+        group_positions: &[(Vec<usize>, usize)],
+    ) {
+        for bond in self.bond_list.iter_mut() {
+            if bond.atom_1_idx >= index {
+                bond.atom_1_idx += 1;
+            }
+            if bond.atom_2_idx >= index {
+                bond.atom_2_idx += 1;
+            }
+        }
+        for (bond_idx, bond) in bond_list.iter() {
+            self.bond_list.insert(*bond_idx, bond.clone());
+        }
+
+        for (group_idx, group) in self.group_list.iter_mut().enumerate() {
+            let positions: Vec<(Vec<usize>, usize)> = group_positions
+                .iter()
+                .filter(|(path, _)| path[0] == group_idx)
+                .map(|(path, position)| (path[1..].to_vec(), *position))
+                .collect();
+            group.insert_atom_index(index, &positions);
+        }
+    }
+
+    /** get_history() returns the undo / redo history of this molecule */
+    pub fn get_history(&self) -> &EditHistory {
+        return &self.history;
+    }
+
+    /** set_history_limit() sets the maximum number of transactions that can be undone */
+    pub fn set_history_limit(&mut self, limit: usize) {
+        self.history.set_limit(limit);
+    }
 }
 
 #[allow(dead_code)]
 impl AtomOperations for Molecule {
     fn add_atom(&mut self, atom: Atom) {
-        self.atom_list.push(atom);
+        self.history.record(|| EditOperation::AddAtom {
+            index: self.atom_list.len(),
+            atom: atom.clone(),
+        });
+        self.insert_atom_at(self.atom_list.len(), atom, &BTreeMap::new());
     }
 
     fn get_number_of_atoms(&mut self) -> usize {
@@ -155,14 +580,15 @@ impl AtomOperations for Molecule {
     }
 
     fn remove_atom(&mut self, index: usize) -> Atom {
-        let removed_atom = self.atom_list.remove(index);
-
-        return Atom {
-            center: removed_atom.center,
-            symbol: removed_atom.symbol.to_string(),
-            charge: removed_atom.charge,
-            remark: removed_atom.remark.to_string(),
+        let operation = self.remove_atom_at(index);
+        let removed_atom = match &operation {
+            EditOperation::RemoveAtom { atom, .. } => atom.clone(),
+            _ => unreachable!(),
         };
+
+        self.history.record(|| operation);
+
+        return removed_atom;
     }
 
     fn index_of(&mut self, atom: &mut Atom) -> usize {
@@ -184,6 +610,7 @@ impl AtomOperations for Molecule {
 #[cfg(test)]
 mod tests {
     use crate::atom::Atom;
+    use crate::atom::AtomGroup;
     use crate::atom::AtomOperations;
     use crate::bond::BondType;
     use crate::point::Point;
@@ -326,4 +753,221 @@ mod tests {
 
         assert_eq!(mol.get_number_of_bonds(), 2);
     }
+
+    #[test]
+    fn molecule_groups() {
+        let mut mol = super::Molecule::new("H2O-dimer".to_string(), "Water dimer".to_string());
+
+        for i in 0..2 {
+            let x = 3.0 * i as f32;
+            mol.add_atom(Atom {
+                center: Point {
+                    x: x,
+                    y: 0.0,
+                    z: 0.0,
+                },
+                charge: 0.0,
+                symbol: "O".to_string(),
+                remark: "Oxygen Atom".to_string(),
+            });
+            mol.add_atom(Atom {
+                center: Point {
+                    x: x + 0.758602,
+                    y: 0.0,
+                    z: 0.504284,
+                },
+                charge: 0.0,
+                symbol: "H".to_string(),
+                remark: "Hydrogen Atom".to_string(),
+            });
+            mol.add_atom(Atom {
+                center: Point {
+                    x: x + 0.758602,
+                    y: 0.0,
+                    z: -0.504284,
+                },
+                charge: 0.0,
+                symbol: "H".to_string(),
+                remark: "Hydrogen Atom".to_string(),
+            });
+        }
+
+        let mut chain = AtomGroup::new("W".to_string(), "Solvent chain".to_string());
+        for i in 0..2 {
+            let mut residue = AtomGroup::new(format!("HOH{}", i + 1), "Water".to_string());
+            for j in 0..3 {
+                residue.add_atom_index(3 * i + j);
+            }
+            chain.add_group(residue);
+        }
+        mol.add_group(chain);
+
+        let mut qm_layer = AtomGroup::new("QM".to_string(), "QM layer".to_string());
+        qm_layer.add_atom_index(0);
+        mol.add_group(qm_layer);
+
+        let residue = mol.find_group(&["W", "HOH2"]).unwrap();
+        assert_eq!(residue.get_atom_indices(), &[3, 4, 5]);
+        let symbols: Vec<&str> = mol
+            .get_group_atoms(residue)
+            .map(|at| at.symbol.as_str())
+            .collect();
+        assert_eq!(symbols, vec!["O", "H", "H"]);
+        assert!(mol.find_group(&["W", "HOH3"]).is_none());
+
+        let groups: Vec<&str> = mol
+            .get_groups_of_atom(0)
+            .iter()
+            .map(|g| g.name.as_str())
+            .collect();
+        assert_eq!(groups, vec!["W", "HOH1", "QM"]);
+
+        mol.remove_atom(1);
+        let residue = mol.find_group(&["W", "HOH2"]).unwrap();
+        assert_eq!(residue.get_atom_indices(), &[2, 3, 4]);
+        assert_eq!(
+            mol.get_group_atoms(mol.find_group(&["W"]).unwrap()).count(),
+            5
+        );
+
+        assert!(mol.remove_group("QM").is_some());
+        assert_eq!(mol.get_groups().len(), 1);
+    }
+
+    #[test]
+    #[should_panic]
+    fn molecule_group_out_of_range() {
+        let mut mol = super::Molecule::new("H2O".to_string(), "Water Molecule".to_string());
+        let mut group = AtomGroup::new("HOH".to_string(), "Water".to_string());
+
+        group.add_atom_index(0);
+        mol.add_group(group);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn molecule_serde() {
+        let mut mol = super::Molecule::new("H2".to_string(), "Hydrogen Molecule".to_string());
+
+        mol.add_atom(Atom {
+            center: Point {
+                x: 0.0,
+                y: 0.0,
+                z: 0.0,
+            },
+            charge: 0.0,
+            symbol: "H".to_string(),
+            remark: "Hydrogen Atom".to_string(),
+        });
+        mol.add_atom(Atom {
+            center: Point {
+                x: 0.74,
+                y: 0.0,
+                z: 0.0,
+            },
+            charge: 0.0,
+            symbol: "H".to_string(),
+            remark: "Hydrogen Atom".to_string(),
+        });
+        mol.add_bond(0, 1, BondType::SINGLE);
+
+        let mut group = AtomGroup::new("H2".to_string(), "".to_string());
+        group.add_atom_index(0);
+        group.add_atom_index(1);
+        mol.add_group(group);
+
+        let json = serde_json::to_string(&mol).unwrap();
+        let read_mol: super::Molecule = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(read_mol.name, mol.name);
+        assert_eq!(read_mol.get_atoms(), mol.get_atoms());
+        assert_eq!(read_mol.get_bonds(), mol.get_bonds());
+        assert_eq!(read_mol.get_groups(), mol.get_groups());
+    }
+
+    #[test]
+    fn molecule_compute_covalent_bonds() {
+        let mut mol = crate::molecule! {
+            name: "CH3OH",
+            atoms: [
+                C -0.047, 0.665, 0.0;
+                O -0.047, -0.758, 0.0;
+                H -1.092, 0.969, 0.0;
+                H 0.446, 1.062, 0.888;
+                H 0.446, 1.062, -0.888;
+                H 0.861, -1.076, 0.0;
+            ],
+            bonds: [0 - 1],
+        };
+
+        mol.compute_covalent_bonds(0.45);
+
+        assert_eq!(mol.get_number_of_bonds(), 5);
+        assert_eq!(mol.get_bond_type(0, 2), BondType::SINGLE);
+        assert_eq!(mol.get_bond_type(1, 5), BondType::SINGLE);
+        assert_eq!(mol.get_neighbours(0), vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn molecule_properties() {
+        use crate::lattice::Lattice;
+        use crate::property::AtomProperty;
+        use crate::property::Property;
+
+        let mut mol = crate::molecule! {
+            name: "H2O",
+            atoms: [
+                O 0.0, 0.0, 0.0;
+                H 0.758602, 0.0, 0.504284;
+                H 0.758602, 0.0, -0.504284;
+            ],
+        };
+
+        mol.set_property("energy", Property::Real(-76.4));
+        assert_eq!(mol.get_property("energy"), Some(&Property::Real(-76.4)));
+        assert!(mol.remove_property("energy").is_some());
+        assert!(mol.get_properties().is_empty());
+
+        mol.set_lattice(Some(Lattice::from_parameters(
+            10.0, 10.0, 10.0, 90.0, 90.0, 90.0,
+        )));
+        assert_eq!(mol.get_lattice().unwrap().vectors[0], [10.0, 0.0, 0.0]);
+
+        mol.set_atom_property("occupancy", AtomProperty::Reals(vec![1.0, 0.5, 0.25]));
+        mol.add_atom(Atom {
+            center: Point {
+                x: 3.0,
+                y: 0.0,
+                z: 0.0,
+            },
+            charge: 0.0,
+            symbol: "He".to_string(),
+            remark: "".to_string(),
+        });
+        assert_eq!(
+            mol.get_atom_property("occupancy"),
+            Some(&AtomProperty::Reals(vec![1.0, 0.5, 0.25, 0.0]))
+        );
+
+        mol.begin_transaction("Remove H".to_string()).unwrap();
+        mol.remove_atom(1);
+        mol.commit_transaction().unwrap();
+        assert_eq!(
+            mol.get_atom_property("occupancy"),
+            Some(&AtomProperty::Reals(vec![1.0, 0.25, 0.0]))
+        );
+
+        mol.undo();
+        assert_eq!(
+            mol.get_atom_property("occupancy"),
+            Some(&AtomProperty::Reals(vec![1.0, 0.5, 0.25, 0.0]))
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn molecule_atom_property_length() {
+        let mut mol = super::Molecule::new("H2O".to_string(), "Water Molecule".to_string());
+        mol.set_atom_property("occupancy", crate::property::AtomProperty::Reals(vec![1.0]));
+    }
 }
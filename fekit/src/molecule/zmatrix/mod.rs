@@ -0,0 +1,700 @@
+/**
+ * Author: V. Ganesh
+ * License: MIT
+ */
+
+/**
+ * zmatrix module, internal coordinates of a molecule.
+ *
+ * Each entry places an atom by its distance to an earlier atom, the angle it makes with
+ * a second earlier atom and the dihedral it makes with a third. The first atom has no
+ * references, the second only a distance and the third no dihedral. Distances are in
+ * angstrom, angles and dihedrals in degrees, and references are 0 based indices into
+ * the entries. Converted to Cartesians, the first atom is at the origin, the second on
+ * the +z axis and the third in the xz plane.
+ *
+ * Built from a molecule, the references of an atom follow its bonds where they can, and a
+ * dummy atom (symbol X) is added off the axis of a linear fragment, as an angle of 180
+ * degrees leaves the dihedral of the next atom undefined. Values may also be given names
+ * (R1, A1, D1, ...) for programs that read a Z-matrix with variables.
+ */
+use std::error::Error;
+use std::fmt;
+
+use crate::atom::Atom;
+use crate::atom::AtomOperations;
+use crate::molecule::Molecule;
+use crate::point::Point;
+
+/** the symbol of dummy atoms, points that only serve as references for other atoms */
+pub const DUMMY_SYMBOL: &str = "X";
+
+/** angles within this many degrees of 0 or 180 are taken as linear */
+const LINEAR_TOLERANCE: f64 = 1.0;
+
+/** the distance of a dummy atom from the atom it is added to, in angstrom */
+const DUMMY_DISTANCE: f64 = 1.0;
+
+/** ZMatrixError - the reason a Z-matrix could not be converted to Cartesians */
+#[allow(dead_code)]
+#[derive(Clone, PartialEq, Debug)]
+pub enum ZMatrixError {
+    /** the entry lacks a reference it needs, what is "distance", "angle" or "dihedral" */
+    MissingReference { entry: usize, what: String },
+    /** a reference is not an earlier entry, or is used twice by the same entry */
+    InvalidReference { entry: usize, reference: usize },
+}
+
+impl fmt::Display for ZMatrixError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ZMatrixError::MissingReference { entry, what } => {
+                write!(f, "entry {} has no {} reference", entry, what)
+            }
+            ZMatrixError::InvalidReference { entry, reference } => write!(
+                f,
+                "entry {} refers to {}, which is not a distinct earlier entry",
+                entry, reference
+            ),
+        }
+    }
+}
+
+impl Error for ZMatrixError {}
+
+/** ZMatrixEntry - one atom of a Z-matrix, each reference is (entry index, value) */
+#[allow(dead_code)]
+#[derive(Clone, PartialEq, Debug)]
+pub struct ZMatrixEntry {
+    pub symbol: String,
+    pub distance: Option<(usize, f64)>,
+    pub angle: Option<(usize, f64)>,
+    pub dihedral: Option<(usize, f64)>,
+}
+
+#[allow(dead_code)]
+impl ZMatrixEntry {
+    pub fn new(symbol: &str) -> ZMatrixEntry {
+        ZMatrixEntry {
+            symbol: symbol.to_string(),
+            distance: None,
+            angle: None,
+            dihedral: None,
+        }
+    }
+
+    /** with_distance() sets the distance to an earlier entry */
+    pub fn with_distance(mut self, reference: usize, distance: f64) -> ZMatrixEntry {
+        self.distance = Some((reference, distance));
+        return self;
+    }
+
+    /** with_angle() sets the angle made with an earlier entry */
+    pub fn with_angle(mut self, reference: usize, angle: f64) -> ZMatrixEntry {
+        self.angle = Some((reference, angle));
+        return self;
+    }
+
+    /** with_dihedral() sets the dihedral made with an earlier entry */
+    pub fn with_dihedral(mut self, reference: usize, dihedral: f64) -> ZMatrixEntry {
+        self.dihedral = Some((reference, dihedral));
+        return self;
+    }
+
+    /** is_dummy() returns whether the entry is a dummy atom */
+    pub fn is_dummy(&self) -> bool {
+        return self.symbol.eq_ignore_ascii_case(DUMMY_SYMBOL);
+    }
+}
+
+/** ZMatrix - a list of entries, in the order the atoms are placed */
+#[allow(dead_code)]
+#[derive(Clone, Default, PartialEq, Debug)]
+pub struct ZMatrix {
+    pub entries: Vec<ZMatrixEntry>,
+}
+
+fn sub(u: &[f64; 3], v: &[f64; 3]) -> [f64; 3] {
+    return [u[0] - v[0], u[1] - v[1], u[2] - v[2]];
+}
+
+fn dot(u: &[f64; 3], v: &[f64; 3]) -> f64 {
+    return u[0] * v[0] + u[1] * v[1] + u[2] * v[2];
+}
+
+fn cross(u: &[f64; 3], v: &[f64; 3]) -> [f64; 3] {
+    return [
+        u[1] * v[2] - u[2] * v[1],
+        u[2] * v[0] - u[0] * v[2],
+        u[0] * v[1] - u[1] * v[0],
+    ];
+}
+
+fn norm(u: &[f64; 3]) -> f64 {
+    return dot(u, u).sqrt();
+}
+
+fn to_array(pt: &Point) -> [f64; 3] {
+    return [pt.x as f64, pt.y as f64, pt.z as f64];
+}
+
+/** get_distance() returns the distance between two positions */
+pub(crate) fn get_distance(a: &[f64; 3], b: &[f64; 3]) -> f64 {
+    return norm(&sub(a, b));
+}
+
+/** get_angle() returns the angle a-b-c in degrees */
+pub(crate) fn get_angle(a: &[f64; 3], b: &[f64; 3], c: &[f64; 3]) -> f64 {
+    let (u, v) = (sub(a, b), sub(c, b));
+    let cosine = dot(&u, &v) / (norm(&u) * norm(&v));
+    return cosine.clamp(-1.0, 1.0).acos().to_degrees();
+}
+
+/**
+ * get_dihedral() returns the dihedral a-b-c-d in degrees, in (-180, 180], positive when
+ * a turns clockwise onto d looking from b to c
+ */
+pub(crate) fn get_dihedral(a: &[f64; 3], b: &[f64; 3], c: &[f64; 3], d: &[f64; 3]) -> f64 {
+    let (b1, b2, b3) = (sub(b, a), sub(c, b), sub(d, c));
+    let (n1, n2) = (cross(&b1, &b2), cross(&b2, &b3));
+    let y = norm(&b2) * dot(&b1, &n2);
+    return y.atan2(dot(&n1, &n2)).to_degrees();
+}
+
+/**
+ * get_perpendicular() returns a unit vector perpendicular to the unit vector u, used when
+ * the references of an atom are collinear and do not fix a plane
+ */
+fn get_perpendicular(u: &[f64; 3]) -> [f64; 3] {
+    let axis = if u[0].abs() < 0.9 {
+        [1.0, 0.0, 0.0]
+    } else {
+        [0.0, 1.0, 0.0]
+    };
+    let v = cross(u, &axis);
+    let length = norm(&v);
+    return [v[0] / length, v[1] / length, v[2] / length];
+}
+
+/**
+ * place() returns the position at the given distance from a, making the given angle with
+ * b and the given dihedral with c, the natural extension reference frame (NeRF) method
+ */
+fn place(
+    a: &[f64; 3],
+    b: &[f64; 3],
+    c: &[f64; 3],
+    distance: f64,
+    angle: f64,
+    dihedral: f64,
+) -> [f64; 3] {
+    let (angle, dihedral) = (angle.to_radians(), dihedral.to_radians());
+
+    let bc = sub(a, b);
+    let length = norm(&bc);
+    let bc = [bc[0] / length, bc[1] / length, bc[2] / length];
+
+    let n = cross(&sub(b, c), &bc);
+    let length = norm(&n);
+    let n = if length < 1e-8 {
+        get_perpendicular(&bc)
+    } else {
+        [n[0] / length, n[1] / length, n[2] / length]
+    };
+    let m = cross(&n, &bc);
+
+    let local = [
+        -distance * angle.cos(),
+        distance * angle.sin() * dihedral.cos(),
+        distance * angle.sin() * dihedral.sin(),
+    ];
+
+    let mut position = *a;
+    for k in 0..3 {
+        position[k] += bc[k] * local[0] + m[k] * local[1] + n[k] * local[2];
+    }
+    return position;
+}
+
+/** is_linear() returns whether an angle, in degrees, is too close to 0 or 180 to fix a plane */
+fn is_linear(angle: f64) -> bool {
+    return !(LINEAR_TOLERANCE..=180.0 - LINEAR_TOLERANCE).contains(&angle);
+}
+
+/** Placement - the entries of a Z-matrix being built from Cartesian positions */
+#[derive(Default)]
+struct Placement {
+    entries: Vec<ZMatrixEntry>,
+    positions: Vec<[f64; 3]>,
+    /** the entries bonded to each entry, a dummy atom is bonded to the atom it was added to */
+    neighbours: Vec<Vec<usize>>,
+}
+
+impl Placement {
+    /**
+     * get_candidates() returns the entries that may be references, except the excluded
+     * ones, the preferred entries first and each group by distance to p
+     */
+    fn get_candidates(&self, p: &[f64; 3], preferred: &[usize], excluded: &[usize]) -> Vec<usize> {
+        let mut candidates: Vec<usize> = (0..self.entries.len())
+            .filter(|k| !excluded.contains(k))
+            .collect();
+        candidates.sort_by(|i, j| {
+            let (di, dj) = (
+                get_distance(p, &self.positions[*i]),
+                get_distance(p, &self.positions[*j]),
+            );
+            (!preferred.contains(i))
+                .cmp(&!preferred.contains(j))
+                .then(di.total_cmp(&dj))
+        });
+        return candidates;
+    }
+
+    /**
+     * get_dihedral_reference() returns the entry fixing the dihedral about a and b, one
+     * bonded to b or a that is not on the a-b line if there is one
+     */
+    fn get_dihedral_reference(&self, a: usize, b: usize) -> usize {
+        let mut preferred = self.neighbours[b].clone();
+        preferred.extend(self.neighbours[a].iter());
+
+        let candidates = self.get_candidates(&self.positions[b], &preferred, &[a, b]);
+        let (pa, pb) = (&self.positions[a], &self.positions[b]);
+        return *candidates
+            .iter()
+            .find(|c| !is_linear(get_angle(pa, pb, &self.positions[**c])))
+            .unwrap_or(&candidates[0]);
+    }
+
+    /**
+     * push() adds an entry at the given position with its distance, angle and dihedral
+     * references, and returns its index
+     */
+    fn push(
+        &mut self,
+        mut entry: ZMatrixEntry,
+        p: [f64; 3],
+        a: Option<usize>,
+        b: Option<usize>,
+        neighbours: &[usize],
+    ) -> usize {
+        let idx = self.entries.len();
+
+        if let Some(a) = a {
+            entry.distance = Some((a, get_distance(&p, &self.positions[a])));
+            if let Some(b) = b {
+                let (pa, pb) = (&self.positions[a], &self.positions[b]);
+                entry.angle = Some((b, get_angle(&p, pa, pb)));
+                if idx >= 3 {
+                    let c = self.get_dihedral_reference(a, b);
+                    let pc = &self.positions[c];
+                    entry.dihedral = Some((c, get_dihedral(&p, pa, pb, pc)));
+                }
+            }
+        }
+
+        self.entries.push(entry);
+        self.positions.push(p);
+        self.neighbours.push(neighbours.to_vec());
+        return idx;
+    }
+
+    /**
+     * add() adds an atom bonded to the given earlier entries and returns its index, after
+     * a dummy atom if the atom is in line with its references
+     */
+    fn add(&mut self, symbol: &str, p: [f64; 3], bonded: &[usize]) -> usize {
+        let entry = ZMatrixEntry::new(symbol);
+        if self.entries.is_empty() {
+            return self.push(entry, p, None, None, bonded);
+        }
+
+        // real atoms are preferred as the distance reference
+        let dummies: Vec<usize> = (0..self.entries.len())
+            .filter(|k| self.entries[*k].is_dummy())
+            .collect();
+        let a = self.get_candidates(&p, bonded, &dummies)[0];
+
+        let b = if self.entries.len() == 1 {
+            None
+        } else {
+            let candidates = self.get_candidates(&self.positions[a], &self.neighbours[a], &[a]);
+            let pa = self.positions[a];
+            match candidates
+                .iter()
+                .find(|b| !is_linear(get_angle(&p, &pa, &self.positions[**b])))
+            {
+                Some(b) => Some(*b),
+                None => {
+                    // a dummy atom off the line through p and a fixes the angle and dihedral
+                    let u = sub(&p, &pa);
+                    let length = norm(&u);
+                    let v = get_perpendicular(&[u[0] / length, u[1] / length, u[2] / length]);
+                    let dummy = [
+                        pa[0] + DUMMY_DISTANCE * v[0],
+                        pa[1] + DUMMY_DISTANCE * v[1],
+                        pa[2] + DUMMY_DISTANCE * v[2],
+                    ];
+                    let entry = ZMatrixEntry::new(DUMMY_SYMBOL);
+                    Some(self.push(entry, dummy, Some(a), Some(candidates[0]), &[a]))
+                }
+            }
+        };
+
+        let idx = self.push(entry, p, Some(a), b, bonded);
+        for neighbour in bonded.iter() {
+            self.neighbours[*neighbour].push(idx);
+        }
+        return idx;
+    }
+}
+
+#[allow(dead_code)]
+impl ZMatrix {
+    pub fn new() -> ZMatrix {
+        ZMatrix {
+            entries: Vec::new(),
+        }
+    }
+
+    /**
+     * from_molecule() builds the Z-matrix of a molecule in the order of its atoms. An atom
+     * refers to the nearest earlier atom bonded to it, then to atoms bonded to that one,
+     * falling back on the nearest earlier atoms, with dummy atoms for linear fragments
+     */
+    pub fn from_molecule(mol: &Molecule) -> ZMatrix {
+        let atoms = mol.get_atoms();
+        let mut bonded: Vec<Vec<usize>> = vec![Vec::new(); atoms.len()];
+        for bond in mol.get_bonds().iter() {
+            bonded[bond.atom_1_idx].push(bond.atom_2_idx);
+            bonded[bond.atom_2_idx].push(bond.atom_1_idx);
+        }
+
+        let mut placement = Placement::default();
+        let mut entry_of_atom: Vec<usize> = Vec::with_capacity(atoms.len());
+        for (idx, atom) in atoms.iter().enumerate() {
+            let neighbours: Vec<usize> = bonded[idx]
+                .iter()
+                .filter(|other| **other < idx)
+                .map(|other| entry_of_atom[*other])
+                .collect();
+            let entry = placement.add(&atom.symbol, to_array(&atom.center), &neighbours);
+            entry_of_atom.push(entry);
+        }
+
+        return ZMatrix {
+            entries: placement.entries,
+        };
+    }
+
+    /**
+     * get_variable_names() returns the names of the distance, angle and dihedral of each
+     * entry, numbered in order as R1, R2, ..., A1, A2, ... and D1, D2, ...
+     */
+    pub fn get_variable_names(&self) -> Vec<[Option<String>; 3]> {
+        let mut counts = [0; 3];
+        let mut names = Vec::with_capacity(self.entries.len());
+
+        for entry in self.entries.iter() {
+            let mut entry_names = [None, None, None];
+            let values = [entry.distance, entry.angle, entry.dihedral];
+            for (k, prefix) in ["R", "A", "D"].iter().enumerate() {
+                if values[k].is_some() {
+                    counts[k] += 1;
+                    entry_names[k] = Some(format!("{}{}", prefix, counts[k]));
+                }
+            }
+            names.push(entry_names);
+        }
+
+        return names;
+    }
+
+    /** check_reference() checks that the reference is an earlier entry not used yet */
+    fn check_reference(
+        entry: usize,
+        reference: Option<(usize, f64)>,
+        what: &str,
+        used: &mut Vec<usize>,
+    ) -> Result<(usize, f64), ZMatrixError> {
+        let (reference, value) = reference.ok_or_else(|| ZMatrixError::MissingReference {
+            entry: entry,
+            what: what.to_string(),
+        })?;
+
+        if reference >= entry || used.contains(&reference) {
+            return Err(ZMatrixError::InvalidReference {
+                entry: entry,
+                reference: reference,
+            });
+        }
+
+        used.push(reference);
+        return Ok((reference, value));
+    }
+
+    /** to_positions() returns the Cartesian position of each entry, in angstrom */
+    pub fn to_positions(&self) -> Result<Vec<[f64; 3]>, ZMatrixError> {
+        let mut positions: Vec<[f64; 3]> = Vec::with_capacity(self.entries.len());
+
+        for (idx, entry) in self.entries.iter().enumerate() {
+            let mut used = Vec::new();
+            let position = match idx {
+                0 => [0.0, 0.0, 0.0],
+                1 => {
+                    let (a, r) = Self::check_reference(idx, entry.distance, "distance", &mut used)?;
+                    let p = positions[a];
+                    [p[0], p[1], p[2] + r]
+                }
+                _ => {
+                    let (a, r) = Self::check_reference(idx, entry.distance, "distance", &mut used)?;
+                    let (b, theta) = Self::check_reference(idx, entry.angle, "angle", &mut used)?;
+
+                    if idx == 2 {
+                        // a point off the a-b line in the xz plane fixes the plane
+                        let p = positions[b];
+                        let c = [p[0] + 1.0, p[1], p[2]];
+                        place(&positions[a], &positions[b], &c, r, theta, 0.0)
+                    } else {
+                        let (c, phi) =
+                            Self::check_reference(idx, entry.dihedral, "dihedral", &mut used)?;
+                        place(&positions[a], &positions[b], &positions[c], r, theta, phi)
+                    }
+                }
+            };
+            positions.push(position);
+        }
+
+        return Ok(positions);
+    }
+
+    /**
+     * to_molecule() returns a molecule with the atoms of the Z-matrix, without bonds and
+     * without its dummy atoms
+     */
+    pub fn to_molecule(&self, name: &str) -> Result<Molecule, ZMatrixError> {
+        let positions = self.to_positions()?;
+        let mut mol = Molecule::new(name.to_string(), "".to_string());
+
+        for (entry, p) in self.entries.iter().zip(positions.iter()) {
+            if entry.is_dummy() {
+                continue;
+            }
+            mol.add_atom(Atom {
+                center: Point {
+                    x: p[0] as f32,
+                    y: p[1] as f32,
+                    z: p[2] as f32,
+                },
+                charge: 0.0,
+                symbol: entry.symbol.clone(),
+                remark: "".to_string(),
+            });
+        }
+
+        return Ok(mol);
+    }
+}
+
+/** Unit tests for the above module */
+#[cfg(test)]
+mod tests {
+    use crate::molecule::builder::MoleculeBuilder;
+    use crate::molecule::zmatrix::ZMatrix;
+    use crate::molecule::zmatrix::ZMatrixEntry;
+    use crate::molecule::zmatrix::ZMatrixError;
+
+    fn get_positions(mol: &crate::molecule::Molecule) -> Vec<[f64; 3]> {
+        return mol
+            .get_atoms()
+            .iter()
+            .map(|atom| super::to_array(&atom.center))
+            .collect();
+    }
+
+    #[test]
+    fn zmatrix_geometry() {
+        let a = [1.0, 0.0, 0.0];
+        let b = [0.0, 0.0, 0.0];
+        let c = [0.0, 0.0, 1.0];
+        let d = [0.0, 1.0, 1.0];
+        assert!((super::get_angle(&a, &b, &c) - 90.0).abs() < 1e-9);
+        assert!((super::get_dihedral(&a, &b, &c, &d) - 90.0).abs() < 1e-9);
+
+        let placed = super::place(&c, &b, &a, 1.0, 90.0, 90.0);
+        assert!(super::get_distance(&placed, &d) < 1e-9);
+    }
+
+    #[test]
+    fn zmatrix_round_trip() {
+        // bromochlorofluoromethane, chiral, so a mirror image would not compare equal
+        let mol = MoleculeBuilder::new("CHBrClF")
+            .xyz(
+                "C  0.0000  0.0000  0.0000
+                 H  0.0000  0.0000  1.0900
+                 Br 1.8200  0.0000 -0.6400
+                 Cl -0.8400 1.4600 -0.5900
+                 F  -0.7600 -1.2400 -0.4600",
+            )
+            .bonds(&[(0, 1), (0, 2), (0, 3), (0, 4)])
+            .build()
+            .unwrap();
+
+        let zmatrix = ZMatrix::from_molecule(&mol);
+        assert_eq!(zmatrix.entries.len(), 5);
+        assert_eq!(zmatrix.entries[0].distance, None);
+        assert_eq!(zmatrix.entries[2].dihedral, None);
+        assert_eq!(zmatrix.entries[3].distance.unwrap().0, 0);
+        assert_eq!(zmatrix.entries[3].angle.unwrap().0, 1);
+        assert_eq!(zmatrix.entries[3].dihedral.unwrap().0, 2);
+
+        let rebuilt = zmatrix.to_molecule("CHBrClF").unwrap();
+        let (before, after) = (get_positions(&mol), get_positions(&rebuilt));
+        for i in 0..5 {
+            for j in 0..5 {
+                let expected = super::get_distance(&before[i], &before[j]);
+                let found = super::get_distance(&after[i], &after[j]);
+                assert!((expected - found).abs() < 1e-4);
+            }
+        }
+
+        let handedness = |p: &[[f64; 3]]| super::get_dihedral(&p[1], &p[2], &p[3], &p[4]);
+        assert!((handedness(&before) - handedness(&after)).abs() < 1e-3);
+        assert_eq!(after[0], [0.0, 0.0, 0.0]);
+        assert!(after[1][0].abs() < 1e-9 && after[1][1].abs() < 1e-9);
+        assert!(after[2][1].abs() < 1e-9);
+    }
+
+    #[test]
+    fn zmatrix_bond_graph() {
+        // the hydroxyl hydrogen comes last, far from the atoms just before it
+        let mol = MoleculeBuilder::new("methanol")
+            .xyz(
+                "C  -0.0467  0.6630  0.0000
+                 O  -0.0467 -0.7570  0.0000
+                 H  -1.0863  0.9995  0.0000
+                 H   0.4379  1.0563  0.8900
+                 H   0.4379  1.0563 -0.8900
+                 H   0.8563 -1.0866  0.0000",
+            )
+            .bonds(&[(0, 1), (0, 2), (0, 3), (0, 4), (1, 5)])
+            .build()
+            .unwrap();
+
+        let zmatrix = ZMatrix::from_molecule(&mol);
+        let hydroxyl = &zmatrix.entries[5];
+        assert_eq!(hydroxyl.distance.unwrap().0, 1);
+        let (o, h) = (&get_positions(&mol)[1], &get_positions(&mol)[5]);
+        assert!((hydroxyl.distance.unwrap().1 - super::get_distance(o, h)).abs() < 1e-9);
+        assert_eq!(hydroxyl.angle.unwrap().0, 0);
+        // the dihedral is about the C-O bond, to one of the methyl hydrogens
+        assert!([2, 3, 4].contains(&hydroxyl.dihedral.unwrap().0));
+
+        let rebuilt = zmatrix.to_molecule("methanol").unwrap();
+        let (before, after) = (get_positions(&mol), get_positions(&rebuilt));
+        for i in 0..6 {
+            for j in 0..6 {
+                let expected = super::get_distance(&before[i], &before[j]);
+                let found = super::get_distance(&after[i], &after[j]);
+                assert!((expected - found).abs() < 1e-4);
+            }
+        }
+
+        let names = zmatrix.get_variable_names();
+        assert_eq!(names[0], [None, None, None]);
+        assert_eq!(names[1][0].as_deref(), Some("R1"));
+        assert_eq!(names[2][1].as_deref(), Some("A1"));
+        assert_eq!(
+            names[5],
+            [
+                Some("R5".to_string()),
+                Some("A4".to_string()),
+                Some("D3".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn zmatrix_dummy_atoms() {
+        let mol = MoleculeBuilder::new("acetylene")
+            .xyz("H 0.0 0.0 -1.663\nC 0.0 0.0 -0.601\nC 0.0 0.0 0.601\nH 0.0 0.0 1.663")
+            .bonds(&[(0, 1), (1, 2), (2, 3)])
+            .build()
+            .unwrap();
+
+        let zmatrix = ZMatrix::from_molecule(&mol);
+        let symbols: Vec<&str> = zmatrix.entries.iter().map(|e| e.symbol.as_str()).collect();
+        // the last hydrogen uses the dummy atom off the axis as its angle reference
+        assert_eq!(symbols, ["H", "C", "X", "C", "H"]);
+        assert_eq!(zmatrix.entries[4].angle.unwrap().0, 2);
+        assert!(zmatrix.entries[2].is_dummy());
+        assert_eq!(zmatrix.entries[3].angle, Some((2, 90.0)));
+        assert!(zmatrix
+            .entries
+            .iter()
+            .all(|e| e.angle.is_none_or(|a| a.1 < 179.0)));
+
+        let rebuilt = zmatrix.to_molecule("acetylene").unwrap();
+        assert_eq!(rebuilt.get_atoms().len(), 4);
+        let (before, after) = (get_positions(&mol), get_positions(&rebuilt));
+        for i in 0..4 {
+            for j in 0..4 {
+                let expected = super::get_distance(&before[i], &before[j]);
+                let found = super::get_distance(&after[i], &after[j]);
+                assert!((expected - found).abs() < 1e-4);
+            }
+        }
+
+        // hydrogen cyanide by internal coordinates, the dummy atom keeps the angles at 90
+        let zmatrix = ZMatrix {
+            entries: vec![
+                ZMatrixEntry::new("C"),
+                ZMatrixEntry::new("N").with_distance(0, 1.156),
+                ZMatrixEntry::new("X")
+                    .with_distance(0, 1.0)
+                    .with_angle(1, 90.0),
+                ZMatrixEntry::new("H")
+                    .with_distance(0, 1.064)
+                    .with_angle(2, 90.0)
+                    .with_dihedral(1, 180.0),
+            ],
+        };
+        let hcn = zmatrix.to_molecule("HCN").unwrap();
+        let symbols: Vec<&str> = hcn.get_atoms().iter().map(|a| a.symbol.as_str()).collect();
+        assert_eq!(symbols, ["C", "N", "H"]);
+        let p = get_positions(&hcn);
+        assert!((super::get_angle(&p[1], &p[0], &p[2]) - 180.0).abs() < 1e-3);
+        assert!((super::get_distance(&p[0], &p[2]) - 1.064).abs() < 1e-6);
+    }
+
+    #[test]
+    fn zmatrix_errors() {
+        let mut zmatrix = ZMatrix::new();
+        zmatrix.entries.push(ZMatrixEntry::new("O"));
+        zmatrix.entries.push(ZMatrixEntry::new("H"));
+        assert_eq!(
+            zmatrix.to_positions().unwrap_err(),
+            ZMatrixError::MissingReference {
+                entry: 1,
+                what: "distance".to_string()
+            }
+        );
+
+        zmatrix.entries[1].distance = Some((0, 0.96));
+        let mut h = ZMatrixEntry::new("H");
+        h.distance = Some((0, 0.96));
+        h.angle = Some((0, 104.5));
+        zmatrix.entries.push(h);
+        assert_eq!(
+            zmatrix.to_positions().unwrap_err().to_string(),
+            "entry 2 refers to 0, which is not a distinct earlier entry"
+        );
+
+        zmatrix.entries[2].angle = Some((1, 104.5));
+        let positions = zmatrix.to_positions().unwrap();
+        assert!((super::get_distance(&positions[0], &positions[2]) - 0.96).abs() < 1e-9);
+        let angle = super::get_angle(&positions[1], &positions[0], &positions[2]);
+        assert!((angle - 104.5).abs() < 1e-9);
+    }
+}
@@ -8,6 +8,7 @@
 /** Point - represents a point in space */
 #[allow(dead_code)]
 #[derive(Default, Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Point {
     pub x: f32,
     pub y: f32,
@@ -0,0 +1,226 @@
+/**
+ * Author: V. Ganesh
+ * License: MIT
+ */
+
+/**
+ * property module, named values attached to a Molecule.
+ *
+ * Property holds a single value for the whole molecule (an energy, a title, a flag).
+ * AtomProperty holds one value per atom as a column, e.g. forces, occupancies or
+ * residue numbers read from a file, and is kept in step with the atom list.
+ */
+use std::fmt;
+
+/** Property - a value attached to a molecule */
+#[allow(dead_code)]
+#[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Property {
+    Text(String),
+    Integer(i64),
+    Real(f64),
+    Logical(bool),
+    Integers(Vec<i64>),
+    Reals(Vec<f64>),
+    Logicals(Vec<bool>),
+}
+
+#[allow(dead_code)]
+impl Property {
+    /** get_real() returns the value as a real number, if it is numeric */
+    pub fn get_real(&self) -> Option<f64> {
+        match self {
+            Property::Real(value) => return Some(*value),
+            Property::Integer(value) => return Some(*value as f64),
+            _ => return None,
+        }
+    }
+
+    /** get_text() returns the value if it is text */
+    pub fn get_text(&self) -> Option<&str> {
+        match self {
+            Property::Text(value) => return Some(value.as_str()),
+            _ => return None,
+        }
+    }
+}
+
+/** Property is shown as its value, lists are separated by spaces and logicals are T or F */
+impl fmt::Display for Property {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let join = |words: Vec<String>| words.join(" ");
+        let logical = |value: &bool| if *value { "T" } else { "F" }.to_string();
+
+        match self {
+            Property::Text(value) => write!(f, "{}", value),
+            Property::Integer(value) => write!(f, "{}", value),
+            Property::Real(value) => write!(f, "{}", value),
+            Property::Logical(value) => write!(f, "{}", logical(value)),
+            Property::Integers(values) => {
+                write!(
+                    f,
+                    "{}",
+                    join(values.iter().map(|v| v.to_string()).collect())
+                )
+            }
+            Property::Reals(values) => {
+                write!(
+                    f,
+                    "{}",
+                    join(values.iter().map(|v| v.to_string()).collect())
+                )
+            }
+            Property::Logicals(values) => {
+                write!(f, "{}", join(values.iter().map(logical).collect()))
+            }
+        }
+    }
+}
+
+/** AtomProperty - a column of values, one per atom */
+#[allow(dead_code)]
+#[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AtomProperty {
+    Texts(Vec<String>),
+    Integers(Vec<i64>),
+    Reals(Vec<f64>),
+    Logicals(Vec<bool>),
+    Vectors(Vec<[f64; 3]>),
+}
+
+#[allow(dead_code)]
+impl AtomProperty {
+    /** len() returns the number of values in the column */
+    pub fn len(&self) -> usize {
+        match self {
+            AtomProperty::Texts(values) => return values.len(),
+            AtomProperty::Integers(values) => return values.len(),
+            AtomProperty::Reals(values) => return values.len(),
+            AtomProperty::Logicals(values) => return values.len(),
+            AtomProperty::Vectors(values) => return values.len(),
+        }
+    }
+
+    /** is_empty() checks if the column has no values */
+    pub fn is_empty(&self) -> bool {
+        return self.len() == 0;
+    }
+
+    /** insert_default() inserts an empty, zero or false value at the given index */
+    pub fn insert_default(&mut self, index: usize) {
+        match self {
+            AtomProperty::Texts(values) => values.insert(index, "".to_string()),
+            AtomProperty::Integers(values) => values.insert(index, 0),
+            AtomProperty::Reals(values) => values.insert(index, 0.0),
+            AtomProperty::Logicals(values) => values.insert(index, false),
+            AtomProperty::Vectors(values) => values.insert(index, [0.0; 3]),
+        }
+    }
+
+    /** is_same_kind() checks if another column holds the same kind of values */
+    pub fn is_same_kind(&self, other: &AtomProperty) -> bool {
+        return std::mem::discriminant(self) == std::mem::discriminant(other);
+    }
+
+    /** remove() removes the value at the given index, and returns it as a column of one */
+    pub fn remove(&mut self, index: usize) -> AtomProperty {
+        match self {
+            AtomProperty::Texts(values) => return AtomProperty::Texts(vec![values.remove(index)]),
+            AtomProperty::Integers(values) => {
+                return AtomProperty::Integers(vec![values.remove(index)])
+            }
+            AtomProperty::Reals(values) => return AtomProperty::Reals(vec![values.remove(index)]),
+            AtomProperty::Logicals(values) => {
+                return AtomProperty::Logicals(vec![values.remove(index)])
+            }
+            AtomProperty::Vectors(values) => {
+                return AtomProperty::Vectors(vec![values.remove(index)])
+            }
+        }
+    }
+
+    /** insert() inserts the first value of another column of the same kind at the given index */
+    pub fn insert(&mut self, index: usize, value: &AtomProperty) {
+        match (self, value) {
+            (AtomProperty::Texts(values), AtomProperty::Texts(v)) => {
+                values.insert(index, v[0].to_string())
+            }
+            (AtomProperty::Integers(values), AtomProperty::Integers(v)) => {
+                values.insert(index, v[0])
+            }
+            (AtomProperty::Reals(values), AtomProperty::Reals(v)) => values.insert(index, v[0]),
+            (AtomProperty::Logicals(values), AtomProperty::Logicals(v)) => {
+                values.insert(index, v[0])
+            }
+            (AtomProperty::Vectors(values), AtomProperty::Vectors(v)) => values.insert(index, v[0]),
+            _ => panic!("cannot insert a value of a different kind of atom property"),
+        }
+    }
+
+    /** get_text() returns the value at the given index formatted as text */
+    pub fn get_text(&self, index: usize) -> String {
+        match self {
+            AtomProperty::Texts(values) => return values[index].to_string(),
+            AtomProperty::Integers(values) => return values[index].to_string(),
+            AtomProperty::Reals(values) => return values[index].to_string(),
+            AtomProperty::Logicals(values) => {
+                return if values[index] { "T" } else { "F" }.to_string()
+            }
+            AtomProperty::Vectors(values) => {
+                let v = values[index];
+                return format!("{} {} {}", v[0], v[1], v[2]);
+            }
+        }
+    }
+}
+
+/** Unit tests for the above module */
+#[cfg(test)]
+mod tests {
+    use super::AtomProperty;
+    use super::Property;
+
+    #[test]
+    fn property_values() {
+        assert_eq!(Property::Real(-76.4).get_real(), Some(-76.4));
+        assert_eq!(Property::Integer(2).get_real(), Some(2.0));
+        assert_eq!(Property::Text("opt".to_string()).get_real(), None);
+        assert_eq!(Property::Text("opt".to_string()).get_text(), Some("opt"));
+        assert_eq!(
+            Property::Reals(vec![0.5, -1.0]).to_string(),
+            "0.5 -1".to_string()
+        );
+        assert_eq!(
+            Property::Logicals(vec![true, false]).to_string(),
+            "T F".to_string()
+        );
+    }
+
+    #[test]
+    fn atom_property_column() {
+        let mut forces = AtomProperty::Vectors(vec![[0.1, 0.0, 0.0], [0.0, 0.2, 0.0]]);
+
+        forces.insert_default(2);
+        assert_eq!(forces.len(), 3);
+        assert_eq!(forces.get_text(1), "0 0.2 0".to_string());
+
+        let removed = forces.remove(1);
+        assert_eq!(removed, AtomProperty::Vectors(vec![[0.0, 0.2, 0.0]]));
+        assert_eq!(forces.len(), 2);
+
+        forces.insert(1, &removed);
+        assert_eq!(
+            forces,
+            AtomProperty::Vectors(vec![[0.1, 0.0, 0.0], [0.0, 0.2, 0.0], [0.0, 0.0, 0.0]])
+        );
+
+        let mut flags = AtomProperty::Logicals(vec![true]);
+        flags.insert_default(1);
+        assert!(flags.is_same_kind(&AtomProperty::Logicals(vec![])));
+        assert!(!flags.is_same_kind(&AtomProperty::Reals(vec![])));
+        assert_eq!(flags.get_text(0), "T".to_string());
+        assert_eq!(flags.get_text(1), "F".to_string());
+    }
+}
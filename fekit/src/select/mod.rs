@@ -0,0 +1,721 @@
+/**
+ * Author: V. Ganesh
+ * License: MIT
+ */
+
+/**
+ * select module, a small VMD / PyMOL like atom selection language.
+ *
+ * A query is evaluated against a Molecule and returns the matching atom indices, or
+ * an AtomGroup referring to them. Supported keywords:
+ *
+ *   all, none                       every / no atom
+ *   element C N ...                 element symbol (case insensitive)
+ *   index 1 2 5 to 9                atom indices, `to` gives an inclusive range
+ *   resname HOH, chain A, model 1   atoms in residue / chain / model groups with that name
+ *   group QM                        atoms in any group with that name
+ *   ring                            atoms that are part of a ring
+ *   charge < -0.3                   compares charge, x, y, z or index; < <= > >= == !=
+ *   within 3.5 of <sel>             atoms within the distance of any atom in <sel>
+ *   bonded to <sel>                 atoms bonded to any atom in <sel>
+ *   not, and, or, ( )               in decreasing order of precedence
+ *
+ * `within` and `bonded to` apply to the selection directly following them, use
+ * parentheses to combine more: `within 5 of (resname LIG and element N)`.
+ */
+use std::error::Error;
+use std::fmt;
+
+use crate::atom::AtomGroup;
+use crate::atom::GroupKind;
+use crate::molecule::cells::CellGrid;
+use crate::molecule::Molecule;
+
+/** SelectionError - a query that could not be parsed, with the character offset of the problem */
+#[allow(dead_code)]
+#[derive(Clone, PartialEq, Debug)]
+pub struct SelectionError {
+    pub message: String,
+    pub position: usize,
+}
+
+impl fmt::Display for SelectionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at position {}", self.message, self.position)
+    }
+}
+
+impl Error for SelectionError {}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Property {
+    Index,
+    Charge,
+    X,
+    Y,
+    Z,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Comparison {
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    Equal,
+    NotEqual,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+enum Expr {
+    All,
+    Nothing,
+    Element(Vec<String>),
+    Index(Vec<(usize, usize)>),
+    Group(Option<GroupKind>, Vec<String>),
+    Ring,
+    Compare(Property, Comparison, f64),
+    Within(f64, Box<Expr>),
+    BondedTo(Box<Expr>),
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+}
+
+#[derive(Clone, PartialEq, Debug)]
+enum Token {
+    Word(String),
+    Number(f64),
+    Operator(Comparison),
+    Open,
+    Close,
+}
+
+fn tokenize(query: &str) -> Result<Vec<(Token, usize)>, SelectionError> {
+    let chars: Vec<char> = query.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let start = i;
+
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+
+        if c == '(' || c == ')' {
+            tokens.push((if c == '(' { Token::Open } else { Token::Close }, start));
+            i += 1;
+            continue;
+        }
+
+        if "<>=!".contains(c) {
+            let has_eq = i + 1 < chars.len() && chars[i + 1] == '=';
+            let operator = match (c, has_eq) {
+                ('<', false) => Comparison::Less,
+                ('<', true) => Comparison::LessEqual,
+                ('>', false) => Comparison::Greater,
+                ('>', true) => Comparison::GreaterEqual,
+                ('=', _) => Comparison::Equal,
+                ('!', true) => Comparison::NotEqual,
+                _ => {
+                    return Err(SelectionError {
+                        message: format!("unexpected character '{}'", c),
+                        position: start,
+                    })
+                }
+            };
+
+            i += if has_eq { 2 } else { 1 };
+            tokens.push((Token::Operator(operator), start));
+            continue;
+        }
+
+        while i < chars.len() && !chars[i].is_whitespace() && !"()<>=!".contains(chars[i]) {
+            i += 1;
+        }
+
+        let text: String = chars[start..i].iter().collect();
+        let starts_numeric = c.is_ascii_digit() || c == '-' || c == '+' || c == '.';
+
+        match text.parse::<f64>() {
+            Ok(value) if starts_numeric => tokens.push((Token::Number(value), start)),
+            _ => tokens.push((Token::Word(text), start)),
+        }
+    }
+
+    return Ok(tokens);
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    position: usize,
+    length: usize,
+    source: Vec<char>,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        return self.tokens.get(self.position).map(|(t, _)| t);
+    }
+
+    fn offset(&self) -> usize {
+        return self
+            .tokens
+            .get(self.position)
+            .map(|(_, p)| *p)
+            .unwrap_or(self.length);
+    }
+
+    fn error<T>(&self, message: &str) -> Result<T, SelectionError> {
+        return Err(SelectionError {
+            message: message.to_string(),
+            position: self.offset(),
+        });
+    }
+
+    fn peek_keyword(&self, keyword: &str) -> bool {
+        return matches!(self.peek(), Some(Token::Word(w)) if w.eq_ignore_ascii_case(keyword));
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<(), SelectionError> {
+        if !self.peek_keyword(keyword) {
+            return self.error(&format!("expected '{}'", keyword));
+        }
+
+        self.position += 1;
+        return Ok(());
+    }
+
+    fn next_number(&mut self) -> Result<f64, SelectionError> {
+        match self.peek() {
+            Some(Token::Number(value)) => {
+                let value = *value;
+                self.position += 1;
+                return Ok(value);
+            }
+            _ => return self.error("expected a number"),
+        }
+    }
+
+    /** a value list ends at `and`, `or`, `)` or the end of the query */
+    fn at_value(&self) -> bool {
+        match self.peek() {
+            Some(Token::Word(_)) => !self.peek_keyword("and") && !self.peek_keyword("or"),
+            Some(Token::Number(_)) => true,
+            _ => false,
+        }
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, SelectionError> {
+        let mut expr = self.parse_and()?;
+
+        while self.peek_keyword("or") {
+            self.position += 1;
+            expr = Expr::Or(Box::new(expr), Box::new(self.parse_and()?));
+        }
+
+        return Ok(expr);
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, SelectionError> {
+        let mut expr = self.parse_not()?;
+
+        while self.peek_keyword("and") {
+            self.position += 1;
+            expr = Expr::And(Box::new(expr), Box::new(self.parse_not()?));
+        }
+
+        return Ok(expr);
+    }
+
+    fn parse_not(&mut self) -> Result<Expr, SelectionError> {
+        if self.peek_keyword("not") {
+            self.position += 1;
+            return Ok(Expr::Not(Box::new(self.parse_not()?)));
+        }
+
+        return self.parse_primary();
+    }
+
+    fn parse_names(&mut self) -> Result<Vec<String>, SelectionError> {
+        let mut names = Vec::new();
+
+        while self.at_value() {
+            match self.peek() {
+                Some(Token::Word(w)) => names.push(w.to_string()),
+                Some(Token::Number(_)) => {
+                    let start = self.tokens[self.position].1;
+                    let end = self
+                        .tokens
+                        .get(self.position + 1)
+                        .map(|(_, p)| *p)
+                        .unwrap_or(self.length);
+                    names.push(self.source_text(start, end));
+                }
+                _ => break,
+            }
+            self.position += 1;
+        }
+
+        if names.is_empty() {
+            return self.error("expected a name");
+        }
+
+        return Ok(names);
+    }
+
+    fn parse_indices(&mut self) -> Result<Vec<(usize, usize)>, SelectionError> {
+        let mut ranges = Vec::new();
+
+        while self.at_value() {
+            let first = self.next_index()?;
+
+            if self.peek_keyword("to") {
+                self.position += 1;
+                let last = self.next_index()?;
+                ranges.push((first, last));
+            } else {
+                ranges.push((first, first));
+            }
+        }
+
+        if ranges.is_empty() {
+            return self.error("expected an atom index");
+        }
+
+        return Ok(ranges);
+    }
+
+    fn next_index(&mut self) -> Result<usize, SelectionError> {
+        let value = self.next_number()?;
+
+        if value < 0.0 || value.fract() != 0.0 {
+            self.position -= 1;
+            return self.error("expected a non negative integer");
+        }
+
+        return Ok(value as usize);
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr, SelectionError> {
+        let word = match self.peek() {
+            Some(Token::Open) => {
+                self.position += 1;
+                let expr = self.parse_or()?;
+
+                if self.peek() != Some(&Token::Close) {
+                    return self.error("expected ')'");
+                }
+
+                self.position += 1;
+                return Ok(expr);
+            }
+            Some(Token::Word(w)) => w.to_lowercase(),
+            Some(_) => return self.error("expected a keyword"),
+            None => return self.error("unexpected end of selection"),
+        };
+
+        self.position += 1;
+
+        match word.as_str() {
+            "all" => return Ok(Expr::All),
+            "none" => return Ok(Expr::Nothing),
+            "ring" => return Ok(Expr::Ring),
+            "element" | "symbol" => return Ok(Expr::Element(self.parse_names()?)),
+            "resname" => return Ok(Expr::Group(Some(GroupKind::RESIDUE), self.parse_names()?)),
+            "chain" => return Ok(Expr::Group(Some(GroupKind::CHAIN), self.parse_names()?)),
+            "model" => return Ok(Expr::Group(Some(GroupKind::MODEL), self.parse_names()?)),
+            "group" => return Ok(Expr::Group(None, self.parse_names()?)),
+            "index" => {
+                if let Some(Token::Operator(_)) = self.peek() {
+                    return self.parse_comparison(Property::Index);
+                }
+
+                return Ok(Expr::Index(self.parse_indices()?));
+            }
+            "charge" => return self.parse_comparison(Property::Charge),
+            "x" => return self.parse_comparison(Property::X),
+            "y" => return self.parse_comparison(Property::Y),
+            "z" => return self.parse_comparison(Property::Z),
+            "within" => {
+                let distance = self.next_number()?;
+                self.expect_keyword("of")?;
+                return Ok(Expr::Within(distance, Box::new(self.parse_not()?)));
+            }
+            "bonded" => {
+                self.expect_keyword("to")?;
+                return Ok(Expr::BondedTo(Box::new(self.parse_not()?)));
+            }
+            _ => {
+                self.position -= 1;
+                return self.error(&format!("unknown keyword '{}'", word));
+            }
+        }
+    }
+
+    fn parse_comparison(&mut self, property: Property) -> Result<Expr, SelectionError> {
+        let comparison = match self.peek() {
+            Some(Token::Operator(op)) => *op,
+            _ => return self.error("expected a comparison operator"),
+        };
+
+        self.position += 1;
+        let value = self.next_number()?;
+
+        return Ok(Expr::Compare(property, comparison, value));
+    }
+
+    fn source_text(&self, start: usize, end: usize) -> String {
+        let text: String = self.source[start..end].iter().collect();
+        return text.trim().to_string();
+    }
+}
+
+/** Selection - a parsed selection query, that can be evaluated against any Molecule */
+#[allow(dead_code)]
+#[derive(Clone, PartialEq, Debug)]
+pub struct Selection {
+    pub query: String,
+
+    expr: Expr,
+}
+
+#[allow(dead_code)]
+impl Selection {
+    /** parse() compiles a selection query */
+    pub fn parse(query: &str) -> Result<Selection, SelectionError> {
+        let mut parser = Parser {
+            tokens: tokenize(query)?,
+            position: 0,
+            length: query.chars().count(),
+            source: query.chars().collect(),
+        };
+
+        let expr = parser.parse_or()?;
+        if parser.peek().is_some() {
+            return parser.error("unexpected trailing input");
+        }
+
+        return Ok(Selection {
+            query: query.to_string(),
+            expr: expr,
+        });
+    }
+
+    /** evaluate() returns the sorted indices of the atoms matching this selection */
+    pub fn evaluate(&self, mol: &Molecule) -> Vec<usize> {
+        let mask = evaluate_expr(&self.expr, mol);
+
+        return (0..mask.len()).filter(|idx| mask[*idx]).collect();
+    }
+
+    /** to_atom_group() evaluates this selection as an AtomGroup referring to the matching atoms */
+    pub fn to_atom_group(&self, mol: &Molecule, name: String) -> AtomGroup {
+        let mut group = AtomGroup::new(name, self.query.to_string());
+
+        for idx in self.evaluate(mol) {
+            group.add_atom_index(idx);
+        }
+
+        return group;
+    }
+}
+
+/** select() parses and evaluates a query, the resulting group is named "selection" */
+#[allow(dead_code)]
+pub fn select(mol: &Molecule, query: &str) -> Result<AtomGroup, SelectionError> {
+    let selection = Selection::parse(query)?;
+    return Ok(selection.to_atom_group(mol, "selection".to_string()));
+}
+
+fn mark_groups(groups: &[AtomGroup], kind: Option<GroupKind>, names: &[String], mask: &mut [bool]) {
+    for group in groups.iter() {
+        let kind_matches = kind.map(|k| k == group.kind).unwrap_or(true);
+
+        if kind_matches && names.contains(&group.name) {
+            for idx in group.get_all_atom_indices() {
+                mask[idx] = true;
+            }
+        } else {
+            mark_groups(group.get_groups(), kind, names, mask);
+        }
+    }
+}
+
+fn evaluate_expr(expr: &Expr, mol: &Molecule) -> Vec<bool> {
+    let atoms = mol.get_atoms();
+    let number_of_atoms = atoms.len();
+
+    match expr {
+        Expr::All => return vec![true; number_of_atoms],
+        Expr::Nothing => return vec![false; number_of_atoms],
+        Expr::Element(symbols) => {
+            return atoms
+                .iter()
+                .map(|at| {
+                    symbols
+                        .iter()
+                        .any(|s| s.eq_ignore_ascii_case(at.symbol.trim()))
+                })
+                .collect();
+        }
+        Expr::Index(ranges) => {
+            return (0..number_of_atoms)
+                .map(|idx| ranges.iter().any(|(a, b)| *a <= idx && idx <= *b))
+                .collect();
+        }
+        Expr::Group(kind, names) => {
+            let mut mask = vec![false; number_of_atoms];
+            mark_groups(mol.get_groups(), *kind, names, &mut mask);
+            return mask;
+        }
+        Expr::Ring => return mol.get_ring_atoms(),
+        Expr::Compare(property, comparison, value) => {
+            return atoms
+                .iter()
+                .enumerate()
+                .map(|(idx, at)| {
+                    let lhs = match property {
+                        Property::Index => idx as f64,
+                        Property::Charge => at.charge as f64,
+                        Property::X => at.center.x as f64,
+                        Property::Y => at.center.y as f64,
+                        Property::Z => at.center.z as f64,
+                    };
+
+                    match comparison {
+                        Comparison::Less => lhs < *value,
+                        Comparison::LessEqual => lhs <= *value,
+                        Comparison::Greater => lhs > *value,
+                        Comparison::GreaterEqual => lhs >= *value,
+                        Comparison::Equal => lhs == *value,
+                        Comparison::NotEqual => lhs != *value,
+                    }
+                })
+                .collect();
+        }
+        Expr::Within(distance, inner) => {
+            let inner_mask = evaluate_expr(inner, mol);
+
+            // cells no smaller than the distance, a little over it for the f32 rounding
+            let mut cells = CellGrid::new((*distance as f32 * 1.001).max(1.0));
+            for (idx, at) in atoms.iter().enumerate() {
+                if inner_mask[idx] {
+                    cells.add(idx, &at.center);
+                }
+            }
+
+            return atoms
+                .iter()
+                .map(|at| {
+                    let mut center = at.center;
+                    cells
+                        .get_neighbours(&at.center)
+                        .iter()
+                        .any(|idx| (center.distance_from(&atoms[*idx].center) as f64) <= *distance)
+                })
+                .collect();
+        }
+        Expr::BondedTo(inner) => {
+            let inner_mask = evaluate_expr(inner, mol);
+            let mut mask = vec![false; number_of_atoms];
+
+            for bond in mol.get_bonds().iter() {
+                if inner_mask[bond.atom_1_idx] {
+                    mask[bond.atom_2_idx] = true;
+                }
+                if inner_mask[bond.atom_2_idx] {
+                    mask[bond.atom_1_idx] = true;
+                }
+            }
+
+            return mask;
+        }
+        Expr::Not(inner) => return evaluate_expr(inner, mol).iter().map(|m| !m).collect(),
+        Expr::And(lhs, rhs) => {
+            let rhs_mask = evaluate_expr(rhs, mol);
+            return evaluate_expr(lhs, mol)
+                .iter()
+                .zip(rhs_mask.iter())
+                .map(|(a, b)| *a && *b)
+                .collect();
+        }
+        Expr::Or(lhs, rhs) => {
+            let rhs_mask = evaluate_expr(rhs, mol);
+            return evaluate_expr(lhs, mol)
+                .iter()
+                .zip(rhs_mask.iter())
+                .map(|(a, b)| *a || *b)
+                .collect();
+        }
+    }
+}
+
+/** Unit tests for the above module */
+#[cfg(test)]
+mod tests {
+    use crate::atom::Atom;
+    use crate::atom::AtomGroup;
+    use crate::atom::AtomOperations;
+    use crate::atom::GroupKind;
+    use crate::bond::BondType;
+    use crate::molecule::Molecule;
+    use crate::point::Point;
+
+    /** a cyclopropane ring (0-2) with an amino group (3-5), and a water (6-8) 3 A away */
+    fn make_molecule() -> Molecule {
+        let mut mol = Molecule::new("test".to_string(), "".to_string());
+        let atoms = [
+            ("C", 0.0, 0.0, 0.0, -0.2),
+            ("C", 1.5, 0.0, 0.0, -0.2),
+            ("C", 0.75, 1.3, 0.0, 0.1),
+            ("N", 0.75, 2.7, 0.0, -0.8),
+            ("H", 0.0, 3.2, 0.0, 0.4),
+            ("H", 1.5, 3.2, 0.0, 0.4),
+            ("O", 0.75, 5.9, 0.0, -0.8),
+            ("H", 1.5, 6.4, 0.0, 0.4),
+            ("H", 0.0, 6.4, 0.0, 0.4),
+        ];
+
+        for (symbol, x, y, z, charge) in atoms {
+            mol.add_atom(Atom {
+                center: Point { x: x, y: y, z: z },
+                charge: charge,
+                symbol: symbol.to_string(),
+                remark: "".to_string(),
+            });
+        }
+
+        for (a, b) in [
+            (0, 1),
+            (1, 2),
+            (2, 0),
+            (2, 3),
+            (3, 4),
+            (3, 5),
+            (6, 7),
+            (6, 8),
+        ] {
+            mol.add_bond(a, b, BondType::SINGLE);
+        }
+
+        let mut chain = AtomGroup::new("A".to_string(), "".to_string()).with_kind(GroupKind::CHAIN);
+        let mut ligand =
+            AtomGroup::new("LIG".to_string(), "".to_string()).with_kind(GroupKind::RESIDUE);
+        let mut water =
+            AtomGroup::new("HOH".to_string(), "".to_string()).with_kind(GroupKind::RESIDUE);
+        for idx in 0..6 {
+            ligand.add_atom_index(idx);
+        }
+        for idx in 6..9 {
+            water.add_atom_index(idx);
+        }
+        chain.add_group(ligand);
+        chain.add_group(water);
+        mol.add_group(chain);
+
+        return mol;
+    }
+
+    fn indices(mol: &Molecule, query: &str) -> Vec<usize> {
+        return super::Selection::parse(query).unwrap().evaluate(mol);
+    }
+
+    #[test]
+    fn select_simple() {
+        let mol = make_molecule();
+
+        assert_eq!(indices(&mol, "all").len(), 9);
+        assert_eq!(indices(&mol, "none").len(), 0);
+        assert_eq!(indices(&mol, "element C"), vec![0, 1, 2]);
+        assert_eq!(indices(&mol, "element n o"), vec![3, 6]);
+        assert_eq!(indices(&mol, "index 1 4 to 6"), vec![1, 4, 5, 6]);
+        assert_eq!(indices(&mol, "resname HOH"), vec![6, 7, 8]);
+        assert_eq!(indices(&mol, "chain A").len(), 9);
+        assert_eq!(indices(&mol, "chain HOH").len(), 0);
+        assert_eq!(indices(&mol, "group HOH"), vec![6, 7, 8]);
+        assert_eq!(indices(&mol, "ring"), vec![0, 1, 2]);
+        assert_eq!(indices(&mol, "charge < -0.3"), vec![3, 6]);
+        assert_eq!(indices(&mol, "index >= 7"), vec![7, 8]);
+        assert_eq!(indices(&mol, "y>6"), vec![7, 8]);
+    }
+
+    #[test]
+    fn select_compound() {
+        let mol = make_molecule();
+
+        assert_eq!(indices(&mol, "bonded to element N"), vec![2, 4, 5]);
+        assert_eq!(
+            indices(&mol, "element H and bonded to element N"),
+            vec![4, 5]
+        );
+        assert_eq!(indices(&mol, "within 1.0 of index 3"), vec![3, 4, 5]);
+        assert_eq!(
+            indices(&mol, "element H and not within 3.5 of index 3"),
+            vec![7, 8]
+        );
+        assert_eq!(
+            indices(&mol, "not (element H or ring) and charge < 0"),
+            vec![3, 6]
+        );
+        assert_eq!(
+            indices(&mol, "ring or resname HOH and element O"),
+            vec![0, 1, 2, 6]
+        );
+        assert_eq!(
+            indices(&mol, "within 3.3 of (resname HOH and element O)"),
+            vec![3, 4, 5, 6, 7, 8]
+        );
+
+        // distances below the size of the cells, and across cells at negative coordinates
+        let mut line = Molecule::new("line".to_string(), "".to_string());
+        for idx in 0..40 {
+            line.add_atom(Atom {
+                center: Point {
+                    x: idx as f32 * 0.25 - 5.0,
+                    y: 0.0,
+                    z: 0.0,
+                },
+                charge: 0.0,
+                symbol: "C".to_string(),
+                remark: "".to_string(),
+            });
+        }
+        assert_eq!(indices(&line, "within 0.3 of index 10"), vec![9, 10, 11]);
+        assert_eq!(indices(&line, "within 0 of index 0"), vec![0]);
+        assert_eq!(
+            indices(&line, "within 2.5 of index 20"),
+            (10..=30).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn select_atom_group() {
+        let mol = make_molecule();
+        let group = super::select(&mol, "element O or element N").unwrap();
+
+        assert_eq!(group.name, "selection".to_string());
+        assert_eq!(group.remark, "element O or element N".to_string());
+        assert_eq!(group.get_atom_indices(), &[3, 6]);
+    }
+
+    #[test]
+    fn select_errors() {
+        let err = super::Selection::parse("element C and").unwrap_err();
+        assert_eq!(err.position, 13);
+
+        let err = super::Selection::parse("element C and frobnicate 3").unwrap_err();
+        assert_eq!(err.message, "unknown keyword 'frobnicate'".to_string());
+        assert_eq!(err.position, 14);
+
+        assert!(super::Selection::parse("(element C").is_err());
+        assert!(super::Selection::parse("within of element C").is_err());
+        assert!(super::Selection::parse("charge 3").is_err());
+        assert!(super::Selection::parse("index -1").is_err());
+        assert!(super::Selection::parse("element").is_err());
+        assert!(super::Selection::parse("element C )").is_err());
+    }
+}
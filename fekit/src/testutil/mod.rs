@@ -0,0 +1,40 @@
+/**
+ * Author: V. Ganesh
+ * License: MIT
+ */
+
+/** testutil module, molecules and temporary files shared by the unit tests */
+use std::path::PathBuf;
+
+use crate::atom::Atom;
+use crate::molecule::builder::MoleculeBuilder;
+use crate::molecule::Molecule;
+use crate::point::Point;
+
+/** get_atom() returns an uncharged atom at the given position */
+pub(crate) fn get_atom(symbol: &str, x: f32, y: f32, z: f32) -> Atom {
+    return Atom {
+        center: Point { x: x, y: y, z: z },
+        charge: 0.0,
+        symbol: symbol.to_string(),
+        remark: "".to_string(),
+    };
+}
+
+/** get_water() returns a water molecule named "water", with its two O-H bonds */
+pub(crate) fn get_water() -> Molecule {
+    return MoleculeBuilder::new("water")
+        .xyz("O 0.0 0.0 0.1173\nH 0.0 0.7572 -0.4692\nH 0.0 -0.7572 -0.4692")
+        .bonds(&[(0, 1), (0, 2)])
+        .build()
+        .unwrap();
+}
+
+/**
+ * get_path() returns a file in the temporary directory, unique to this process and to the
+ * module of the test using it
+ */
+pub(crate) fn get_path(module: &str, name: &str) -> PathBuf {
+    let name = format!("fekit_{}_{}_{}", module, std::process::id(), name);
+    return std::env::temp_dir().join(name);
+}